@@ -0,0 +1,156 @@
+//! Annotations, which attach comments to cells or rectangular regions of a program from a sidecar file, since
+//! Befunge source has no room for comments next to the code they describe.
+//!
+//! An annotation file lists one annotation per line as a cell's coordinates, or the inclusive corners of a region
+//! separated by `-`, followed by the comment; lines starting with `#` are comments of the file itself. A name
+//! starting with `@` in front of the coordinates names the region, so that breakpoints, error messages, the
+//! debugger and the HTML report can refer to it by name instead of by coordinates; the comment is optional then:
+//!
+//! ```text
+//! # annotations for fizzbuzz.bf
+//! 0,0 reads the upper limit
+//! @main_loop 4,1-20,1 the main loop
+//! @print_routine 4,2-20,3
+//! ```
+
+use std::path::Path;
+
+use crate::Coordinates;
+use crate::Error;
+use crate::Position;
+
+/// All annotations of a program, in the order of the file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Annotations {
+    pub annotations: Vec<Annotation>,
+}
+
+/// A comment on a rectangular region of cells.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    /// Top left corner of the region.
+    pub min: Position,
+    /// Bottom right corner of the region, which is part of it.
+    pub max: Position,
+    /// Name of the region, without the `@`.
+    pub name: Option<String>,
+    pub text: String,
+}
+
+impl Annotation {
+    pub fn contains(&self, position: Position) -> bool {
+        position.cmpge(self.min).all() && position.cmple(self.max).all()
+    }
+
+    /// The cells of the region, row by row.
+    pub fn cells(&self) -> impl Iterator<Item = Position> + '_ {
+        (self.min.y..=self.max.y)
+            .flat_map(move |y| (self.min.x..=self.max.x).map(move |x| Position::new(x, y, 0)))
+    }
+}
+
+impl Annotations {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut annotations = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let invalid = || {
+                Error::InvalidAnnotations(format!(
+                    "line {}: expected \"[@name] x,y comment\" or \"[@name] x,y-x,y comment\", got \"{}\"",
+                    number + 1,
+                    line
+                ))
+            };
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let line = line.trim();
+            let (name, line) = match line.strip_prefix('@') {
+                Some(line) => {
+                    let (name, line) = line.split_once(' ').unwrap_or((line, ""));
+                    if name.is_empty()
+                        || !name
+                            .chars()
+                            .all(|character| character.is_ascii_alphanumeric() || character == '_')
+                    {
+                        return Err(invalid());
+                    }
+                    (Some(name.to_string()), line.trim_start())
+                }
+                None => (None, line),
+            };
+            // only named regions may go without a comment
+            let (region, text) = match line.split_once(' ') {
+                Some(split) => split,
+                None if name.is_some() => (line, ""),
+                None => return Err(invalid()),
+            };
+            let position = |coordinates: &str| {
+                let (x, y) = coordinates.split_once(',')?;
+                Some(Position::new(
+                    x.trim().parse().ok()?,
+                    y.trim().parse().ok()?,
+                    0,
+                ))
+            };
+            // the corners are separated by the first minus after a digit, the others are signs of coordinates
+            let separator = (1..region.len()).find(|&index| {
+                region.as_bytes()[index] == b'-' && region.as_bytes()[index - 1].is_ascii_digit()
+            });
+            let (min, max) = match separator {
+                Some(index) => (position(&region[..index]), position(&region[index + 1..])),
+                None => (position(region), position(region)),
+            };
+            let (Some(min), Some(max)) = (min, max) else {
+                return Err(invalid());
+            };
+            annotations.push(Annotation {
+                min: min.min(max),
+                max: min.max(max),
+                name,
+                text: text.trim().to_string(),
+            });
+        }
+        Ok(Self { annotations })
+    }
+
+    /// The comments on the cell, in the order of the file.
+    pub fn at(&self, position: Position) -> impl Iterator<Item = &str> {
+        self.annotations
+            .iter()
+            .filter(move |annotation| annotation.contains(position) && !annotation.text.is_empty())
+            .map(|annotation| annotation.text.as_str())
+    }
+
+    /// The region with the given name, without the `@`.
+    pub fn region(&self, name: &str) -> Option<&Annotation> {
+        self.annotations
+            .iter()
+            .find(|annotation| annotation.name.as_deref() == Some(name))
+    }
+
+    /// The name of the first named region that contains the position.
+    pub fn region_at(&self, position: Position) -> Option<&str> {
+        self.annotations
+            .iter()
+            .find(|annotation| annotation.name.is_some() && annotation.contains(position))
+            .and_then(|annotation| annotation.name.as_deref())
+    }
+
+    /// The position, followed by the name of the region it lies in if there is one, e.g. "[4, 3] in @print".
+    pub fn locate(&self, position: Position) -> String {
+        match self.region_at(position) {
+            Some(name) => format!("{} in @{}", Coordinates(position), name),
+            None => Coordinates(position).to_string(),
+        }
+    }
+
+    /// The comments on the cell joined into one line, or `None` if it has none.
+    pub fn describe(&self, position: Position) -> Option<String> {
+        let text = self.at(position).collect::<Vec<_>>().join("; ");
+        (!text.is_empty()).then_some(text)
+    }
+}
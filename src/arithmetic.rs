@@ -0,0 +1,269 @@
+//! Arithmetic whose result with negative operands differs between Befunge implementations, and integers wider than a
+//! cell.
+
+use std::ffi::c_ulong;
+use std::fmt::Display;
+
+use crate::Int;
+
+/// Ways to take the remainder of a division when an operand is negative. They agree for positive operands, and all
+/// of them are 0 if the divisor is zero, like the result of `/`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Remainder {
+    /// The remainder of the division rounded towards zero, which has the sign of the dividend, like C's `%`.
+    /// This is what `%` and the `R` instruction of the MODU fingerprint use.
+    Truncated,
+    /// The remainder of the division rounded down, which has the sign of the divisor. Used by `M` of MODU.
+    Floored,
+    /// The absolute value of the truncated remainder, as defined by Sam Holden. Used by `U` of MODU.
+    Unsigned,
+}
+
+impl Remainder {
+    #[inline]
+    pub fn of(self, a: Int, b: Int) -> Int {
+        if b == 0 {
+            return 0;
+        }
+        // wrapping avoids the overflow of the minimum value divided by -1, whose remainder is 0 anyway
+        let truncated = a.wrapping_rem(b);
+        match self {
+            Self::Truncated => truncated,
+            Self::Floored if truncated != 0 && (truncated < 0) != (b < 0) => truncated + b,
+            Self::Floored => truncated,
+            Self::Unsigned => truncated.wrapping_abs(),
+        }
+    }
+}
+
+/// The unsigned counterpart of [`Int`], one cell's worth of a [`Wide`] integer.
+type Limb = c_ulong;
+
+/// A two's complement integer `N` cells wide, for arithmetic beyond the range of a single cell, such as that of the
+/// LONG fingerprint. Like cells, it wraps around on overflow. The carries between the cells are handled here, so
+/// that wider cells can reuse the arithmetic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Wide<const N: usize> {
+    /// The cells as unsigned limbs, least significant first.
+    limbs: [Limb; N],
+}
+
+impl<const N: usize> Wide<N> {
+    pub const BITS: u32 = Limb::BITS * N as u32;
+    pub const ZERO: Self = Self { limbs: [0; N] };
+
+    /// The value of a cell, sign-extended.
+    pub fn from_int(value: Int) -> Self {
+        let mut limbs = [if value < 0 { Limb::MAX } else { 0 }; N];
+        limbs[0] = value as Limb;
+        Self { limbs }
+    }
+
+    /// The integer stored in cells with the most significant cell first, as they are pushed onto the stack.
+    pub fn from_cells(cells: [Int; N]) -> Self {
+        let mut limbs = [0; N];
+        for (limb, cell) in limbs.iter_mut().zip(cells.iter().rev()) {
+            *limb = *cell as Limb;
+        }
+        Self { limbs }
+    }
+
+    /// The cells storing the integer, most significant first.
+    pub fn to_cells(self) -> [Int; N] {
+        let mut cells = [0; N];
+        for (cell, limb) in cells.iter_mut().zip(self.limbs.iter().rev()) {
+            *cell = *limb as Int;
+        }
+        cells
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.limbs[N - 1] >> (Limb::BITS - 1) == 1
+    }
+
+    pub fn wrapping_add(self, other: Self) -> Self {
+        let mut limbs = [0; N];
+        let mut carry = false;
+        for (limb, (a, b)) in limbs.iter_mut().zip(self.limbs.iter().zip(other.limbs)) {
+            let (sum, first_carry) = a.overflowing_add(b);
+            let (sum, second_carry) = sum.overflowing_add(carry.into());
+            *limb = sum;
+            carry = first_carry || second_carry;
+        }
+        Self { limbs }
+    }
+
+    pub fn wrapping_neg(self) -> Self {
+        Self {
+            limbs: self.limbs.map(|limb| !limb),
+        }
+        .wrapping_add(Self::from_int(1))
+    }
+
+    pub fn wrapping_sub(self, other: Self) -> Self {
+        self.wrapping_add(other.wrapping_neg())
+    }
+
+    /// The absolute value, which is the minimum value itself for the minimum value.
+    pub fn wrapping_abs(self) -> Self {
+        if self.is_negative() {
+            self.wrapping_neg()
+        } else {
+            self
+        }
+    }
+
+    pub fn wrapping_mul(self, other: Self) -> Self {
+        // the lower half of the full product is the same for signed and unsigned operands
+        let mut limbs = [0; N];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.limbs[..N - i].iter().enumerate() {
+                let product = a as u128 * b as u128 + limbs[i + j] as u128 + carry;
+                limbs[i + j] = product as Limb;
+                carry = product >> Limb::BITS;
+            }
+        }
+        Self { limbs }
+    }
+
+    /// Shift left, which is 0 for shifts by at least the width.
+    pub fn shift_left(self, shift: u32) -> Self {
+        let (whole, bits) = ((shift / Limb::BITS) as usize, shift % Limb::BITS);
+        let mut limbs = [0; N];
+        for (index, limb) in limbs.iter_mut().enumerate().skip(whole) {
+            *limb = self.limbs[index - whole] << bits;
+            if bits > 0 && index > whole {
+                *limb |= self.limbs[index - whole - 1] >> (Limb::BITS - bits);
+            }
+        }
+        Self { limbs }
+    }
+
+    /// Shift right arithmetically, which is 0 or -1 for shifts by at least the width.
+    pub fn shift_right(self, shift: u32) -> Self {
+        let (whole, bits) = ((shift / Limb::BITS) as usize, shift % Limb::BITS);
+        let fill = if self.is_negative() { Limb::MAX } else { 0 };
+        let mut limbs = [fill; N];
+        for (index, limb) in limbs.iter_mut().enumerate().take(N.saturating_sub(whole)) {
+            *limb = self.limbs[index + whole] >> bits;
+            if bits > 0 {
+                let above = self.limbs.get(index + whole + 1).copied().unwrap_or(fill);
+                *limb |= above << (Limb::BITS - bits);
+            }
+        }
+        Self { limbs }
+    }
+
+    fn unsigned_less(self, other: Self) -> bool {
+        self.limbs.iter().rev().lt(other.limbs.iter().rev())
+    }
+
+    /// Quotient and remainder of the operands taken as unsigned, by shifting and subtracting. The divisor must not
+    /// be larger than the minimum value taken as unsigned, so that the remainder never overflows when shifted.
+    fn unsigned_div_rem(self, divisor: Self) -> (Self, Self) {
+        let mut quotient = Self::ZERO;
+        let mut remainder = Self::ZERO;
+        for bit in (0..Self::BITS).rev() {
+            let (index, bit) = ((bit / Limb::BITS) as usize, bit % Limb::BITS);
+            remainder = remainder.shift_left(1);
+            remainder.limbs[0] |= (self.limbs[index] >> bit) & 1;
+            if !remainder.unsigned_less(divisor) {
+                remainder = remainder.wrapping_sub(divisor);
+                quotient.limbs[index] |= 1 << bit;
+            }
+        }
+        (quotient, remainder)
+    }
+
+    /// Quotient rounded towards zero and remainder with the sign of the dividend, like `/` and `%`, or `None` if the
+    /// divisor is zero. The minimum value divided by -1 wraps around to itself.
+    pub fn checked_div_rem(self, divisor: Self) -> Option<(Self, Self)> {
+        if divisor == Self::ZERO {
+            return None;
+        }
+        let (quotient, remainder) = self.wrapping_abs().unsigned_div_rem(divisor.wrapping_abs());
+        let quotient = if self.is_negative() != divisor.is_negative() {
+            quotient.wrapping_neg()
+        } else {
+            quotient
+        };
+        let remainder = if self.is_negative() {
+            remainder.wrapping_neg()
+        } else {
+            remainder
+        };
+        Some((quotient, remainder))
+    }
+
+    /// Multiply the value taken as unsigned by a single limb and add another, returning the carry out of the top.
+    fn mul_add_limb(self, factor: Limb, addend: Limb) -> (Self, Limb) {
+        let mut limbs = [0; N];
+        let mut carry = addend as u128;
+        for (limb, &value) in limbs.iter_mut().zip(&self.limbs) {
+            let product = value as u128 * factor as u128 + carry;
+            *limb = product as Limb;
+            carry = product >> Limb::BITS;
+        }
+        (Self { limbs }, carry as Limb)
+    }
+
+    /// Divide the value taken as unsigned by a single limb, returning the remainder.
+    fn div_rem_limb(self, divisor: Limb) -> (Self, Limb) {
+        let mut limbs = [0; N];
+        let mut remainder = 0;
+        for (limb, &value) in limbs.iter_mut().zip(&self.limbs).rev() {
+            let current = (remainder << Limb::BITS) | value as u128;
+            *limb = (current / divisor as u128) as Limb;
+            remainder = current % divisor as u128;
+        }
+        (Self { limbs }, remainder as Limb)
+    }
+
+    /// Parse a decimal number with an optional sign, or `None` if it is not one or out of range.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if digits.is_empty() {
+            return None;
+        }
+        let mut magnitude = Self::ZERO;
+        for digit in digits.chars() {
+            let (value, carry) = magnitude.mul_add_limb(10, digit.to_digit(10)?.into());
+            if carry != 0 {
+                return None;
+            }
+            magnitude = value;
+        }
+        let value = if negative {
+            magnitude.wrapping_neg()
+        } else {
+            magnitude
+        };
+        // the magnitude has to fit, except for the minimum value, whose magnitude is one larger than the maximum
+        (magnitude == Self::ZERO || value.is_negative() == negative).then_some(value)
+    }
+}
+
+impl<const N: usize> Display for Wide<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the magnitude of the minimum value is correct when taken as unsigned
+        let mut magnitude = self.wrapping_abs();
+        let mut digits = Vec::new();
+        loop {
+            let (quotient, digit) = magnitude.div_rem_limb(10);
+            digits.push(b'0' + digit as u8);
+            magnitude = quotient;
+            if magnitude == Self::ZERO {
+                break;
+            }
+        }
+        if self.is_negative() {
+            digits.push(b'-');
+        }
+        digits.reverse();
+        f.write_str(&String::from_utf8_lossy(&digits))
+    }
+}
@@ -0,0 +1,144 @@
+//! The determinism audit, which looks for anything that could make a program behave differently between runs or
+//! hosts, e.g. before submitting it to a judge.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+use crate::Coordinates;
+use crate::Executer;
+use crate::Grid;
+use crate::InstructionInfo;
+use crate::InstructionSet;
+use crate::Int;
+use crate::LanguageStandard;
+use crate::Position;
+use crate::RunRequest;
+
+/// Step limit for audited programs that have no limit of their own.
+pub const AUDIT_STEPS: usize = 100_000_000;
+
+/// How far the clock of the second run is ahead: a year, a day, an hour, a minute and a second, so that every part
+/// of the date and time `y` reports differs.
+const CLOCK_SHIFT: Duration = Duration::from_secs(367 * 86400 + 3661);
+
+/// Something that makes a program's behavior depend on more than its seed and input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Finding {
+    /// The two runs differed for the first time after this step. They only differ in the time `y` reports, so the
+    /// program depends on the wall-clock time.
+    Divergence { step: usize, position: Position },
+    /// A value that does not fit into 32 bits, which is computed differently on hosts with 32-bit cells.
+    WideValue {
+        step: usize,
+        position: Position,
+        value: Int,
+    },
+    /// An instruction popped more values than the stack held, reading zeros instead.
+    EmptyStackPop {
+        step: usize,
+        position: Position,
+        instruction: u8,
+    },
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Divergence { step, position } => write!(
+                f,
+                "step {} at {}: a run at another time diverges here, so the program depends on the wall-clock time",
+                step, position
+            ),
+            Self::WideValue {
+                step,
+                position,
+                value,
+            } => write!(
+                f,
+                "step {} at {}: {} does not fit into 32 bits, so the result depends on the host's cell width",
+                step, Coordinates(*position), value
+            ),
+            Self::EmptyStackPop {
+                step,
+                position,
+                instruction,
+            } => write!(
+                f,
+                "step {} at {}: '{}' pops more values than the stack holds and reads zeros instead",
+                step, Coordinates(*position), *instruction as char
+            ),
+        }
+    }
+}
+
+/// Run the program twice with the same seed and input, but a different clock, and report where the runs diverge,
+/// along with the first value that does not fit into 32 bits and the first pop from the empty stack.
+/// The request's program is ignored in favor of the grid; without a seed or step limit, fixed ones are used.
+pub fn audit_determinism(grid: &Grid, request: &RunRequest) -> Vec<Finding> {
+    let request = RunRequest {
+        seed: Some(request.seed.unwrap_or_default()),
+        max_steps: Some(request.max_steps.unwrap_or(AUDIT_STEPS)),
+        ..request.clone()
+    };
+    let mut shifted = request.clone();
+    shifted.system_info.time_shift += CLOCK_SHIFT;
+    let instructions =
+        InstructionSet::for_standard(request.standard.unwrap_or(LanguageStandard::Befunge93));
+
+    let (mut output, mut shifted_output) = (Vec::new(), Vec::new());
+    let mut first = request.interpreter(grid.clone(), &mut output);
+    let mut second = shifted.interpreter(grid.clone(), &mut shifted_output);
+    let mut findings = Vec::new();
+    let (mut found_wide_value, mut found_empty_pop) = (false, false);
+    loop {
+        let step = first.steps() + 1;
+        let position = first.position();
+        if !found_empty_pop && !first.string_mode() {
+            let instruction = first.grid().cell(position);
+            if let Some(info) = instructions.describe(instruction) {
+                if popped_values(info) > first.stack().len() {
+                    found_empty_pop = true;
+                    findings.push(Finding::EmptyStackPop {
+                        step,
+                        position,
+                        instruction,
+                    });
+                }
+            }
+        }
+
+        let result = first.run_step();
+        let shifted_result = second.run_step();
+        if !found_wide_value {
+            if let Some(&value) = first.stack().last() {
+                if i32::try_from(value).is_err() {
+                    found_wide_value = true;
+                    findings.push(Finding::WideValue {
+                        step,
+                        position,
+                        value,
+                    });
+                }
+            }
+        }
+        if result != shifted_result
+            || first.position() != second.position()
+            || first.stack() != second.stack()
+        {
+            findings.push(Finding::Divergence { step, position });
+            break;
+        }
+        if result.is_err() {
+            break;
+        }
+    }
+    findings
+}
+
+/// Number of values the instruction pops according to its stack effect, or 0 if that depends on the values.
+fn popped_values(info: &InstructionInfo) -> usize {
+    match info.stack_effect.split_once("--") {
+        Some((popped, _)) if !popped.contains("..") => popped.split_whitespace().count(),
+        _ => 0,
+    }
+}
@@ -1,14 +1,46 @@
 //! Normal Boxfunge executable.
 
+use argh::FromArgs;
 use boxfunge::*;
 
 fn main() {
-    let args: Arguments = argh::from_env();
+    // everything after "--" belongs to the Befunge program, not to boxfunge
+    let mut command_line: Vec<String> = std::env::args().collect();
+    let program_arguments = match command_line.iter().position(|argument| argument == "--") {
+        Some(separator) => command_line.split_off(separator).split_off(1),
+        None => Vec::new(),
+    };
+    let command = std::path::Path::new(&command_line[0])
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(&command_line[0]);
+    let options = command_line[1..]
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let mut args = Arguments::from_args(&[command], &options).unwrap_or_else(|early_exit| {
+        std::process::exit(match early_exit.status {
+            Ok(()) => {
+                println!("{}", early_exit.output);
+                0
+            }
+            Err(()) => {
+                eprintln!(
+                    "{}\nRun {} --help for more information.",
+                    early_exit.output, command
+                );
+                1
+            }
+        })
+    });
+    args.order_input_sources(&options);
+    args.program_arguments.extend(program_arguments);
 
-    if args.language_standard != LanguageStandard::Befunge93 {
-        eprintln!("only Befunge-93 is currently supported");
-        std::process::exit(1);
-    }
-
-    run_interpreter(args).unwrap();
+    std::process::exit(match run_interpreter(args) {
+        Ok(exit_code) => exit_code,
+        Err(why) => {
+            eprintln!("error: {}", why);
+            why.exit_code()
+        }
+    });
 }
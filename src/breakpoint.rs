@@ -0,0 +1,134 @@
+//! Breakpoints, which stop a program when its program counter reaches a cell or a named region, optionally only for
+//! one instruction pointer of a concurrent program or only if a condition holds.
+
+use std::collections::HashMap;
+
+use argh::FromArgValue;
+
+use crate::Annotations;
+use crate::Error;
+use crate::Executer;
+use crate::Expression;
+use crate::Int;
+use crate::Interpreter;
+use crate::Position;
+
+/// Where a breakpoint is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Location {
+    Cell(Position),
+    /// A region named in the annotations, without the `@`. The breakpoint is on every cell of the region.
+    Region(String),
+}
+
+/// A breakpoint on a cell or a named region, written as "x,y", "x,y,z" or "@name", optionally followed by "thread id"
+/// and "if condition", e.g. "5,5 if stack.len() > 100", "@print_routine" or "5,5 thread 2 if stack[0] == 0".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub location: Location,
+    /// The breakpoint only triggers for the instruction pointer with this ID, as `y` reports it; without one, it
+    /// triggers for all of them.
+    pub ip: Option<Int>,
+    /// The breakpoint only triggers if this expression holds; without one, it always triggers.
+    pub condition: Option<Expression>,
+}
+
+impl FromArgValue for Breakpoint {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        let (position, condition) = match value.split_once(" if ") {
+            Some((position, condition)) => (position, Some(Expression::parse(condition)?)),
+            None => (value, None),
+        };
+        let (position, ip) =
+            match position.split_once(" thread ") {
+                Some((position, ip)) => (
+                    position,
+                    Some(ip.trim().parse().map_err(|_| {
+                        format!("'{}' is not an instruction pointer ID", ip.trim())
+                    })?),
+                ),
+                None => (position, None),
+            };
+        if let Some(name) = position.trim().strip_prefix('@') {
+            return Ok(Self {
+                location: Location::Region(name.to_string()),
+                ip,
+                condition,
+            });
+        }
+        let coordinates = position
+            .split(',')
+            .map(|part| part.trim().parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("'{}' is not a position \"x,y[,z]\"", position))?;
+        let position = match coordinates[..] {
+            [x, y] => Position::new(x, y, 0),
+            [x, y, z] => Position::new(x, y, z),
+            _ => return Err(format!("'{}' is not a position \"x,y[,z]\"", position)),
+        };
+        Ok(Self {
+            location: Location::Cell(position),
+            ip,
+            condition,
+        })
+    }
+}
+
+/// What has to hold for a breakpoint on a cell to trigger.
+#[derive(Clone, Debug)]
+struct Trigger {
+    ip: Option<Int>,
+    condition: Option<Expression>,
+}
+
+/// A set of breakpoints, looked up by the position of the program counter, so that conditions are only evaluated
+/// on the cells that carry breakpoints.
+#[derive(Clone, Debug, Default)]
+pub struct Breakpoints {
+    triggers: HashMap<Position, Vec<Trigger>>,
+}
+
+impl Breakpoints {
+    /// Look up the regions of the breakpoints in the annotations, which fails for regions that are not named there.
+    pub fn new(breakpoints: Vec<Breakpoint>, annotations: &Annotations) -> Result<Self, Error> {
+        let mut triggers: HashMap<_, Vec<_>> = HashMap::new();
+        for breakpoint in breakpoints {
+            let trigger = Trigger {
+                ip: breakpoint.ip,
+                condition: breakpoint.condition,
+            };
+            match breakpoint.location {
+                Location::Cell(position) => triggers.entry(position).or_default().push(trigger),
+                Location::Region(name) => {
+                    let region = annotations.region(&name).ok_or_else(|| {
+                        Error::InvalidAnnotations(format!(
+                            "breakpoint on @{}, but no region has that name",
+                            name
+                        ))
+                    })?;
+                    for position in region.cells() {
+                        triggers.entry(position).or_default().push(trigger.clone());
+                    }
+                }
+            }
+        }
+        Ok(Self { triggers })
+    }
+
+    /// Whether a breakpoint triggers before the interpreter executes its next step. With several instruction
+    /// pointers, [`Interpreter::schedule`] has to switch to the one that runs next first.
+    #[inline]
+    pub fn triggers(&self, interpreter: &Interpreter) -> bool {
+        self.triggers
+            .get(&interpreter.position())
+            .is_some_and(|triggers| {
+                triggers.iter().any(|trigger| {
+                    trigger.ip.is_none_or(|ip| ip == interpreter.ip_id())
+                        && trigger
+                            .condition
+                            .as_ref()
+                            .is_none_or(|condition| condition.holds(interpreter))
+                })
+            })
+    }
+}
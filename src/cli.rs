@@ -0,0 +1,946 @@
+//! The command-line interface, which runs a program with the options of [`Arguments`].
+
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::analyze_paths;
+use crate::audit_determinism;
+use crate::check_start;
+use crate::conformance_matrix;
+use crate::find_endless_loop;
+use crate::metrics::METRICS_INTERVAL;
+use crate::post_mortem;
+use crate::run_daemon;
+use crate::run_entries;
+use crate::with_arguments;
+use crate::Animation;
+use crate::Annotations;
+use crate::Arguments;
+use crate::Breakpoints;
+use crate::BridgeEdge;
+use crate::EchoInput;
+use crate::EncodeOutput;
+use crate::Error;
+use crate::Executer;
+use crate::FileIo;
+use crate::Grid;
+use crate::GridStats;
+use crate::Heatmap;
+use crate::InputSource;
+use crate::InstructionSet;
+use crate::Interpreter;
+use crate::IpUsage;
+use crate::LanguageStandard;
+use crate::LazySource;
+use crate::Manifest;
+use crate::Metadata;
+use crate::Metrics;
+use crate::Newline;
+use crate::Normalize;
+use crate::OutputEncoding;
+use crate::Outs;
+use crate::Overlay;
+use crate::Package;
+use crate::Report;
+use crate::RunRequest;
+use crate::State;
+use crate::SystemInfo;
+use crate::ThreadedInput;
+use crate::Transcript;
+use crate::Watches;
+use crate::WrapMode;
+
+/// Run the program step by step, logging the stack to the stack log, counting executed cells in the heatmap,
+/// rendering animation frames, printing watches, stopping at breakpoints and updating the metrics.
+/// The stack log gets a CSV line with the stack depth and top-of-stack value after every step, where the top-of-stack
+/// column is empty whenever the stack is empty.
+fn run_instrumented(
+    interpreter: &mut Interpreter,
+    log: Option<File>,
+    mut heatmap: Option<&mut Heatmap>,
+    mut animation: Option<Animation<io::BufWriter<File>>>,
+    mut watches: Option<Watches>,
+    breakpoints: Option<Breakpoints>,
+    metrics: Option<&Metrics>,
+) -> Result<(), Error> {
+    let mut log = log.map(io::BufWriter::new);
+    if let Some(log) = &mut log {
+        writeln!(log, "step,depth,top")?;
+    }
+    loop {
+        // with several instruction pointers, the instruments look at the one that runs next
+        let scheduled = interpreter.schedule();
+        if scheduled.is_ok()
+            && breakpoints
+                .as_ref()
+                .is_some_and(|breakpoints| breakpoints.triggers(interpreter))
+        {
+            if let Some(log) = &mut log {
+                log.flush()?;
+            }
+            if let Some(animation) = &mut animation {
+                animation.frame(interpreter)?;
+                animation.flush()?;
+            }
+            return Err(Error::Breakpoint(interpreter.position()));
+        }
+        if let Some(heatmap) = &mut heatmap {
+            heatmap.record(interpreter.position());
+        }
+        if let Some(animation) = &mut animation {
+            animation.step(interpreter)?;
+        }
+        let result = scheduled.and_then(|()| interpreter.run_step());
+        if let Some(metrics) = metrics {
+            if result.is_err() || interpreter.steps().is_multiple_of(METRICS_INTERVAL) {
+                metrics.record(interpreter);
+            }
+        }
+        if let Some(log) = &mut log {
+            let stack = interpreter.stack();
+            write!(log, "{},{},", interpreter.steps(), stack.len())?;
+            if let Some(top) = stack.last() {
+                write!(log, "{}", top)?;
+            }
+            writeln!(log)?;
+        }
+        if let Some(line) = watches
+            .as_mut()
+            .and_then(|watches| watches.changes(interpreter))
+        {
+            eprintln!("{}", line);
+        }
+        if result.is_err() {
+            if let Some(animation) = &mut animation {
+                animation.frame(interpreter)?;
+                animation.flush()?;
+            }
+        }
+        if result.as_ref().is_err_and(|e| e == &Error::ProgramEnd) {
+            if let Some(log) = &mut log {
+                log.flush()?;
+            }
+            return Ok(());
+        }
+        result?;
+    }
+}
+
+fn compile_embedded_befunge(grid: &Grid, output: PathBuf) -> Result<(), Error> {
+    #[cfg(not(windows))]
+    const EXECUTABLE_NAME: &str = "embedded_befunge";
+    #[cfg(windows)]
+    const EXECUTABLE_NAME: &str = "embedded_befunge.exe";
+
+    if grid.has_far_cells() {
+        let size = grid.bounds().size();
+        return Err(Error::InvalidGridSize(size.x as usize, size.y as usize));
+    }
+    let stringified_grid = format!("Grid::new({:?})", grid.cells());
+
+    let mut gridfile = tempfile::NamedTempFile::new()?;
+    gridfile.write_all(stringified_grid.as_bytes())?;
+    let target_dir = tempfile::tempdir()?;
+
+    // This is a big fucking hack and only works when you have the repo locally and call the program from the right directory.
+    Command::new("cargo")
+        .args(["build", "-q", "--release", "--bin", "embedded_befunge"])
+        .args([
+            &OsString::from("--target-dir"),
+            target_dir.path().as_os_str(),
+        ])
+        .env("BEFUNGE_CODE_SRC", gridfile.path())
+        .status()?;
+
+    let mut executable = target_dir.path().to_owned();
+    executable.push("release");
+    executable.push(EXECUTABLE_NAME);
+    std::fs::rename(executable, &output)?;
+
+    println!(
+        "Written embedded befunge executable to {}",
+        output.to_string_lossy()
+    );
+
+    Ok(())
+}
+
+fn print_info(metadata: &Metadata, grid: &Grid) {
+    let or_unknown = |value: &Option<String>| value.clone().unwrap_or_else(|| "unknown".into());
+    println!("title: {}", or_unknown(&metadata.title));
+    println!("author: {}", or_unknown(&metadata.author));
+    match metadata.standard {
+        Some(standard) => println!("standard: {}", standard),
+        None => println!("standard: unknown"),
+    }
+    if !metadata.extensions.is_empty() {
+        println!("extensions: {}", metadata.extensions.join(", "));
+    }
+    for (key, value) in &metadata.other {
+        println!("{}: {}", key, value);
+    }
+
+    let stats = GridStats::new(grid);
+    match stats.size() {
+        size if size.z > 1 => println!("size: {} x {} x {}", size.x, size.y, size.z),
+        size => println!("size: {} x {}", size.x, size.y),
+    }
+    println!("non-space cells: {}", stats.non_space_cells);
+    println!("reads input: {}", stats.reads_input);
+    println!("self-modifying: {}", stats.modifies_itself);
+    println!("uses randomness: {}", stats.uses_randomness);
+}
+
+fn print_matrix(args: &Arguments) {
+    let base = RunRequest {
+        standard: Some(args.language_standard),
+        wrap_mode: args.wrap,
+        bridge_edge: args.bridge_edge,
+        string_spaces: args.string_spaces,
+        cell_values: args.cell_values,
+        lenient_numbers: args.lenient_numbers,
+        seed: args.seed,
+        ..Default::default()
+    };
+    for (case, entries) in conformance_matrix(&base) {
+        println!("{}: {}", case.name, case.description);
+        for entry in entries {
+            println!("  {:<24} {}", entry.setting, entry.behavior());
+        }
+    }
+}
+
+fn audit(args: &Arguments, grid: &Grid) -> Result<(), Error> {
+    let mut input = Vec::new();
+    let stdin = InputSource::open_all(&args.stdin)?;
+    with_arguments(&args.program_arguments, stdin).read_to_end(&mut input)?;
+    let request = RunRequest {
+        input,
+        seed: args.seed,
+        max_steps: args.sandbox.sandbox(None).max_steps,
+        start: args.start.first().copied().unwrap_or_default(),
+        standard: Some(args.language_standard),
+        wrap_mode: args.wrap,
+        bridge_edge: args.bridge_edge,
+        lenient_numbers: args.lenient_numbers,
+        string_spaces: args.string_spaces,
+        cell_values: args.cell_values,
+        system_info: SystemInfo::collect(
+            [args.input.display().to_string()]
+                .into_iter()
+                .chain(args.program_arguments.iter().cloned())
+                .collect(),
+            &args.sandbox.hide_sysinfo,
+            &args.sandbox.sandbox(None),
+        ),
+        ..Default::default()
+    };
+    let findings = audit_determinism(grid, &request);
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    if findings.is_empty() {
+        println!("no determinism problems found");
+        Ok(())
+    } else {
+        Err(Error::Nondeterministic(findings.len()))
+    }
+}
+
+/// The program's input: the input sources one after another, preceded by the program arguments.
+fn open_input(args: &Arguments, stdin: &[InputSource]) -> Result<Box<dyn Read>, Error> {
+    let input = InputSource::open_all(stdin)?;
+    // reading on another thread lets the time limit stop a program that waits for input
+    let mut input: Box<dyn Read> = match args.sandbox.sandbox(None).timeout {
+        Some(timeout) => {
+            Box::new(ThreadedInput::new(input).with_deadline(Instant::now() + timeout))
+        }
+        None => input,
+    };
+    input = with_arguments(&args.program_arguments, input);
+    if args.echo_input {
+        input = Box::new(EchoInput::new(input, io::stderr()));
+    }
+    Ok(input)
+}
+
+/// Apply the options every run of the program shares, from the standard and the sandbox to the initial stack.
+fn configure<'rw>(
+    interpreter: Interpreter<'rw>,
+    args: &Arguments,
+    manifest: Option<&Manifest>,
+) -> Result<Interpreter<'rw>, Error> {
+    let mut interpreter = interpreter;
+    if let Some(seed) = args.seed {
+        interpreter = interpreter.with_seed(seed);
+    }
+    if let Some(stack_dump) = args.stack_dump {
+        interpreter = interpreter.with_stack_dump_instruction(stack_dump);
+    }
+    let sandbox = args.sandbox.sandbox(manifest);
+    let system_info = SystemInfo::collect(
+        [args.input.display().to_string()]
+            .into_iter()
+            .chain(args.program_arguments.iter().cloned())
+            .collect(),
+        &args.sandbox.hide_sysinfo,
+        &sandbox,
+    );
+    interpreter = interpreter
+        .with_system_info(system_info)
+        .with_sandbox(sandbox)
+        .with_exec_paradigm(args.sandbox.exec.clone())
+        .with_standard(args.language_standard)
+        .with_bridge_edge(args.bridge_edge)
+        .with_string_spaces(args.string_spaces)
+        .with_protection(args.protect.clone(), args.protect_policy)
+        .with_lenient_numbers(args.lenient_numbers)
+        .with_paranoid(args.paranoid)
+        .with_deterministic_scheduling(args.deterministic_scheduling)
+        .with_stack_stats(args.stack_stats);
+    if args.sandbox.file_io {
+        interpreter = interpreter.with_file_io(true).with_fingerprint(&FileIo);
+    }
+    if args.output_streams {
+        interpreter = interpreter.with_fingerprint(&Outs);
+    }
+    if let Some(capacity) = args.stack_capacity {
+        interpreter = interpreter.with_stack_capacity(capacity);
+    }
+    if let Some(rate) = args.write_hotspots {
+        interpreter = interpreter.with_write_hotspots(rate);
+    }
+    if let Some(path) = &args.load_stack {
+        let stack = args.stack_format.read(&std::fs::read(path)?)?;
+        interpreter = interpreter.with_stack(stack);
+    }
+    if let Some(wrap_mode) = args.wrap {
+        interpreter = interpreter.with_wrap_mode(wrap_mode);
+    }
+    if let Some(cell_values) = args.cell_values {
+        interpreter = interpreter.with_cell_values(cell_values);
+    }
+    Ok(interpreter)
+}
+
+/// Run the command-line interface, returning the exit code the process should end with.
+/// Errors of the program itself are reported on stderr and only determine the exit code, see [`Error::exit_code`],
+/// while errors that keep the program from running at all are returned.
+pub fn run_interpreter(args: Arguments) -> Result<i32, Error> {
+    run_interpreter_with_output(args, Box::new(io::stdout()))
+}
+
+/// Like [`run_interpreter`], but the program writes its output to the given writer instead of stdout.
+pub fn run_interpreter_with_output(
+    mut args: Arguments,
+    output: Box<dyn Write + '_>,
+) -> Result<i32, Error> {
+    if run_tool(&args)? {
+        return Ok(0);
+    }
+
+    // a package is run from a temporary directory it is extracted to, which lives until the end of the run
+    let mut package_directory = None;
+    if let Some(path) = args.run_dir.as_mut().filter(|path| path.is_file()) {
+        let directory = tempfile::tempdir()?;
+        Package::from_zip(&std::fs::read(&*path)?)?.extract(directory.path())?;
+        *path = directory.path().to_owned();
+        package_directory = Some(directory);
+    }
+    let manifest = args.run_dir.as_deref().map(Manifest::load).transpose()?;
+    let expected = match manifest.as_ref().map(|manifest| &manifest.expected) {
+        _ if !args.test => None,
+        Some(Some(expected)) => Some(std::fs::read(expected)?),
+        _ => {
+            return Err(Error::InvalidManifest(
+                "--test needs a manifest naming the expected output".to_string(),
+            ))
+        }
+    };
+    if let Some(manifest) = &manifest {
+        args.input = manifest.program.clone();
+        // explicit arguments take precedence over the manifest
+        if args.stdin.is_empty() {
+            args.stdin.extend(manifest.stdin.clone());
+        }
+        args.annotations = args.annotations.or_else(|| manifest.annotations.clone());
+        if let Some(standard) = manifest.standard {
+            args.language_standard = standard;
+        }
+    }
+
+    let mut program = load_program(&mut args, manifest.as_ref())?;
+    if apply_overlays(&args, &mut program.grid)? || inspect(&args, &program)? {
+        return Ok(0);
+    }
+    for &start in &args.start {
+        check_start(&program.grid, start)?;
+    }
+    if args.start.len() > 1 {
+        return run_all_entries(&args, &program.grid, manifest.as_ref());
+    }
+
+    let exit_code = run_program(args, program, manifest.as_ref(), expected, output);
+    drop(package_directory);
+    exit_code
+}
+
+/// Run one of the tools that don't run a program: `--ops`, `--matrix`, `--pack` or `--daemon`.
+/// Returns whether any of them ran.
+fn run_tool(args: &Arguments) -> Result<bool, Error> {
+    if args.ops {
+        for info in InstructionSet::for_standard(args.language_standard).documentation() {
+            println!(
+                "'{}'  {:<18} {:<12} {}",
+                info.instruction as char, info.name, info.stack_effect, info.description
+            );
+        }
+        return Ok(true);
+    }
+
+    if args.matrix {
+        print_matrix(args);
+        return Ok(true);
+    }
+
+    if let Some(package) = &args.pack {
+        let directory = args.run_dir.as_ref().ok_or_else(|| {
+            Error::InvalidManifest("--pack needs a run directory given with --run-dir".to_string())
+        })?;
+        // make sure the package can actually be run
+        Manifest::load(directory)?;
+        std::fs::write(package, Package::from_dir(directory)?.to_zip())?;
+        println!("Written package to {}", package.to_string_lossy());
+        return Ok(true);
+    }
+
+    if let Some(socket) = &args.daemon.socket {
+        let sandbox = args.sandbox.sandbox(None);
+        let defaults = RunRequest {
+            max_steps: sandbox.max_steps,
+            timeout: sandbox.timeout,
+            max_output: sandbox.max_output,
+            seed: args.seed,
+            standard: Some(args.language_standard),
+            ..Default::default()
+        };
+        run_daemon(socket, &defaults)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Source code of a program loaded by the command-line interface.
+enum Source {
+    /// The source as read, which is empty for a resumed program.
+    Text(String),
+    Lazy(Arc<LazySource>),
+}
+
+impl Source {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Text(text) => text.as_bytes(),
+            Self::Lazy(source) => source.source(),
+        }
+    }
+}
+
+/// A program loaded by the command-line interface.
+struct LoadedProgram {
+    source: Source,
+    metadata: Metadata,
+    grid: Grid,
+    resume: Option<State>,
+}
+
+/// Load the program from its source file or the state it resumes from, selecting the standard it asks for.
+fn load_program(args: &mut Arguments, manifest: Option<&Manifest>) -> Result<LoadedProgram, Error> {
+    // a resumed program brings its own grid and standard
+    let resume = args.resume.as_deref().map(State::load).transpose()?;
+    // Trefunge programs are recognized by their extension, unless another standard was asked for
+    if args.language_standard == LanguageStandard::default() {
+        if let Some(standard) = LanguageStandard::for_path(&args.input) {
+            args.language_standard = standard;
+        }
+    }
+    // a standard given by the manifest takes precedence over the program's own header
+    let manifest_standard = manifest.and_then(|manifest| manifest.standard);
+    let mut select_standard = |metadata: &Metadata| {
+        if let (Some(standard), None) = (metadata.standard, manifest_standard) {
+            args.language_standard = standard;
+        }
+    };
+    let (source, metadata, mut grid) = match &resume {
+        Some(state) => {
+            args.language_standard = state.standard;
+            (
+                Source::Text(String::new()),
+                Metadata::default(),
+                state.snapshot.grid.clone(),
+            )
+        }
+        None if args.lazy => {
+            let (metadata, lazy) = if args.input == Path::new("-") {
+                let mut source = Vec::new();
+                io::stdin().read_to_end(&mut source)?;
+                LazySource::from_bytes(source)?
+            } else {
+                LazySource::open(&args.input)?
+            };
+            select_standard(&metadata);
+            let lazy = Arc::new(lazy);
+            // only unbounded grids are worth loading lazily, and lazy sources have a single plane
+            let grid = if args.language_standard.is_unbounded()
+                && args.language_standard.dimensions() == 2
+            {
+                Grid::lazy(lazy.clone())
+            } else {
+                let text = String::from_utf8_lossy(lazy.source());
+                Interpreter::parse_grid_for_standard(
+                    Metadata::parse(&text)?.1,
+                    args.language_standard,
+                )?
+            };
+            (Source::Lazy(lazy), metadata, grid)
+        }
+        None => {
+            let mut source = String::new();
+            if args.input == Path::new("-") {
+                io::stdin().read_to_string(&mut source)?;
+            } else {
+                File::open(&args.input)?.read_to_string(&mut source)?;
+            }
+            let (metadata, program) = Metadata::parse(&source)?;
+            select_standard(&metadata);
+            let grid = Interpreter::parse_grid_for_standard(program, args.language_standard)?;
+            (Source::Text(source), metadata, grid)
+        }
+    };
+    if let Some(manifest) = manifest {
+        manifest.stamp(&mut grid)?;
+    }
+    Ok(LoadedProgram {
+        source,
+        metadata,
+        grid,
+        resume,
+    })
+}
+
+/// Apply the overlays to the grid. Returns whether the differences to an overlay were printed instead, in which case
+/// the program doesn't run.
+fn apply_overlays(args: &Arguments, grid: &mut Grid) -> Result<bool, Error> {
+    let base = args.overlay_diff.then(|| grid.clone());
+    for overlay in &args.overlay {
+        Overlay::load(overlay)?.apply(grid)?;
+    }
+    if let Some(base) = base {
+        for patch in Overlay::diff(&base, grid).patches {
+            println!(
+                "{},{} '{}' -> '{}'",
+                patch.x,
+                patch.y,
+                base.get(patch.x, patch.y).unwrap_or(b' ') as char,
+                patch.value as char
+            );
+        }
+        return Ok(true);
+    }
+    if let Some(edited) = &args.overlay_from {
+        let edited = std::fs::read_to_string(edited)?;
+        let (_, edited) = Metadata::parse(&edited)?;
+        let edited = Interpreter::parse_grid_for_standard(edited, args.language_standard)?;
+        print!("{}", Overlay::diff(grid, &edited));
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Print information about the program, analyze it or compile it instead of running it.
+/// Returns whether anything of this was asked for.
+fn inspect(args: &Arguments, program: &LoadedProgram) -> Result<bool, Error> {
+    let grid = &program.grid;
+    let options = &args.analysis;
+    if options.info {
+        print_info(&program.metadata, grid);
+        return Ok(true);
+    }
+
+    if options.branches || options.strings || options.outline {
+        let paths = analyze_paths(grid, args.start.first().copied().unwrap_or_default());
+        if options.branches {
+            for branch in &paths.branches {
+                println!("{}", branch);
+            }
+        }
+        if options.strings {
+            for string in &paths.strings {
+                println!("{}", string);
+            }
+        }
+        if options.outline {
+            println!("{}", paths.outline_json());
+        }
+        return Ok(true);
+    }
+
+    if options.audit_determinism {
+        audit(args, grid)?;
+        return Ok(true);
+    }
+
+    if let Some(output) = &args.output {
+        compile_embedded_befunge(grid, output.clone())?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Run the program once from every start position and print a report of all runs.
+/// The exit code is that of the first entry that failed.
+fn run_all_entries(
+    args: &Arguments,
+    grid: &Grid,
+    manifest: Option<&Manifest>,
+) -> Result<i32, Error> {
+    // the entries can't share the process's stdin, so they get no input unless told otherwise
+    let stdin = match args.stdin.is_empty() {
+        true => vec![InputSource::Null],
+        false => args.stdin.clone(),
+    };
+    let entries = run_entries(
+        grid,
+        &args.start,
+        || open_input(args, &stdin),
+        |interpreter| configure(interpreter, args, manifest),
+    )?;
+    let mut exit_code = 0;
+    for (index, entry) in entries.iter().enumerate() {
+        match &entry.result {
+            Ok(_) => println!(
+                "entry {} ({}): terminated after {} steps",
+                index, entry.start, entry.steps
+            ),
+            Err(why) => {
+                if exit_code == 0 {
+                    exit_code = why.exit_code();
+                }
+                println!(
+                    "entry {} ({}): error after {} steps: {}",
+                    index, entry.start, entry.steps, why
+                );
+            }
+        }
+        println!("{}", String::from_utf8_lossy(&entry.output));
+    }
+    Ok(exit_code)
+}
+
+/// The instruments that watch a run step by step through [`run_instrumented`].
+struct Instruments {
+    stack_log: Option<File>,
+    animation: Option<Animation<io::BufWriter<File>>>,
+    watches: Option<Watches>,
+    breakpoints: Option<Breakpoints>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl Instruments {
+    fn new(args: &mut Arguments, annotations: &Annotations) -> Result<Self, Error> {
+        let stack_log = args.stack_log.take().map(File::create).transpose()?;
+        let animation = match args.animate_to.take() {
+            Some(path) => Some(
+                Animation::new(
+                    io::BufWriter::new(File::create(path)?),
+                    args.animate_interval,
+                )
+                .with_annotations(annotations.clone()),
+            ),
+            None => None,
+        };
+
+        let watches = (!args.debugger.watch.is_empty())
+            .then(|| Watches::new(std::mem::take(&mut args.debugger.watch)));
+        let breakpoints = if args.debugger.breakpoint.is_empty() {
+            None
+        } else {
+            Some(Breakpoints::new(
+                std::mem::take(&mut args.debugger.breakpoint),
+                annotations,
+            )?)
+        };
+
+        let metrics = match args.metrics {
+            Some(address) => {
+                let metrics = Arc::new(Metrics::new());
+                metrics.clone().serve(TcpListener::bind(address)?);
+                Some(metrics)
+            }
+            None => None,
+        };
+        Ok(Self {
+            stack_log,
+            animation,
+            watches,
+            breakpoints,
+            metrics,
+        })
+    }
+
+    /// Run the program until it ends, step by step if any instrument or the heatmap needs to see every step.
+    fn run(
+        self,
+        interpreter: &mut Interpreter,
+        heatmap: Option<&mut Heatmap>,
+    ) -> Result<(), Error> {
+        if self.stack_log.is_none()
+            && heatmap.is_none()
+            && self.animation.is_none()
+            && self.watches.is_none()
+            && self.breakpoints.is_none()
+            && self.metrics.is_none()
+        {
+            return interpreter.run_forever();
+        }
+        run_instrumented(
+            interpreter,
+            self.stack_log,
+            heatmap,
+            self.animation,
+            self.watches,
+            self.breakpoints,
+            self.metrics.as_deref(),
+        )
+    }
+}
+
+/// Run the loaded program with all the options of the command line, returning the exit code.
+fn run_program(
+    mut args: Arguments,
+    program: LoadedProgram,
+    manifest: Option<&Manifest>,
+    expected: Option<Vec<u8>>,
+    output: Box<dyn Write + '_>,
+) -> Result<i32, Error> {
+    // the analysis only knows the default playfield edges
+    let wrap = args
+        .wrap
+        .unwrap_or_else(|| WrapMode::for_standard(args.language_standard));
+    if wrap == WrapMode::Torus && args.bridge_edge == BridgeEdge::Skip {
+        let start = args.start.first().copied().unwrap_or_default();
+        if let Some(endless_loop) = find_endless_loop(&program.grid, start) {
+            eprintln!(
+                "warning: the program never leaves the loop at {}",
+                endless_loop
+            );
+        }
+    }
+
+    let input_transcript = RefCell::new(Vec::new());
+    let transcript = RefCell::new(Vec::new());
+    let mut input = open_input(&args, &args.stdin)?;
+    if args.golden.is_some() {
+        input = Box::new(Transcript::new(input, &input_transcript));
+    }
+    let mut output = output;
+    if args.newline != Newline::Lf || args.output_encoding != OutputEncoding::Ascii {
+        output = Box::new(EncodeOutput::new(
+            output,
+            args.newline,
+            args.output_encoding,
+        ));
+    }
+    // the transcript records the output as the program produced it, before encoding
+    if args.report.is_some() || args.golden.is_some() || expected.is_some() {
+        output = Box::new(Transcript::new(output, &transcript));
+    }
+    let mut interpreter = Box::new(configure(
+        Interpreter::new_with_io_and_grid(program.grid, input, output),
+        &args,
+        manifest,
+    )?);
+    // a golden test needs to know the seed to be reproducible
+    let seed = match args.seed {
+        None if args.golden.is_some() => {
+            let seed = std::time::SystemTime::now()
+                .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64;
+            *interpreter = interpreter.with_seed(seed);
+            Some(seed)
+        }
+        seed => seed,
+    };
+    if let Some(&start) = args.start.first() {
+        *interpreter = interpreter.with_start(start);
+    }
+    if let Some(state) = program.resume {
+        interpreter.restore(state.snapshot);
+    }
+
+    let annotations = match &args.annotations {
+        Some(path) => Annotations::load(path)?,
+        None => Annotations::default(),
+    };
+    let mut heatmap = args.report.as_ref().map(|_| Heatmap::new());
+    let instruments = Instruments::new(&mut args, &annotations)?;
+    // the clocks of the instruction pointers start with the one of the whole run
+    if args.show_performance {
+        *interpreter = interpreter.with_ip_profile(true);
+    }
+    let start = std::time::Instant::now();
+    let result = instruments.run(&mut interpreter, heatmap.as_mut());
+    let duration = start.elapsed();
+    let ip_usage = interpreter.ip_profile();
+
+    if let (Some(report), Some(heatmap)) = (&args.report, &heatmap) {
+        let html = Report {
+            metadata: &program.metadata,
+            grid: interpreter.grid(),
+            heatmap,
+            output: &transcript.borrow(),
+            steps: interpreter.steps(),
+            duration,
+            result: &result,
+            annotations: &annotations,
+        }
+        .to_html();
+        std::fs::write(report, html)?;
+    }
+
+    if let Some(golden) = &args.golden {
+        let name = match args.input.file_stem() {
+            Some(stem) if args.input != Path::new("-") => stem.to_owned(),
+            _ => "stdin".into(),
+        };
+        let path = |extension| golden.join(&name).with_extension(extension);
+        std::fs::create_dir_all(golden)?;
+        std::fs::write(path("bf"), program.source.as_bytes())?;
+        std::fs::write(path("in"), &*input_transcript.borrow())?;
+        std::fs::write(path("expected"), &*transcript.borrow())?;
+        std::fs::write(path("seed"), format!("{}\n", seed.unwrap_or_default()))?;
+    }
+
+    if let Err(why) = &result {
+        eprintln!(
+            "error at {}: {}",
+            annotations.locate(interpreter.position()),
+            why
+        );
+        if args.debugger.post_mortem || args.debugger.script.is_some() {
+            let mut script = match &args.debugger.script {
+                Some(path) => Some(io::BufReader::new(File::open(path)?)),
+                None => None,
+            };
+            let mut log = match &args.debugger.log {
+                Some(path) => Some(File::options().create(true).append(true).open(path)?),
+                None => None,
+            };
+            post_mortem(
+                &interpreter,
+                why,
+                &annotations,
+                script.as_mut().map(|script| script as &mut dyn io::BufRead),
+                io::stdin().lock(),
+                io::stderr(),
+                log.as_mut().map(|log| log as &mut dyn Write),
+            )?;
+        }
+    }
+    save_results(&args, &interpreter)?;
+    if let (Some(expected), Ok(_)) = (&expected, &result) {
+        let output = String::from_utf8_lossy(&transcript.borrow()).into_owned();
+        if Normalize::ALL.equal(&output, &String::from_utf8_lossy(expected)) {
+            eprintln!("test passed");
+        } else {
+            eprintln!("test failed: {}", Error::OutputMismatch);
+            return Ok(Error::OutputMismatch.exit_code());
+        }
+    }
+    print_statistics(&args, &interpreter, duration, ip_usage);
+
+    // a program ending with q chooses its own exit code
+    let exit_code = result
+        .as_ref()
+        .map_or_else(Error::exit_code, |_| interpreter.exit_status() as i32);
+    let position = interpreter.position();
+    let exit = interpreter.exit_reason(result);
+    if args.exit_summary {
+        eprintln!("exit: {} ({})", exit.name(), exit);
+        eprintln!("steps: {}", interpreter.steps());
+        eprintln!("position: {}", annotations.locate(position));
+        eprintln!("exit code: {}", exit_code);
+    }
+    Ok(exit_code)
+}
+
+/// Save the state and the stack of the finished program if asked to.
+fn save_results(args: &Arguments, interpreter: &Interpreter) -> Result<(), Error> {
+    if let Some(path) = &args.save_state {
+        State {
+            standard: args.language_standard,
+            snapshot: interpreter.snapshot(),
+        }
+        .save(path)?;
+    }
+    if let Some(path) = &args.dump_stack_on_exit {
+        args.stack_format
+            .write(interpreter.stack(), io::BufWriter::new(File::create(path)?))?;
+    }
+    Ok(())
+}
+
+/// Print the performance, stack and hotspot statistics that were asked for.
+fn print_statistics(
+    args: &Arguments,
+    interpreter: &Interpreter,
+    time: Duration,
+    ip_usage: Option<Vec<IpUsage>>,
+) {
+    if args.show_performance {
+        let steps = interpreter.steps().max(1);
+        let time_per_step = time.div_f64(steps as f64);
+        println!();
+        println!(
+            "execution took {:?}, {} steps, {:?} / step, {:.3} Msteps/s",
+            time,
+            interpreter.steps(),
+            time_per_step,
+            steps as f64 / time.as_secs_f64().max(f64::EPSILON) / 1_000_000.0
+        );
+        // concurrent programs also show which instruction pointer took the steps and the time
+        if let Some(usage) = ip_usage.filter(|usage| usage.len() > 1) {
+            for ip in usage {
+                println!(
+                    "  ip {}: {} steps ({:.1}%), {:?}",
+                    ip.id,
+                    ip.steps,
+                    ip.steps as f64 * 100.0 / steps as f64,
+                    ip.time
+                );
+            }
+        }
+    }
+    if let Some(peak) = interpreter.peak_stack_depth() {
+        eprintln!(
+            "peak stack depth {}, stack capacity {}",
+            peak,
+            interpreter.stack_capacity()
+        );
+    }
+    if let Some(hotspots) = interpreter.write_hotspots() {
+        eprint!("{}", hotspots);
+    }
+}
@@ -0,0 +1,182 @@
+//! The daemon, which runs programs sent to it over a Unix socket in one long-lived process, for services that run
+//! many short programs and would otherwise start boxfunge for every one of them.
+//!
+//! A client sends any number of requests over a connection and gets one response per request, in order.
+//! Connections are served one after another, so the requests of all clients form a single queue. A request
+//! consists of `key value` lines and ends with a `run` line. `program` and `input` take a byte count, and that
+//! many bytes follow the line, followed by a newline:
+//!
+//! ```text
+//! program 6
+//! &2*.@
+//!
+//! input 3
+//! 21
+//!
+//! max_steps 1000
+//! run
+//! ```
+//!
+//! The other keys are `standard` (93, 96, 97, 98 or trefunge), `seed`, `max_steps`, `timeout` (in milliseconds) and
+//! `max_output` (in bytes). Limits not given are those the daemon was started with. The response has the same form
+//! and ends with an `end` line:
+//!
+//! ```text
+//! exit finished
+//! message terminated via @
+//! steps 5
+//! duration_us 14
+//! output 3
+//! 42
+//! end
+//! ```
+//!
+//! An invalid request is answered with an `error` line and an `end` line instead, and the connection is closed.
+
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use argh::FromArgValue;
+
+use crate::run;
+use crate::Error;
+use crate::LanguageStandard;
+use crate::RunRequest;
+
+/// Largest program or input a request may contain.
+pub const MAX_REQUEST_BYTES: usize = 64 << 20;
+
+/// Read the next request, starting from the defaults. Returns `None` at the end of the input.
+fn read_request(
+    input: &mut impl BufRead,
+    defaults: &RunRequest,
+) -> io::Result<Option<Result<RunRequest, String>>> {
+    let mut request = defaults.clone();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("invalid value \"{}\" for {}", value, key))
+        };
+        match key {
+            "" => {}
+            "run" => return Ok(Some(Ok(request))),
+            "program" | "input" => {
+                let length = match number() {
+                    Ok(length) if length as usize <= MAX_REQUEST_BYTES => length as usize,
+                    Ok(_) => return Ok(Some(Err(format!("{} is too large", key)))),
+                    Err(why) => return Ok(Some(Err(why))),
+                };
+                let mut bytes = vec![0; length];
+                input.read_exact(&mut bytes)?;
+                let mut newline = [0];
+                input.read_exact(&mut newline)?;
+                if newline != *b"\n" {
+                    return Ok(Some(Err(format!(
+                        "{} is longer than {} bytes",
+                        key, length
+                    ))));
+                }
+                if key == "input" {
+                    request.input = bytes;
+                } else {
+                    match String::from_utf8(bytes) {
+                        Ok(program) => request.program = program,
+                        Err(_) => return Ok(Some(Err("program is not UTF-8".to_string()))),
+                    }
+                }
+            }
+            "standard" => match LanguageStandard::from_arg_value(value) {
+                Ok(standard) => request.standard = Some(standard),
+                Err(why) => return Ok(Some(Err(why))),
+            },
+            "seed" | "max_steps" | "timeout" | "max_output" => {
+                let number = match number() {
+                    Ok(number) => number,
+                    Err(why) => return Ok(Some(Err(why))),
+                };
+                match key {
+                    "seed" => request.seed = Some(number),
+                    "max_steps" => request.max_steps = Some(number as usize),
+                    "timeout" => request.timeout = Some(Duration::from_millis(number)),
+                    _ => request.max_output = Some(number as usize),
+                }
+            }
+            _ => return Ok(Some(Err(format!("unknown key \"{}\"", key)))),
+        }
+    }
+}
+
+/// Answer the requests of one connection until it ends or sends an invalid request.
+pub fn serve_requests(
+    mut input: impl BufRead,
+    mut output: impl Write,
+    defaults: &RunRequest,
+) -> io::Result<()> {
+    while let Some(request) = read_request(&mut input, defaults)? {
+        let request = match request {
+            Ok(request) => request,
+            Err(why) => {
+                writeln!(output, "error {}\nend", why)?;
+                return output.flush();
+            }
+        };
+        let report = run(request);
+        // the message must stay on its line
+        let message = report.exit.to_string().replace('\n', " ");
+        writeln!(output, "exit {}", report.exit.name())?;
+        writeln!(output, "message {}", message)?;
+        writeln!(output, "steps {}", report.steps)?;
+        writeln!(output, "duration_us {}", report.duration.as_micros())?;
+        writeln!(output, "output {}", report.output.len())?;
+        output.write_all(&report.output)?;
+        writeln!(output, "\nend")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Listen on the Unix socket at the path and answer requests until the process is killed.
+/// A socket left over at the path by an earlier daemon is replaced.
+#[cfg(unix)]
+pub fn run_daemon(socket: &Path, defaults: &RunRequest) -> Result<(), Error> {
+    use std::io::BufReader;
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    if std::fs::symlink_metadata(socket).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        std::fs::remove_file(socket)?;
+    }
+    let listener = UnixListener::bind(socket)?;
+    eprintln!("listening on {}", socket.display());
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let result = stream
+            .try_clone()
+            .and_then(|reader| serve_requests(BufReader::new(reader), stream, defaults));
+        // a client that goes away must not take the daemon down
+        if let Err(why) = result {
+            eprintln!("connection failed: {}", why);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn run_daemon(_socket: &Path, _defaults: &RunRequest) -> Result<(), Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the daemon needs Unix sockets, which this platform doesn't have",
+    )
+    .into())
+}
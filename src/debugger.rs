@@ -0,0 +1,234 @@
+//! The post-mortem debugger, a REPL for inspecting the state of a program at the moment it failed.
+//!
+//! Concurrent programs have several instruction pointers. The debugger starts out with the one that failed selected,
+//! and `thread ID` selects another one, whose program counter and stacks `where`, `info stack`, `print` and `list`
+//! then show.
+//!
+//! Commands can also come from a script, which runs before the interactive input, and every command can be logged to
+//! a file that works as a script itself, so that a debugging session can be repeated. Lines starting with `#` are
+//! comments.
+
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::report::ip_style;
+use crate::Annotations;
+use crate::Error;
+use crate::Executer;
+use crate::Expression;
+use crate::Int;
+use crate::Interpreter;
+use crate::IpView;
+use crate::Position;
+
+/// Rows shown above and below the program counter by `list`.
+const LIST_RADIUS: i64 = 5;
+/// Columns shown to the left and right of the program counter by `list`.
+const LIST_WIDTH: i64 = 30;
+
+const HELP: &str = "\
+where              where and why the program stopped
+info stack         the stack, top last, and the stacks below it
+info cell X Y [Z]  the value of a cell and its annotations
+info threads       all instruction pointers, the selected one marked with *
+thread ID          select the instruction pointer with the given ID
+print EXPRESSION   the value of an expression, e.g. \"stack[0] + stack[1]\" or \"cell(10, 3)\"
+list               the playfield around the program counter
+help               this list
+quit               leave the debugger";
+
+/// Read commands from the script, if there is one, and then from the input, and answer them on the output until `quit`
+/// or the end of the input. Commands from the script are echoed after the prompt, so that the output reads like an
+/// interactive session. Every command is appended to the log, if there is one.
+/// The interpreter is only inspected, so its state stays as it was when the program stopped.
+/// Commands refer to the selected instruction pointer, which is the current one until `thread` selects another.
+pub fn post_mortem(
+    interpreter: &Interpreter,
+    error: &Error,
+    annotations: &Annotations,
+    mut script: Option<&mut dyn BufRead>,
+    mut input: impl BufRead,
+    mut output: impl Write,
+    mut log: Option<&mut dyn Write>,
+) -> io::Result<()> {
+    writeln!(
+        output,
+        "post-mortem: {}, type \"help\" for a list of commands",
+        error
+    )?;
+    if let Some(log) = &mut log {
+        writeln!(log, "# post-mortem: {}", error)?;
+        log.flush()?;
+    }
+    let mut selected = interpreter.ip_id();
+    loop {
+        // the selected instruction pointer always exists, since the interpreter doesn't change
+        let ip = interpreter
+            .ips()
+            .find(|ip| ip.id == selected)
+            .unwrap_or_else(|| interpreter.current_ip());
+        write!(output, "(boxfunge) ")?;
+        output.flush()?;
+        let mut line = String::new();
+        let mut scripted = false;
+        if let Some(commands) = &mut script {
+            scripted = commands.read_line(&mut line)? > 0;
+            if scripted {
+                write!(output, "{}", line)?;
+                if !line.ends_with('\n') {
+                    writeln!(output)?;
+                }
+            } else {
+                // the script is done, the interactive input takes over
+                script = None;
+            }
+        }
+        if !scripted && input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(());
+        }
+        let line = line.trim();
+        if let Some(log) = &mut log {
+            if !line.is_empty() {
+                writeln!(log, "{}", line)?;
+                log.flush()?;
+            }
+        }
+        let (command, arguments) = line.split_once(' ').unwrap_or((line, ""));
+        match (command, arguments.trim()) {
+            ("", _) => {}
+            (comment, _) if comment.starts_with('#') => {}
+            ("quit" | "q", _) => return Ok(()),
+            ("help" | "h", _) => writeln!(output, "{}", HELP)?,
+            ("where" | "w", _) if ip.id == interpreter.ip_id() => {
+                let program_counter = interpreter.program_counter();
+                writeln!(
+                    output,
+                    "stopped at {} moving {} after {} steps: {}",
+                    annotations.locate(program_counter.position),
+                    program_counter.delta,
+                    interpreter.steps(),
+                    error
+                )?;
+            }
+            ("where" | "w", _) => writeln!(
+                output,
+                "thread {} waits at {} moving {}",
+                ip.id,
+                annotations.locate(ip.program_counter.position),
+                ip.program_counter.delta
+            )?,
+            ("info" | "i", "stack") => {
+                writeln!(output, "stack: {:?}", ip.stack)?;
+                for (index, stack) in ip.stack_stack.iter().rev().enumerate() {
+                    writeln!(output, "stack {} below: {:?}", index + 1, stack)?;
+                }
+            }
+            ("info" | "i", "threads") => {
+                for other in interpreter.ips() {
+                    writeln!(
+                        output,
+                        "{} thread {} at {} moving {}, stack depth {}",
+                        if other.id == ip.id { '*' } else { ' ' },
+                        other.id,
+                        annotations.locate(other.program_counter.position),
+                        other.program_counter.delta,
+                        other.stack.len()
+                    )?;
+                }
+            }
+            ("thread" | "t", "") => writeln!(output, "thread {} is selected", ip.id)?,
+            ("thread" | "t", id) => match id.parse::<Int>() {
+                Ok(id) if interpreter.ips().any(|ip| ip.id == id) => {
+                    selected = id;
+                    writeln!(output, "thread {} selected", id)?;
+                }
+                _ => writeln!(output, "no thread {}, see \"info threads\"", id)?,
+            },
+            ("info" | "i", arguments) if arguments.starts_with("cell") => {
+                let coordinates = arguments["cell".len()..]
+                    .split_whitespace()
+                    .map(str::parse::<Int>)
+                    .collect::<Result<Vec<_>, _>>();
+                let position = match coordinates.as_deref() {
+                    // Int is not an i64 on every platform
+                    #[allow(clippy::unnecessary_cast)]
+                    Ok(&[x, y]) => Some(Position::new(x as i64, y as i64, 0)),
+                    #[allow(clippy::unnecessary_cast)]
+                    Ok(&[x, y, z]) => Some(Position::new(x as i64, y as i64, z as i64)),
+                    _ => None,
+                };
+                match position {
+                    Some(position) => {
+                        let value = interpreter.read_cell(position);
+                        match u8::try_from(value) {
+                            Ok(character) if character.is_ascii_graphic() => {
+                                writeln!(output, "{} '{}'", value, character as char)?
+                            }
+                            _ => writeln!(output, "{}", value)?,
+                        }
+                        for annotation in annotations.at(position) {
+                            writeln!(output, "  # {}", annotation)?;
+                        }
+                    }
+                    None => writeln!(output, "usage: info cell X Y [Z]")?,
+                }
+            }
+            ("print" | "p", expression) => match Expression::parse(expression) {
+                Ok(expression) => {
+                    writeln!(output, "{}", expression.evaluate_for(interpreter, &ip))?
+                }
+                Err(why) => writeln!(output, "invalid expression: {}", why)?,
+            },
+            ("list" | "l", _) => list(interpreter, &ip, &mut output)?,
+            _ => writeln!(
+                output,
+                "unknown command \"{}\", type \"help\" for a list of commands",
+                line
+            )?,
+        }
+    }
+}
+
+/// Print the playfield around the selected program counter, with the cells of all program counters highlighted in
+/// their colors and the selected one underlined.
+fn list(interpreter: &Interpreter, selected: &IpView, output: &mut impl Write) -> io::Result<()> {
+    let ips = interpreter.ips().collect::<Vec<_>>();
+    let concurrent = ips.len() > 1;
+    let position = selected.program_counter.position;
+    let bounds = interpreter.grid().bounds();
+    let rows = position.y.saturating_sub(LIST_RADIUS).max(bounds.min.y)
+        ..=position.y.saturating_add(LIST_RADIUS).min(bounds.max.y);
+    let columns = position.x.saturating_sub(LIST_WIDTH).max(bounds.min.x)
+        ..=position.x.saturating_add(LIST_WIDTH).min(bounds.max.x);
+    for y in rows {
+        write!(output, "{:>4} ", y)?;
+        for x in columns.clone() {
+            // Int is not an i64 on every platform
+            #[allow(clippy::unnecessary_cast)]
+            let cell = interpreter.grid().get(x as Int, y as Int).unwrap_or(b' ');
+            let cell = if cell.is_ascii_graphic() {
+                cell as char
+            } else {
+                ' '
+            };
+            // the selected instruction pointer wins over others on the same cell
+            let here = |ip: &&IpView| ip.program_counter.position == Position::new(x, y, 0);
+            match Some(selected)
+                .filter(here)
+                .or_else(|| ips.iter().find(here))
+            {
+                Some(ip) => write!(
+                    output,
+                    "{}{}\x1b[0m",
+                    ip_style(ip.id, ip.id == selected.id, concurrent),
+                    cell
+                )?,
+                None => write!(output, "{}", cell)?,
+            }
+        }
+        writeln!(output)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Translation of program output for tools with other expectations about line endings and encodings.
+
+use std::io;
+use std::io::Write;
+
+use argh::FromArgValue;
+
+/// Line ending written for a `\n` output by the program.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Newline {
+    /// Write `\n` unchanged.
+    #[default]
+    Lf,
+    /// Write `\r\n`.
+    Crlf,
+    /// Write the platform's line ending: `\r\n` on Windows, `\n` elsewhere.
+    Native,
+}
+
+impl FromArgValue for Newline {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "lf" => Self::Lf,
+            "crlf" => Self::Crlf,
+            "native" => Self::Native,
+            _ => {
+                return Err(
+                    "unknown newline mode, possible values are [lf, crlf, native]".to_string(),
+                )
+            }
+        })
+    }
+}
+
+/// Encoding of the output stream. Programs only output ASCII, so this only matters for tools expecting UTF-16.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OutputEncoding {
+    /// ASCII, which is also valid UTF-8 and Latin-1.
+    #[default]
+    Ascii,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl FromArgValue for OutputEncoding {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "ascii" | "utf-8" => Self::Ascii,
+            "utf-16le" => Self::Utf16Le,
+            "utf-16be" => Self::Utf16Be,
+            _ => return Err(
+                "unknown output encoding, possible values are [ascii, utf-8, utf-16le, utf-16be]"
+                    .to_string(),
+            ),
+        })
+    }
+}
+
+/// Output wrapper that translates line endings and encodes the output.
+pub struct EncodeOutput<W> {
+    output: W,
+    crlf: bool,
+    encoding: OutputEncoding,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncodeOutput<W> {
+    pub fn new(output: W, newline: Newline, encoding: OutputEncoding) -> Self {
+        Self {
+            output,
+            crlf: newline == Newline::Crlf || (newline == Newline::Native && cfg!(windows)),
+            encoding,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for EncodeOutput<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.clear();
+        for &byte in buf {
+            if byte == b'\n' && self.crlf {
+                self.encode(b'\r');
+            }
+            self.encode(byte);
+        }
+        self.output.write_all(&self.buffer)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+impl<W> EncodeOutput<W> {
+    fn encode(&mut self, byte: u8) {
+        match self.encoding {
+            OutputEncoding::Ascii => self.buffer.push(byte),
+            OutputEncoding::Utf16Le => self.buffer.extend([byte, 0]),
+            OutputEncoding::Utf16Be => self.buffer.extend([0, byte]),
+        }
+    }
+}
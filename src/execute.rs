@@ -0,0 +1,74 @@
+//! The Befunge-98 `=` instruction, which runs a command and pushes its exit status.
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+
+use argh::FromArgValue;
+
+use crate::Int;
+
+/// How `=` runs commands, which `y` reports as the operating paradigm. Either way, `=` only runs commands if the
+/// sandbox allows it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ExecParadigm {
+    /// `=` reflects.
+    Unavailable,
+    /// Like C's `system()`: with `sh -c`, or `cmd /C` on Windows.
+    #[default]
+    System,
+    /// With the given shell, which gets the command after `-c`.
+    Shell(PathBuf),
+}
+
+impl ExecParadigm {
+    /// The operating paradigm as `y` reports it.
+    pub fn paradigm(&self) -> Int {
+        match self {
+            Self::Unavailable => 0,
+            Self::System => 1,
+            Self::Shell(_) => 2,
+        }
+    }
+
+    /// Run the command and return its exit status and standard output, or `None` if it can't be run.
+    /// The command gets no input and shares boxfunge's stderr.
+    pub fn run(&self, command: &str) -> Option<(Int, Vec<u8>)> {
+        let mut process = match self {
+            Self::Unavailable => return None,
+            Self::System if cfg!(windows) => {
+                let mut process = Command::new("cmd");
+                process.arg("/C");
+                process
+            }
+            Self::System => {
+                let mut process = Command::new("sh");
+                process.arg("-c");
+                process
+            }
+            Self::Shell(shell) => {
+                let mut process = Command::new(shell);
+                process.arg("-c");
+                process
+            }
+        };
+        let output = process
+            .arg(command)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .ok()?;
+        // a command killed by a signal has no exit status
+        Some((output.status.code().unwrap_or(-1) as Int, output.stdout))
+    }
+}
+
+impl FromArgValue for ExecParadigm {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "unavailable" => Self::Unavailable,
+            "system" => Self::System,
+            _ => Self::Shell(value.into()),
+        })
+    }
+}
@@ -0,0 +1,340 @@
+//! A small expression language over the interpreter's state, used by watch expressions and breakpoint conditions,
+//! e.g. `stack[0] + stack[1]` or `stack.len() > 100 && cell(5, 5) == 64`.
+//!
+//! Expressions consist of numbers, `stack[i]` (the i-th value from the top, 0 being the top), `depth` or
+//! `stack.len()` (the number of values on the stack), `cell(x, y)` (the value of a cell), `x` and `y` (the position
+//! of the program counter), the arithmetic operators `+`, `-`, `*`, `/` and `%`, the comparisons `==`, `!=`, `<`,
+//! `<=`, `>` and `>=`, the logical operators `&&`, `||` and `!`, and parentheses. Like the instructions, they read 0
+//! from an empty stack and divide by zero to 0. Comparisons and logical operators result in 1 for true and 0 for
+//! false, and any value except 0 is true.
+
+use std::fmt::Display;
+
+use argh::FromArgValue;
+
+use crate::Int;
+use crate::Interpreter;
+use crate::IpView;
+use crate::Remainder;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Node {
+    Number(Int),
+    Stack(Box<Node>),
+    Depth,
+    Cell(Box<Node>, Box<Node>),
+    X,
+    Y,
+    Negate(Box<Node>),
+    Not(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn evaluate(&self, interpreter: &Interpreter, ip: &IpView) -> Int {
+        match self {
+            Self::Number(number) => *number,
+            Self::Stack(index) => {
+                let stack = ip.stack;
+                usize::try_from(index.evaluate(interpreter, ip))
+                    .ok()
+                    .and_then(|index| stack.len().checked_sub(index.checked_add(1)?))
+                    .map_or(0, |index| stack[index])
+            }
+            Self::Depth => ip.stack.len() as Int,
+            Self::Cell(x, y) => interpreter
+                .grid()
+                .get_value(x.evaluate(interpreter, ip), y.evaluate(interpreter, ip))
+                .unwrap_or_default(),
+            // Int is not an i64 on every platform
+            #[allow(clippy::unnecessary_cast)]
+            Self::X => ip.program_counter.position.x as Int,
+            #[allow(clippy::unnecessary_cast)]
+            Self::Y => ip.program_counter.position.y as Int,
+            Self::Negate(value) => value.evaluate(interpreter, ip).wrapping_neg(),
+            Self::Not(value) => (value.evaluate(interpreter, ip) == 0).into(),
+            Self::Binary(operator, a, b) => {
+                let (a, b) = (a.evaluate(interpreter, ip), b.evaluate(interpreter, ip));
+                match operator {
+                    Operator::Add => a.wrapping_add(b),
+                    Operator::Subtract => a.wrapping_sub(b),
+                    Operator::Multiply => a.wrapping_mul(b),
+                    Operator::Divide => a.checked_div(b).unwrap_or_default(),
+                    Operator::Remainder => Remainder::Truncated.of(a, b),
+                    Operator::Equal => (a == b).into(),
+                    Operator::NotEqual => (a != b).into(),
+                    Operator::Less => (a < b).into(),
+                    Operator::LessOrEqual => (a <= b).into(),
+                    Operator::Greater => (a > b).into(),
+                    Operator::GreaterOrEqual => (a >= b).into(),
+                }
+            }
+            Self::And(a, b) => {
+                (a.evaluate(interpreter, ip) != 0 && b.evaluate(interpreter, ip) != 0).into()
+            }
+            Self::Or(a, b) => {
+                (a.evaluate(interpreter, ip) != 0 || b.evaluate(interpreter, ip) != 0).into()
+            }
+        }
+    }
+}
+
+/// Recursive descent parser over the bytes of an expression, with the usual precedence of the operators.
+struct Parser<'a> {
+    source: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn skip_spaces(&mut self) {
+        while self
+            .source
+            .get(self.position)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_spaces();
+        self.source.get(self.position).copied()
+    }
+
+    /// Consume the token if it comes next.
+    fn accept(&mut self, token: &str) -> bool {
+        self.skip_spaces();
+        let found = self.source[self.position..].starts_with(token.as_bytes());
+        if found {
+            self.position += token.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        if self.accept(expected) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(byte) => Err(format!(
+                "expected '{}' at column {}, found '{}'",
+                expected,
+                self.position + 1,
+                byte as char
+            )),
+            None => Err(format!("expected '{}' at the end", expected)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Node, String> {
+        let mut node = self.and()?;
+        while self.accept("||") {
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        let mut node = self.comparison()?;
+        while self.accept("&&") {
+            node = Node::And(Box::new(node), Box::new(self.comparison()?));
+        }
+        Ok(node)
+    }
+
+    fn comparison(&mut self) -> Result<Node, String> {
+        let mut node = self.sum()?;
+        // the two-character operators have to be tried first, so that "<=" is not taken for "<"
+        const COMPARISONS: [(&str, Operator); 6] = [
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            ("<=", Operator::LessOrEqual),
+            (">=", Operator::GreaterOrEqual),
+            ("<", Operator::Less),
+            (">", Operator::Greater),
+        ];
+        while let Some(&(_, operator)) = COMPARISONS.iter().find(|(token, _)| self.accept(token)) {
+            node = Node::Binary(operator, Box::new(node), Box::new(self.sum()?));
+        }
+        Ok(node)
+    }
+
+    fn sum(&mut self) -> Result<Node, String> {
+        let mut node = self.product()?;
+        loop {
+            let operator = match self.peek() {
+                Some(b'+') => Operator::Add,
+                Some(b'-') => Operator::Subtract,
+                _ => return Ok(node),
+            };
+            self.position += 1;
+            node = Node::Binary(operator, Box::new(node), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Node, String> {
+        let mut node = self.factor()?;
+        loop {
+            let operator = match self.peek() {
+                Some(b'*') => Operator::Multiply,
+                Some(b'/') => Operator::Divide,
+                Some(b'%') => Operator::Remainder,
+                _ => return Ok(node),
+            };
+            self.position += 1;
+            node = Node::Binary(operator, Box::new(node), Box::new(self.factor()?));
+        }
+    }
+
+    fn factor(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some(b'-') => {
+                self.position += 1;
+                Ok(Node::Negate(Box::new(self.factor()?)))
+            }
+            Some(b'!') => {
+                self.position += 1;
+                Ok(Node::Not(Box::new(self.factor()?)))
+            }
+            Some(b'(') => {
+                self.position += 1;
+                let node = self.or()?;
+                self.expect(")")?;
+                Ok(node)
+            }
+            Some(b'0'..=b'9') => {
+                let start = self.position;
+                while self
+                    .source
+                    .get(self.position)
+                    .is_some_and(u8::is_ascii_digit)
+                {
+                    self.position += 1;
+                }
+                let digits = String::from_utf8_lossy(&self.source[start..self.position]);
+                digits
+                    .parse()
+                    .map(Node::Number)
+                    .map_err(|_| format!("{} is too large", digits))
+            }
+            Some(byte) if byte.is_ascii_alphabetic() => {
+                let start = self.position;
+                while self
+                    .source
+                    .get(self.position)
+                    .is_some_and(u8::is_ascii_alphanumeric)
+                {
+                    self.position += 1;
+                }
+                match &self.source[start..self.position] {
+                    b"stack" if self.accept(".") => {
+                        self.expect("len")?;
+                        self.expect("(")?;
+                        self.expect(")")?;
+                        Ok(Node::Depth)
+                    }
+                    b"stack" => {
+                        self.expect("[")?;
+                        let index = self.or()?;
+                        self.expect("]")?;
+                        Ok(Node::Stack(Box::new(index)))
+                    }
+                    b"cell" => {
+                        self.expect("(")?;
+                        let x = self.or()?;
+                        self.expect(",")?;
+                        let y = self.or()?;
+                        self.expect(")")?;
+                        Ok(Node::Cell(Box::new(x), Box::new(y)))
+                    }
+                    b"depth" => Ok(Node::Depth),
+                    b"x" => Ok(Node::X),
+                    b"y" => Ok(Node::Y),
+                    name => Err(format!(
+                        "unknown name '{}', possible names are [stack, cell, depth, x, y]",
+                        String::from_utf8_lossy(name)
+                    )),
+                }
+            }
+            Some(byte) => Err(format!(
+                "unexpected '{}' at column {}",
+                byte as char,
+                self.position + 1
+            )),
+            None => Err("unexpected end of the expression".to_string()),
+        }
+    }
+}
+
+/// An expression, parsed once so that evaluating it is cheap enough to do on every step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expression {
+    source: String,
+    node: Node,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            source: source.as_bytes(),
+            position: 0,
+        };
+        let node = parser.or()?;
+        if let Some(byte) = parser.peek() {
+            return Err(format!(
+                "unexpected '{}' at column {}",
+                byte as char,
+                parser.position + 1
+            ));
+        }
+        Ok(Self {
+            source: source.trim().to_string(),
+            node,
+        })
+    }
+
+    /// The value of the expression in the interpreter's current state.
+    pub fn evaluate(&self, interpreter: &Interpreter) -> Int {
+        self.evaluate_for(interpreter, &interpreter.current_ip())
+    }
+
+    /// The value of the expression for one of the interpreter's instruction pointers, whose stack and position
+    /// `stack[i]`, `x` and `y` refer to.
+    pub fn evaluate_for(&self, interpreter: &Interpreter, ip: &IpView) -> Int {
+        self.node.evaluate(interpreter, ip)
+    }
+
+    /// Whether the expression is true, i.e. not 0, in the interpreter's current state.
+    pub fn holds(&self, interpreter: &Interpreter) -> bool {
+        self.evaluate(interpreter) != 0
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromArgValue for Expression {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Self::parse(value)
+    }
+}
@@ -0,0 +1,130 @@
+//! Files that programs open, e.g. with the FILE fingerprint. They are shared by all instruction pointers of an
+//! interpreter and closed when it is dropped.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+
+use crate::Int;
+use crate::Position;
+
+/// How a file is opened, numbered like the modes of the FILE fingerprint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileMode {
+    /// `r`: read an existing file.
+    Read,
+    /// `w`: write a new or truncated file.
+    Write,
+    /// `a`: write to the end of a new or existing file.
+    Append,
+    /// `r+`: read and write an existing file.
+    ReadWrite,
+    /// `w+`: read and write a new or truncated file.
+    ReadWriteTruncate,
+    /// `a+`: read a new or existing file and write to its end.
+    ReadAppend,
+}
+
+impl FileMode {
+    pub fn from_int(mode: Int) -> Option<Self> {
+        Some(match mode {
+            0 => Self::Read,
+            1 => Self::Write,
+            2 => Self::Append,
+            3 => Self::ReadWrite,
+            4 => Self::ReadWriteTruncate,
+            5 => Self::ReadAppend,
+            _ => return None,
+        })
+    }
+
+    fn options(self) -> OpenOptions {
+        let mut options = OpenOptions::new();
+        match self {
+            Self::Read => options.read(true),
+            Self::Write => options.write(true).create(true).truncate(true),
+            Self::Append => options.append(true).create(true),
+            Self::ReadWrite => options.read(true).write(true),
+            Self::ReadWriteTruncate => options.read(true).write(true).create(true).truncate(true),
+            Self::ReadAppend => options.read(true).append(true).create(true),
+        };
+        options
+    }
+}
+
+/// A file a program opened.
+#[derive(Debug)]
+pub struct OpenFile {
+    file: File,
+    /// Where the program reads and writes blocks of the file in the playfield.
+    pub buffer: Position,
+}
+
+impl OpenFile {
+    /// Read a line including its newline, or up to the end of the file. Returns an empty line at the end.
+    pub fn read_line(&mut self) -> io::Result<Vec<u8>> {
+        let mut line = Vec::new();
+        let mut byte = [0];
+        while self.file.read(&mut byte)? == 1 {
+            line.push(byte[0]);
+            if byte[0] == b'\n' {
+                break;
+            }
+        }
+        Ok(line)
+    }
+
+    /// Read up to the given number of bytes, fewer at the end of the file.
+    pub fn read(&mut self, count: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        (&mut self.file)
+            .take(count as u64)
+            .read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.file.write_all(bytes)
+    }
+
+    pub fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.file.seek(position)
+    }
+
+    pub fn position(&mut self) -> io::Result<u64> {
+        self.file.stream_position()
+    }
+}
+
+/// The files a program has open, by handle.
+#[derive(Debug, Default)]
+pub struct FileTable {
+    files: BTreeMap<Int, OpenFile>,
+    next_handle: Int,
+}
+
+impl FileTable {
+    /// Open a file and return its handle.
+    pub fn open(&mut self, path: &Path, mode: FileMode, buffer: Position) -> io::Result<Int> {
+        let file = mode.options().open(path)?;
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.files.insert(handle, OpenFile { file, buffer });
+        Ok(handle)
+    }
+
+    pub fn get_mut(&mut self, handle: Int) -> Option<&mut OpenFile> {
+        self.files.get_mut(&handle)
+    }
+
+    /// Close a file, returning whether the handle was open.
+    pub fn close(&mut self, handle: Int) -> bool {
+        self.files.remove(&handle).is_some()
+    }
+}
@@ -0,0 +1,58 @@
+//! Befunge-98 fingerprints, which give the instructions `A` to `Z` a meaning once a program loads them with `(`.
+//!
+//! Each fingerprint is implemented in its own module and listed in [`FINGERPRINTS`].
+
+mod file;
+mod float;
+mod long;
+mod modu;
+mod null;
+mod orth;
+mod outs;
+mod roma;
+mod toys;
+
+pub use file::FileIo;
+pub use float::Fpdp;
+pub use float::Fpsp;
+pub use long::Long;
+pub use modu::Modu;
+pub use null::Null;
+pub use orth::Orth;
+pub use outs::Outs;
+pub use roma::Roma;
+pub use toys::Toys;
+
+use crate::Error;
+use crate::Int;
+use crate::Interpreter;
+use crate::Sandbox;
+
+/// A set of instructions among `A` to `Z` that programs can load with `(` and unload with `)`.
+pub trait Fingerprint: Sync {
+    /// The fingerprint's name, whose characters make up its ID, see [`fingerprint_id`].
+    fn name(&self) -> &'static str;
+    /// The instructions the fingerprint defines.
+    fn instructions(&self) -> &'static [u8];
+    /// Execute one of the fingerprint's instructions. The program counter moves on afterwards.
+    fn execute(&self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error>;
+    /// Whether programs may load the fingerprint under the given sandbox; loading it reflects otherwise.
+    /// Fingerprints that reach outside the interpreter check the corresponding permission.
+    fn permitted(&self, _sandbox: &Sandbox) -> bool {
+        true
+    }
+}
+
+/// Fingerprints boxfunge implements and makes available by default. [`FileIo`] and [`Outs`] have to be enabled explicitly.
+pub static FINGERPRINTS: &[&dyn Fingerprint] =
+    &[&Fpdp, &Fpsp, &Long, &Modu, &Null, &Orth, &Roma, &Toys];
+
+/// For every instruction `A` to `Z`, the IDs of the loaded fingerprints that define it, the one in effect last.
+pub type Semantics = [Vec<Int>; 26];
+
+/// The ID of a fingerprint name, which is its characters read as a big-endian number.
+pub fn fingerprint_id(name: &str) -> Int {
+    name.bytes().fold(0, |id: Int, character| {
+        id.wrapping_shl(8).wrapping_add(character as Int)
+    })
+}
@@ -0,0 +1,160 @@
+//! FILE: opening, reading and writing files.
+//!
+//! A program opens a file with a buffer vector, relative to the storage offset, which `R` reads the file into and
+//! `W` writes it from, along a row of cells. All instructions but `O`, `C` and `D` leave the handle on the stack.
+//! They reflect if a file operation fails or the handle is not open.
+//!
+//! Programs can only load the fingerprint if the sandbox allows filesystem access, and the interpreter has to be
+//! given it with [`Interpreter::with_fingerprint`], e.g. by the `--file-io` switch.
+
+use std::io::SeekFrom;
+use std::path::Path;
+
+use crate::fingerprint::toys::MAX_BLOCK_CELLS;
+use crate::Error;
+use crate::FileMode;
+use crate::Fingerprint;
+use crate::FromStack;
+use crate::Int;
+use crate::Interpreter;
+use crate::Position;
+use crate::Sandbox;
+
+pub struct FileIo;
+
+/// Push bytes as a zero-terminated string with the first byte on top.
+fn push_bytes(interpreter: &mut Interpreter, bytes: &[u8]) {
+    interpreter.push(0);
+    for &byte in bytes.iter().rev() {
+        interpreter.push(byte.into());
+    }
+}
+
+/// Pop the number of bytes `R` or `W` transfer, or `None` if it is negative or too large.
+fn pop_count(interpreter: &mut Interpreter) -> Option<usize> {
+    usize::try_from(interpreter.pop())
+        .ok()
+        .filter(|&count| count <= MAX_BLOCK_CELLS)
+}
+
+/// Run an instruction, returning `None` if it fails and has to reflect.
+fn try_execute(instruction: u8, interpreter: &mut Interpreter) -> Option<Result<(), Error>> {
+    match instruction {
+        b'O' => {
+            let path = String::from_stack(interpreter.stack_mut());
+            let mode = FileMode::from_int(interpreter.pop());
+            let buffer = interpreter
+                .pop_vector()
+                .wrapping_add(interpreter.storage_offset());
+            let handle = interpreter
+                .files_mut()
+                .open(Path::new(&path), mode?, buffer)
+                .ok()?;
+            interpreter.push(handle);
+        }
+        b'C' => {
+            let handle = interpreter.pop();
+            interpreter.files_mut().close(handle).then_some(())?;
+        }
+        b'D' => {
+            let path = String::from_stack(interpreter.stack_mut());
+            std::fs::remove_file(path).ok()?;
+        }
+        b'G' => {
+            let handle = interpreter.pop();
+            let line = interpreter.files_mut().get_mut(handle)?.read_line().ok()?;
+            interpreter.push(handle);
+            push_bytes(interpreter, &line);
+            interpreter.push(line.len() as Int);
+        }
+        b'L' => {
+            let handle = interpreter.pop();
+            let position = interpreter.files_mut().get_mut(handle)?.position().ok()?;
+            interpreter.push(handle);
+            interpreter.push(Int::try_from(position).ok()?);
+        }
+        b'P' => {
+            let string = String::from_stack(interpreter.stack_mut());
+            let handle = interpreter.pop();
+            let file = interpreter.files_mut().get_mut(handle)?;
+            file.write(string.as_bytes()).ok()?;
+            interpreter.push(handle);
+        }
+        b'R' => {
+            let count = pop_count(interpreter);
+            let handle = interpreter.pop();
+            let file = interpreter.files_mut().get_mut(handle)?;
+            let buffer = file.buffer;
+            let bytes = file.read(count?).ok()?;
+            for (offset, byte) in bytes.into_iter().enumerate() {
+                let position = buffer.wrapping_add(Position::new(offset as i64, 0, 0));
+                if let Err(why) = interpreter.write_cell(position, byte.into()) {
+                    return Some(Err(why));
+                }
+            }
+            interpreter.push(handle);
+        }
+        b'S' => {
+            let offset = interpreter.pop();
+            let whence = interpreter.pop();
+            let handle = interpreter.pop();
+            // Int is not an i64 on every platform
+            #[allow(clippy::unnecessary_cast)]
+            let position = match whence {
+                0 => SeekFrom::Start(u64::try_from(offset).ok()?),
+                1 => SeekFrom::Current(offset as i64),
+                2 => SeekFrom::End(offset as i64),
+                _ => return None,
+            };
+            interpreter
+                .files_mut()
+                .get_mut(handle)?
+                .seek(position)
+                .ok()?;
+            interpreter.push(handle);
+        }
+        b'W' => {
+            let count = pop_count(interpreter);
+            let handle = interpreter.pop();
+            let buffer = interpreter.files_mut().get_mut(handle)?.buffer;
+            let bytes: Vec<u8> = (0..count?)
+                .map(|offset| {
+                    interpreter.read_cell(buffer.wrapping_add(Position::new(offset as i64, 0, 0)))
+                        as u8
+                })
+                .collect();
+            interpreter
+                .files_mut()
+                .get_mut(handle)?
+                .write(&bytes)
+                .ok()?;
+            interpreter.push(handle);
+        }
+        _ => return None,
+    }
+    Some(Ok(()))
+}
+
+impl Fingerprint for FileIo {
+    fn name(&self) -> &'static str {
+        "FILE"
+    }
+
+    fn instructions(&self) -> &'static [u8] {
+        b"CDGLOPRSW"
+    }
+
+    fn execute(&self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        match try_execute(instruction, interpreter) {
+            Some(result) => result,
+            None => {
+                interpreter.reflect();
+                Ok(())
+            }
+        }
+    }
+
+    fn permitted(&self, sandbox: &Sandbox) -> bool {
+        sandbox.allow_filesystem
+    }
+}
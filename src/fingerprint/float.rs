@@ -0,0 +1,135 @@
+//! FPSP and FPDP: single and double precision floating point arithmetic.
+//!
+//! Both fingerprints define the same instructions and only differ in how floats are packed into cells, see
+//! [`pack_f32`] and [`pack_f64`]: FPSP floats take one cell, FPDP floats two. Angles are in radians. `P` prints a
+//! float like `printf("%f ")`, and `R` reflects if the string is not a number.
+
+use crate::float::format_float;
+use crate::float::pack_f32;
+use crate::float::pack_f64;
+use crate::float::unpack_f32;
+use crate::float::unpack_f64;
+use crate::Error;
+use crate::Fingerprint;
+use crate::FromStack;
+use crate::Int;
+use crate::Interpreter;
+
+pub struct Fpsp;
+pub struct Fpdp;
+
+const INSTRUCTIONS: &[u8] = b"ABCDEFGHIKLMNPQRSTVXY";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Precision {
+    Single,
+    Double,
+}
+
+impl Precision {
+    fn pop(self, interpreter: &mut Interpreter) -> f64 {
+        match self {
+            Self::Single => unpack_f32(interpreter.pop()).into(),
+            Self::Double => {
+                let low = interpreter.pop();
+                let high = interpreter.pop();
+                unpack_f64([high, low])
+            }
+        }
+    }
+
+    fn push(self, interpreter: &mut Interpreter, value: f64) {
+        match self {
+            Self::Single => interpreter.push(pack_f32(value as f32)),
+            Self::Double => {
+                for cell in pack_f64(value) {
+                    interpreter.push(cell);
+                }
+            }
+        }
+    }
+
+    fn execute(self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        let result = match instruction {
+            b'A' | b'D' | b'M' | b'S' | b'Y' => {
+                let b = self.pop(interpreter);
+                let a = self.pop(interpreter);
+                match instruction {
+                    b'A' => a + b,
+                    b'D' => a / b,
+                    b'M' => a * b,
+                    b'S' => a - b,
+                    _ => a.powf(b),
+                }
+            }
+            b'F' => interpreter.pop() as f64,
+            b'I' => {
+                let value = self.pop(interpreter);
+                // truncates towards zero, saturating at the limits of a cell, and NaN is 0
+                interpreter.push(value as Int);
+                return Ok(());
+            }
+            b'P' => {
+                let value = self.pop(interpreter);
+                return interpreter.output(format!("{} ", format_float(value)).as_bytes());
+            }
+            b'R' => {
+                let string = String::from_stack(interpreter.stack_mut());
+                match string.trim().parse() {
+                    Ok(value) => value,
+                    Err(_) => {
+                        interpreter.reflect();
+                        return Ok(());
+                    }
+                }
+            }
+            _ => {
+                let value = self.pop(interpreter);
+                match instruction {
+                    b'B' => value.sin(),
+                    b'C' => value.cos(),
+                    b'E' => value.asin(),
+                    b'G' => value.atan(),
+                    b'H' => value.acos(),
+                    b'K' => value.ln(),
+                    b'L' => value.log10(),
+                    b'N' => -value,
+                    b'Q' => value.sqrt(),
+                    b'T' => value.tan(),
+                    b'V' => value.abs(),
+                    _ => value.exp(),
+                }
+            }
+        };
+        self.push(interpreter, result);
+        Ok(())
+    }
+}
+
+impl Fingerprint for Fpsp {
+    fn name(&self) -> &'static str {
+        "FPSP"
+    }
+
+    fn instructions(&self) -> &'static [u8] {
+        INSTRUCTIONS
+    }
+
+    fn execute(&self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        Precision::Single.execute(instruction, interpreter)
+    }
+}
+
+impl Fingerprint for Fpdp {
+    fn name(&self) -> &'static str {
+        "FPDP"
+    }
+
+    fn instructions(&self) -> &'static [u8] {
+        INSTRUCTIONS
+    }
+
+    fn execute(&self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        Precision::Double.execute(instruction, interpreter)
+    }
+}
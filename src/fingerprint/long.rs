@@ -0,0 +1,96 @@
+//! LONG: integers two cells wide, with the high cell below the low cell, see [`Wide`].
+//!
+//! Like `/` and `%`, `D` and `O` push 0 if the divisor is zero. `L` and `R` shift by a single cell and reflect if it
+//! is negative, discarding their operands. `E` sign-extends a cell, `P` prints a number followed by a space and `Z` parses a 0gnirts, reflecting
+//! if it is not a number in range.
+
+use crate::arithmetic::Wide;
+use crate::Error;
+use crate::Fingerprint;
+use crate::FromStack;
+use crate::Interpreter;
+
+pub struct Long;
+
+type Double = Wide<2>;
+
+fn pop(interpreter: &mut Interpreter) -> Double {
+    let low = interpreter.pop();
+    let high = interpreter.pop();
+    Double::from_cells([high, low])
+}
+
+fn push(interpreter: &mut Interpreter, value: Double) {
+    for cell in value.to_cells() {
+        interpreter.push(cell);
+    }
+}
+
+impl Fingerprint for Long {
+    fn name(&self) -> &'static str {
+        "LONG"
+    }
+
+    fn instructions(&self) -> &'static [u8] {
+        b"ABDELMNOPRSZ"
+    }
+
+    fn execute(&self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        let result = match instruction {
+            b'A' | b'D' | b'M' | b'O' | b'S' => {
+                let b = pop(interpreter);
+                let a = pop(interpreter);
+                match instruction {
+                    b'A' => a.wrapping_add(b),
+                    b'M' => a.wrapping_mul(b),
+                    b'S' => a.wrapping_sub(b),
+                    _ => match a.checked_div_rem(b) {
+                        Some((quotient, _)) if instruction == b'D' => quotient,
+                        Some((_, remainder)) => remainder,
+                        None => Double::ZERO,
+                    },
+                }
+            }
+            b'L' | b'R' => {
+                let shift = interpreter.pop();
+                let value = pop(interpreter);
+                if shift < 0 {
+                    interpreter.reflect();
+                    return Ok(());
+                }
+                // shifts by at least the width all have the same result
+                let shift = u32::try_from(shift).unwrap_or(u32::MAX);
+                if instruction == b'L' {
+                    value.shift_left(shift)
+                } else {
+                    value.shift_right(shift)
+                }
+            }
+            b'E' => Double::from_int(interpreter.pop()),
+            b'P' => {
+                let value = pop(interpreter);
+                return interpreter.output(format!("{} ", value).as_bytes());
+            }
+            b'Z' => {
+                let string = String::from_stack(interpreter.stack_mut());
+                match Double::parse(string.trim()) {
+                    Some(value) => value,
+                    None => {
+                        interpreter.reflect();
+                        return Ok(());
+                    }
+                }
+            }
+            _ => {
+                let value = pop(interpreter);
+                if instruction == b'B' {
+                    value.wrapping_abs()
+                } else {
+                    value.wrapping_neg()
+                }
+            }
+        };
+        push(interpreter, result);
+        Ok(())
+    }
+}
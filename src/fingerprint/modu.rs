@@ -0,0 +1,30 @@
+//! MODU: the variants of the remainder of a division with negative operands, see [`Remainder`].
+
+use crate::Error;
+use crate::Fingerprint;
+use crate::Interpreter;
+use crate::Remainder;
+
+pub struct Modu;
+
+impl Fingerprint for Modu {
+    fn name(&self) -> &'static str {
+        "MODU"
+    }
+
+    fn instructions(&self) -> &'static [u8] {
+        b"MRU"
+    }
+
+    fn execute(&self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        let b = interpreter.pop();
+        let a = interpreter.pop();
+        let remainder = match instruction {
+            b'M' => Remainder::Floored,
+            b'R' => Remainder::Truncated,
+            _ => Remainder::Unsigned,
+        };
+        interpreter.push(remainder.of(a, b));
+        Ok(())
+    }
+}
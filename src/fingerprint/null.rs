@@ -0,0 +1,22 @@
+//! NULL: makes every instruction `A` to `Z` reflect.
+
+use crate::Error;
+use crate::Fingerprint;
+use crate::Interpreter;
+
+pub struct Null;
+
+impl Fingerprint for Null {
+    fn name(&self) -> &'static str {
+        "NULL"
+    }
+
+    fn instructions(&self) -> &'static [u8] {
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+    }
+
+    fn execute(&self, _: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        interpreter.reflect();
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! ORTH: orthogonal easement, bitwise logic and direct access to the program counter's coordinates and delta.
+//!
+//! `G` and `P` are `g` and `p` with the coordinates swapped, popping x before y. `X` and `Y` set a coordinate of
+//! the program counter, which moves on from there afterwards. `V` and `W` set a component of the delta.
+
+use crate::Error;
+use crate::Fingerprint;
+use crate::FromStack;
+use crate::Int;
+use crate::Interpreter;
+use crate::Position;
+
+pub struct Orth;
+
+impl Fingerprint for Orth {
+    fn name(&self) -> &'static str {
+        "ORTH"
+    }
+
+    fn instructions(&self) -> &'static [u8] {
+        b"AEGOPSVWXYZ"
+    }
+
+    fn execute(&self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        match instruction {
+            b'A' | b'E' | b'O' => {
+                let b = interpreter.pop();
+                let a = interpreter.pop();
+                interpreter.push(match instruction {
+                    b'A' => a & b,
+                    b'O' => a | b,
+                    _ => a ^ b,
+                });
+            }
+            b'G' => {
+                let x = interpreter.pop();
+                let y = interpreter.pop();
+                let value = interpreter.read_cell(orthogonal(interpreter, x, y));
+                interpreter.push(value);
+            }
+            b'P' => {
+                let x = interpreter.pop();
+                let y = interpreter.pop();
+                let value = interpreter.pop();
+                let position = orthogonal(interpreter, x, y);
+                interpreter.write_cell(position, value)?;
+            }
+            b'S' => {
+                let string = String::from_stack(interpreter.stack_mut());
+                if let Some(character) = string.chars().find(|character| !character.is_ascii()) {
+                    return Err(Error::NonAscii(character as Int));
+                }
+                interpreter.output(string.as_bytes())?;
+            }
+            #[allow(clippy::unnecessary_cast)]
+            b'V' => interpreter.program_counter_mut().delta.0.x = interpreter.pop() as i64,
+            #[allow(clippy::unnecessary_cast)]
+            b'W' => interpreter.program_counter_mut().delta.0.y = interpreter.pop() as i64,
+            #[allow(clippy::unnecessary_cast)]
+            b'X' => interpreter.program_counter_mut().position.x = interpreter.pop() as i64,
+            #[allow(clippy::unnecessary_cast)]
+            b'Y' => interpreter.program_counter_mut().position.y = interpreter.pop() as i64,
+            // ramp if zero: skip the next cell like # if the value is zero
+            _ => {
+                if interpreter.pop() == 0 {
+                    let program_counter = interpreter.program_counter_mut();
+                    program_counter.position += program_counter.delta;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The coordinates `G` and `P` address relative to the storage offset. ORTH only has two dimensions, so in Trefunge-98
+/// they lie on the plane of the storage offset.
+// Int is not an i64 on every platform
+#[allow(clippy::unnecessary_cast)]
+fn orthogonal(interpreter: &Interpreter, x: Int, y: Int) -> Position {
+    Position::new(x as i64, y as i64, 0).wrapping_add(interpreter.storage_offset())
+}
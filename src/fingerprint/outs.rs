@@ -0,0 +1,33 @@
+//! OUTS: chooses where the output of `,`, `.` and every other instruction that writes program output goes, so that
+//! programs can separate diagnostics from their data. `E` sends it to stderr from now on, `O` back to stdout, and `S`
+//! pushes 1 if it currently goes to stderr and 0 otherwise. The choice applies to all instruction pointers.
+
+use crate::Error;
+use crate::Fingerprint;
+use crate::Int;
+use crate::Interpreter;
+use crate::OutputStream;
+
+pub struct Outs;
+
+impl Fingerprint for Outs {
+    fn name(&self) -> &'static str {
+        "OUTS"
+    }
+
+    fn instructions(&self) -> &'static [u8] {
+        b"EOS"
+    }
+
+    fn execute(&self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        match instruction {
+            b'E' => interpreter.select_output(OutputStream::Error)?,
+            b'O' => interpreter.select_output(OutputStream::Standard)?,
+            _ => {
+                let error = interpreter.output_stream() == OutputStream::Error;
+                interpreter.push(error as Int);
+            }
+        }
+        Ok(())
+    }
+}
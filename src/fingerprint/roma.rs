@@ -0,0 +1,30 @@
+//! ROMA: Roman numerals, which push their value.
+
+use crate::Error;
+use crate::Fingerprint;
+use crate::Interpreter;
+
+pub struct Roma;
+
+impl Fingerprint for Roma {
+    fn name(&self) -> &'static str {
+        "ROMA"
+    }
+
+    fn instructions(&self) -> &'static [u8] {
+        b"CDILMVX"
+    }
+
+    fn execute(&self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        interpreter.push(match instruction {
+            b'I' => 1,
+            b'V' => 5,
+            b'X' => 10,
+            b'L' => 50,
+            b'C' => 100,
+            b'D' => 500,
+            _ => 1000,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,343 @@
+//! TOYS: an assortment of instructions, most of which copy, move or fill blocks of cells.
+//!
+//! Vectors are popped like those of `g` and `p`: y, then x, with z popped first in Trefunge-98. All vectors except
+//! block sizes are relative to the storage offset, like the coordinates of `g` and `p`. Blocks of Befunge programs
+//! are a single plane deep.
+
+use crate::stack::MAX_STACK_TRANSFER;
+use crate::Direction;
+use crate::Error;
+use crate::Fingerprint;
+use crate::Int;
+use crate::Interpreter;
+use crate::Position;
+
+/// Largest number of cells the block instructions copy, move or fill at once. Like the stack stack instructions,
+/// they reflect for larger blocks, as if the interpreter ran out of memory.
+pub const MAX_BLOCK_CELLS: usize = 1 << 20;
+
+pub struct Toys;
+
+/// Order in which the cells of a block are visited, which matters when a block is copied onto itself.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Order {
+    /// From the top left corner, row by row and plane by plane.
+    Low,
+    /// From the bottom right corner of the last plane, row by row and plane by plane.
+    High,
+}
+
+/// Offsets of the cells of a block of the given size, or `None` if the size is negative or the block has more
+/// than `limit` cells.
+fn block(size: Position, order: Order, limit: usize) -> Option<impl Iterator<Item = Position>> {
+    let width = usize::try_from(size.x).ok()?;
+    let height = usize::try_from(size.y).ok()?;
+    let depth = usize::try_from(size.z).ok()?;
+    let cells = width
+        .checked_mul(height)
+        .and_then(|cells| cells.checked_mul(depth))
+        .filter(|&cells| cells <= limit)?;
+    Some((0..cells).map(move |index| {
+        let index = match order {
+            Order::Low => index,
+            Order::High => cells - 1 - index,
+        };
+        Position::new(
+            (index % width) as i64,
+            (index / width % height) as i64,
+            (index / width / height) as i64,
+        )
+    }))
+}
+
+/// Pop the size of a block, which is a single plane deep unless the program is a Trefunge-98 program.
+fn pop_size(interpreter: &mut Interpreter) -> Position {
+    let size = interpreter.pop_vector();
+    match interpreter.dimensions() {
+        3 => size,
+        _ => size.with_z(1),
+    }
+}
+
+/// Pop a vector and add the storage offset to it.
+fn pop_offset_vector(interpreter: &mut Interpreter) -> Position {
+    interpreter
+        .pop_vector()
+        .wrapping_add(interpreter.storage_offset())
+}
+
+fn read(interpreter: &Interpreter, position: Position) -> Int {
+    interpreter.read_cell(position)
+}
+
+fn write(interpreter: &mut Interpreter, position: Position, value: Int) -> Result<(), Error> {
+    interpreter.write_cell(position, value)
+}
+
+/// Copy the block of the given size from the origin to the target, one cell at a time in the given order. Moving
+/// the block clears each cell of the origin to a space right after reading it. Reflects if the block is too large.
+fn transfer(
+    interpreter: &mut Interpreter,
+    origin: Position,
+    size: Position,
+    target: Position,
+    order: Order,
+    clear: bool,
+) -> Result<(), Error> {
+    let Some(offsets) = block(size, order, MAX_BLOCK_CELLS) else {
+        interpreter.reflect();
+        return Ok(());
+    };
+    for offset in offsets {
+        let value = read(interpreter, origin.wrapping_add(offset));
+        if clear {
+            write(interpreter, origin.wrapping_add(offset), b' '.into())?;
+        }
+        write(interpreter, target.wrapping_add(offset), value)?;
+    }
+    Ok(())
+}
+
+impl Fingerprint for Toys {
+    fn name(&self) -> &'static str {
+        "TOYS"
+    }
+
+    fn instructions(&self) -> &'static [u8] {
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+    }
+
+    fn execute(&self, instruction: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+        match instruction {
+            // gable: push n copies of a value
+            b'A' => {
+                let count = interpreter.pop();
+                let value = interpreter.pop();
+                match usize::try_from(count) {
+                    Ok(count) if count <= MAX_STACK_TRANSFER => {
+                        for _ in 0..count {
+                            interpreter.push(value);
+                        }
+                    }
+                    _ => interpreter.reflect(),
+                }
+            }
+            // pair of shoes: push the sum and the difference
+            b'B' => {
+                let b = interpreter.pop();
+                let a = interpreter.pop();
+                interpreter.push(a.wrapping_add(b));
+                interpreter.push(a.wrapping_sub(b));
+            }
+            // bracelet, scissors, dixiecup and kittycat: copy or move a block from low or high coordinates on
+            b'C' | b'K' | b'V' | b'M' => {
+                let target = pop_offset_vector(interpreter);
+                let size = pop_size(interpreter);
+                let origin = pop_offset_vector(interpreter);
+                let order = match instruction {
+                    b'C' | b'V' => Order::Low,
+                    _ => Order::High,
+                };
+                let clear = matches!(instruction, b'V' | b'M');
+                transfer(interpreter, origin, size, target, order, clear)?;
+            }
+            // toilet seat, doric column and lightning bolt: decrement, increment and negate
+            b'D' => {
+                let value = interpreter.pop();
+                interpreter.push(value.wrapping_sub(1));
+            }
+            b'I' => {
+                let value = interpreter.pop();
+                interpreter.push(value.wrapping_add(1));
+            }
+            b'N' => {
+                let value = interpreter.pop();
+                interpreter.push(value.wrapping_neg());
+            }
+            // pitchfork head and mailbox: replace the whole stack with its sum or product
+            b'E' | b'P' => {
+                let mut result = Int::from(instruction == b'P');
+                while !interpreter.stack().is_empty() {
+                    let value = interpreter.pop();
+                    result = if instruction == b'E' {
+                        result.wrapping_add(value)
+                    } else {
+                        result.wrapping_mul(value)
+                    };
+                }
+                interpreter.push(result);
+            }
+            // calipers: pop a matrix into a block, the first value popped into the top left corner
+            b'F' => {
+                let target = pop_offset_vector(interpreter);
+                let width = interpreter.pop();
+                let height = interpreter.pop();
+                #[allow(clippy::unnecessary_cast)]
+                let size = Position::new(width as i64, height as i64, 1);
+                match block(size, Order::Low, MAX_STACK_TRANSFER) {
+                    Some(offsets) => {
+                        for offset in offsets {
+                            let value = interpreter.pop();
+                            write(interpreter, target.wrapping_add(offset), value)?;
+                        }
+                    }
+                    None => interpreter.reflect(),
+                }
+            }
+            // counterclockwise: push a block as a matrix that F pops back
+            b'G' => {
+                let origin = pop_offset_vector(interpreter);
+                let width = interpreter.pop();
+                let height = interpreter.pop();
+                #[allow(clippy::unnecessary_cast)]
+                let size = Position::new(width as i64, height as i64, 1);
+                match block(size, Order::High, MAX_STACK_TRANSFER) {
+                    Some(offsets) => {
+                        for offset in offsets {
+                            let value = read(interpreter, origin.wrapping_add(offset));
+                            interpreter.push(value);
+                        }
+                    }
+                    None => interpreter.reflect(),
+                }
+            }
+            // pair of stilts: shift left by a positive amount and arithmetically right by a negative one
+            b'H' => {
+                let shift = interpreter.pop();
+                let value = interpreter.pop();
+                let sign = if value < 0 { -1 } else { 0 };
+                interpreter.push(if shift >= 0 {
+                    u32::try_from(shift)
+                        .ok()
+                        .and_then(|shift| value.checked_shl(shift))
+                        .unwrap_or(0)
+                } else {
+                    u32::try_from(shift.unsigned_abs())
+                        .ok()
+                        .and_then(|shift| value.checked_shr(shift))
+                        .unwrap_or(sign)
+                });
+            }
+            // fishhook and boulder: move the current column down or the current row right, with negative amounts
+            // moving it up or left
+            b'J' | b'O' => {
+                let distance = interpreter.pop();
+                let position = interpreter.program_counter_mut().position;
+                let bounds = *interpreter.grid().bounds();
+                #[allow(clippy::unnecessary_cast)]
+                let (origin, size, shift) = if instruction == b'J' {
+                    (
+                        Position::new(position.x, bounds.min.y, position.z),
+                        Position::new(1, bounds.size().y, 1),
+                        Position::new(0, distance as i64, 0),
+                    )
+                } else {
+                    (
+                        Position::new(bounds.min.x, position.y, position.z),
+                        Position::new(bounds.size().x, 1, 1),
+                        Position::new(distance as i64, 0, 0),
+                    )
+                };
+                // the cells have to be moved away from the end they move towards first
+                let order = if distance > 0 {
+                    Order::High
+                } else {
+                    Order::Low
+                };
+                let target = origin.wrapping_add(shift);
+                transfer(interpreter, origin, size, target, order, true)?;
+            }
+            // corner and can opener: push the cell to the left or right of the program counter
+            b'L' | b'R' => {
+                let program_counter = *interpreter.program_counter_mut();
+                let side = if instruction == b'L' {
+                    program_counter.delta.turned_left()
+                } else {
+                    program_counter.delta.turned_right()
+                };
+                let value = read(interpreter, program_counter.position + side);
+                interpreter.push(value);
+            }
+            // necklace: write a value into the cell behind the program counter
+            b'Q' => {
+                let value = interpreter.pop();
+                let program_counter = *interpreter.program_counter_mut();
+                let behind = program_counter.position + program_counter.delta.reversed();
+                write(interpreter, behind, value)?;
+            }
+            // chicane: fill a block with a value
+            b'S' => {
+                let origin = pop_offset_vector(interpreter);
+                let size = pop_size(interpreter);
+                let value = interpreter.pop();
+                match block(size, Order::Low, MAX_BLOCK_CELLS) {
+                    Some(offsets) => {
+                        for offset in offsets {
+                            write(interpreter, origin.wrapping_add(offset), value)?;
+                        }
+                    }
+                    None => interpreter.reflect(),
+                }
+            }
+            // barstool: _ for dimension 0, | for dimension 1 and m for dimension 2
+            b'T' => {
+                let dimension = interpreter.pop();
+                if !(0..interpreter.dimensions() as Int).contains(&dimension) {
+                    interpreter.reflect();
+                    return Ok(());
+                }
+                let zero = interpreter.pop() == 0;
+                interpreter.program_counter_mut().delta = match (dimension, zero) {
+                    (0, true) => Direction::Right,
+                    (0, false) => Direction::Left,
+                    (1, true) => Direction::Down,
+                    (1, false) => Direction::Up,
+                    (_, true) => Direction::Low,
+                    (_, false) => Direction::High,
+                }
+                .into();
+            }
+            // tumbler: go in a random direction like ? and replace itself with the arrow of that direction
+            b'U' => {
+                let direction = interpreter.random_direction();
+                let program_counter = interpreter.program_counter_mut();
+                program_counter.delta = direction.into();
+                let position = program_counter.position;
+                let arrow = match direction {
+                    Direction::Up => b'^',
+                    Direction::Down => b'v',
+                    Direction::Left => b'<',
+                    Direction::Right => b'>',
+                    Direction::High => b'h',
+                    Direction::Low => b'l',
+                };
+                write(interpreter, position, arrow.into())?;
+            }
+            // television antenna: wait here while a cell is less than a value, and reflect if it is greater
+            b'W' => {
+                let vector = interpreter.pop_vector();
+                let value = interpreter.pop();
+                let cell = read(
+                    interpreter,
+                    vector.wrapping_add(interpreter.storage_offset()),
+                );
+                if cell < value {
+                    interpreter.push(value);
+                    interpreter.push_vector(vector);
+                    // step back, so that the program counter moves onto W again
+                    let program_counter = interpreter.program_counter_mut();
+                    program_counter.position += program_counter.delta.reversed();
+                } else if cell > value {
+                    interpreter.reflect();
+                }
+            }
+            // buried treasure and slingshot: move one cell right or down in addition to the usual movement
+            b'X' => interpreter.program_counter_mut().position.x += 1,
+            b'Y' => interpreter.program_counter_mut().position.y += 1,
+            // barn door moves along the third dimension, which only Trefunge-98 programs have
+            _ if interpreter.dimensions() == 3 => interpreter.program_counter_mut().position.z += 1,
+            _ => interpreter.reflect(),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+//! Packing floating point numbers into cells, for the FPSP and FPDP fingerprints.
+//!
+//! The conventions follow other Funge-98 interpreters such as cfunge and RC/Funge-98: a single precision float is
+//! stored as its IEEE 754 bits in one cell, and a double precision float as two cells with the high 32 bits below
+//! the low 32 bits. Each 32-bit half is stored sign-extended, so that programs see the same cell values as with
+//! 32-bit cells. Unpacking only looks at the low 32 bits of each cell, so zero-extended halves unpack the same.
+
+use crate::Int;
+
+/// The cell holding a single precision float.
+pub fn pack_f32(value: f32) -> Int {
+    value.to_bits() as i32 as Int
+}
+
+/// The single precision float in a cell, from its low 32 bits.
+pub fn unpack_f32(cell: Int) -> f32 {
+    f32::from_bits(cell as u32)
+}
+
+/// The two cells holding a double precision float, high half first.
+pub fn pack_f64(value: f64) -> [Int; 2] {
+    let bits = value.to_bits();
+    [(bits >> 32) as u32 as i32 as Int, bits as u32 as i32 as Int]
+}
+
+/// The double precision float in two cells, high half first, from their low 32 bits.
+pub fn unpack_f64([high, low]: [Int; 2]) -> f64 {
+    f64::from_bits(((high as u32 as u64) << 32) | low as u32 as u64)
+}
+
+/// A float formatted like C's `printf("%f")`, which is how other interpreters print them.
+pub fn format_float(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value < 0.0 { "-inf" } else { "inf" }.to_string()
+    } else {
+        format!("{:.6}", value)
+    }
+}
@@ -0,0 +1,216 @@
+//! Static control flow analysis of the playfield.
+//!
+//! The analysis only looks at the grid as it is, so it doesn't know about string mode, the stack or
+//! changes made by `p`. It assumes Befunge-93 semantics: the program counter wraps around the whole grid,
+//! and `#` always skips the next cell.
+//!
+//! [`Reachability`] keeps the set of reachable cells up to date while the grid is edited, which is what an
+//! editor needs to grey out dead code on every keystroke.
+
+use crate::Bounds;
+use crate::Direction;
+use crate::Grid;
+use crate::Int;
+use crate::Position;
+use crate::WrapMode;
+use crate::GRID_HEIGHT;
+use crate::GRID_WIDTH;
+use crate::PC;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+impl Grid {
+    /// Where execution can continue after the cell at the given coordinates, for any direction the program
+    /// counter may arrive from. Each target is the position of the next cell to execute and the direction
+    /// the program counter moves in. Coordinates outside the grid have no targets.
+    pub fn flow_targets(&self, x: Int, y: Int) -> Vec<PC> {
+        let mut targets = Vec::new();
+        // Int is not an i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        let position = Position::new(x as i64, y as i64, 0);
+        for arrival in DIRECTIONS {
+            for target in self.successors(position, arrival) {
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+        targets
+    }
+
+    /// The cells from which execution can continue at the given coordinates. Each source is the position
+    /// of such a cell and the direction the program counter leaves it in.
+    pub fn flow_sources(&self, x: Int, y: Int) -> Vec<PC> {
+        // Int is not an i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        let target = Position::new(x as i64, y as i64, 0);
+        let mut sources = Vec::new();
+        if !Bounds::GRID.contains(target) {
+            return sources;
+        }
+        for source_y in Bounds::GRID.min.y..=Bounds::GRID.max.y {
+            for source_x in Bounds::GRID.min.x..=Bounds::GRID.max.x {
+                let position = Position::new(source_x, source_y, 0);
+                for arrival in DIRECTIONS {
+                    for successor in self.successors(position, arrival) {
+                        let source = PC {
+                            position,
+                            delta: successor.delta,
+                        };
+                        if successor.position == target && !sources.contains(&source) {
+                            sources.push(source);
+                        }
+                    }
+                }
+            }
+        }
+        sources
+    }
+
+    /// The program counters possible after executing the cell at the position, having arrived in the direction.
+    fn successors(&self, position: Position, arrival: Direction) -> Vec<PC> {
+        // Int is not an i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        let Some(cell) = self.get(position.x as Int, position.y as Int) else {
+            return Vec::new();
+        };
+        let directions: &[Direction] = match cell {
+            b'@' => &[],
+            b'>' => &[Direction::Right],
+            b'<' => &[Direction::Left],
+            b'^' => &[Direction::Up],
+            b'v' => &[Direction::Down],
+            b'_' => &[Direction::Right, Direction::Left],
+            b'|' => &[Direction::Down, Direction::Up],
+            b'?' => &DIRECTIONS,
+            _ => &[arrival],
+        };
+        let distance = if cell == b'#' { 2 } else { 1 };
+        directions
+            .iter()
+            .map(|&direction| {
+                let mut pc = PC {
+                    position,
+                    delta: direction.into(),
+                };
+                for _ in 0..distance {
+                    pc.step();
+                    pc.constrain(WrapMode::Torus, &Bounds::GRID);
+                }
+                pc
+            })
+            .collect()
+    }
+}
+
+/// The cells reachable from a start position, maintained incrementally across edits of the grid.
+///
+/// For every cell, the analysis tracks the directions the program counter can arrive at it in. Editing a cell
+/// that is unreachable, or whose new instruction leads execution to the same places, costs nothing beyond
+/// the edit itself; an edit that only adds new places to continue at just explores those. Only an edit that
+/// cuts off a previously possible path recomputes the analysis from the start.
+#[derive(Clone, Debug)]
+pub struct Reachability {
+    grid: Grid,
+    start: PC,
+    /// Bit set of the directions the program counter arrives at each cell in.
+    arrivals: [[u8; GRID_WIDTH]; GRID_HEIGHT],
+    recomputations: usize,
+}
+
+impl Reachability {
+    pub fn new(grid: Grid, start: PC) -> Self {
+        let mut reachability = Self {
+            grid,
+            start,
+            arrivals: [[0; GRID_WIDTH]; GRID_HEIGHT],
+            recomputations: 0,
+        };
+        reachability.explore(vec![start]);
+        reachability
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Whether execution can reach the cell at the given coordinates.
+    pub fn is_reachable(&self, x: Int, y: Int) -> bool {
+        Grid::contains(x, y) && self.arrivals[y as usize][x as usize] != 0
+    }
+
+    /// Number of reachable cells.
+    pub fn reachable_cells(&self) -> usize {
+        self.arrivals
+            .iter()
+            .flatten()
+            .filter(|&&arrivals| arrivals != 0)
+            .count()
+    }
+
+    /// How often an edit required recomputing the analysis from the start.
+    pub fn recomputations(&self) -> usize {
+        self.recomputations
+    }
+
+    /// Set a cell of the grid and update the analysis. Coordinates outside the grid are ignored.
+    pub fn edit(&mut self, x: Int, y: Int, value: u8) {
+        let Some(old) = self.grid.get(x, y) else {
+            return;
+        };
+        let arrivals = self.arrivals[y as usize][x as usize];
+        // Int is not an i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        let position = Position::new(x as i64, y as i64, 0);
+        let reached = DIRECTIONS
+            .into_iter()
+            .filter(|&direction| arrivals & direction_bit(direction) != 0);
+        let old_successors = reached
+            .clone()
+            .flat_map(|arrival| self.grid.successors(position, arrival))
+            .collect::<Vec<_>>();
+        self.grid.set(x, y, value);
+        if arrivals == 0 || old == value {
+            return;
+        }
+        let new_successors = reached
+            .flat_map(|arrival| self.grid.successors(position, arrival))
+            .collect::<Vec<_>>();
+        if old_successors
+            .iter()
+            .all(|successor| new_successors.contains(successor))
+        {
+            self.explore(new_successors);
+        } else {
+            self.recomputations += 1;
+            self.arrivals = [[0; GRID_WIDTH]; GRID_HEIGHT];
+            self.explore(vec![self.start]);
+        }
+    }
+
+    /// Mark everything reachable from the given program counters, stopping at states that are already marked.
+    fn explore(&mut self, mut pending: Vec<PC>) {
+        while let Some(pc) = pending.pop() {
+            // Befunge-93 only ever moves a single step up, down, left or right
+            let Some(direction) = pc.delta.direction() else {
+                continue;
+            };
+            let arrivals = &mut self.arrivals[pc.position.y as usize][pc.position.x as usize];
+            let bit = direction_bit(direction);
+            if *arrivals & bit != 0 {
+                continue;
+            }
+            *arrivals |= bit;
+            pending.extend(self.grid.successors(pc.position, direction));
+        }
+    }
+}
+
+fn direction_bit(direction: Direction) -> u8 {
+    1 << direction as u8
+}
@@ -0,0 +1,123 @@
+//! Game-loop integration for programs embedded as scripts in games. Instead of running to completion, a script runs
+//! a bounded number of steps per frame, and it exchanges events with the game instead of reading and writing bytes.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::Executer;
+use crate::ExitReason;
+use crate::Int;
+use crate::Interpreter;
+
+/// An event the game sends to the script, which reads it as input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputEvent {
+    /// Read by `~`.
+    Character(u8),
+    /// Read by `&`.
+    Number(Int),
+}
+
+/// An event the script sends to the game.
+#[derive(Debug, PartialEq)]
+pub enum OutputEvent {
+    /// Output by `,`.
+    Character(u8),
+    /// Output by `.`.
+    Number(Int),
+    /// The script stopped and does not run anymore.
+    Stopped(ExitReason),
+}
+
+/// Input events that have not been read yet, as bytes.
+#[derive(Clone, Default)]
+struct EventInput(Rc<RefCell<VecDeque<u8>>>);
+
+impl Read for EventInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+/// A program running as a script inside a game loop, see [`GameScript::tick`].
+pub struct GameScript {
+    interpreter: Interpreter<'static>,
+    input: EventInput,
+    steps_per_tick: usize,
+    stopped: bool,
+}
+
+impl GameScript {
+    /// Create the script's interpreter from the given input and output, so that it can be configured like any
+    /// other interpreter, e.g. with `|input, output| Interpreter::new_with_io_and_grid(grid, input, output)`.
+    pub fn new(
+        steps_per_tick: usize,
+        create: impl FnOnce(Box<dyn Read>, Box<dyn Write>) -> Interpreter<'static>,
+    ) -> Self {
+        let input = EventInput::default();
+        Self {
+            interpreter: create(Box::new(input.clone()), Box::new(io::sink())),
+            input,
+            steps_per_tick,
+            stopped: false,
+        }
+    }
+
+    pub fn interpreter(&self) -> &Interpreter<'static> {
+        &self.interpreter
+    }
+
+    /// Access the interpreter, e.g. to register host functions.
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter<'static> {
+        &mut self.interpreter
+    }
+
+    /// Whether the script has stopped, after which ticks do nothing.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Run one frame: queue the input events and run the script for at most the configured number of steps.
+    /// A script that wants to read input while none is queued waits for the next tick instead of reading the end
+    /// of the input.
+    pub fn tick(&mut self, events: &[InputEvent]) -> Vec<OutputEvent> {
+        {
+            let mut input = self.input.0.borrow_mut();
+            for event in events {
+                match event {
+                    InputEvent::Character(character) => input.push_back(*character),
+                    InputEvent::Number(number) => input.extend(format!("{}\n", number).bytes()),
+                }
+            }
+        }
+
+        let mut output = Vec::new();
+        if self.stopped {
+            return output;
+        }
+        for _ in 0..self.steps_per_tick {
+            let instruction = (!self.interpreter.string_mode())
+                .then(|| self.interpreter.grid().cell(self.interpreter.position()));
+            if matches!(instruction, Some(b'~' | b'&')) && self.input.0.borrow().is_empty() {
+                break;
+            }
+            let top = self.interpreter.stack().last().copied().unwrap_or_default();
+            let result = self.interpreter.run_step();
+            if result.is_err() {
+                self.stopped = true;
+                output.push(OutputEvent::Stopped(self.interpreter.exit_reason(result)));
+                break;
+            }
+            match instruction {
+                Some(b',') => output.push(OutputEvent::Character(top as u8)),
+                Some(b'.') => output.push(OutputEvent::Number(top)),
+                _ => {}
+            }
+        }
+        output
+    }
+}
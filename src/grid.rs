@@ -0,0 +1,516 @@
+//! The Befunge playfield.
+
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::ops::Index;
+use std::sync::Arc;
+
+use crate::Int;
+use crate::LazySource;
+use crate::Position;
+
+pub const GRID_HEIGHT: usize = 25;
+pub const GRID_WIDTH: usize = 80;
+pub type Line = [u8; GRID_WIDTH];
+pub type Cells = [Line; GRID_HEIGHT];
+/// Side length of the square chunks that store the cells of an unbounded grid outside of the 80x25 area, which each
+/// lie within a single plane.
+pub const CHUNK_SIZE: usize = 64;
+type Chunk = [[u8; CHUNK_SIZE]; CHUNK_SIZE];
+/// What a cell holding a value that doesn't fit into a byte contains, see [`Grid::set_value`].
+/// It is not an instruction of any standard.
+pub const WIDE_CELL: u8 = 0xff;
+
+/// An inclusive box of grid positions, which is a rectangle for Befunge programs on the plane at z = 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: Position,
+    pub max: Position,
+}
+
+impl Bounds {
+    /// The entire grid.
+    pub const GRID: Self = Self {
+        min: Position::new(0, 0, 0),
+        max: Position::new(GRID_WIDTH as i64 - 1, GRID_HEIGHT as i64 - 1, 0),
+    };
+
+    #[inline]
+    pub fn contains(&self, position: Position) -> bool {
+        position.cmpge(self.min).all() && position.cmple(self.max).all()
+    }
+
+    /// Width, height and depth, at most `i64::MAX` even for bounds spanning more cells.
+    pub fn size(&self) -> Position {
+        self.max
+            .saturating_sub(self.min)
+            .saturating_add(Position::ONE)
+    }
+
+    /// Smallest bounds containing both these bounds and the position.
+    pub fn including(self, position: Position) -> Self {
+        Self {
+            min: self.min.min(position),
+            max: self.max.max(position),
+        }
+    }
+}
+
+/// A change to a single cell of the grid, as reported to grid observers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellChange {
+    pub position: Position,
+    pub old: u8,
+    pub new: u8,
+}
+
+/// Callback notified of every change made to a grid.
+pub type GridObserver = Box<dyn FnMut(&CellChange)>;
+
+/// The playfield of a Befunge program.
+/// All modifications, including those by `p`, go through [`Grid::set`], which notifies the grid's observers.
+///
+/// A Befunge-93 grid is limited to 80x25 cells. An unbounded grid, as used by Befunge-98, additionally stores cells at
+/// any other coordinates sparsely in chunks, which only exist once a non-space cell was written to them. The 80x25
+/// area at z = 0 is always stored densely, so programs that fit into it run just as fast as on a Befunge-93 grid.
+/// The other planes of Trefunge-98 programs are only ever stored in chunks.
+/// A grid created with [`Grid::lazy`] reads the cells outside of the 80x25 area from its source until they are written.
+// Keeping the cells at the start of the grid makes fetching instructions measurably faster.
+#[repr(C)]
+pub struct Grid {
+    cells: Cells,
+    /// Cells outside of the 80x25 area by the position of their chunk, for unbounded grids.
+    chunks: BTreeMap<(i64, i64, i64), Box<Chunk>>,
+    unbounded: bool,
+    /// Values of the cells holding [`WIDE_CELL`] that don't fit into a byte.
+    wide: BTreeMap<(i64, i64, i64), Int>,
+    /// Smallest box containing all non-space cells; only ever grows.
+    bounds: Bounds,
+    /// Source of the cells outside of the 80x25 area whose chunk doesn't exist yet.
+    source: Option<Arc<LazySource>>,
+    observers: Vec<GridObserver>,
+}
+
+impl Grid {
+    pub const fn new(cells: Cells) -> Self {
+        let mut min = (GRID_WIDTH, GRID_HEIGHT);
+        let mut max = (0, 0);
+        let mut y = 0;
+        while y < GRID_HEIGHT {
+            let mut x = 0;
+            while x < GRID_WIDTH {
+                if cells[y][x] != b' ' {
+                    if x < min.0 {
+                        min.0 = x;
+                    }
+                    if y < min.1 {
+                        min.1 = y;
+                    }
+                    if x > max.0 {
+                        max.0 = x;
+                    }
+                    if y > max.1 {
+                        max.1 = y;
+                    }
+                }
+                x += 1;
+            }
+            y += 1;
+        }
+        // a grid of only spaces is considered to span the whole grid
+        let bounds = if min.0 > max.0 {
+            Bounds::GRID
+        } else {
+            Bounds {
+                min: Position::new(min.0 as i64, min.1 as i64, 0),
+                max: Position::new(max.0 as i64, max.1 as i64, 0),
+            }
+        };
+        Self {
+            cells,
+            chunks: BTreeMap::new(),
+            unbounded: false,
+            wide: BTreeMap::new(),
+            bounds,
+            source: None,
+            observers: Vec::new(),
+        }
+    }
+
+    /// An unbounded grid holding the program of the source. Only the 80x25 area is copied into the grid right away,
+    /// the cells outside of it are read from the source until they are written to.
+    pub fn lazy(source: Arc<LazySource>) -> Self {
+        let mut cells = [[b' '; GRID_WIDTH]; GRID_HEIGHT];
+        for (y, line) in cells.iter_mut().enumerate() {
+            for (x, cell) in line.iter_mut().enumerate() {
+                *cell = source.cell(Position::new(x as i64, y as i64, 0));
+            }
+        }
+        let mut grid = Self::new(cells);
+        grid.unbounded = true;
+        if let Some(far) = source.far_bounds() {
+            grid.bounds = grid.bounds.including(far.min).including(far.max);
+        }
+        grid.source = Some(source);
+        grid
+    }
+
+    /// Make the grid unbounded, as in Befunge-98, or limit it to the 80x25 area again, discarding all cells outside it.
+    pub fn set_unbounded(&mut self, unbounded: bool) {
+        self.unbounded = unbounded;
+        if !unbounded {
+            self.source = None;
+        }
+        if !unbounded && !self.chunks.is_empty() {
+            self.chunks.clear();
+            self.wide
+                .retain(|&(x, y, z), _| z == 0 && Self::contains(x as Int, y as Int));
+            self.bounds = Grid::new(self.cells).bounds;
+        }
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.unbounded
+    }
+
+    /// Whether any non-space cell was ever stored outside of the 80x25 area.
+    pub fn has_far_cells(&self) -> bool {
+        !self.chunks.is_empty()
+            || self
+                .source
+                .as_ref()
+                .is_some_and(|source| source.far_bounds().is_some())
+    }
+
+    /// The smallest box containing all non-space cells the grid ever had. For unbounded grids, this may extend
+    /// beyond the 80x25 area.
+    /// This is the area the program counter wraps around in with [`WrapMode::Lahey`](crate::WrapMode::Lahey).
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// The cells of the 80x25 area.
+    pub fn cells(&self) -> &Cells {
+        &self.cells
+    }
+
+    /// Whether the coordinates lie within the 80x25 area.
+    pub fn contains(x: Int, y: Int) -> bool {
+        (0..GRID_WIDTH as Int).contains(&x) && (0..GRID_HEIGHT as Int).contains(&y)
+    }
+
+    /// The cell at the given coordinates of the plane at z = 0, or `None` if they lie outside a bounded grid.
+    pub fn get(&self, x: Int, y: Int) -> Option<u8> {
+        // Int is not an i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        self.get_at(Position::new(x as i64, y as i64, 0))
+    }
+
+    /// The cell at the given position, or `None` if it lies outside a bounded grid.
+    pub fn get_at(&self, position: Position) -> Option<u8> {
+        if self.unbounded || Bounds::GRID.contains(position) {
+            Some(self.cell(position))
+        } else {
+            None
+        }
+    }
+
+    /// The cell at the given position, which must lie within the 80x25 area or the grid must be unbounded.
+    #[inline(always)]
+    pub fn cell(&self, position: Position) -> u8 {
+        if position.z != 0 {
+            return self.far_cell(position);
+        }
+        // negative coordinates turn into huge indices
+        match self
+            .cells
+            .get(position.y as usize)
+            .and_then(|line| line.get(position.x as usize))
+        {
+            Some(&cell) => cell,
+            None => self.far_cell(position),
+        }
+    }
+
+    #[cold]
+    fn far_cell(&self, position: Position) -> u8 {
+        let (chunk, x, y) = chunk_of(position);
+        match self.chunks.get(&chunk) {
+            Some(chunk) => chunk[y][x],
+            None => self.source_cell(position),
+        }
+    }
+
+    /// The cell of the lazy source at the given position, or a space without one.
+    fn source_cell(&self, position: Position) -> u8 {
+        self.source
+            .as_ref()
+            .map_or(b' ', |source| source.cell(position))
+    }
+
+    /// Set the cell at the given coordinates of the plane at z = 0 and notify all observers.
+    /// Returns the previous value of the cell, or `None` without changing anything if the coordinates lie outside a
+    /// bounded grid.
+    pub fn set(&mut self, x: Int, y: Int, value: u8) -> Option<u8> {
+        // Int is not an i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        self.set_at(Position::new(x as i64, y as i64, 0), value)
+    }
+
+    /// Like [`Grid::set`], but for a cell of any plane.
+    pub fn set_at(&mut self, position: Position, value: u8) -> Option<u8> {
+        let old = if Bounds::GRID.contains(position) {
+            let cell = &mut self.cells[position.y as usize][position.x as usize];
+            let old = *cell;
+            *cell = value;
+            old
+        } else if self.unbounded {
+            self.set_far_cell(position, value)
+        } else {
+            return None;
+        };
+        if !self.wide.is_empty() {
+            self.wide.remove(&position.into());
+        }
+        if value != b' ' {
+            self.bounds = self.bounds.including(position);
+        }
+        if !self.observers.is_empty() {
+            let change = CellChange {
+                position,
+                old,
+                new: value,
+            };
+            for observer in &mut self.observers {
+                observer(&change);
+            }
+        }
+        Some(old)
+    }
+
+    /// The value of the cell at the given coordinates of the plane at z = 0, including values that don't fit into a
+    /// byte; bytes are unsigned. Returns `None` if the coordinates lie outside a bounded grid.
+    pub fn get_value(&self, x: Int, y: Int) -> Option<Int> {
+        // Int is not an i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        self.get_value_at(Position::new(x as i64, y as i64, 0))
+    }
+
+    /// Like [`Grid::get_value`], but for a cell of any plane.
+    pub fn get_value_at(&self, position: Position) -> Option<Int> {
+        let cell = self.get_at(position)?;
+        Some(match self.wide.get(&position.into()) {
+            Some(&value) => value,
+            None => cell as Int,
+        })
+    }
+
+    /// Set the cell at the given coordinates of the plane at z = 0 to any value. Values that don't fit into a byte are
+    /// stored on the side, with the cell itself holding [`WIDE_CELL`]. Returns like [`Grid::set`].
+    pub fn set_value(&mut self, x: Int, y: Int, value: Int) -> Option<u8> {
+        // Int is not an i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        self.set_value_at(Position::new(x as i64, y as i64, 0), value)
+    }
+
+    /// Like [`Grid::set_value`], but for a cell of any plane.
+    pub fn set_value_at(&mut self, position: Position, value: Int) -> Option<u8> {
+        match u8::try_from(value) {
+            Ok(byte) => self.set_at(position, byte),
+            Err(_) => {
+                let old = self.set_at(position, WIDE_CELL)?;
+                self.wide.insert(position.into(), value);
+                Some(old)
+            }
+        }
+    }
+
+    #[cold]
+    fn set_far_cell(&mut self, position: Position, value: u8) -> u8 {
+        let (chunk, x, y) = chunk_of(position);
+        if let Some(chunk) = self.chunks.get_mut(&chunk) {
+            return std::mem::replace(&mut chunk[y][x], value);
+        }
+        // a missing chunk is all spaces, or still lies in the lazy source
+        let old = self.source_cell(position);
+        if old == value {
+            return old;
+        }
+        let mut new = Box::new([[b' '; CHUNK_SIZE]; CHUNK_SIZE]);
+        if let Some(source) = &self.source {
+            let origin = position - Position::new(x as i64, y as i64, 0);
+            for (chunk_y, line) in new.iter_mut().enumerate() {
+                for (chunk_x, cell) in line.iter_mut().enumerate() {
+                    *cell = source.cell(origin + Position::new(chunk_x as i64, chunk_y as i64, 0));
+                }
+            }
+        }
+        new[y][x] = value;
+        self.chunks.insert(chunk, new);
+        old
+    }
+
+    /// All non-space cells with their values, the 80x25 area first, row by row, then those outside of it by chunk,
+    /// plane by plane, and finally those still in the lazy source, line by line.
+    pub fn non_space_cells(&self) -> impl Iterator<Item = (Position, Int)> + '_ {
+        let area = self.cells.iter().enumerate().flat_map(|(y, line)| {
+            line.iter()
+                .enumerate()
+                .map(move |(x, &cell)| (Position::new(x as i64, y as i64, 0), cell))
+        });
+        let far = self
+            .chunks
+            .iter()
+            .flat_map(|(&(z, chunk_x, chunk_y), chunk)| {
+                chunk.iter().enumerate().flat_map(move |(y, line)| {
+                    line.iter().enumerate().map(move |(x, &cell)| {
+                        let size = CHUNK_SIZE as i64;
+                        (
+                            Position::new(chunk_x * size + x as i64, chunk_y * size + y as i64, z),
+                            cell,
+                        )
+                    })
+                })
+            });
+        let lazy = self.source.iter().flat_map(|source| {
+            source
+                .far_cells()
+                .filter(|&(position, _)| !self.chunks.contains_key(&chunk_of(position).0))
+        });
+        area.chain(far)
+            .chain(lazy)
+            .filter(|&(_, cell)| cell != b' ')
+            .map(|(position, cell)| {
+                let value = self.wide.get(&position.into()).copied();
+                (position, value.unwrap_or(cell as Int))
+            })
+    }
+
+    /// Set the smallest box containing all non-space cells, which may be larger than the cells require since
+    /// the bounds never shrink.
+    pub(crate) fn set_bounds(&mut self, bounds: Bounds) {
+        self.bounds = bounds;
+    }
+
+    /// Take over the cells of another grid, keeping this grid's observers without notifying them.
+    pub(crate) fn replace_cells(&mut self, other: &Grid) {
+        self.cells = other.cells;
+        self.chunks = other.chunks.clone();
+        self.unbounded = other.unbounded;
+        self.wide = other.wide.clone();
+        self.bounds = other.bounds;
+        self.source = other.source.clone();
+    }
+
+    /// Register an observer that is called after every change to the grid.
+    pub fn subscribe(&mut self, observer: impl FnMut(&CellChange) + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+}
+
+/// The chunk containing a position outside of the 80x25 area and the position within the chunk.
+/// Chunks are ordered by plane first, so that the planes of a Trefunge-98 program are stored one after the other.
+fn chunk_of(position: Position) -> ((i64, i64, i64), usize, usize) {
+    let size = CHUNK_SIZE as i64;
+    (
+        (
+            position.z,
+            position.x.div_euclid(size),
+            position.y.div_euclid(size),
+        ),
+        position.x.rem_euclid(size) as usize,
+        position.y.rem_euclid(size) as usize,
+    )
+}
+
+/// A single write to the grid recorded in a [`WriteJournal`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub change: CellChange,
+    /// Step during which the write happened.
+    pub step: usize,
+    /// Position of the `p` instruction that performed the write.
+    pub writer: Position,
+}
+
+/// Bounded history of the most recent writes to the grid, oldest first.
+#[derive(Clone, Debug)]
+pub struct WriteJournal {
+    entries: VecDeque<JournalEntry>,
+    capacity: usize,
+}
+
+impl WriteJournal {
+    /// Create a journal remembering at most `capacity` writes; older writes are forgotten.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, entry: JournalEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &JournalEntry> {
+        self.entries.iter()
+    }
+
+    /// The most recent remembered write to the given cell.
+    pub fn last_write(&self, position: Position) -> Option<&JournalEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.change.position == position)
+    }
+
+    /// Remove and return the most recent write, e.g. to undo it.
+    pub fn pop(&mut self) -> Option<JournalEntry> {
+        self.entries.pop_back()
+    }
+}
+
+impl Index<usize> for Grid {
+    type Output = Line;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.cells[index]
+    }
+}
+
+/// Clones only the cells; observers are not carried over to the clone.
+impl Clone for Grid {
+    fn clone(&self) -> Self {
+        Self {
+            cells: self.cells,
+            chunks: self.chunks.clone(),
+            unbounded: self.unbounded,
+            wide: self.wide.clone(),
+            bounds: self.bounds,
+            source: self.source.clone(),
+            observers: Vec::new(),
+        }
+    }
+}
+
+impl Debug for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Grid")
+            .field("cells", &self.cells)
+            .field("chunks", &self.chunks.len())
+            .field("unbounded", &self.unbounded)
+            .field("wide", &self.wide)
+            .field("bounds", &self.bounds)
+            .field("source", &self.source)
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
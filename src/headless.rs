@@ -0,0 +1,172 @@
+//! High-level API for running a program to completion without any terminal I/O.
+
+use std::io::Read;
+use std::io::Write;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::BridgeEdge;
+use crate::CellValues;
+use crate::Error;
+use crate::Executer;
+use crate::Grid;
+use crate::Interpreter;
+use crate::LanguageStandard;
+use crate::Sandbox;
+use crate::SandboxProfile;
+use crate::StringSpaces;
+use crate::SystemInfo;
+use crate::WrapMode;
+use crate::PC;
+
+/// Everything needed to run a program, see [`run`].
+#[derive(Clone, Debug, Default)]
+pub struct RunRequest {
+    /// Program source code.
+    pub program: String,
+    /// The entire input available to the program.
+    pub input: Vec<u8>,
+    /// Stop the program after this many steps.
+    pub max_steps: Option<usize>,
+    /// Stop the program after it has run for this long.
+    pub timeout: Option<Duration>,
+    /// Stop the program once it has written more than this many bytes of output.
+    pub max_output: Option<usize>,
+    /// Seed for `?`; a time-based seed is used if not given.
+    pub seed: Option<u64>,
+    pub start: PC,
+    /// Language standard; Befunge-93 if not given, like [`Interpreter`].
+    pub standard: Option<LanguageStandard>,
+    /// Wrap mode; that of the language standard if not given.
+    pub wrap_mode: Option<WrapMode>,
+    pub bridge_edge: BridgeEdge,
+    pub lenient_numbers: bool,
+    pub string_spaces: StringSpaces,
+    /// Cell values; those of the language standard if not given.
+    pub cell_values: Option<CellValues>,
+    /// What `y` reports; nothing by default.
+    pub system_info: SystemInfo,
+}
+
+impl RunRequest {
+    /// An interpreter for the already parsed program, reading the request's input and writing to the output.
+    /// Programs run without any permissions beyond reading their input.
+    pub fn interpreter<'rw>(&'rw self, grid: Grid, output: &'rw mut Vec<u8>) -> Interpreter<'rw> {
+        self.interpreter_with_io(grid, Box::new(&self.input[..]), Box::new(output))
+    }
+
+    /// Like [`RunRequest::interpreter`], but with other input and output instead of the request's input.
+    pub fn interpreter_with_io<'rw>(
+        &self,
+        grid: Grid,
+        input: Box<dyn Read + 'rw>,
+        output: Box<dyn Write + 'rw>,
+    ) -> Interpreter<'rw> {
+        let standard = self.standard.unwrap_or(LanguageStandard::Befunge93);
+        let mut interpreter = Interpreter::new_with_io_and_grid(grid, input, output)
+            .with_sandbox(Sandbox {
+                max_steps: self.max_steps,
+                timeout: self.timeout,
+                max_output: self.max_output,
+                ..SandboxProfile::Strict.sandbox()
+            })
+            .with_system_info(self.system_info.clone())
+            .with_standard(standard)
+            .with_start(self.start)
+            .with_bridge_edge(self.bridge_edge)
+            .with_lenient_numbers(self.lenient_numbers)
+            .with_string_spaces(self.string_spaces);
+        if let Some(seed) = self.seed {
+            interpreter = interpreter.with_seed(seed);
+        }
+        if let Some(wrap_mode) = self.wrap_mode {
+            interpreter = interpreter.with_wrap_mode(wrap_mode);
+        }
+        if let Some(cell_values) = self.cell_values {
+            interpreter = interpreter.with_cell_values(cell_values);
+        }
+        interpreter
+    }
+}
+
+/// Why a program run ended, see [`Interpreter::exit_reason`].
+#[derive(Debug, PartialEq)]
+pub enum ExitReason {
+    /// The program terminated normally via `@`.
+    Finished,
+    /// The program was stopped by a limit while it kept reading past the end of its input.
+    InputEofLoop,
+    StepLimit,
+    Timeout,
+    OutputLimit,
+    /// A program run by another program stopped making progress, see [`NestedRequest::max_stall_steps`].
+    Stalled,
+    /// The program could not be loaded or failed while running.
+    Error(Error),
+}
+
+impl ExitReason {
+    /// Short machine-readable name of the reason.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Finished => "finished",
+            Self::InputEofLoop => "input-eof-loop",
+            Self::StepLimit => "step-limit",
+            Self::Timeout => "timeout",
+            Self::OutputLimit => "output-limit",
+            Self::Stalled => "stalled",
+            Self::Error(_) => "error",
+        }
+    }
+}
+
+impl std::fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Finished => write!(f, "terminated via @"),
+            Self::InputEofLoop => write!(
+                f,
+                "stopped while reading past the end of the input in a loop"
+            ),
+            Self::StepLimit => write!(f, "stopped by the step limit"),
+            Self::Timeout => write!(f, "stopped by the time limit"),
+            Self::OutputLimit => write!(f, "stopped by the output limit"),
+            Self::Stalled => write!(f, "stopped while the interpreted program made no progress"),
+            Self::Error(why) => write!(f, "{}", why),
+        }
+    }
+}
+
+/// Results of a program run, see [`run`].
+#[derive(Debug)]
+pub struct RunReport {
+    pub output: Vec<u8>,
+    pub exit: ExitReason,
+    pub steps: usize,
+    pub duration: Duration,
+}
+
+/// Run a program with in-memory input and output, capturing its output.
+/// Programs run without any permissions beyond reading their input, see [`RunRequest::interpreter`].
+pub fn run(request: RunRequest) -> RunReport {
+    let mut output = Vec::new();
+    let start = Instant::now();
+    let standard = request.standard.unwrap_or(LanguageStandard::Befunge93);
+    let (exit, steps) = match Interpreter::parse_grid_for_standard(&request.program, standard) {
+        Err(why) => (ExitReason::Error(why), 0),
+        Ok(grid) => {
+            let mut interpreter = request.interpreter(grid, &mut output);
+            let result = interpreter.run_forever();
+            let exit = interpreter.exit_reason(result);
+            (exit, interpreter.steps())
+        }
+    };
+    let duration = start.elapsed();
+
+    RunReport {
+        output,
+        exit,
+        steps,
+        duration,
+    }
+}
@@ -0,0 +1,123 @@
+//! Host functions, which let programs call into the Rust program that embeds the interpreter.
+//!
+//! Host functions are ordinary closures whose arguments and result are converted from and to stack values.
+//! A program calls one by pushing its arguments and ID and executing the host call instruction, see
+//! [`crate::Interpreter::with_host_call_instruction`].
+
+use crate::Int;
+use crate::Stack;
+
+/// A value that a host function takes from the stack.
+pub trait FromStack: Sized {
+    /// Pop the value. Like for any instruction, an empty stack provides zeros.
+    fn from_stack(stack: &mut Stack) -> Self;
+}
+
+impl FromStack for Int {
+    fn from_stack(stack: &mut Stack) -> Self {
+        stack.pop().unwrap_or_default()
+    }
+}
+
+/// Any value except 0 is true.
+impl FromStack for bool {
+    fn from_stack(stack: &mut Stack) -> Self {
+        Int::from_stack(stack) != 0
+    }
+}
+
+/// A zero-terminated string with its first character on top, as string mode pushes it after a 0.
+/// Values that are not characters are replaced by U+FFFD.
+impl FromStack for String {
+    fn from_stack(stack: &mut Stack) -> Self {
+        let mut string = String::new();
+        while let Some(value) = stack.pop().filter(|&value| value != 0) {
+            string.push(
+                u32::try_from(value)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .unwrap_or(char::REPLACEMENT_CHARACTER),
+            );
+        }
+        string
+    }
+}
+
+/// A value that a host function pushes onto the stack as its result.
+pub trait ToStack {
+    fn to_stack(self, stack: &mut Stack);
+}
+
+/// Nothing is pushed.
+impl ToStack for () {
+    fn to_stack(self, _: &mut Stack) {}
+}
+
+impl ToStack for Int {
+    fn to_stack(self, stack: &mut Stack) {
+        stack.push(self);
+    }
+}
+
+/// True is pushed as 1, false as 0.
+impl ToStack for bool {
+    fn to_stack(self, stack: &mut Stack) {
+        stack.push(self.into());
+    }
+}
+
+/// Pushed as a zero-terminated string with its first character on top, so that [`FromStack`] reads it back.
+impl ToStack for &str {
+    fn to_stack(self, stack: &mut Stack) {
+        stack.push(0);
+        stack.extend(self.chars().rev().map(|character| character as Int));
+    }
+}
+
+impl ToStack for String {
+    fn to_stack(self, stack: &mut Stack) {
+        self.as_str().to_stack(stack);
+    }
+}
+
+/// The values are pushed in order, so the last one ends up on top.
+impl<A: ToStack, B: ToStack> ToStack for (A, B) {
+    fn to_stack(self, stack: &mut Stack) {
+        self.0.to_stack(stack);
+        self.1.to_stack(stack);
+    }
+}
+
+/// The arguments of a host function as a tuple. The last argument is popped first, so that programs push the
+/// arguments in order like for the built-in instructions.
+pub trait HostArguments: Sized {
+    fn from_stack(stack: &mut Stack) -> Self;
+}
+
+macro_rules! host_arguments {
+    ($($argument:ident)*; $($popped:ident)*) => {
+        impl<$($argument: FromStack),*> HostArguments for ($($argument,)*) {
+            #[allow(non_snake_case, unused_variables, clippy::unused_unit)]
+            fn from_stack(stack: &mut Stack) -> Self {
+                $(let $popped = $popped::from_stack(stack);)*
+                ($($argument,)*)
+            }
+        }
+    };
+}
+
+host_arguments!(;);
+host_arguments!(A; A);
+host_arguments!(A B; B A);
+host_arguments!(A B C; C B A);
+host_arguments!(A B C D; D C B A);
+
+/// A host function with its arguments and result converted from and to stack values.
+pub type HostFunction<'rw> = Box<dyn FnMut(&mut Stack) + 'rw>;
+
+/// Wrap a closure taking a tuple of arguments into a host function.
+pub fn host_function<'rw, A: HostArguments, R: ToStack>(
+    mut function: impl FnMut(A) -> R + 'rw,
+) -> HostFunction<'rw> {
+    Box::new(move |stack| function(A::from_stack(stack)).to_stack(stack))
+}
@@ -0,0 +1,132 @@
+//! Write hot-spots: cells that `p` writes to over and over. Programs that rewrite the same cells in a loop, e.g. a
+//! counter kept in the playfield that is updated on every iteration, often do so by accident and spend much of their
+//! time on it, which is easy to miss in the source.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use argh::FromArgValue;
+
+use crate::Position;
+
+/// Cells listed in the summary of [`WriteHotspots`].
+pub const HOTSPOT_SUMMARY_CELLS: usize = 10;
+
+/// Writes to a single cell that are too many, given as `writes/steps`: more than `writes` writes within `steps`
+/// steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WriteRate {
+    pub writes: usize,
+    pub steps: usize,
+}
+
+impl FromArgValue for WriteRate {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid write rate '{}', expected \"writes/steps\"", value);
+        let (writes, steps) = value.split_once('/').ok_or_else(invalid)?;
+        let (Ok(writes), Ok(steps)) = (writes.trim().parse(), steps.trim().parse()) else {
+            return Err(invalid());
+        };
+        if steps == 0 {
+            return Err(invalid());
+        }
+        Ok(Self { writes, steps })
+    }
+}
+
+/// Writes to one cell, see [`WriteHotspots`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CellWrites {
+    pub total: usize,
+    /// Most writes within a single window of steps.
+    pub peak: usize,
+    /// Step at which the cell was first written more often than the rate allows.
+    pub flagged_at: Option<usize>,
+    window: usize,
+    in_window: usize,
+}
+
+/// Counts the writes to every cell, in consecutive windows of [`WriteRate::steps`] steps, and flags the cells written
+/// more often than the rate allows within one window.
+#[derive(Clone, Debug)]
+pub struct WriteHotspots {
+    rate: WriteRate,
+    cells: HashMap<Position, CellWrites>,
+}
+
+impl WriteHotspots {
+    pub fn new(rate: WriteRate) -> Self {
+        Self {
+            rate,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, position: Position, step: usize) {
+        let cell = self.cells.entry(position).or_default();
+        let window = step / self.rate.steps;
+        if window != cell.window {
+            cell.window = window;
+            cell.in_window = 0;
+        }
+        cell.in_window += 1;
+        cell.total += 1;
+        cell.peak = cell.peak.max(cell.in_window);
+        if cell.in_window > self.rate.writes && cell.flagged_at.is_none() {
+            cell.flagged_at = Some(step);
+        }
+    }
+
+    pub fn rate(&self) -> WriteRate {
+        self.rate
+    }
+
+    /// The written cells, flagged cells first and then by their number of writes, most first.
+    pub fn hotspots(&self) -> Vec<(Position, CellWrites)> {
+        let mut cells = self
+            .cells
+            .iter()
+            .map(|(&position, &writes)| (position, writes))
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|(position, writes)| {
+            (
+                writes.flagged_at.is_none(),
+                usize::MAX - writes.total,
+                position.y,
+                position.x,
+            )
+        });
+        cells
+    }
+}
+
+/// A summary of the flagged cells and the cells written most, one per line.
+impl Display for WriteHotspots {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hotspots = self.hotspots();
+        let flagged = hotspots
+            .iter()
+            .filter(|(_, writes)| writes.flagged_at.is_some())
+            .count();
+        writeln!(
+            f,
+            "{} cells written, {} of them more than {} times within {} steps",
+            hotspots.len(),
+            flagged,
+            self.rate.writes,
+            self.rate.steps
+        )?;
+        for (position, writes) in hotspots.iter().take(HOTSPOT_SUMMARY_CELLS) {
+            write!(
+                f,
+                "  {},{}: {} writes, at most {} within {} steps",
+                position.x, position.y, writes.total, writes.peak, self.rate.steps
+            )?;
+            if let Some(step) = writes.flagged_at {
+                write!(f, ", flagged at step {}", step)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
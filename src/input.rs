@@ -0,0 +1,93 @@
+//! Program input that can be given up on.
+//!
+//! Reading from stdin blocks until the user types something, and while the interpreter waits in `~` or `&`, it can't
+//! check its time limit. A [`ThreadedInput`] reads on a separate thread instead and hands the bytes over through a
+//! channel, so that waiting for them can end at a deadline.
+
+use std::io;
+use std::io::Read;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Instant;
+
+/// Size of the chunks the reader thread reads at once.
+const CHUNK_SIZE: usize = 4096;
+
+/// Input read by a separate thread. Reads wait for the thread until the deadline, if there is one, and then fail with
+/// [`io::ErrorKind::TimedOut`].
+pub struct ThreadedInput {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    /// Rest of the chunk received last.
+    chunk: Vec<u8>,
+    offset: usize,
+    deadline: Option<Instant>,
+}
+
+impl ThreadedInput {
+    /// Start reading the input on a new thread. The thread reads at most one chunk ahead of the program and ends
+    /// at the end of the input or once the `ThreadedInput` is dropped and it has read the next chunk.
+    pub fn new(mut input: impl Read + Send + 'static) -> Self {
+        // a rendezvous channel keeps the thread from reading the whole input ahead of the program
+        let (sender, chunks) = mpsc::sync_channel(0);
+        std::thread::spawn(move || loop {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let chunk = match input.read(&mut chunk) {
+                Ok(0) => return,
+                Ok(count) => {
+                    chunk.truncate(count);
+                    Ok(chunk)
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => Err(error),
+            };
+            if sender.send(chunk).is_err() {
+                return;
+            }
+        });
+        Self {
+            chunks,
+            chunk: Vec::new(),
+            offset: 0,
+            deadline: None,
+        }
+    }
+
+    /// Give up waiting for input at the deadline.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+}
+
+impl Read for ThreadedInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset == self.chunk.len() {
+            let chunk = match self.deadline {
+                Some(deadline) => {
+                    match self
+                        .chunks
+                        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    {
+                        Ok(chunk) => chunk,
+                        Err(RecvTimeoutError::Timeout) => {
+                            return Err(io::ErrorKind::TimedOut.into())
+                        }
+                        // the thread ended at the end of the input
+                        Err(RecvTimeoutError::Disconnected) => return Ok(0),
+                    }
+                }
+                None => match self.chunks.recv() {
+                    Ok(chunk) => chunk,
+                    Err(_) => return Ok(0),
+                },
+            };
+            self.chunk = chunk?;
+            self.offset = 0;
+        }
+        let count = buf.len().min(self.chunk.len() - self.offset);
+        buf[..count].copy_from_slice(&self.chunk[self.offset..self.offset + count]);
+        self.offset += count;
+        Ok(count)
+    }
+}
@@ -0,0 +1,421 @@
+//! Per-standard instruction tables.
+
+use crate::LanguageStandard;
+
+/// Instructions available in Befunge-93.
+const BEFUNGE93_INSTRUCTIONS: &[u8] = b" !\"#$%&*+,-./0123456789:<>?@\\^_`gpv|~";
+/// Instructions Befunge-96 adds to Befunge-93: hexadecimal digits, turning, jumping, iterating and the like.
+const BEFUNGE96_INSTRUCTIONS: &[u8] = b"';[]abcdefjknqrswxz";
+/// Instructions Befunge-97 adds to Befunge-96: the stack stack, concurrency and system information.
+const BEFUNGE97_INSTRUCTIONS: &[u8] = b"tuy{}";
+/// Instructions Befunge-98 adds to Befunge-97 that boxfunge implements.
+/// `A` to `Z` only do something once a fingerprint defines them.
+const BEFUNGE98_INSTRUCTIONS: &[u8] = b"()=ABCDEFGHIJKLMNOPQRSTUVWXYZio";
+/// Instructions Trefunge-98 adds to Befunge-98 to move between planes.
+const TREFUNGE98_INSTRUCTIONS: &[u8] = b"hlm";
+
+/// Documentation of a single instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InstructionInfo {
+    pub instruction: u8,
+    pub name: &'static str,
+    /// Values popped and pushed, in Forth notation: `a b -- a+b` pops b, then a, and pushes their sum.
+    pub stack_effect: &'static str,
+    pub description: &'static str,
+}
+
+macro_rules! info {
+    ($instruction:expr, $name:expr, $stack_effect:expr, $description:expr) => {
+        InstructionInfo {
+            instruction: $instruction,
+            name: $name,
+            stack_effect: $stack_effect,
+            description: $description,
+        }
+    };
+}
+
+/// Documentation of every instruction boxfunge knows, in ascending order.
+static INSTRUCTION_INFO: &[InstructionInfo] = &[
+    info!(b' ', "nop", "", "Does nothing."),
+    info!(
+        b'!',
+        "not", "a -- !a", "Pushes 1 if the value is zero, otherwise 0."
+    ),
+    info!(
+        b'"',
+        "string mode",
+        "",
+        "Toggles string mode, in which the value of every cell is pushed until the next \"."
+    ),
+    info!(b'#', "bridge", "", "Skips the next cell."),
+    info!(b'$', "pop", "a --", "Discards the top value."),
+    info!(
+        b'%',
+        "remainder",
+        "a b -- a%b",
+        "Remainder of a divided by b, with the sign of a like in C; 0 if b is zero."
+    ),
+    info!(
+        b'&',
+        "input number", "-- n", "Reads a decimal number from the input."
+    ),
+    info!(
+        b'\'',
+        "fetch character", "-- c", "Pushes the value of the next cell and skips it."
+    ),
+    info!(
+        b'(',
+        "load fingerprint",
+        "f1..fn n -- id 1",
+        "Loads the fingerprint named by the n values, which defines some of A to Z; reflects if it is unknown."
+    ),
+    info!(
+        b')',
+        "unload fingerprint",
+        "f1..fn n --",
+        "Unloads the instructions of the fingerprint named by the n values; reflects if it is unknown."
+    ),
+    info!(b'*', "multiply", "a b -- a*b", "Product of a and b."),
+    info!(b'+', "add", "a b -- a+b", "Sum of a and b."),
+    info!(
+        b',',
+        "output character", "c --", "Outputs the value as an ASCII character."
+    ),
+    info!(b'-', "subtract", "a b -- a-b", "Difference of a and b."),
+    info!(
+        b'.',
+        "output number", "n --", "Outputs the value as a decimal number followed by a space."
+    ),
+    info!(
+        b'/',
+        "divide", "a b -- a/b", "Quotient of a and b, rounded towards zero; 0 if b is zero."
+    ),
+    info!(b'0', "push 0", "-- 0", "Pushes the digit's value."),
+    info!(b'1', "push 1", "-- 1", "Pushes the digit's value."),
+    info!(b'2', "push 2", "-- 2", "Pushes the digit's value."),
+    info!(b'3', "push 3", "-- 3", "Pushes the digit's value."),
+    info!(b'4', "push 4", "-- 4", "Pushes the digit's value."),
+    info!(b'5', "push 5", "-- 5", "Pushes the digit's value."),
+    info!(b'6', "push 6", "-- 6", "Pushes the digit's value."),
+    info!(b'7', "push 7", "-- 7", "Pushes the digit's value."),
+    info!(b'8', "push 8", "-- 8", "Pushes the digit's value."),
+    info!(b'9', "push 9", "-- 9", "Pushes the digit's value."),
+    info!(b':', "duplicate", "a -- a a", "Duplicates the top value."),
+    info!(
+        b';',
+        "jump over", "", "Skips everything up to and including the next ;."
+    ),
+    info!(b'<', "go west", "", "Moves left from now on."),
+    info!(
+        b'=',
+        "execute",
+        "0gnirts -- status",
+        "Runs the string as a command and pushes its exit status; reflects if running commands isn't allowed."
+    ),
+    info!(b'>', "go east", "", "Moves right from now on."),
+    info!(
+        b'?',
+        "go away", "", "Moves in a random direction from now on."
+    ),
+    info!(
+        b'@',
+        "stop", "", "Ends the program, or only the current one of several instruction pointers."
+    ),
+    info!(
+        b'A',
+        "fingerprint A", "", "Runs A of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'B',
+        "fingerprint B", "", "Runs B of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'C',
+        "fingerprint C", "", "Runs C of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'D',
+        "fingerprint D", "", "Runs D of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'E',
+        "fingerprint E", "", "Runs E of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'F',
+        "fingerprint F", "", "Runs F of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'G',
+        "fingerprint G", "", "Runs G of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'H',
+        "fingerprint H", "", "Runs H of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'I',
+        "fingerprint I", "", "Runs I of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'J',
+        "fingerprint J", "", "Runs J of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'K',
+        "fingerprint K", "", "Runs K of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'L',
+        "fingerprint L", "", "Runs L of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'M',
+        "fingerprint M", "", "Runs M of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'N',
+        "fingerprint N", "", "Runs N of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'O',
+        "fingerprint O", "", "Runs O of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'P',
+        "fingerprint P", "", "Runs P of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'Q',
+        "fingerprint Q", "", "Runs Q of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'R',
+        "fingerprint R", "", "Runs R of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'S',
+        "fingerprint S", "", "Runs S of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'T',
+        "fingerprint T", "", "Runs T of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'U',
+        "fingerprint U", "", "Runs U of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'V',
+        "fingerprint V", "", "Runs V of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'W',
+        "fingerprint W", "", "Runs W of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'X',
+        "fingerprint X", "", "Runs X of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'Y',
+        "fingerprint Y", "", "Runs Y of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(
+        b'Z',
+        "fingerprint Z", "", "Runs Z of the fingerprint loaded last that defines it; reflects if there is none."
+    ),
+    info!(b'[', "turn left", "", "Turns 90 degrees counterclockwise."),
+    info!(b'\\', "swap", "a b -- b a", "Swaps the top two values."),
+    info!(b']', "turn right", "", "Turns 90 degrees clockwise."),
+    info!(b'^', "go north", "", "Moves up from now on."),
+    info!(
+        b'_',
+        "east-west if", "a --", "Moves right if the value is zero, otherwise left."
+    ),
+    info!(
+        b'`',
+        "greater than", "a b -- a>b", "Pushes 1 if a is greater than b, otherwise 0."
+    ),
+    info!(b'a', "push 10", "-- 10", "Pushes the hexadecimal digit's value."),
+    info!(b'b', "push 11", "-- 11", "Pushes the hexadecimal digit's value."),
+    info!(b'c', "push 12", "-- 12", "Pushes the hexadecimal digit's value."),
+    info!(b'd', "push 13", "-- 13", "Pushes the hexadecimal digit's value."),
+    info!(b'e', "push 14", "-- 14", "Pushes the hexadecimal digit's value."),
+    info!(b'f', "push 15", "-- 15", "Pushes the hexadecimal digit's value."),
+    info!(
+        b'g',
+        "get", "x y -- v", "Pushes the value of the cell at (x, y); 0 outside the grid."
+    ),
+    info!(b'h', "go high", "", "Moves to the next plane from now on."),
+    info!(
+        b'i',
+        "input file",
+        "0gnirts flags x y -- w h x y",
+        "Loads the named file into the grid at (x, y), as text or with bit 0 of the flags as a single row of bytes, and pushes the size of the loaded area; reflects if the file can't be read."
+    ),
+    info!(
+        b'j',
+        "jump forward", "n --", "Skips n cells; backwards if n is negative."
+    ),
+    info!(
+        b'k',
+        "iterate",
+        "n --",
+        "Runs the next instruction n times in a single step and skips it; reflects if it is another k."
+    ),
+    info!(b'l', "go low", "", "Moves to the previous plane from now on."),
+    info!(
+        b'm',
+        "high-low if",
+        "a --",
+        "Goes low if the value is zero, otherwise high."
+    ),
+    info!(b'n', "clear stack", "a.. --", "Discards all values."),
+    info!(
+        b'o',
+        "output file",
+        "w h x y flags 0gnirts --",
+        "Writes the w by h cells at (x, y) to the named file, one line per row, or with bit 0 of the flags without trailing spaces and empty lines; reflects if the file can't be written."
+    ),
+    info!(
+        b'p',
+        "put", "v x y --", "Stores v in the cell at (x, y); writes outside the grid are ignored."
+    ),
+    info!(
+        b'q',
+        "quit", "n --", "Ends the program with n as the exit code."
+    ),
+    info!(b'r', "reflect", "", "Reverses the direction."),
+    info!(
+        b's',
+        "store character", "c --", "Stores the value in the next cell and skips it."
+    ),
+    info!(
+        b't',
+        "split",
+        "",
+        "Starts a new instruction pointer heading the other way, with a copy of the stacks."
+    ),
+    info!(
+        b'u',
+        "stack under stack",
+        "n --",
+        "Moves n values from the second stack to the current one, or -n values back."
+    ),
+    info!(b'v', "go south", "", "Moves down from now on."),
+    info!(
+        b'w',
+        "compare", "a b --", "Turns left if a is less than b, right if it is greater."
+    ),
+    info!(
+        b'x',
+        "absolute delta",
+        "dx dy --",
+        "Moves along the delta from now on, flying over the cells in between."
+    ),
+    info!(
+        b'y',
+        "system info",
+        "n -- info..",
+        "Pushes information about the interpreter, the program and the system, or only its nth value if n is positive."
+    ),
+    info!(b'z', "no-op", "", "Does nothing."),
+    info!(
+        b'{',
+        "begin block",
+        "a1..an n --",
+        "Pushes a new stack with the top n values and sets the storage offset to the next cell."
+    ),
+    info!(
+        b'|',
+        "north-south if", "a --", "Moves down if the value is zero, otherwise up."
+    ),
+    info!(
+        b'}',
+        "end block",
+        "a1..an n --",
+        "Removes the current stack, moving its top n values to the one below, and restores the storage offset."
+    ),
+    info!(
+        b'~',
+        "input character", "-- c", "Reads a character from the input; -1 at the end of the input."
+    ),
+];
+
+/// The set of instructions that are legal under a language standard.
+/// The interpreter consults this table before executing an instruction, so supporting another standard
+/// means adding its table here instead of checking the standard inside every instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionSet {
+    legal: [bool; 256],
+}
+
+static BEFUNGE93: InstructionSet = InstructionSet::from_instructions(BEFUNGE93_INSTRUCTIONS);
+// Every standard is a superset of the one before it; Befunge-98's own instructions are added as they are implemented.
+static BEFUNGE96: InstructionSet =
+    InstructionSet::from_instructions(BEFUNGE93_INSTRUCTIONS).with(BEFUNGE96_INSTRUCTIONS);
+static BEFUNGE97: InstructionSet = InstructionSet::from_instructions(BEFUNGE93_INSTRUCTIONS)
+    .with(BEFUNGE96_INSTRUCTIONS)
+    .with(BEFUNGE97_INSTRUCTIONS);
+static BEFUNGE98: InstructionSet = InstructionSet::from_instructions(BEFUNGE93_INSTRUCTIONS)
+    .with(BEFUNGE96_INSTRUCTIONS)
+    .with(BEFUNGE97_INSTRUCTIONS)
+    .with(BEFUNGE98_INSTRUCTIONS);
+static TREFUNGE98: InstructionSet = InstructionSet::from_instructions(BEFUNGE93_INSTRUCTIONS)
+    .with(BEFUNGE96_INSTRUCTIONS)
+    .with(BEFUNGE97_INSTRUCTIONS)
+    .with(BEFUNGE98_INSTRUCTIONS)
+    .with(TREFUNGE98_INSTRUCTIONS);
+
+impl InstructionSet {
+    const fn from_instructions(instructions: &[u8]) -> Self {
+        Self {
+            legal: [false; 256],
+        }
+        .with(instructions)
+    }
+
+    const fn with(mut self, instructions: &[u8]) -> Self {
+        let mut i = 0;
+        while i < instructions.len() {
+            self.legal[instructions[i] as usize] = true;
+            i += 1;
+        }
+        self
+    }
+
+    pub fn for_standard(standard: LanguageStandard) -> &'static Self {
+        match standard {
+            LanguageStandard::Befunge93 => &BEFUNGE93,
+            LanguageStandard::Befunge96 => &BEFUNGE96,
+            LanguageStandard::Befunge97 => &BEFUNGE97,
+            LanguageStandard::Befunge98 => &BEFUNGE98,
+            LanguageStandard::Trefunge98 => &TREFUNGE98,
+        }
+    }
+
+    #[inline]
+    pub fn is_legal(&self, instruction: u8) -> bool {
+        self.legal[instruction as usize]
+    }
+
+    /// All legal instructions, in ascending order.
+    pub fn instructions(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=u8::MAX).filter(|&instruction| self.is_legal(instruction))
+    }
+
+    /// Documentation of all legal instructions, in ascending order.
+    pub fn documentation(&self) -> impl Iterator<Item = &'static InstructionInfo> + '_ {
+        INSTRUCTION_INFO
+            .iter()
+            .filter(|info| self.is_legal(info.instruction))
+    }
+
+    /// Documentation of the instruction, if it is legal.
+    pub fn describe(&self, instruction: u8) -> Option<&'static InstructionInfo> {
+        self.documentation()
+            .find(|info| info.instruction == instruction)
+    }
+}
@@ -0,0 +1,188 @@
+//! Lazily loaded program sources for very large Befunge-98 programs.
+//!
+//! Loading a program normally copies every line into the playfield, so a source of several hundred megabytes takes
+//! as long to load and as much memory as its size. A [`LazySource`] only indexes the lines of the source, and a grid
+//! created with [`Grid::lazy`](crate::Grid::lazy) reads the cells outside of the 80x25 area from the source whenever
+//! the program looks at them. Only the chunks the program writes to are copied into the grid.
+//!
+//! With the `mmap` feature, the file is mapped into memory instead of being read, so that the operating system only
+//! loads the pages the program actually touches.
+
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::Bounds;
+use crate::Error;
+use crate::Metadata;
+use crate::Position;
+use crate::GRID_HEIGHT;
+use crate::GRID_WIDTH;
+
+enum Bytes {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Bytes {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Owned(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(map) => map,
+        }
+    }
+}
+
+/// The indexed lines of a program source, whose cells are read on demand.
+pub struct LazySource {
+    bytes: Bytes,
+    /// Range of every line within the bytes, without the line terminator.
+    lines: Vec<Range<usize>>,
+    /// Smallest rectangle containing the non-space cells outside of the 80x25 area, if there are any.
+    far_bounds: Option<Bounds>,
+}
+
+impl LazySource {
+    /// Open a program file and split its metadata header off, like [`Metadata::parse`] does.
+    /// The file is mapped into memory with the `mmap` feature and read otherwise.
+    pub fn open(path: &Path) -> Result<(Metadata, Self), Error> {
+        let file = File::open(path)?;
+        #[cfg(feature = "mmap")]
+        // SAFETY: The map is only ever read, and boxfunge itself never writes to the program file. Other processes
+        // that modify the file while it runs change the program under its feet, just like `p` would.
+        let bytes = Bytes::Mapped(unsafe { memmap2::Mmap::map(&file)? });
+        #[cfg(not(feature = "mmap"))]
+        let bytes = Bytes::Owned({
+            let mut bytes = Vec::new();
+            io::Read::read_to_end(&mut &file, &mut bytes)?;
+            bytes
+        });
+        Self::new(bytes)
+    }
+
+    /// Like [`LazySource::open`], but for a program source that is already in memory.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<(Metadata, Self), Error> {
+        Self::new(Bytes::Owned(bytes))
+    }
+
+    fn new(bytes: Bytes) -> Result<(Metadata, Self), Error> {
+        let source = bytes.as_slice();
+        if let Some(position) = source.iter().position(|byte| !byte.is_ascii()) {
+            // invalid UTF-8 is an I/O error, just like when reading the source into a string
+            let rest = &source[position..];
+            let valid = match std::str::from_utf8(rest) {
+                Ok(rest) => rest,
+                Err(error) => std::str::from_utf8(&rest[..error.valid_up_to()]).unwrap_or_default(),
+            };
+            return Err(match valid.chars().next() {
+                Some(character) => Error::NonAsciiSource(character),
+                None => io::Error::from(io::ErrorKind::InvalidData).into(),
+            });
+        }
+
+        // pure ASCII is valid UTF-8, so this doesn't copy anything
+        let text = std::str::from_utf8(source).unwrap_or_default();
+        let (metadata, program) = Metadata::parse(text)?;
+
+        let mut lines = Vec::new();
+        let mut far_bounds: Option<Bounds> = None;
+        let mut start = source.len() - program.len();
+        while start < source.len() {
+            let end = source[start..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(source.len(), |length| start + length);
+            let next = end + 1;
+            // like str::lines, a carriage return before the line feed belongs to the line terminator
+            let end = if end > start && source[end - 1] == b'\r' && end < source.len() {
+                end - 1
+            } else {
+                end
+            };
+            let y = lines.len() as i64;
+            let line = &source[start..end];
+            // only the part of the line outside of the 80x25 area counts towards the far bounds
+            let far = if lines.len() < GRID_HEIGHT {
+                line.get(GRID_WIDTH..).map(|far| (GRID_WIDTH, far))
+            } else {
+                Some((0, line))
+            };
+            if let Some((offset, far)) = far {
+                let first = far.iter().position(|&cell| cell != b' ');
+                let last = far.iter().rposition(|&cell| cell != b' ');
+                if let (Some(first), Some(last)) = (first, last) {
+                    let min = Position::new((offset + first) as i64, y, 0);
+                    let max = Position::new((offset + last) as i64, y, 0);
+                    far_bounds = Some(match far_bounds {
+                        Some(bounds) => bounds.including(min).including(max),
+                        None => Bounds { min, max },
+                    });
+                }
+            }
+            lines.push(start..end);
+            start = next;
+        }
+        Ok((
+            metadata,
+            Self {
+                bytes,
+                lines,
+                far_bounds,
+            },
+        ))
+    }
+
+    /// The whole source, including the metadata header.
+    pub fn source(&self) -> &[u8] {
+        self.bytes.as_slice()
+    }
+
+    /// The cell at the given position, which is a space outside of the source and on every plane but z = 0.
+    #[inline]
+    pub fn cell(&self, position: Position) -> u8 {
+        let (Ok(x), Ok(y), 0) = (
+            usize::try_from(position.x),
+            usize::try_from(position.y),
+            position.z,
+        ) else {
+            return b' ';
+        };
+        self.lines
+            .get(y)
+            .and_then(|line| self.bytes.as_slice()[line.clone()].get(x))
+            .copied()
+            .unwrap_or(b' ')
+    }
+
+    /// Smallest rectangle containing the non-space cells outside of the 80x25 area, if there are any.
+    pub fn far_bounds(&self) -> Option<Bounds> {
+        self.far_bounds
+    }
+
+    /// All non-space cells outside of the 80x25 area, line by line.
+    pub fn far_cells(&self) -> impl Iterator<Item = (Position, u8)> + '_ {
+        let source = self.bytes.as_slice();
+        self.lines.iter().enumerate().flat_map(move |(y, line)| {
+            let skip = if y < GRID_HEIGHT { GRID_WIDTH } else { 0 };
+            source[line.clone()]
+                .iter()
+                .enumerate()
+                .skip(skip)
+                .filter(|&(_, &cell)| cell != b' ')
+                .map(move |(x, &cell)| (Position::new(x as i64, y as i64, 0), cell))
+        })
+    }
+}
+
+impl std::fmt::Debug for LazySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LazySource")
+            .field("bytes", &self.bytes.as_slice().len())
+            .field("lines", &self.lines.len())
+            .field("far_bounds", &self.far_bounds)
+            .finish()
+    }
+}
@@ -1,105 +1,773 @@
 #![doc = include_str!("../README.md")]
 
 use argh::FromArgValue;
-use argh::FromArgs;
 use rand::distributions::Distribution;
 use rand::distributions::Standard;
 use rand::Rng;
 use rand::SeedableRng;
-use std::ffi::OsString;
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
 use std::fs::File;
-use std::hint::unreachable_unchecked;
 use std::io;
 use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
-use std::process::Command;
 use std::slice;
 use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
 
+use crate::stack::pop_vector;
+use crate::stack::push_vector;
+
+mod annotations;
+mod arithmetic;
+mod audit;
+mod breakpoint;
+mod cli;
+mod daemon;
+mod debugger;
+mod encoding;
+mod execute;
+mod expression;
+mod files;
+mod fingerprint;
+mod float;
+mod flow;
+mod game;
+mod grid;
+mod headless;
+mod host;
+mod hotspots;
+mod input;
+mod instructions;
+mod lazy;
+mod manifest;
+mod matrix;
+mod metadata;
+mod metrics;
+mod nested;
+mod normalize;
+mod options;
+mod overlay;
+mod package;
+mod report;
+mod sandbox;
+mod stack;
+mod state;
+mod symbolic;
+mod sysinfo;
 #[cfg(test)]
 mod test;
+mod watch;
+
+pub use annotations::Annotation;
+pub use annotations::Annotations;
+pub use arithmetic::Remainder;
+pub use audit::audit_determinism;
+pub use audit::Finding;
+pub use breakpoint::Breakpoint;
+pub use breakpoint::Breakpoints;
+pub use breakpoint::Location;
+pub use cli::run_interpreter;
+pub use cli::run_interpreter_with_output;
+pub use daemon::run_daemon;
+pub use daemon::serve_requests;
+pub use debugger::post_mortem;
+pub use encoding::EncodeOutput;
+pub use encoding::Newline;
+pub use encoding::OutputEncoding;
+pub use execute::ExecParadigm;
+pub use expression::Expression;
+pub use files::FileMode;
+pub use files::FileTable;
+pub use files::OpenFile;
+pub use fingerprint::fingerprint_id;
+pub use fingerprint::FileIo;
+pub use fingerprint::Fingerprint;
+pub use fingerprint::Fpdp;
+pub use fingerprint::Fpsp;
+pub use fingerprint::Long;
+pub use fingerprint::Modu;
+pub use fingerprint::Null;
+pub use fingerprint::Orth;
+pub use fingerprint::Outs;
+pub use fingerprint::Roma;
+pub use fingerprint::Semantics;
+pub use fingerprint::Toys;
+pub use fingerprint::FINGERPRINTS;
+pub use flow::Reachability;
+pub use game::GameScript;
+pub use game::InputEvent;
+pub use game::OutputEvent;
+pub use grid::Bounds;
+pub use grid::CellChange;
+pub use grid::Cells;
+pub use grid::Grid;
+pub use grid::GridObserver;
+pub use grid::JournalEntry;
+pub use grid::Line;
+pub use grid::WriteJournal;
+pub use grid::GRID_HEIGHT;
+pub use grid::GRID_WIDTH;
+pub use grid::WIDE_CELL;
+pub use headless::run;
+pub use headless::ExitReason;
+pub use headless::RunReport;
+pub use headless::RunRequest;
+pub use host::host_function;
+pub use host::FromStack;
+pub use host::HostArguments;
+pub use host::HostFunction;
+pub use host::ToStack;
+pub use hotspots::CellWrites;
+pub use hotspots::WriteHotspots;
+pub use hotspots::WriteRate;
+pub use input::ThreadedInput;
+pub use instructions::InstructionInfo;
+pub use instructions::InstructionSet;
+pub use lazy::LazySource;
+pub use manifest::Manifest;
+pub use manifest::Stamp;
+pub use matrix::conformance_matrix;
+pub use matrix::MatrixCase;
+pub use matrix::MatrixEntry;
+pub use metadata::GridStats;
+pub use metadata::Metadata;
+pub use metrics::Metrics;
+pub use nested::run_nested;
+pub use nested::NestedReport;
+pub use nested::NestedRequest;
+pub use normalize::Normalize;
+pub use options::AnalysisOptions;
+pub use options::Arguments;
+pub use options::DaemonOptions;
+pub use options::DebuggerOptions;
+pub use options::SandboxOptions;
+pub use overlay::Overlay;
+pub use overlay::Patch;
+pub use package::Package;
+pub use report::Animation;
+pub use report::Heatmap;
+pub use report::Report;
+pub use report::Transcript;
+pub use sandbox::Sandbox;
+pub use sandbox::SandboxProfile;
+pub use stack::Stack;
+pub use stack::StackFormat;
+pub use stack::StackStack;
+pub use state::State;
+pub use symbolic::analyze_paths;
+pub use symbolic::find_endless_loop;
+pub use symbolic::BranchPoint;
+pub use symbolic::EndlessLoop;
+pub use symbolic::GridWrite;
+pub use symbolic::PathAnalysis;
+pub use symbolic::StringLiteral;
+pub use symbolic::Symbol;
+pub use sysinfo::MachineState;
+pub use sysinfo::SystemInfo;
+pub use sysinfo::SystemInfoItem;
+pub use watch::Watches;
 
 /// "each cell of the stack can hold as much as a C language signed long int on the same platform."
-type Int = std::ffi::c_long;
+pub type Int = std::ffi::c_long;
+
+/// Initial capacity of the stack. Most programs never need more, so the stack is usually never reallocated.
+pub const DEFAULT_STACK_CAPACITY: usize = 64;
+
+/// Number of consecutive reads at EOF after which a program stopped by a limit is considered stuck in an EOF loop.
+pub const EOF_LOOP_READS: usize = 1000;
 
-pub const GRID_HEIGHT: usize = 25;
-pub const GRID_WIDTH: usize = 80;
-pub type Line = [u8; GRID_WIDTH];
-pub type Grid = [Line; GRID_HEIGHT];
-type Stack = Vec<Int>;
+/// Largest number of cells the Befunge-98 `o` instruction writes to a file at once.
+pub const MAX_FILE_CELLS: Int = 1 << 24;
+
+/// Steps between checks of the clock for timeouts and time slices, since checking it every step would be needlessly
+/// slow.
+const CLOCK_CHECK_INTERVAL: usize = 4096;
+
+/// Separates the planes of Trefunge-98 programs, like line feeds separate their lines.
+pub const FORM_FEED: u8 = 0x0c;
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum LanguageStandard {
     Befunge93,
+    /// The first draft of Befunge-98: Befunge-93 on the same 80x25 grid with a first set of new instructions,
+    /// under which illegal instructions reflect.
+    Befunge96,
+    /// The second draft of Befunge-98, which adds the stack stack, concurrency and `y` to Befunge-96 and makes the
+    /// playfield unbounded, but has neither fingerprints nor `i`, `o` and `=`.
+    Befunge97,
     #[default]
     Befunge98,
+    /// Befunge-98 in three dimensions, with the `h`, `l` and `m` instructions to move between planes.
+    Trefunge98,
+}
+
+impl LanguageStandard {
+    /// Whether the playfield is unbounded, so that programs may be of any size.
+    pub fn is_unbounded(self) -> bool {
+        matches!(self, Self::Befunge97 | Self::Befunge98 | Self::Trefunge98)
+    }
+
+    /// Number of coordinates of positions and vectors, such as those `g`, `p` and `x` pop.
+    pub fn dimensions(self) -> usize {
+        match self {
+            Self::Trefunge98 => 3,
+            _ => 2,
+        }
+    }
+
+    /// The standard of a program file by its extension, for Trefunge-98 programs, which are usually named `.tf` or
+    /// `.t98`.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "tf" | "t98" => Some(Self::Trefunge98),
+            _ => None,
+        }
+    }
+
+    /// Whether illegal instructions and reading at the end of the input reflect the program counter instead of being
+    /// errors.
+    pub fn reflects(self) -> bool {
+        self != Self::Befunge93
+    }
 }
 
 impl FromArgValue for LanguageStandard {
     fn from_arg_value(value: &str) -> Result<Self, String> {
         Ok(match value {
             "93" => Self::Befunge93,
+            "96" => Self::Befunge96,
+            "97" => Self::Befunge97,
             "98" => Self::Befunge98,
-            _ => {
-                return Err(
-                    "unknown Befunge language standard, possible values are [98, 93]".to_string(),
-                )
-            }
+            "trefunge" => Self::Trefunge98,
+            _ => return Err(
+                "unknown Befunge language standard, possible values are [98, 97, 96, 93, trefunge]"
+                    .to_string(),
+            ),
+        })
+    }
+}
+
+impl std::fmt::Display for LanguageStandard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Befunge93 => "93",
+            Self::Befunge96 => "96",
+            Self::Befunge97 => "97",
+            Self::Befunge98 => "98",
+            Self::Trefunge98 => "trefunge",
+        })
+    }
+}
+
+/// Where a program's input comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputSource {
+    File(PathBuf),
+    /// The process's stdin.
+    Stdin,
+    /// The bytes of a string given on the command line.
+    Text(String),
+    /// Every read immediately reaches EOF.
+    Null,
+    /// Reads never end and only produce zero bytes.
+    Zero,
+}
+
+impl InputSource {
+    pub fn open(&self) -> Result<Box<dyn Read + Send>, io::Error> {
+        Ok(match self {
+            Self::File(path) => Box::new(File::open(path)?),
+            Self::Stdin => Box::new(io::stdin()),
+            Self::Text(text) => Box::new(io::Cursor::new(text.clone().into_bytes())),
+            Self::Null => Box::new(io::empty()),
+            Self::Zero => Box::new(io::repeat(0)),
+        })
+    }
+
+    /// Open the sources as a single input that reads them one after another and ends with the last one.
+    /// No sources at all are the process's stdin.
+    pub fn open_all(sources: &[Self]) -> Result<Box<dyn Read + Send>, io::Error> {
+        let Some((first, rest)) = sources.split_first() else {
+            return Self::Stdin.open();
+        };
+        rest.iter().try_fold(first.open()?, |input, source| {
+            Ok(Box::new(input.chain(source.open()?)) as Box<dyn Read + Send>)
         })
     }
 }
 
-#[derive(FromArgs)]
-/// Befunge-93 interpreter.
-pub struct Arguments {
-    /// input file to read
-    #[argh(positional)]
-    pub input: PathBuf,
-    /// collect and show performance metrics
-    #[argh(switch, short = 'p')]
-    pub show_performance: bool,
-    /// language standard to use, for future compatibility. default: 98
-    #[argh(option, short = 's', default = "LanguageStandard::default()")]
-    pub language_standard: LanguageStandard,
-    /// file to use as stdin for the program; particularly useful with self-interpreters
-    #[argh(option, short = 'i')]
-    pub stdin: Option<PathBuf>,
-    /// output program name. If this is given, boxfunge produces an executable from the given source file instead of running it.
-    #[argh(option, short = 'o')]
-    pub output: Option<PathBuf>,
+impl FromArgValue for InputSource {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "-" => Self::Stdin,
+            "null" => Self::Null,
+            "zero" => Self::Zero,
+            _ => Self::File(value.into()),
+        })
+    }
 }
 
-type Position = glam::I64Vec2;
+/// A position in funge-space. Befunge programs only ever use the plane at z = 0, Trefunge-98 programs use all three
+/// dimensions.
+pub type Position = glam::I64Vec3;
+
+/// Formats a position as "[x, y]", or as "[x, y, z]" if it doesn't lie on the plane at z = 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Coordinates(pub Position);
+
+impl std::fmt::Display for Coordinates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.z {
+            0 => write!(f, "[{}, {}]", self.0.x, self.0.y),
+            z => write!(f, "[{}, {}, {}]", self.0.x, self.0.y, z),
+        }
+    }
+}
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
-enum Direction {
+pub enum Direction {
     Up,
     Down,
     Left,
     #[default]
     Right,
+    /// Towards the next plane, as in Trefunge-98 only.
+    High,
+    /// Towards the previous plane, as in Trefunge-98 only.
+    Low,
+}
+
+/// How far the program counter moves in a step. Befunge-93 only moves a single cell up, down, left or right, while
+/// Befunge-98 programs can set any delta with `x`, and the program counter then flies over the cells in between.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Delta(pub Position);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PC {
+    pub position: Position,
+    pub delta: Delta,
+}
+
+impl FromArgValue for Direction {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            ">" | "right" => Self::Right,
+            "<" | "left" => Self::Left,
+            "^" | "up" => Self::Up,
+            "v" | "down" => Self::Down,
+            "h" | "high" => Self::High,
+            "l" | "low" => Self::Low,
+            _ => {
+                return Err(format!(
+                    "unknown direction '{}', possible values are [>, <, ^, v, h, l]",
+                    value
+                ))
+            }
+        })
+    }
+}
+
+/// Parses either a direction or a delta given as "dx,dy" or "dx,dy,dz".
+impl FromArgValue for Delta {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        if let Ok(direction) = Direction::from_arg_value(value) {
+            return Ok(direction.into());
+        }
+        match value
+            .split(',')
+            .map(|part| part.trim().parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .as_deref()
+        {
+            Ok(&[x, y]) => Ok(Self::new(x, y, 0)),
+            Ok(&[x, y, z]) => Ok(Self::new(x, y, z)),
+            _ => Err(format!(
+                "unknown delta '{}', expected one of [>, <, ^, v, h, l], \"dx,dy\" or \"dx,dy,dz\"",
+                value
+            )),
+        }
+    }
+}
+
+impl FromArgValue for PC {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        let parts = value.split(',').map(str::trim).collect::<Vec<_>>();
+        let ([x, y, direction] | [x, y, _, direction]) = parts[..] else {
+            return Err(format!(
+                "invalid start '{}', expected \"x,y,direction\" or \"x,y,z,direction\"",
+                value
+            ));
+        };
+        // whether the start lies within the grid depends on the standard, see `check_start`
+        let parse_coordinate = |coordinate: &str| {
+            coordinate
+                .parse::<i64>()
+                .map_err(|_| format!("invalid start coordinate '{}'", coordinate))
+        };
+        Ok(Self {
+            position: Position::new(
+                parse_coordinate(x)?,
+                parse_coordinate(y)?,
+                match parts[..] {
+                    [_, _, z, _] => parse_coordinate(z)?,
+                    _ => 0,
+                },
+            ),
+            delta: Direction::from_arg_value(direction)?.into(),
+        })
+    }
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Up => "^",
+            Self::Down => "v",
+            Self::Left => "<",
+            Self::Right => ">",
+            Self::High => "h",
+            Self::Low => "l",
+        })
+    }
+}
+
+/// Formats the delta as the instruction of its direction, or as "dx,dy" if it has none, with the third coordinate
+/// only if it isn't 0.
+impl std::fmt::Display for Delta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.direction() {
+            Some(direction) => write!(f, "{}", direction),
+            None if self.0.z == 0 => write!(f, "{},{}", self.0.x, self.0.y),
+            None => write!(f, "{},{},{}", self.0.x, self.0.y, self.0.z),
+        }
+    }
+}
+
+/// Formats the program counter as "x,y,direction", with the third coordinate only if it isn't 0.
+impl std::fmt::Display for PC {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position.z {
+            0 => write!(f, "{},{},{}", self.position.x, self.position.y, self.delta),
+            z => write!(
+                f,
+                "{},{},{},{}",
+                self.position.x, self.position.y, z, self.delta
+            ),
+        }
+    }
+}
+
+/// How the program counter behaves when it leaves the playfield.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum WrapMode {
+    /// Wrap around the edges of the 80x25 grid, as in Befunge-93.
+    #[default]
+    Torus,
+    /// Wrap around the edges of the smallest rectangle containing all non-space cells, as in Befunge-98.
+    /// The rectangle grows with every non-space cell written outside of it, but never shrinks.
+    Lahey,
+    /// Reverse direction at the edges of the grid, so that the edge cell is followed by its neighbor.
+    Reflect,
+}
+
+impl WrapMode {
+    /// The wrapping the language standard specifies.
+    pub fn for_standard(standard: LanguageStandard) -> Self {
+        match standard {
+            LanguageStandard::Befunge93 | LanguageStandard::Befunge96 => Self::Torus,
+            LanguageStandard::Befunge97
+            | LanguageStandard::Befunge98
+            | LanguageStandard::Trefunge98 => Self::Lahey,
+        }
+    }
+}
+
+impl FromArgValue for WrapMode {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "torus" => Self::Torus,
+            "lahey" => Self::Lahey,
+            "reflect" => Self::Reflect,
+            _ => {
+                return Err(
+                    "unknown wrap mode, possible values are [torus, lahey, reflect]".to_string(),
+                )
+            }
+        })
+    }
+}
+
+/// How string mode treats runs of spaces.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StringSpaces {
+    /// Push every space, as in Befunge-93.
+    #[default]
+    Literal,
+    /// Push a single space for a run of spaces in a single step, as in Befunge-98.
+    Sgml,
+}
+
+impl FromArgValue for StringSpaces {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "literal" => Self::Literal,
+            "sgml" => Self::Sgml,
+            _ => {
+                return Err(
+                    "unknown string space mode, possible values are [literal, sgml]".to_string(),
+                )
+            }
+        })
+    }
+}
+
+/// How cells store the values written by `p` and `s`, and how `g`, `'` and string mode read them back.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CellValues {
+    /// Cells are bytes, read back as -128 to 127 like in most Befunge-93 interpreters.
+    #[default]
+    Signed,
+    /// Cells are bytes, read back as 0 to 255.
+    Unsigned,
+    /// Cells store any value unchanged, as in Befunge-98.
+    Full,
+}
+
+impl CellValues {
+    /// The cell values the language standard specifies.
+    pub fn for_standard(standard: LanguageStandard) -> Self {
+        match standard {
+            LanguageStandard::Befunge93 | LanguageStandard::Befunge96 => Self::Signed,
+            LanguageStandard::Befunge97
+            | LanguageStandard::Befunge98
+            | LanguageStandard::Trefunge98 => Self::Full,
+        }
+    }
+}
+
+impl FromArgValue for CellValues {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "signed" => Self::Signed,
+            "unsigned" => Self::Unsigned,
+            "full" => Self::Full,
+            _ => {
+                return Err(
+                    "unknown cell value model, possible values are [signed, unsigned, full]"
+                        .to_string(),
+                )
+            }
+        })
+    }
 }
 
+/// What `#` does when the cell it skips lies beyond the edge of the playfield.
+/// Reference interpreters disagree on this, and programs depend on either behavior.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
-struct PC {
-    position: Position,
-    direction: Direction,
+pub enum BridgeEdge {
+    /// Skip the first cell on the other side of the edge.
+    #[default]
+    Skip,
+    /// Land on the first cell on the other side of the edge, like the reference implementation `bef`
+    /// does at the left and top edges.
+    Land,
+}
+
+/// What happens when `p` writes to a write-protected region of the playfield.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ProtectPolicy {
+    /// Leave the cell unchanged and continue.
+    Ignore,
+    /// Stop the program with [`Error::ProtectedWrite`].
+    #[default]
+    Error,
+}
+
+/// Where program output goes, which programs choose with the OUTS fingerprint.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OutputStream {
+    #[default]
+    Standard,
+    Error,
+}
+
+impl FromArgValue for ProtectPolicy {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "ignore" => Self::Ignore,
+            "error" => Self::Error,
+            _ => {
+                return Err(
+                    "unknown protect policy, possible values are [ignore, error]".to_string(),
+                )
+            }
+        })
+    }
+}
+
+impl FromArgValue for BridgeEdge {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "skip" => Self::Skip,
+            "land" => Self::Land,
+            _ => {
+                return Err("unknown bridge edge mode, possible values are [skip, land]".to_string())
+            }
+        })
+    }
+}
+
+impl Direction {
+    pub fn reversed(self) -> Self {
+        match self {
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+            Self::High => Self::Low,
+            Self::Low => Self::High,
+        }
+    }
+}
+
+impl Delta {
+    pub const fn new(x: i64, y: i64, z: i64) -> Self {
+        Self(Position::new(x, y, z))
+    }
+
+    /// The direction of the delta, if it is a single step up, down, left, right, high or low.
+    pub fn direction(self) -> Option<Direction> {
+        Some(match self.0.to_array() {
+            [0, -1, 0] => Direction::Up,
+            [0, 1, 0] => Direction::Down,
+            [-1, 0, 0] => Direction::Left,
+            [1, 0, 0] => Direction::Right,
+            [0, 0, 1] => Direction::High,
+            [0, 0, -1] => Direction::Low,
+            _ => return None,
+        })
+    }
+
+    pub fn reversed(self) -> Self {
+        Self(Position::ZERO.wrapping_sub(self.0))
+    }
+
+    /// The delta after turning 90 degrees to the left, counterclockwise on screen. Trefunge-98 turns around the
+    /// z axis, keeping the movement between planes.
+    pub fn turned_left(self) -> Self {
+        Self::new(self.0.y, self.0.x.wrapping_neg(), self.0.z)
+    }
+
+    /// The delta after turning 90 degrees to the right, clockwise on screen.
+    pub fn turned_right(self) -> Self {
+        self.turned_left().reversed()
+    }
+}
+
+impl Default for Delta {
+    fn default() -> Self {
+        Direction::default().into()
+    }
+}
+
+impl From<Direction> for Delta {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Up => Self::new(0, -1, 0),
+            Direction::Down => Self::new(0, 1, 0),
+            Direction::Left => Self::new(-1, 0, 0),
+            Direction::Right => Self::new(1, 0, 0),
+            Direction::High => Self::new(0, 0, 1),
+            Direction::Low => Self::new(0, 0, -1),
+        }
+    }
 }
 
 impl PC {
     pub fn step(&mut self) {
-        self.position += self.direction;
+        self.position += self.delta;
+    }
+
+    /// How many steps the program counter can take from its position without leaving the bounds, which it has to be
+    /// inside of. A delta of zero never leaves them.
+    fn steps_within(&self, bounds: &Bounds) -> i128 {
+        let steps = |position: i64, delta: i64, min: i64, max: i64| match delta.signum() {
+            1 => (max as i128 - position as i128) / delta as i128,
+            -1 => (position as i128 - min as i128) / -(delta as i128),
+            _ => i128::MAX,
+        };
+        steps(self.position.x, self.delta.0.x, bounds.min.x, bounds.max.x)
+            .min(steps(
+                self.position.y,
+                self.delta.0.y,
+                bounds.min.y,
+                bounds.max.y,
+            ))
+            .min(steps(
+                self.position.z,
+                self.delta.0.z,
+                bounds.min.z,
+                bounds.max.z,
+            ))
     }
 
-    pub fn constrain(&mut self) {
-        if !(0..GRID_WIDTH).contains(&(self.position.x as usize)) {
-            self.position.x = (self.position.x + GRID_WIDTH as i64) % GRID_WIDTH as i64;
+    /// Bring the program counter back into the bounds after it has stepped outside of them. A step beyond the limits
+    /// of `i64` wraps around to the other limit first, which this undoes.
+    #[inline]
+    pub fn constrain(&mut self, wrap_mode: WrapMode, bounds: &Bounds) {
+        if !bounds.contains(self.position) {
+            self.wrap(wrap_mode, bounds);
         }
-        if !(0..GRID_HEIGHT).contains(&(self.position.y as usize)) {
-            self.position.y = (self.position.y + GRID_HEIGHT as i64) % GRID_HEIGHT as i64;
+    }
+
+    #[cold]
+    fn wrap(&mut self, wrap_mode: WrapMode, bounds: &Bounds) {
+        let delta = self.delta.0;
+        let from = self.position.wrapping_sub(delta);
+        match wrap_mode {
+            // Go back along the delta to the last cell within the bounds, which is where the program counter enters
+            // them again when it goes on. For a single step, that is the cell at the opposite edge.
+            WrapMode::Torus | WrapMode::Lahey if bounds.contains(from) => {
+                let back = PC {
+                    position: from,
+                    delta: self.delta.reversed(),
+                };
+                let steps = back.steps_within(bounds);
+                self.position = Position::new(
+                    (from.x as i128 - steps * delta.x as i128) as i64,
+                    (from.y as i128 - steps * delta.y as i128) as i64,
+                    (from.z as i128 - steps * delta.z as i128) as i64,
+                );
+            }
+            // the program counter was put outside of the bounds, e.g. by a snapshot or a fingerprint
+            WrapMode::Torus | WrapMode::Lahey => {
+                // bounds of unbounded grids can be wider than i64, and so can the step that left them
+                let wrap = |from: i64, delta: i64, min: i64, max: i64| {
+                    let size = max as i128 - min as i128 + 1;
+                    ((from as i128 + delta as i128 - min as i128).rem_euclid(size) + min as i128)
+                        as i64
+                };
+                self.position = Position::new(
+                    wrap(from.x, delta.x, bounds.min.x, bounds.max.x),
+                    wrap(from.y, delta.y, bounds.min.y, bounds.max.y),
+                    wrap(from.z, delta.z, bounds.min.z, bounds.max.z),
+                );
+            }
+            WrapMode::Reflect => {
+                self.position = match bounds.contains(from) {
+                    true => from,
+                    false => self.position.clamp(bounds.min, bounds.max),
+                };
+                self.delta = self.delta.reversed();
+                self.step();
+                // for a bounds one cell wide, stepping back leaves them again
+                self.position = self.position.clamp(bounds.min, bounds.max);
+            }
         }
     }
 }
@@ -110,27 +778,22 @@ impl Distribution<Direction> for Standard {
             0 => Direction::Up,
             1 => Direction::Down,
             2 => Direction::Left,
-            3 => Direction::Right,
-            _ => unsafe { unreachable_unchecked() },
+            _ => Direction::Right,
         }
     }
 }
 
-impl std::ops::Add<Direction> for Position {
+impl std::ops::Add<Delta> for Position {
     type Output = Self;
 
-    fn add(self, rhs: Direction) -> Self::Output {
-        self + Position::from(match rhs {
-            Direction::Up => (0, -1),
-            Direction::Down => (0, 1),
-            Direction::Left => (-1, 0),
-            Direction::Right => (1, 0),
-        })
+    /// Wraps around at the limits of `i64`, see [`PC::constrain`].
+    fn add(self, rhs: Delta) -> Self::Output {
+        self.wrapping_add(rhs.0)
     }
 }
 
-impl std::ops::AddAssign<Direction> for Position {
-    fn add_assign(&mut self, rhs: Direction) {
+impl std::ops::AddAssign<Delta> for Position {
+    fn add_assign(&mut self, rhs: Delta) {
         *self = *self + rhs;
     }
 }
@@ -143,204 +806,1820 @@ pub trait Executer {
     fn position(&self) -> Position;
 }
 
-/// The Befunge interpreter.
-/// Lifetime parameter is for the I/O structures, which must outlive the interpreter.
-pub struct Interpreter<'rw> {
-    // Data and program
-    program_grid: Grid,
-    // Core state
-    stack: Stack,
-    string_mode: bool,
-    program_counter: PC,
-    // I/O
-    input: Box<dyn Read + 'rw>,
-    output: Box<dyn Write + 'rw>,
-    rng: rand::rngs::SmallRng,
-    // Debugging
-    steps: usize,
-}
+/// The Befunge interpreter.
+/// Lifetime parameter is for the I/O structures, which must outlive the interpreter.
+pub struct Interpreter<'rw> {
+    // Data and program
+    program_grid: Grid,
+    // Core state
+    stacks: StackStack,
+    /// Befunge-98 offset of all coordinates used by `g` and `p`.
+    storage_offset: Position,
+    /// Highest stack depth before any step so far, if it is tracked.
+    peak_stack_depth: Option<usize>,
+    string_mode: bool,
+    program_counter: PC,
+    /// Unique ID of the current instruction pointer, reported by `y`.
+    ip_id: Int,
+    /// Befunge-98 instruction pointers other than the current one, in the order they run next.
+    other_ips: VecDeque<InstructionPointer>,
+    /// ID of the next instruction pointer `t` creates.
+    next_ip_id: Int,
+    /// Whether instruction pointers take turns in order of their IDs, see [`Interpreter::with_deterministic_scheduling`].
+    deterministic_scheduling: bool,
+    /// Steps and time of the instruction pointers' turns, if they are tracked.
+    ip_profile: Option<IpProfile>,
+    /// Befunge-98 fingerprint instructions of the current instruction pointer.
+    semantics: Semantics,
+    /// Fingerprints programs can load.
+    fingerprints: Vec<&'static dyn Fingerprint>,
+    // I/O
+    input: Box<dyn Read + 'rw>,
+    output: Box<dyn Write + 'rw>,
+    /// Where output goes while the program has chosen stderr, see [`OutputStream`].
+    error_output: Box<dyn Write + 'rw>,
+    output_stream: OutputStream,
+    rng: rand::rngs::SmallRng,
+    /// Number of bytes consumed from the input so far.
+    input_offset: usize,
+    /// Exit code requested by the Befunge-98 `q` instruction.
+    exit_status: Int,
+    /// Number of bytes written to the output so far.
+    output_bytes: usize,
+    /// Number of times `~` found the input at EOF since it last read a byte.
+    eof_reads: usize,
+    lenient_numbers: bool,
+    // Configuration
+    standard: LanguageStandard,
+    instructions: &'static InstructionSet,
+    wrap_mode: WrapMode,
+    /// Bounds the program counter wraps around in. For Lahey wrapping, these may lag behind the grid's bounds.
+    wrap_bounds: Bounds,
+    bridge_edge: BridgeEdge,
+    string_spaces: StringSpaces,
+    cell_values: CellValues,
+    /// Regions of the grid that `p` must not write to.
+    protected: Vec<Bounds>,
+    protect_policy: ProtectPolicy,
+    sandbox: Sandbox,
+    /// Whether `i` and `o` may access files if the sandbox allows it.
+    file_io: bool,
+    exec_paradigm: ExecParadigm,
+    system_info: SystemInfo,
+    /// Deadline and the timeout it was derived from.
+    deadline: Option<(Instant, Duration)>,
+    /// Step at which the limits need to be checked next.
+    next_limit_check: usize,
+    paranoid: bool,
+    // Debugging
+    steps: usize,
+    write_journal: Option<WriteJournal>,
+    write_hotspots: Option<WriteHotspots>,
+    stack_dump_instruction: Option<u8>,
+    stack_observers: Vec<StackObserver<'rw>>,
+    // Embedding
+    host_call_instruction: Option<u8>,
+    host_functions: BTreeMap<Int, HostFunction<'rw>>,
+    /// Files the program opened, shared by all instruction pointers.
+    files: FileTable,
+}
+
+/// The state of a program: everything that determines what it does next, apart from its input.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub grid: Grid,
+    /// Top of stack last.
+    pub stack: Vec<Int>,
+    pub program_counter: PC,
+    pub string_mode: bool,
+    /// Befunge-98 stacks below the current one, bottom first.
+    pub stack_stack: Vec<Vec<Int>>,
+    pub storage_offset: Position,
+    pub ip_id: Int,
+    pub semantics: Semantics,
+    /// Befunge-98 instruction pointers other than the current one, in the order they run next.
+    pub other_ips: Vec<InstructionPointer>,
+}
+
+/// A Befunge-98 instruction pointer created by `t`, with the state each thread of a concurrent program has for itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionPointer {
+    pub program_counter: PC,
+    pub string_mode: bool,
+    /// Top of stack last.
+    pub stack: Vec<Int>,
+    /// Stacks below the current one, bottom first.
+    pub stack_stack: Vec<Vec<Int>>,
+    pub storage_offset: Position,
+    /// Unique ID, in the order the instruction pointers were created, reported by `y`.
+    pub id: Int,
+    pub semantics: Semantics,
+}
+
+/// How long an instruction pointer has run, see [`Interpreter::ip_profile`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IpUsage {
+    pub id: Int,
+    pub steps: usize,
+    /// Wall-clock time of the instruction pointer's turns, including the time spent waiting for input.
+    pub time: Duration,
+}
+
+/// Usage of the instruction pointers in their finished turns, and where the current turn started.
+#[derive(Clone, Debug)]
+struct IpProfile {
+    /// In order of the IDs.
+    usage: Vec<IpUsage>,
+    turn_steps: usize,
+    turn_start: Instant,
+}
+
+impl IpProfile {
+    /// Add the current turn up to now to the usage of the instruction pointer that ran it.
+    fn end_turn(&mut self, id: Int, steps: usize) {
+        let now = Instant::now();
+        let index = match self.usage.binary_search_by_key(&id, |usage| usage.id) {
+            Ok(index) => index,
+            Err(index) => {
+                self.usage.insert(
+                    index,
+                    IpUsage {
+                        id,
+                        ..Default::default()
+                    },
+                );
+                index
+            }
+        };
+        self.usage[index].steps += steps - self.turn_steps;
+        self.usage[index].time += now - self.turn_start;
+        self.turn_steps = steps;
+        self.turn_start = now;
+    }
+}
+
+/// A look at one instruction pointer of a running program, see [`Interpreter::ips`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpView<'a> {
+    pub id: Int,
+    pub program_counter: PC,
+    pub string_mode: bool,
+    /// Top of stack last.
+    pub stack: &'a [Int],
+    /// Stacks below the current one, bottom first.
+    pub stack_stack: &'a [Vec<Int>],
+}
+
+/// Callback notified whenever the stack dump instruction is executed, with the instruction's position and the stack.
+pub type StackObserver<'rw> = Box<dyn FnMut(Position, &[Int]) + 'rw>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Input/Output error")]
+    Io(#[from] io::Error),
+    #[error("Grid size {0} x {1} invalid")]
+    InvalidGridSize(usize, usize),
+    #[error("Non-ASCII character \"{0:x}\" in input")]
+    NonAscii(Int),
+    #[error("Non-ASCII character '{0}' in program source")]
+    NonAsciiSource(char),
+    #[error("Invalid number \"{text}\" at input offset {offset}")]
+    InvalidNumber { text: String, offset: usize },
+    #[error("Illegal command '{}' ({command:x})", *.command as char)]
+    IllegalCommand { command: u8 },
+    #[error("Start position {0} outside of the 80x25 grid")]
+    InvalidStart(PC),
+    #[error("Invalid metadata header line \"{0}\"")]
+    InvalidMetadata(String),
+    #[error("Invalid manifest: {0}")]
+    InvalidManifest(String),
+    #[error("Invalid package: {0}")]
+    InvalidPackage(String),
+    #[error("Invalid overlay: {0}")]
+    InvalidOverlay(String),
+    #[error("Invalid annotations: {0}")]
+    InvalidAnnotations(String),
+    #[error("Invalid stack file: {0}")]
+    InvalidStack(String),
+    #[error("Invalid state file: {0}")]
+    InvalidState(String),
+    #[error("Step limit of {0} reached")]
+    StepLimit(usize),
+    #[error("Time limit of {0:?} exceeded")]
+    Timeout(Duration),
+    #[error("Output limit of {0} bytes exceeded")]
+    OutputLimit(usize),
+    #[error("Write to protected cell {}", Coordinates(*.0))]
+    ProtectedWrite(Position),
+    #[error("Internal invariant violated: {0}")]
+    InvariantViolation(&'static str),
+    #[error("Output differs from the expected output")]
+    OutputMismatch,
+    #[error("Determinism audit found problems: {0}")]
+    Nondeterministic(usize),
+    #[error("Breakpoint reached at {}", Coordinates(*.0))]
+    Breakpoint(Position),
+    #[error("Program terminated normally")]
+    ProgramEnd,
+}
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Io(_), Self::Io(_)) => false,
+            (Self::InvalidGridSize(l0, l1), Self::InvalidGridSize(r0, r1)) => l0 == r0 && l1 == r1,
+            (Self::NonAscii(l0), Self::NonAscii(r0)) => l0 == r0,
+            (Self::NonAsciiSource(l0), Self::NonAsciiSource(r0)) => l0 == r0,
+            (
+                Self::InvalidNumber {
+                    text: l_text,
+                    offset: l_offset,
+                },
+                Self::InvalidNumber {
+                    text: r_text,
+                    offset: r_offset,
+                },
+            ) => l_text == r_text && l_offset == r_offset,
+            _ => core::mem::discriminant(self) == core::mem::discriminant(other),
+        }
+    }
+}
+
+impl Error {
+    /// Exit code of the command-line interface when it stops because of this error.
+    /// See the README for the documented list of exit codes.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::ProgramEnd => 0,
+            Self::InvalidGridSize(..)
+            | Self::NonAsciiSource(_)
+            | Self::InvalidMetadata(_)
+            | Self::InvalidManifest(_)
+            | Self::InvalidPackage(_)
+            | Self::InvalidOverlay(_)
+            | Self::InvalidAnnotations(_)
+            | Self::InvalidStack(_)
+            | Self::InvalidState(_) => 2,
+            Self::IllegalCommand { .. } => 3,
+            Self::Io(_) | Self::InvalidNumber { .. } => 4,
+            Self::NonAscii(_) => 5,
+            Self::StepLimit(_) => 6,
+            Self::Timeout(_) => 7,
+            Self::OutputLimit(_) => 8,
+            Self::ProtectedWrite(_) => 9,
+            Self::InvariantViolation(_) => 10,
+            Self::OutputMismatch => 11,
+            Self::Nondeterministic(_) => 12,
+            Self::Breakpoint(_) => 13,
+            Self::InvalidStart(_) => 14,
+        }
+    }
+}
+
+/// Input that yields the program arguments, one per line, before the actual input.
+/// Numeric arguments can be read with `&`, and all arguments can be read with `~` up to the newline.
+pub fn with_arguments<'a>(arguments: &[String], input: Box<dyn Read + 'a>) -> Box<dyn Read + 'a> {
+    if arguments.is_empty() {
+        return input;
+    }
+    let mut queue = arguments.join("\n").into_bytes();
+    queue.push(b'\n');
+    Box::new(io::Cursor::new(queue).chain(input))
+}
+
+/// Input wrapper that mirrors every byte read through it to another stream, highlighted with ANSI colors.
+pub struct EchoInput<R, W> {
+    input: R,
+    echo: W,
+}
+
+impl<R: Read, W: Write> EchoInput<R, W> {
+    pub fn new(input: R, echo: W) -> Self {
+        Self { input, echo }
+    }
+}
+
+impl<R: Read, W: Write> Read for EchoInput<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.input.read(buf)?;
+        if count > 0 {
+            self.echo.write_all(b"\x1b[7m")?;
+            self.echo.write_all(&buf[..count])?;
+            self.echo.write_all(b"\x1b[0m")?;
+            self.echo.flush()?;
+        }
+        Ok(count)
+    }
+}
+
+/// Enough room for any Int in decimal, including the sign, and the trailing space.
+const NUMBER_BUFFER_SIZE: usize = 24;
+
+/// Format the number as `.` outputs it, in decimal followed by a space.
+/// This is considerably faster than going through [`std::fmt`] for programs that output a lot of numbers.
+fn format_number(number: Int, buffer: &mut [u8; NUMBER_BUFFER_SIZE]) -> &[u8] {
+    let mut start = NUMBER_BUFFER_SIZE - 1;
+    buffer[start] = b' ';
+    let mut magnitude = number.unsigned_abs();
+    loop {
+        start -= 1;
+        buffer[start] = b'0' + (magnitude % 10) as u8;
+        magnitude /= 10;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if number < 0 {
+        start -= 1;
+        buffer[start] = b'-';
+    }
+    &buffer[start..]
+}
+
+/// A whitespace-delimited token read by [`read_token`].
+struct Token {
+    /// Number of whitespace bytes skipped before the token.
+    skipped: usize,
+    text: Vec<u8>,
+    /// Number of bytes consumed from the input, including skipped whitespace and the terminating byte.
+    consumed: usize,
+}
+
+/// Longest token [`read_token`] accepts.
+const MAX_TOKEN_LENGTH: usize = 4096;
+
+/// Modified from text_io's implementation to accept Read instead of iterators as an input.
+fn read_token(input: &mut impl Read) -> Result<Token, io::Error> {
+    let mut consumed = 0;
+    let mut next_byte = || {
+        let mut buffer = 0;
+        match input.read_exact(slice::from_mut(&mut buffer)) {
+            Ok(_) => {
+                consumed += 1;
+                Ok(Some(buffer))
+            }
+            Err(why) if why.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(why) => Err(why),
+        }
+    };
+
+    let mut skipped = 0;
+    let mut current = next_byte()?;
+    while current.is_some_and(|byte| (byte as char).is_whitespace()) {
+        skipped += 1;
+        current = next_byte()?;
+    }
+    // Without this, reading a number at the end of input would never terminate.
+    if current.is_none() {
+        return Err(io::Error::from(ErrorKind::UnexpectedEof));
+    }
+
+    // NUL ends a token as well, so that endless zero input can't produce an endless token.
+    // Endless input of other characters can, so overly long tokens are an error; they are no valid numbers anyway.
+    let mut text = Vec::new();
+    while let Some(byte) = current.filter(|byte| !(*byte as char).is_whitespace() && *byte != 0) {
+        if text.len() >= MAX_TOKEN_LENGTH {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("input token is longer than {MAX_TOKEN_LENGTH} bytes"),
+            ));
+        }
+        text.push(byte);
+        current = next_byte()?;
+    }
+
+    Ok(Token {
+        skipped,
+        text,
+        consumed,
+    })
+}
+
+/// Read the next whitespace-delimited value from the input.
+pub fn scan_next<T>(input: &mut impl Read) -> Result<T, io::Error>
+where
+    T: FromStr,
+    <T as FromStr>::Err: std::error::Error + Send + Sync + 'static,
+{
+    match String::from_utf8(read_token(input)?.text) {
+        Ok(s) => {
+            FromStr::from_str(&s).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+        }
+        Err(_) => Err(io::Error::from(io::ErrorKind::InvalidData)),
+    }
+}
+
+/// Parse a number read by `&`. A leading `+` is always accepted; lenient parsing additionally ignores underscores.
+fn parse_number(text: &[u8], lenient: bool) -> Option<Int> {
+    let text = std::str::from_utf8(text).ok()?;
+    if lenient {
+        text.replace('_', "").parse().ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+impl<'rw> Interpreter<'rw> {
+    pub fn new(grid: &str) -> Result<Self, Error> {
+        let input = Box::new(io::stdin());
+        let output = Box::new(io::stdout());
+        Self::new_with_io(grid, input, output)
+    }
+
+    pub fn new_with_io(
+        grid: &str,
+        input: Box<dyn Read + 'rw>,
+        output: Box<dyn Write + 'rw>,
+    ) -> Result<Self, Error> {
+        let parsed_grid = Self::parse_grid(grid)?;
+        Ok(Self::new_with_io_and_grid(parsed_grid, input, output))
+    }
+
+    pub fn new_with_io_and_grid(
+        grid: Grid,
+        input: Box<dyn Read + 'rw>,
+        output: Box<dyn Write + 'rw>,
+    ) -> Self {
+        let start = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        Self {
+            stacks: StackStack {
+                top: Stack::with_capacity(DEFAULT_STACK_CAPACITY),
+                below: Vec::new(),
+            },
+            storage_offset: Position::ZERO,
+            peak_stack_depth: None,
+            program_grid: grid,
+            string_mode: false,
+            program_counter: PC::default(),
+            ip_id: 0,
+            other_ips: VecDeque::new(),
+            next_ip_id: 1,
+            deterministic_scheduling: false,
+            ip_profile: None,
+            semantics: Semantics::default(),
+            fingerprints: FINGERPRINTS.to_vec(),
+            input,
+            output,
+            error_output: Box::new(io::stderr()),
+            output_stream: OutputStream::default(),
+            rng: rand::rngs::SmallRng::seed_from_u64(start.to_bits()),
+            input_offset: 0,
+            exit_status: 0,
+            output_bytes: 0,
+            eof_reads: 0,
+            lenient_numbers: false,
+            standard: LanguageStandard::Befunge93,
+            instructions: InstructionSet::for_standard(LanguageStandard::Befunge93),
+            wrap_mode: WrapMode::default(),
+            wrap_bounds: Bounds::GRID,
+            bridge_edge: BridgeEdge::default(),
+            string_spaces: StringSpaces::default(),
+            cell_values: CellValues::default(),
+            protected: Vec::new(),
+            protect_policy: ProtectPolicy::default(),
+            sandbox: Sandbox::default(),
+            file_io: false,
+            exec_paradigm: ExecParadigm::default(),
+            system_info: SystemInfo::default(),
+            deadline: None,
+            next_limit_check: usize::MAX,
+            paranoid: false,
+            steps: 0,
+            write_journal: None,
+            write_hotspots: None,
+            stack_dump_instruction: None,
+            stack_observers: Vec::new(),
+            host_call_instruction: None,
+            host_functions: BTreeMap::new(),
+            files: FileTable::default(),
+        }
+    }
+
+    /// Parse a Befunge-93 program, which must fit into 80x25 cells.
+    pub fn parse_grid(grid: &str) -> Result<Grid, Error> {
+        Self::parse_grid_for_standard(grid, LanguageStandard::Befunge93)
+    }
+
+    /// Parse a program of the given standard. Befunge-97 and -98 programs may be of any size and get an unbounded grid.
+    /// Every form feed in a Trefunge-98 program starts the next plane at its top left corner.
+    pub fn parse_grid_for_standard(grid: &str, standard: LanguageStandard) -> Result<Grid, Error> {
+        let parse_lines = |plane: &str| {
+            plane
+                .lines()
+                .map(|line| {
+                    line.chars()
+                        .map(|x| {
+                            if x.is_ascii() {
+                                Ok(x as u8)
+                            } else {
+                                Err(Error::NonAsciiSource(x))
+                            }
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                })
+                .collect::<Result<Vec<_>, Error>>()
+        };
+        let mut planes = match standard.dimensions() {
+            3 => grid.split(FORM_FEED as char).collect(),
+            _ => vec![grid],
+        }
+        .into_iter();
+        let mut lines = parse_lines(planes.next().unwrap_or_default())?;
+        let height = lines.len();
+        let width = lines.iter().map(Vec::len).max().unwrap_or(0);
+        let unbounded = standard.is_unbounded();
+        if !unbounded && (height > GRID_HEIGHT || width > GRID_WIDTH) {
+            return Err(Error::InvalidGridSize(width, height));
+        }
+        // everything beyond the 80x25 area is added to the unbounded grid cell by cell
+        let far_lines = lines.split_off(height.min(GRID_HEIGHT));
+        let mut far_columns = Vec::new();
+        let mut grid = Grid::new(
+            Cells::try_from({
+                let mut grid = lines
+                    .into_iter()
+                    .map(|mut line| {
+                        far_columns.push(line.split_off(line.len().min(GRID_WIDTH)));
+                        line.resize(GRID_WIDTH, b' ');
+                        Line::try_from(line).unwrap()
+                    })
+                    .collect::<Vec<_>>();
+                grid.resize(GRID_HEIGHT, [b' '; GRID_WIDTH]);
+                grid
+            })
+            .unwrap(),
+        );
+        grid.set_unbounded(unbounded);
+        let far_cells = far_columns
+            .into_iter()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.into_iter()
+                    .enumerate()
+                    .map(move |(x, cell)| (x + GRID_WIDTH, y, cell))
+            })
+            .chain(far_lines.into_iter().enumerate().flat_map(|(y, line)| {
+                line.into_iter()
+                    .enumerate()
+                    .map(move |(x, cell)| (x, y + GRID_HEIGHT, cell))
+            }));
+        for (x, y, cell) in far_cells {
+            if cell != b' ' {
+                grid.set(x as Int, y as Int, cell);
+            }
+        }
+        for (z, plane) in (1..).zip(planes) {
+            for (y, line) in parse_lines(plane)?.into_iter().enumerate() {
+                for (x, cell) in line.into_iter().enumerate() {
+                    if cell != b' ' {
+                        grid.set_at(Position::new(x as i64, y as i64, z), cell);
+                    }
+                }
+            }
+        }
+        Ok(grid)
+    }
+
+    /// Start execution at the given program counter instead of the top left corner heading right.
+    /// Positions outside a bounded grid are wrapped around, so the standard must be selected first.
+    pub fn with_start(mut self, start: PC) -> Self {
+        self.program_counter = start;
+        if !self.program_grid.is_unbounded() {
+            self.program_counter.position = Position::new(
+                start.position.x.rem_euclid(GRID_WIDTH as i64),
+                start.position.y.rem_euclid(GRID_HEIGHT as i64),
+                0,
+            );
+        }
+        self
+    }
+
+    /// Select the language standard, which determines the legal instructions. The default is Befunge-93.
+    /// Under every standard after Befunge-93, illegal instructions and reading at the end of the input reflect the
+    /// program counter instead of being errors.
+    /// Befunge-97 and -98 also make the grid unbounded, so that `g` and `p` can address any coordinates.
+    /// The wrap mode and cell values are reset to those of the standard, so different ones must be selected afterwards.
+    pub fn with_standard(mut self, standard: LanguageStandard) -> Self {
+        self.standard = standard;
+        self.instructions = InstructionSet::for_standard(standard);
+        self.cell_values = CellValues::for_standard(standard);
+        self.program_grid.set_unbounded(standard.is_unbounded());
+        self.with_wrap_mode(WrapMode::for_standard(standard))
+    }
+
+    /// Exit code requested by the program with the Befunge-98 `q` instruction, 0 otherwise.
+    pub fn exit_status(&self) -> Int {
+        self.exit_status
+    }
+
+    /// The stacks below the current one, bottom first, as created by the Befunge-98 `{` instruction.
+    pub fn stack_stack(&self) -> &[Vec<Int>] {
+        &self.stacks.below
+    }
+
+    pub fn with_wrap_mode(mut self, wrap_mode: WrapMode) -> Self {
+        self.wrap_mode = wrap_mode;
+        self.reset_wrap_bounds();
+        self
+    }
+
+    fn reset_wrap_bounds(&mut self) {
+        self.wrap_bounds = match self.wrap_mode {
+            WrapMode::Lahey => *self.program_grid.bounds(),
+            WrapMode::Torus | WrapMode::Reflect => Bounds::GRID,
+        };
+    }
+
+    /// Slow path of moving the program counter, taken whenever it has left the wrap bounds.
+    #[cold]
+    fn wrap_program_counter(&mut self) {
+        // The grid's bounds only ever grow, so the cached ones might just be outdated.
+        if self.wrap_mode == WrapMode::Lahey {
+            self.wrap_bounds = *self.program_grid.bounds();
+        }
+        self.program_counter
+            .constrain(self.wrap_mode, &self.wrap_bounds);
+    }
+
+    pub fn with_bridge_edge(mut self, bridge_edge: BridgeEdge) -> Self {
+        self.bridge_edge = bridge_edge;
+        self
+    }
+
+    pub fn with_string_spaces(mut self, string_spaces: StringSpaces) -> Self {
+        self.string_spaces = string_spaces;
+        self
+    }
+
+    pub fn with_cell_values(mut self, cell_values: CellValues) -> Self {
+        self.cell_values = cell_values;
+        self
+    }
+
+    /// The value of the cell at the given position according to the cell values; 0 outside a bounded grid.
+    #[inline]
+    fn cell_value(&self, position: Position) -> Int {
+        match self.cell_values {
+            CellValues::Signed => self
+                .program_grid
+                .get_at(position)
+                .map_or(0, |cell| cell as i8 as Int),
+            CellValues::Unsigned => self
+                .program_grid
+                .get_at(position)
+                .map_or(0, |cell| cell as Int),
+            CellValues::Full => self.program_grid.get_value_at(position).unwrap_or(0),
+        }
+    }
+
+    /// The value of the cell under the program counter, which is not ASCII.
+    #[cold]
+    fn current_cell_value(&self) -> Int {
+        self.cell_value(self.program_counter.position)
+    }
+
+    /// Move the program counter onto the last space of the run of spaces it is on, for SGML-style string mode.
+    #[cold]
+    fn skip_string_spaces(&mut self) {
+        if self.wrap_mode == WrapMode::Lahey {
+            self.wrap_bounds = *self.program_grid.bounds();
+        }
+        // a row or column of only spaces would otherwise be skipped forever
+        for _ in 0..self.search_limit() {
+            let mut next = self.program_counter;
+            next.step();
+            next.constrain(self.wrap_mode, &self.wrap_bounds);
+            if self.program_grid.cell(next.position) != b' ' {
+                break;
+            }
+            self.program_counter = next;
+        }
+    }
+
+    /// Number of cells after which moving along a line of the grid has passed every cell on it, even with reflection.
+    fn search_limit(&self) -> usize {
+        let size = self.wrap_bounds.size();
+        (GRID_WIDTH * GRID_HEIGHT).max(2 * size.max_element() as usize)
+    }
+
+    /// Write-protect regions of the grid against `p`, which then behaves according to the policy.
+    pub fn with_protection(mut self, regions: Vec<Bounds>, policy: ProtectPolicy) -> Self {
+        self.protected = regions;
+        self.protect_policy = policy;
+        self
+    }
+
+    /// Enable a debugging instruction that prints the whole stack to stderr without modifying it.
+    /// The instruction must be one that is otherwise illegal in the current standard.
+    pub fn with_stack_dump_instruction(mut self, instruction: u8) -> Self {
+        self.stack_dump_instruction = Some(instruction);
+        self
+    }
+
+    /// Register an observer called whenever the stack dump instruction is executed.
+    pub fn on_stack_dump(&mut self, observer: impl FnMut(Position, &[Int]) + 'rw) {
+        self.stack_observers.push(Box::new(observer));
+    }
+
+    #[cold]
+    fn dump_stack(&mut self) {
+        let position = self.program_counter.position;
+        eprintln!("stack at {}: {:?}", Coordinates(position), self.stacks.top);
+        for observer in &mut self.stack_observers {
+            observer(position, &self.stacks.top);
+        }
+    }
+
+    /// Enable an instruction that pops an ID and calls the host function registered under it, which reflects if
+    /// there is none. The instruction must be one that is otherwise illegal in the current standard.
+    pub fn with_host_call_instruction(mut self, instruction: u8) -> Self {
+        self.host_call_instruction = Some(instruction);
+        self
+    }
+
+    /// Register a host function that programs can call with the host call instruction, replacing any function
+    /// with the same ID. The function takes its arguments as a tuple, e.g. `|(a, b): (Int, String)| ...`,
+    /// which are popped from the stack, and pushes its result, see [`FromStack`] and [`ToStack`].
+    pub fn register_host_function<A: HostArguments, R: ToStack>(
+        &mut self,
+        id: Int,
+        function: impl FnMut(A) -> R + 'rw,
+    ) {
+        self.host_functions.insert(id, host_function(function));
+    }
+
+    #[cold]
+    fn call_host_function(&mut self) {
+        let id = self.stacks.top.pop().unwrap_or_default();
+        match self.host_functions.get_mut(&id) {
+            Some(function) => function(&mut self.stacks.top),
+            None => self.reflect(),
+        }
+    }
+
+    /// Apply the sandbox's limits and permissions. The time limit counts from now.
+    /// The interpreter can't stop while it waits for input, so input that may block for long, like stdin, should be
+    /// wrapped in a [`ThreadedInput`] with the same deadline for the time limit to apply to `~` and `&` as well.
+    pub fn with_sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.deadline = sandbox
+            .timeout
+            .map(|timeout| (Instant::now() + timeout, timeout));
+        self.sandbox = sandbox;
+        self.next_limit_check = self.steps;
+        self
+    }
+
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+
+    /// Let the Befunge-98 `i` and `o` instructions access files if the sandbox allows it. Without this, they reflect
+    /// like in the strict sandbox, so that running a program never touches files unless asked to.
+    pub fn with_file_io(mut self, file_io: bool) -> Self {
+        self.file_io = file_io;
+        self
+    }
+
+    /// Whether `i` and `o` may access files, which requires both [`Self::with_file_io`] and the sandbox's permission.
+    pub fn file_io(&self) -> bool {
+        self.file_io && self.sandbox.allow_filesystem
+    }
+
+    /// Choose how `=` runs commands if the sandbox allows it.
+    pub fn with_exec_paradigm(mut self, exec_paradigm: ExecParadigm) -> Self {
+        self.exec_paradigm = exec_paradigm;
+        self
+    }
+
+    /// How `=` runs commands, which is [`ExecParadigm::Unavailable`] if the sandbox doesn't allow it.
+    pub fn exec_paradigm(&self) -> &ExecParadigm {
+        match self.sandbox.allow_exec {
+            true => &self.exec_paradigm,
+            false => &ExecParadigm::Unavailable,
+        }
+    }
+
+    /// What the Befunge-98 `y` instruction reports about the world outside the interpreter.
+    pub fn with_system_info(mut self, system_info: SystemInfo) -> Self {
+        self.system_info = system_info;
+        self
+    }
+
+    /// Write program output, enforcing the sandbox's output limit.
+    /// Output that would exceed the limit is not written at all.
+    fn write_output(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        if let Some(max_output) = self.sandbox.max_output {
+            if self.output_bytes + bytes.len() > max_output {
+                return Err(Error::OutputLimit(max_output));
+            }
+        }
+        match self.output_stream {
+            OutputStream::Standard => self.output.write_all(bytes)?,
+            OutputStream::Error => self.error_output.write_all(bytes)?,
+        }
+        self.output_bytes += bytes.len();
+        Ok(())
+    }
+
+    /// The error for a failed read. Input that gave up waiting at the deadline means the time limit was exceeded.
+    #[cold]
+    fn input_error(&self, error: io::Error) -> Error {
+        match self.deadline {
+            Some((_, timeout)) if error.kind() == ErrorKind::TimedOut => Error::Timeout(timeout),
+            _ => error.into(),
+        }
+    }
+
+    #[cold]
+    fn check_limits(&mut self) -> Result<(), Error> {
+        let max_steps = self.sandbox.max_steps.unwrap_or(usize::MAX);
+        if self.steps >= max_steps {
+            return Err(Error::StepLimit(max_steps));
+        }
+        self.next_limit_check = max_steps;
+        // concurrent instruction pointers take turns, one step each
+        if let Some(next) = self.other_ips.pop_front() {
+            let previous = self.switch_ip(next);
+            self.other_ips.push_back(previous);
+            self.next_limit_check = self.steps + 1;
+        }
+        if let Some((deadline, timeout)) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout(timeout));
+            }
+            // instruction pointers switching on the next step must not wait for the next clock check
+            self.next_limit_check = self.next_limit_check.min(self.steps + CLOCK_CHECK_INTERVAL);
+        }
+        if self.paranoid {
+            self.check_invariants()?;
+            self.next_limit_check = self.steps + 1;
+        }
+        if let Some(peak) = &mut self.peak_stack_depth {
+            // A step grows the stack by at most one value, so the depth between steps is the peak.
+            *peak = (*peak).max(self.stacks.top.len());
+            self.next_limit_check = self.steps + 1;
+        }
+        Ok(())
+    }
+
+    /// Check internal invariants before every step, stopping with [`Error::InvariantViolation`] if one doesn't hold.
+    /// This makes execution a lot slower.
+    pub fn with_paranoid(mut self, paranoid: bool) -> Self {
+        self.paranoid = paranoid;
+        self.next_limit_check = self.steps;
+        self
+    }
+
+    fn check_invariants(&self) -> Result<(), Error> {
+        let grid_bounds = self.program_grid.bounds();
+        let contains =
+            |outer: &Bounds, inner: &Bounds| outer.contains(inner.min) && outer.contains(inner.max);
+        // an unbounded grid's program counter may be anywhere within its bounds, but it starts within the 80x25 area
+        let space = if self.program_grid.is_unbounded() {
+            Bounds::GRID
+                .including(grid_bounds.min)
+                .including(grid_bounds.max)
+        } else {
+            Bounds::GRID
+        };
+        if !space.contains(self.program_counter.position) {
+            return Err(Error::InvariantViolation(
+                "program counter outside the grid",
+            ));
+        }
+        if !self.program_grid.is_unbounded() && !contains(&Bounds::GRID, grid_bounds) {
+            return Err(Error::InvariantViolation(
+                "grid bounds larger than the grid",
+            ));
+        }
+        // the cached wrap bounds may lag behind the grid's bounds, but never exceed them
+        if self.wrap_mode == WrapMode::Lahey && !contains(grid_bounds, &self.wrap_bounds) {
+            return Err(Error::InvariantViolation(
+                "wrap bounds larger than the grid bounds",
+            ));
+        }
+        if self.wrap_mode != WrapMode::Lahey && self.wrap_bounds != Bounds::GRID {
+            return Err(Error::InvariantViolation(
+                "wrap bounds differ from the grid",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Seed the random number generator used by `?`, making runs reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Record the most recent `capacity` writes to the grid by `p` in a [`WriteJournal`].
+    pub fn with_write_journal(mut self, capacity: usize) -> Self {
+        self.write_journal = Some(WriteJournal::new(capacity));
+        self
+    }
+
+    pub fn write_journal(&self) -> Option<&WriteJournal> {
+        self.write_journal.as_ref()
+    }
+
+    pub fn write_journal_mut(&mut self) -> Option<&mut WriteJournal> {
+        self.write_journal.as_mut()
+    }
+
+    /// Schedule concurrent instruction pointers in strict round-robin order of their creation. Every round runs each
+    /// instruction pointer once, oldest first, and one created by `t` takes its first turn at the end of the round it
+    /// was created in. By default, a new instruction pointer runs right before its parent, as the Funge-98
+    /// specification describes, so the order of a round depends on which instruction pointers split.
+    pub fn with_deterministic_scheduling(mut self, deterministic_scheduling: bool) -> Self {
+        self.deterministic_scheduling = deterministic_scheduling;
+        self.order_ips();
+        self
+    }
+
+    /// Accept underscores as digit separators in numbers read by `&`.
+    pub fn with_lenient_numbers(mut self, lenient_numbers: bool) -> Self {
+        self.lenient_numbers = lenient_numbers;
+        self
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.program_grid
+    }
+
+    /// Mutable access to the grid, e.g. for subscribing to cell changes.
+    pub fn grid_mut(&mut self) -> &mut Grid {
+        &mut self.program_grid
+    }
+
+    /// The current stack, with the top of stack as the last element.
+    pub fn stack(&self) -> &[Int] {
+        &self.stacks.top
+    }
+
+    /// Replace the stack, e.g. with one saved by an earlier run. The top of stack is the last element.
+    pub fn with_stack(mut self, stack: Vec<Int>) -> Self {
+        self.stacks.top.clear();
+        self.stacks.top.extend(stack);
+        self
+    }
+
+    /// Preallocate room for at least this many values on the stack.
+    pub fn with_stack_capacity(mut self, capacity: usize) -> Self {
+        self.stacks
+            .top
+            .reserve(capacity.saturating_sub(self.stacks.top.len()));
+        self
+    }
+
+    /// Count the writes to every cell by `p` and flag those written more often than the rate allows, see
+    /// [`WriteHotspots`].
+    pub fn with_write_hotspots(mut self, rate: WriteRate) -> Self {
+        self.write_hotspots = Some(WriteHotspots::new(rate));
+        self
+    }
+
+    pub fn write_hotspots(&self) -> Option<&WriteHotspots> {
+        self.write_hotspots.as_ref()
+    }
+
+    /// Track the highest number of values the stack holds, see [`Interpreter::peak_stack_depth`].
+    /// Like the paranoid mode, this makes execution slower.
+    pub fn with_stack_stats(mut self, enabled: bool) -> Self {
+        self.peak_stack_depth = enabled.then_some(self.stacks.top.len());
+        self.next_limit_check = self.steps;
+        self
+    }
+
+    /// Highest number of values the stack has held so far, if stack stats are enabled.
+    pub fn peak_stack_depth(&self) -> Option<usize> {
+        self.peak_stack_depth
+            .map(|peak| peak.max(self.stacks.top.len()))
+    }
+
+    /// Attribute steps and time to the instruction pointers that run them, see [`Interpreter::ip_profile`].
+    /// This only costs time when concurrent instruction pointers take turns, and starts the clock of the current one.
+    pub fn with_ip_profile(mut self, enabled: bool) -> Self {
+        self.ip_profile = enabled.then(|| IpProfile {
+            usage: Vec::new(),
+            turn_steps: self.steps,
+            turn_start: Instant::now(),
+        });
+        self
+    }
+
+    /// Steps and time each instruction pointer has run for so far in order of their IDs, if the profile is enabled.
+    /// Instruction pointers that have stopped are included.
+    pub fn ip_profile(&self) -> Option<Vec<IpUsage>> {
+        let mut profile = self.ip_profile.clone()?;
+        profile.end_turn(self.ip_id, self.steps);
+        Some(profile.usage)
+    }
+
+    /// Why the program stopped, given the result of running it.
+    /// A program stopped by a limit while it keeps reading past the end of its input is stuck in an EOF loop.
+    pub fn exit_reason(&self, result: Result<(), Error>) -> ExitReason {
+        match result {
+            Ok(_) | Err(Error::ProgramEnd) => ExitReason::Finished,
+            Err(Error::StepLimit(_) | Error::Timeout(_)) if self.eof_reads >= EOF_LOOP_READS => {
+                ExitReason::InputEofLoop
+            }
+            Err(Error::StepLimit(_)) => ExitReason::StepLimit,
+            Err(Error::Timeout(_)) => ExitReason::Timeout,
+            Err(Error::OutputLimit(_)) => ExitReason::OutputLimit,
+            Err(why) => ExitReason::Error(why),
+        }
+    }
+
+    /// Number of values the stack can hold without reallocating.
+    pub fn stack_capacity(&self) -> usize {
+        self.stacks.top.capacity()
+    }
+
+    /// Capture the program state, which can later be restored with [`Interpreter::restore`].
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            grid: self.program_grid.clone(),
+            stack: self.stacks.top.clone(),
+            program_counter: self.program_counter,
+            string_mode: self.string_mode,
+            stack_stack: self.stacks.below.clone(),
+            storage_offset: self.storage_offset,
+            ip_id: self.ip_id,
+            semantics: self.semantics.clone(),
+            other_ips: self.other_ips.iter().cloned().collect(),
+        }
+    }
+
+    /// Continue from the given program state. Grid observers are kept, but not notified of the new grid.
+    /// A program counter outside the grid is wrapped into it.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.program_grid.replace_cells(&snapshot.grid);
+        self.stacks.top = snapshot.stack;
+        self.program_counter = snapshot.program_counter;
+        // an unbounded grid extends beyond 80x25, but a program counter outside its bounds still has to wrap
+        let bounds = match self.program_grid.is_unbounded() {
+            true => *self.program_grid.bounds(),
+            false => Bounds::GRID,
+        };
+        self.program_counter.constrain(WrapMode::Torus, &bounds);
+        self.string_mode = snapshot.string_mode;
+        self.stacks.below = snapshot.stack_stack;
+        self.storage_offset = snapshot.storage_offset;
+        self.ip_id = snapshot.ip_id;
+        self.semantics = snapshot.semantics;
+        self.other_ips = snapshot.other_ips.into();
+        self.next_ip_id = self
+            .other_ips
+            .iter()
+            .map(|ip| ip.id)
+            .fold(self.ip_id, Int::max)
+            .saturating_add(1);
+        self.order_ips();
+        self.reset_wrap_bounds();
+        // the instruction pointers take turns in check_limits
+        self.next_limit_check = self.steps;
+    }
+
+    /// Push a value onto the stack, e.g. from a fingerprint instruction.
+    pub fn push(&mut self, value: Int) {
+        self.stacks.top.push(value);
+    }
+
+    /// Pop a value from the stack, which is 0 if the stack is empty.
+    pub fn pop(&mut self) -> Int {
+        self.stacks.top.pop().unwrap_or_default()
+    }
+
+    /// Reverse the direction of the current instruction pointer, which is how Befunge-98 instructions fail.
+    pub fn reflect(&mut self) {
+        self.program_counter.delta = self.program_counter.delta.reversed();
+    }
+
+    /// The current instruction pointer's program counter.
+    pub fn program_counter(&self) -> PC {
+        self.program_counter
+    }
+
+    /// The current instruction pointer's program counter, e.g. for a fingerprint instruction that moves it.
+    /// The program counter still moves on after the instruction.
+    pub fn program_counter_mut(&mut self) -> &mut PC {
+        &mut self.program_counter
+    }
+
+    /// The current stack with the top of stack as the last element, e.g. for a fingerprint instruction that pushes
+    /// or pops a string, see [`FromStack`] and [`ToStack`].
+    pub fn stack_mut(&mut self) -> &mut Stack {
+        &mut self.stacks.top
+    }
+
+    /// The files the program opened, e.g. for a fingerprint instruction that opens or reads a file.
+    pub fn files_mut(&mut self) -> &mut FileTable {
+        &mut self.files
+    }
+
+    /// The Befunge-98 offset that `g` and `p` add to their coordinates.
+    pub fn storage_offset(&self) -> Position {
+        self.storage_offset
+    }
+
+    /// Read a cell like `g`, but without adding the storage offset.
+    pub fn read_cell(&self, position: Position) -> Int {
+        self.cell_value(position)
+    }
+
+    /// Write a cell like `p`, but without adding the storage offset.
+    pub fn write_cell(&mut self, position: Position, value: Int) -> Result<(), Error> {
+        self.put(position, value)
+    }
+
+    /// Number of coordinates of the vectors instructions pop and push, see [`LanguageStandard::dimensions`].
+    pub fn dimensions(&self) -> usize {
+        self.standard.dimensions()
+    }
+
+    /// Pop a vector like `g` does, e.g. for a fingerprint instruction, with its last coordinate on top.
+    #[inline]
+    pub fn pop_vector(&mut self) -> Position {
+        pop_vector(&mut self.stacks.top, self.standard.dimensions())
+    }
+
+    /// Push a vector like `y` does, so that [`Interpreter::pop_vector`] pops it back.
+    pub fn push_vector(&mut self, vector: Position) {
+        push_vector(&mut self.stacks.top, vector, self.standard.dimensions());
+    }
+
+    /// Write program output like `,`, e.g. from a fingerprint instruction, enforcing the sandbox's output limit.
+    pub fn output(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.write_output(bytes)
+    }
+
+    /// A random direction, like `?` chooses it. Trefunge-98 programs may also go high or low.
+    pub fn random_direction(&mut self) -> Direction {
+        match self.dimensions() {
+            3 => [
+                Direction::Up,
+                Direction::Down,
+                Direction::Left,
+                Direction::Right,
+                Direction::High,
+                Direction::Low,
+            ][self.rng.gen_range(0..6)],
+            _ => self.rng.gen(),
+        }
+    }
+
+    /// Replace stderr as the output programs can choose instead of their normal output, see [`OutputStream`].
+    pub fn with_error_output(mut self, error_output: Box<dyn Write + 'rw>) -> Self {
+        self.error_output = error_output;
+        self
+    }
+
+    /// Send program output to the given stream from now on. Output written so far is flushed, so that it keeps its
+    /// order with the output to come.
+    pub fn select_output(&mut self, stream: OutputStream) -> Result<(), Error> {
+        match self.output_stream {
+            OutputStream::Standard => self.output.flush()?,
+            OutputStream::Error => self.error_output.flush()?,
+        }
+        self.output_stream = stream;
+        Ok(())
+    }
+
+    pub fn output_stream(&self) -> OutputStream {
+        self.output_stream
+    }
+
+    /// Make a fingerprint available to programs in addition to the built-in ones, replacing any with the same name.
+    pub fn with_fingerprint(mut self, fingerprint: &'static dyn Fingerprint) -> Self {
+        self.fingerprints
+            .retain(|other| other.name() != fingerprint.name());
+        self.fingerprints.push(fingerprint);
+        self
+    }
+
+    /// Whether the current instruction pointer is in string mode.
+    pub fn string_mode(&self) -> bool {
+        self.string_mode
+    }
+
+    /// Number of instruction pointers, which is only ever more than one for concurrent Befunge-98 programs.
+    pub fn ip_count(&self) -> usize {
+        1 + self.other_ips.len()
+    }
+
+    /// ID of the current instruction pointer, as `y` reports it.
+    pub fn ip_id(&self) -> Int {
+        self.ip_id
+    }
+
+    /// The current instruction pointer.
+    pub fn current_ip(&self) -> IpView<'_> {
+        IpView {
+            id: self.ip_id,
+            program_counter: self.program_counter,
+            string_mode: self.string_mode,
+            stack: &self.stacks.top,
+            stack_stack: &self.stacks.below,
+        }
+    }
+
+    /// All instruction pointers, the current one first and the others in the order they run next.
+    pub fn ips(&self) -> impl Iterator<Item = IpView<'_>> {
+        [self.current_ip()]
+            .into_iter()
+            .chain(self.other_ips.iter().map(|ip| IpView {
+                id: ip.id,
+                program_counter: ip.program_counter,
+                string_mode: ip.string_mode,
+                stack: &ip.stack,
+                stack_stack: &ip.stack_stack,
+            }))
+    }
 
-#[derive(Debug, thiserror::Error)]
-pub enum Error {
-    #[error("Input/Output error")]
-    Io(#[from] io::Error),
-    #[error("Grid size {0} x {1} invalid")]
-    InvalidGridSize(usize, usize),
-    #[error("Non-ASCII character \"{0:x}\" in input")]
-    NonAscii(Int),
-    #[error("Illegal command '{}' ({command:x})", *.command as char)]
-    IllegalCommand { command: u8 },
-    #[error("Program terminated normally")]
-    ProgramEnd,
-}
+    /// Switch to the instruction pointer that runs next and check the limits, which [`Interpreter::run_step`]
+    /// otherwise does by itself at the start of the step. Instruments that look at the program counter before a step
+    /// call this first, so that they see the instruction pointer that is about to run.
+    pub fn schedule(&mut self) -> Result<(), Error> {
+        if self.steps >= self.next_limit_check {
+            self.check_limits()?;
+        }
+        Ok(())
+    }
 
-impl PartialEq for Error {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::Io(_), Self::Io(_)) => false,
-            (Self::InvalidGridSize(l0, l1), Self::InvalidGridSize(r0, r1)) => l0 == r0 && l1 == r1,
-            (Self::NonAscii(l0), Self::NonAscii(r0)) => l0 == r0,
-            _ => core::mem::discriminant(self) == core::mem::discriminant(other),
+    /// Make the given instruction pointer the current one, returning the previously current one.
+    fn switch_ip(&mut self, ip: InstructionPointer) -> InstructionPointer {
+        if let Some(profile) = &mut self.ip_profile {
+            profile.end_turn(self.ip_id, self.steps);
+        }
+        InstructionPointer {
+            program_counter: std::mem::replace(&mut self.program_counter, ip.program_counter),
+            string_mode: std::mem::replace(&mut self.string_mode, ip.string_mode),
+            stack: std::mem::replace(&mut self.stacks.top, ip.stack),
+            stack_stack: std::mem::replace(&mut self.stacks.below, ip.stack_stack),
+            storage_offset: std::mem::replace(&mut self.storage_offset, ip.storage_offset),
+            id: std::mem::replace(&mut self.ip_id, ip.id),
+            semantics: std::mem::replace(&mut self.semantics, ip.semantics),
         }
     }
-}
 
-/// Modified from text_io's implementation to accept Read instead of iterators as an input.
-pub fn scan_next<T>(input: &mut impl Read) -> Result<T, io::Error>
-where
-    T: FromStr,
-    <T as FromStr>::Err: std::error::Error + Send + Sync + 'static,
-{
-    let mut buffer = b' ';
-    while (buffer as char).is_whitespace() || buffer == 0 {
-        let result = input.read_exact(slice::from_mut(&mut buffer));
-        match result {
-            Ok(_) => {}
-            Err(why) if why.kind() == ErrorKind::UnexpectedEof => buffer = 0,
-            Err(why) => return Err(why),
+    /// Sort the other instruction pointers into the order of their IDs for deterministic scheduling, continuing after
+    /// the current one. Restored programs may come with any order.
+    fn order_ips(&mut self) {
+        if self.deterministic_scheduling {
+            let current = self.ip_id;
+            self.other_ips
+                .make_contiguous()
+                .sort_by_key(|ip| (ip.id < current, ip.id));
         }
     }
 
-    let mut raw = Vec::new();
-    while !(buffer as char).is_whitespace() && buffer != 0 {
-        raw.push(buffer);
-        let result = input.read_exact(slice::from_mut(&mut buffer));
-        match result {
-            Ok(_) => {}
-            Err(why) if why.kind() == ErrorKind::UnexpectedEof => buffer = 0,
-            Err(why) => return Err(why),
+    /// Create a new instruction pointer for the Befunge-98 `t` instruction. It is a copy of the current one heading
+    /// in the opposite direction, and runs right before the current one from the next step on, or after all others
+    /// at the end of the round with deterministic scheduling.
+    #[cold]
+    fn split_ip(&mut self) {
+        let mut child = InstructionPointer {
+            program_counter: self.program_counter,
+            string_mode: false,
+            stack: self.stacks.top.clone(),
+            stack_stack: self.stacks.below.clone(),
+            storage_offset: self.storage_offset,
+            id: self.next_ip_id,
+            semantics: self.semantics.clone(),
+        };
+        self.next_ip_id += 1;
+        child.program_counter.delta = child.program_counter.delta.reversed();
+        child.program_counter.step();
+        if self.wrap_mode == WrapMode::Lahey {
+            self.wrap_bounds = *self.program_grid.bounds();
+        }
+        child
+            .program_counter
+            .constrain(self.wrap_mode, &self.wrap_bounds);
+        if self.deterministic_scheduling {
+            // the instruction pointers younger than the current one come first, and the new one is the youngest
+            let current = self.ip_id;
+            let end_of_round = self
+                .other_ips
+                .iter()
+                .take_while(|ip| ip.id > current)
+                .count();
+            self.other_ips.insert(end_of_round, child);
+        } else {
+            self.other_ips.push_back(child);
         }
+        self.next_limit_check = self.steps;
     }
 
-    match String::from_utf8(raw) {
-        Ok(s) => {
-            FromStr::from_str(&s).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    /// Stop the current instruction pointer for `@`, which ends the program once no other instruction pointer is left.
+    #[cold]
+    fn stop_ip(&mut self) -> Result<(), Error> {
+        // the instruction pointer that ran before the stopped one takes its place until the next step,
+        // when the next one takes its turn
+        match self.other_ips.pop_back() {
+            Some(previous) => {
+                self.switch_ip(previous);
+                Ok(())
+            }
+            None => Err(Error::ProgramEnd),
         }
-        Err(_) => Err(io::Error::from(io::ErrorKind::InvalidData)),
     }
-}
 
-impl<'rw> Interpreter<'rw> {
-    pub fn new(grid: &str) -> Result<Self, Error> {
-        let input = Box::new(io::stdin());
-        let output = Box::new(io::stdout());
-        Self::new_with_io(grid, input, output)
+    /// Push the system information for the Befunge-98 `y` instruction. If the count is positive, only the value that
+    /// many cells down from the top is pushed, which is a value of the stack itself if the information is shorter.
+    #[cold]
+    fn push_system_info(&mut self, count: Int) {
+        let cells = self.system_info.cells(&MachineState {
+            ip_id: self.ip_id,
+            dimensions: self.dimensions(),
+            position: self.program_counter.position,
+            delta: self.program_counter.delta.0,
+            storage_offset: self.storage_offset,
+            bounds: *self.program_grid.bounds(),
+            stacks: &self.stacks,
+            // Befunge-97 has y, but neither i and o nor =
+            files: self.file_io() && self.instructions.is_legal(b'i'),
+            exec_paradigm: if self.instructions.is_legal(b'=') {
+                self.exec_paradigm().paradigm()
+            } else {
+                ExecParadigm::Unavailable.paradigm()
+            },
+        });
+        if count <= 0 {
+            self.stacks.top.extend(cells);
+            return;
+        }
+        let depth = usize::try_from(count).unwrap_or(usize::MAX);
+        let value = match depth.checked_sub(cells.len() + 1) {
+            None => cells[cells.len() - depth],
+            // the value right below the information is the top of the stack
+            Some(depth) => self
+                .stacks
+                .top
+                .len()
+                .checked_sub(depth + 1)
+                .map_or(0, |index| self.stacks.top[index]),
+        };
+        self.stacks.top.push(value);
     }
 
-    pub fn new_with_io(
-        grid: &str,
-        input: Box<dyn Read + 'rw>,
-        output: Box<dyn Write + 'rw>,
-    ) -> Result<Self, Error> {
-        let parsed_grid = Self::parse_grid(grid)?;
-        Ok(Self::new_with_io_and_grid(parsed_grid, input, output))
+    /// Load a fingerprint for the Befunge-98 `(` instruction, or unload it for `)`. Both reflect if the fingerprint
+    /// is unknown.
+    #[cold]
+    fn load_fingerprint(&mut self, load: bool) {
+        let count = self.stacks.top.pop().unwrap_or_default();
+        let mut id: Int = 0;
+        for _ in 0..count.clamp(0, self.stacks.top.len() as Int) {
+            id = id
+                .wrapping_shl(8)
+                .wrapping_add(self.stacks.top.pop().unwrap_or_default());
+        }
+        let Some(fingerprint) = self.fingerprints.iter().find(|fingerprint| {
+            fingerprint_id(fingerprint.name()) == id && fingerprint.permitted(&self.sandbox)
+        }) else {
+            self.reflect();
+            return;
+        };
+        for &instruction in fingerprint.instructions() {
+            let semantics = &mut self.semantics[(instruction - b'A') as usize];
+            if load {
+                semantics.push(id);
+            } else {
+                semantics.pop();
+            }
+        }
+        if load {
+            self.stacks.top.extend([id, 1]);
+        }
     }
 
-    pub fn new_with_io_and_grid(
-        grid: Grid,
-        input: Box<dyn Read + 'rw>,
-        output: Box<dyn Write + 'rw>,
-    ) -> Self {
-        let start = std::time::SystemTime::now()
-            .duration_since(std::time::SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs_f64();
-        Self {
-            stack: Stack::new(),
-            program_grid: grid,
-            string_mode: false,
-            program_counter: PC::default(),
-            input,
-            output,
-            rng: rand::rngs::SmallRng::seed_from_u64(start.to_bits()),
-            steps: 0,
+    /// Run a command for the Befunge-98 `=` instruction and push its exit status. Its output becomes program output.
+    /// Reflects if the sandbox doesn't allow running commands or the command can't be run.
+    #[cold]
+    fn execute_instruction(&mut self) -> Result<(), Error> {
+        let command = String::from_stack(&mut self.stacks.top);
+        match self.exec_paradigm().run(&command) {
+            Some((status, output)) => {
+                self.write_output(&output)?;
+                self.stacks.top.push(status);
+            }
+            None => self.reflect(),
         }
+        Ok(())
     }
 
-    pub fn parse_grid(grid: &str) -> Result<Grid, Error> {
-        let lines = grid
-            .lines()
-            .map(|line| {
-                line.chars()
-                    .map(|x| {
-                        if x.is_ascii() {
-                            Ok(x as u8)
-                        } else {
-                            Err(Error::NonAscii(x as Int))
+    /// Load a file into the grid for the Befunge-98 `i` instruction, or write a rectangle of the grid to a file for
+    /// `o`. Both reflect if file access isn't enabled, the sandbox doesn't allow it or the file can't be read or
+    /// written.
+    ///
+    /// `i` loads text files line by line like programs, or binary files into a single row if bit 0 of the flags is
+    /// set; spaces don't overwrite cells either way. `o` writes one line per row, without trailing spaces and empty
+    /// lines at the end if bit 0 of the flags is set. Trefunge-98 programs load and write a box instead, with a form
+    /// feed between its planes.
+    #[cold]
+    fn file_instruction(&mut self, write: bool) -> Result<(), Error> {
+        if !self.file_io() {
+            self.reflect();
+            return Ok(());
+        }
+        let path = String::from_stack(&mut self.stacks.top);
+        let flags = self.pop();
+        let origin = self.pop_vector();
+        let corner = origin.wrapping_add(self.storage_offset);
+        let three_dimensional = self.dimensions() == 3;
+        if write {
+            let size = self.pop_vector();
+            // the rectangle of a Befunge program is a single plane deep
+            let size = if three_dimensional {
+                size
+            } else {
+                size.with_z(1)
+            };
+            // Int is not an i64 on every platform
+            #[allow(clippy::unnecessary_cast)]
+            if size.min_element() < 0
+                || size
+                    .x
+                    .checked_mul(size.y)
+                    .and_then(|cells| cells.checked_mul(size.z))
+                    .is_none_or(|cells| cells > MAX_FILE_CELLS as i64)
+            {
+                self.reflect();
+                return Ok(());
+            }
+            let planes = (0..size.z)
+                .map(|plane| {
+                    let mut lines = (0..size.y)
+                        .map(|row| {
+                            let mut line = (0..size.x)
+                                .map(|column| {
+                                    let offset = Position::new(column, row, plane);
+                                    self.cell_value(corner.wrapping_add(offset)) as u8
+                                })
+                                .collect::<Vec<_>>();
+                            if flags & 1 != 0 {
+                                line.truncate(
+                                    line.iter()
+                                        .rposition(|&cell| cell != b' ')
+                                        .map_or(0, |end| end + 1),
+                                );
+                            }
+                            line
+                        })
+                        .collect::<Vec<_>>();
+                    if flags & 1 != 0 {
+                        while lines.last().is_some_and(Vec::is_empty) {
+                            lines.pop();
                         }
-                    })
-                    .collect::<Result<Vec<_>, Error>>()
-            })
-            .collect::<Result<Vec<_>, Error>>()?;
-        let height = lines.len();
-        let width = lines.first().map_or(0, |v| v.len());
-        if height > GRID_HEIGHT || width > GRID_WIDTH {
-            return Err(Error::InvalidGridSize(width, height));
+                    }
+                    lines
+                        .into_iter()
+                        .flat_map(|line| line.into_iter().chain([b'\n']))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            if std::fs::write(&path, planes.join(&FORM_FEED)).is_err() {
+                self.reflect();
+            }
+            return Ok(());
         }
-        Ok(Grid::try_from({
-            let mut grid = lines
+
+        let Ok(contents) = std::fs::read(&path) else {
+            self.reflect();
+            return Ok(());
+        };
+        let planes = if flags & 1 != 0 {
+            vec![vec![&contents[..]]]
+        } else {
+            let planes = if three_dimensional {
+                contents.split(|&byte| byte == FORM_FEED).collect()
+            } else {
+                vec![&contents[..]]
+            };
+            planes
                 .into_iter()
-                .map(|mut line| {
-                    line.resize(GRID_WIDTH, b' ');
-                    Line::try_from(line).unwrap()
+                .map(|plane| {
+                    let mut lines = plane
+                        .split(|&byte| byte == b'\n')
+                        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+                        .collect::<Vec<_>>();
+                    // a final newline doesn't start another line
+                    if lines.last().is_some_and(|line| line.is_empty()) {
+                        lines.pop();
+                    }
+                    lines
                 })
-                .collect::<Vec<_>>();
-            grid.resize(GRID_HEIGHT, [b' '; GRID_WIDTH]);
-            grid
-        })
-        .unwrap())
+                .collect()
+        };
+        let mut size = Position::new(0, 0, planes.len() as i64);
+        for (plane, lines) in planes.iter().enumerate() {
+            size.y = size.y.max(lines.len() as i64);
+            for (row, line) in lines.iter().enumerate() {
+                size.x = size.x.max(line.len() as i64);
+                for (column, &byte) in line.iter().enumerate() {
+                    if byte != b' ' {
+                        let offset = Position::new(column as i64, row as i64, plane as i64);
+                        self.put(corner.wrapping_add(offset), byte.into())?;
+                    }
+                }
+            }
+        }
+        self.push_vector(size);
+        self.push_vector(origin);
+        Ok(())
+    }
+
+    /// Run an instruction `A` to `Z` as defined by the fingerprint loaded last that defines it, or reflect if there
+    /// is none.
+    #[cold]
+    fn run_fingerprint_instruction(&mut self, instruction: u8) -> Result<(), Error> {
+        let fingerprint = self.semantics[(instruction - b'A') as usize]
+            .last()
+            .and_then(|&id| {
+                self.fingerprints
+                    .iter()
+                    .find(|fingerprint| fingerprint_id(fingerprint.name()) == id)
+            });
+        match fingerprint {
+            Some(&fingerprint) => fingerprint.execute(instruction, self),
+            None => {
+                self.reflect();
+                Ok(())
+            }
+        }
+    }
+
+    /// Write a cell like `p`, respecting the write protection and recording the write in the journal.
+    #[inline]
+    fn put(&mut self, position: Position, value: Int) -> Result<(), Error> {
+        if !self.protected.is_empty()
+            && self
+                .protected
+                .iter()
+                .any(|region| region.contains(position))
+        {
+            if self.protect_policy == ProtectPolicy::Error {
+                return Err(Error::ProtectedWrite(position));
+            }
+            return Ok(());
+        }
+        let (old, new) = if self.cell_values == CellValues::Full {
+            (
+                self.program_grid.set_value_at(position, value),
+                u8::try_from(value).unwrap_or(WIDE_CELL),
+            )
+        } else {
+            (self.program_grid.set_at(position, value as u8), value as u8)
+        };
+        if let (Some(journal), Some(old)) = (&mut self.write_journal, old) {
+            journal.record(JournalEntry {
+                change: CellChange { position, old, new },
+                step: self.steps,
+                writer: self.program_counter.position,
+            });
+        }
+        if let (Some(hotspots), Some(_)) = (&mut self.write_hotspots, old) {
+            hotspots.record(position, self.steps);
+        }
+        Ok(())
+    }
+
+    /// Move the program counter onto the `;` ending the Befunge-98 comment it is in.
+    #[cold]
+    fn skip_comment(&mut self) {
+        // a comment that is never closed would otherwise be skipped forever
+        for _ in 0..self.search_limit() {
+            self.program_counter.step();
+            self.wrap_program_counter();
+            if self.program_grid.cell(self.program_counter.position) == b';' {
+                break;
+            }
+        }
+    }
+
+    /// Move the program counter by the given number of cells for the Befunge-98 `j` instruction;
+    /// negative distances move it backwards.
+    #[cold]
+    fn jump(&mut self, distance: Int) {
+        if self.program_counter.delta.0 == Position::ZERO {
+            return;
+        }
+        if self.wrap_mode == WrapMode::Lahey {
+            self.wrap_bounds = *self.program_grid.bounds();
+        }
+        // the program counter only ever visits the cells on its line through the bounds
+        let back = PC {
+            position: self.program_counter.position,
+            delta: self.program_counter.delta.reversed(),
+        };
+        let line = (self.program_counter.steps_within(&self.wrap_bounds).max(0))
+            .saturating_add(back.steps_within(&self.wrap_bounds).max(0))
+            .saturating_add(1)
+            .min(u64::MAX as i128) as u64;
+        // After this many cells, the program counter is back where it was, apart from the single cell a reflected
+        // program counter at the edge leaves and never returns to.
+        let period = match self.wrap_mode {
+            WrapMode::Torus | WrapMode::Lahey => line,
+            WrapMode::Reflect => 2 * (line - 1).max(1),
+        };
+        #[allow(clippy::unnecessary_cast)]
+        let mut cells = (distance as i64).unsigned_abs();
+        if cells >= period {
+            cells = period + cells % period;
+        }
+        let backwards = distance < 0;
+        if backwards {
+            self.program_counter.delta = self.program_counter.delta.reversed();
+        }
+        for _ in 0..cells {
+            self.program_counter.step();
+            self.wrap_program_counter();
+        }
+        if backwards {
+            self.program_counter.delta = self.program_counter.delta.reversed();
+        }
+    }
+
+    /// Run the next instruction in the program counter's path, skipping spaces and comments, as many times as the
+    /// popped count for the Befunge-98 `k` instruction, all in a single step.
+    ///
+    /// The instruction runs in place of the `k`, so that instructions that move the program counter move it from
+    /// there. If it just moved on, as after most instructions, it skips the instruction. A count of zero or less only
+    /// skips it. `k` reflects if the instruction is another `k`, which could nest without end. Every run after the
+    /// first counts as a step of its own, so the step limit and the timeout stop large counts.
+    #[cold]
+    fn iterate(&mut self) -> Result<(), Error> {
+        let count = self.stacks.top.pop().unwrap_or_default();
+        if self.wrap_mode == WrapMode::Lahey {
+            self.wrap_bounds = *self.program_grid.bounds();
+        }
+        let origin = self.program_counter;
+        let mut target = origin;
+        let mut in_comment = false;
+        let mut instruction = None;
+        for _ in 0..self.search_limit() {
+            target.step();
+            target.constrain(self.wrap_mode, &self.wrap_bounds);
+            match self.program_grid.cell(target.position) {
+                b';' => in_comment = !in_comment,
+                b' ' => {}
+                cell if !in_comment => {
+                    instruction = Some(cell);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let instruction = match instruction {
+            // a line of nothing but spaces has no instruction to run
+            None => {
+                self.move_pc();
+                return Ok(());
+            }
+            Some(b'k') => {
+                self.program_counter.delta = self.program_counter.delta.reversed();
+                self.move_pc();
+                return Ok(());
+            }
+            Some(instruction) => instruction,
+        };
+        if count <= 0 {
+            self.program_counter = target;
+            self.move_pc();
+            return Ok(());
+        }
+        let max_steps = self.sandbox.max_steps.unwrap_or(usize::MAX);
+        for iteration in 0..count {
+            if iteration > 0 {
+                if self.steps >= max_steps {
+                    return Err(Error::StepLimit(max_steps));
+                }
+                if let Some((deadline, timeout)) = self.deadline {
+                    if (iteration as usize).is_multiple_of(CLOCK_CHECK_INTERVAL)
+                        && Instant::now() >= deadline
+                    {
+                        return Err(Error::Timeout(timeout));
+                    }
+                }
+                self.steps += 1;
+            }
+            self.program_counter.position = origin.position;
+            self.run_instruction(instruction)?;
+        }
+        let mut next = origin;
+        next.step();
+        next.constrain(self.wrap_mode, &self.wrap_bounds);
+        if self.program_counter == next {
+            self.program_counter = target;
+            self.move_pc();
+        }
+        Ok(())
     }
 
+    #[inline(always)]
     pub fn run_step(&mut self) -> Result<(), Error> {
         macro_rules! move_pc {
             () => {
                 self.program_counter.step();
-                self.program_counter.constrain();
+                if !self.wrap_bounds.contains(self.program_counter.position) {
+                    self.wrap_program_counter();
+                }
             };
         }
+        if self.steps >= self.next_limit_check {
+            self.check_limits()?;
+        }
         self.steps += 1;
 
-        let current_char = self.program_grid[self.program_counter.position.y as usize]
-            [self.program_counter.position.x as usize];
+        let current_char = self.program_grid.cell(self.program_counter.position);
         if self.string_mode {
             if current_char == b'"' {
                 self.string_mode = false;
             } else {
-                self.stack.push(current_char as Int);
+                self.stacks.top.push(if current_char.is_ascii() {
+                    current_char as Int
+                } else {
+                    self.current_cell_value()
+                });
+                if current_char == b' ' && self.string_spaces == StringSpaces::Sgml {
+                    self.skip_string_spaces();
+                }
             }
             move_pc!();
             Ok(())
+        } else {
+            self.run_instruction(current_char)
+        }
+    }
+
+    /// Execute an instruction outside of string mode, as if it were in the current cell.
+    #[inline(always)]
+    fn run_instruction(&mut self, current_char: u8) -> Result<(), Error> {
+        macro_rules! move_pc {
+            () => {
+                self.program_counter.step();
+                if !self.wrap_bounds.contains(self.program_counter.position) {
+                    self.wrap_program_counter();
+                }
+            };
+        }
+        if !self.instructions.is_legal(current_char) {
+            if Some(current_char) == self.stack_dump_instruction {
+                self.dump_stack();
+                move_pc!();
+                Ok(())
+            } else if Some(current_char) == self.host_call_instruction {
+                self.call_host_function();
+                move_pc!();
+                Ok(())
+            } else if self.standard.reflects() {
+                self.program_counter.delta = self.program_counter.delta.reversed();
+                move_pc!();
+                Ok(())
+            } else {
+                Err(Error::IllegalCommand {
+                    command: current_char,
+                })
+            }
         } else {
             match current_char {
                 // PC redirection
                 b'>' => {
-                    self.program_counter.direction = Direction::Right;
+                    self.program_counter.delta = Direction::Right.into();
                     move_pc!();
                     Ok(())
                 }
                 b'<' => {
-                    self.program_counter.direction = Direction::Left;
+                    self.program_counter.delta = Direction::Left.into();
                     move_pc!();
                     Ok(())
                 }
                 b'^' => {
-                    self.program_counter.direction = Direction::Up;
+                    self.program_counter.delta = Direction::Up.into();
                     move_pc!();
                     Ok(())
                 }
                 b'v' => {
-                    self.program_counter.direction = Direction::Down;
+                    self.program_counter.delta = Direction::Down.into();
                     move_pc!();
                     Ok(())
                 }
                 b'?' => {
-                    self.program_counter.direction = self.rng.gen();
+                    self.program_counter.delta = self.random_direction().into();
                     move_pc!();
                     Ok(())
                 }
                 b'#' => {
+                    let unwrapped = self.program_counter.position + self.program_counter.delta;
                     move_pc!();
-                    move_pc!();
+                    if self.bridge_edge == BridgeEdge::Skip
+                        || self.program_counter.position == unwrapped
+                    {
+                        move_pc!();
+                    }
                     Ok(())
                 }
                 b' ' => {
@@ -355,97 +2634,107 @@ impl<'rw> Interpreter<'rw> {
                 }
                 b'0'..=b'9' => {
                     let number = current_char - b'0';
-                    self.stack.push(number as Int);
+                    self.stacks.top.push(number as Int);
                     move_pc!();
                     Ok(())
                 }
                 // Stack ops
                 b':' => {
-                    let top = self.stack.pop().unwrap_or_default();
-                    self.stack.push(top);
-                    self.stack.push(top);
+                    let top = self.stacks.top.pop().unwrap_or_default();
+                    self.stacks.top.push(top);
+                    self.stacks.top.push(top);
                     move_pc!();
                     Ok(())
                 }
                 b'\\' => {
-                    let top = self.stack.pop().unwrap_or_default();
-                    let second = self.stack.pop().unwrap_or_default();
-                    self.stack.push(top);
-                    self.stack.push(second);
+                    let top = self.stacks.top.pop().unwrap_or_default();
+                    let second = self.stacks.top.pop().unwrap_or_default();
+                    self.stacks.top.push(top);
+                    self.stacks.top.push(second);
                     move_pc!();
                     Ok(())
                 }
                 b'$' => {
-                    let _ = self.stack.pop();
+                    let _ = self.stacks.top.pop();
                     move_pc!();
                     Ok(())
                 }
                 // Math ops
                 b'+' => {
-                    let b = self.stack.pop().unwrap_or_default();
-                    let a = self.stack.pop().unwrap_or_default();
-                    self.stack.push(a.wrapping_add(b));
+                    let b = self.stacks.top.pop().unwrap_or_default();
+                    let a = self.stacks.top.pop().unwrap_or_default();
+                    self.stacks.top.push(a.wrapping_add(b));
                     move_pc!();
                     Ok(())
                 }
                 b'-' => {
-                    let b = self.stack.pop().unwrap_or_default();
-                    let a = self.stack.pop().unwrap_or_default();
-                    self.stack.push(a.wrapping_sub(b));
+                    let b = self.stacks.top.pop().unwrap_or_default();
+                    let a = self.stacks.top.pop().unwrap_or_default();
+                    self.stacks.top.push(a.wrapping_sub(b));
                     move_pc!();
                     Ok(())
                 }
                 b'*' => {
-                    let b = self.stack.pop().unwrap_or_default();
-                    let a = self.stack.pop().unwrap_or_default();
-                    self.stack.push(a.wrapping_mul(b));
+                    let b = self.stacks.top.pop().unwrap_or_default();
+                    let a = self.stacks.top.pop().unwrap_or_default();
+                    self.stacks.top.push(a.wrapping_mul(b));
                     move_pc!();
                     Ok(())
                 }
                 b'/' => {
-                    let b = self.stack.pop().unwrap_or_default();
-                    let a = self.stack.pop().unwrap_or_default();
-                    self.stack.push(a.wrapping_div(b));
+                    let b = self.stacks.top.pop().unwrap_or_default();
+                    let a = self.stacks.top.pop().unwrap_or_default();
+                    // Befunge-93 wants to ask the user for the result of a division by zero; like Befunge-98, we push 0.
+                    self.stacks
+                        .top
+                        .push(if b == 0 { 0 } else { a.wrapping_div(b) });
                     move_pc!();
                     Ok(())
                 }
                 b'%' => {
-                    let b = self.stack.pop().unwrap_or_default();
-                    let a = self.stack.pop().unwrap_or_default();
-                    self.stack.push(a.wrapping_rem(b));
+                    let b = self.stacks.top.pop().unwrap_or_default();
+                    let a = self.stacks.top.pop().unwrap_or_default();
+                    self.stacks.top.push(Remainder::Truncated.of(a, b));
                     move_pc!();
                     Ok(())
                 }
                 b'!' => {
-                    let b = self.stack.pop().unwrap_or_default();
-                    self.stack.push(if b == 0 { 1 } else { 0 });
+                    let b = self.stacks.top.pop().unwrap_or_default();
+                    self.stacks.top.push(if b == 0 { 1 } else { 0 });
                     move_pc!();
                     Ok(())
                 }
                 b'`' => {
-                    let b = self.stack.pop().unwrap_or_default();
-                    let a = self.stack.pop().unwrap_or_default();
-                    self.stack.push(if a > b { 1 } else { 0 });
+                    let b = self.stacks.top.pop().unwrap_or_default();
+                    let a = self.stacks.top.pop().unwrap_or_default();
+                    self.stacks.top.push(if a > b { 1 } else { 0 });
                     move_pc!();
                     Ok(())
                 }
                 // I/O
                 b',' => {
-                    let top = self.stack.pop().unwrap_or_default();
+                    let top = self.stacks.top.pop().unwrap_or_default();
+                    // Befunge-98 outputs any value, as the byte C's putchar would write for it
+                    if self.standard.reflects() {
+                        self.write_output(&[top as u8])?;
+                        move_pc!();
+                        return Ok(());
+                    }
                     let ascii =
                         char::try_from(u32::try_from(top).map_err(|_| Error::NonAscii(top))?)
                             .map_err(|_| Error::NonAscii(top))?;
                     if !ascii.is_ascii() {
                         Err(Error::NonAscii(ascii as Int))
                     } else {
-                        self.output.write_all(&[ascii as u8])?;
+                        self.write_output(&[ascii as u8])?;
                         move_pc!();
                         Ok(())
                     }
                 }
                 b'.' => {
-                    let top = self.stack.pop().unwrap_or_default();
-                    write!(self.output, "{} ", top)?;
+                    let top = self.stacks.top.pop().unwrap_or_default();
+                    let mut buffer = [0; NUMBER_BUFFER_SIZE];
+                    self.write_output(format_number(top, &mut buffer))?;
                     move_pc!();
                     Ok(())
                 }
@@ -454,82 +2743,298 @@ impl<'rw> Interpreter<'rw> {
                     // jsFunge (and probably all others) will retrieve -1 on EOF, and not a null character.
                     // Conveniently, 0xff is not a valid byte for UTF-8 coding, so we can use it here.
                     let mut ascii = 0xff;
-                    self.input
-                        .read_exact(slice::from_mut(&mut ascii))
-                        .map_or_else(
-                            |e| {
-                                if e.kind() == ErrorKind::UnexpectedEof {
-                                    Ok(())
-                                } else {
-                                    Err(e)
-                                }
-                            },
-                            |_| Ok(()),
-                        )?;
-                    self.stack
+                    match self.input.read_exact(slice::from_mut(&mut ascii)) {
+                        Ok(_) => {
+                            self.input_offset += 1;
+                            self.eof_reads = 0;
+                        }
+                        Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                            self.eof_reads += 1;
+                            if self.standard.reflects() {
+                                self.program_counter.delta = self.program_counter.delta.reversed();
+                                move_pc!();
+                                return Ok(());
+                            }
+                        }
+                        Err(e) => return Err(self.input_error(e)),
+                    }
+                    self.stacks
+                        .top
                         .push(if ascii != 0xff { ascii.into() } else { -1 });
                     move_pc!();
                     Ok(())
                 }
                 b'&' => {
-                    let number = scan_next(&mut self.input)?;
-                    self.stack.push(number);
+                    let token = match read_token(&mut self.input) {
+                        Ok(token) => token,
+                        Err(e)
+                            if e.kind() == ErrorKind::UnexpectedEof && self.standard.reflects() =>
+                        {
+                            self.program_counter.delta = self.program_counter.delta.reversed();
+                            move_pc!();
+                            return Ok(());
+                        }
+                        Err(e) => return Err(self.input_error(e)),
+                    };
+                    let offset = self.input_offset + token.skipped;
+                    self.input_offset += token.consumed;
+                    let Some(number) = parse_number(&token.text, self.lenient_numbers) else {
+                        // Befunge-98 reflects on input that isn't a number
+                        if self.standard.reflects() {
+                            self.program_counter.delta = self.program_counter.delta.reversed();
+                            move_pc!();
+                            return Ok(());
+                        }
+                        return Err(Error::InvalidNumber {
+                            text: String::from_utf8_lossy(&token.text).into_owned(),
+                            offset,
+                        });
+                    };
+                    self.stacks.top.push(number);
                     move_pc!();
                     Ok(())
                 }
                 // Conditionals
                 b'_' => {
-                    let top = self.stack.pop().unwrap_or_default();
-                    self.program_counter.direction = if top == 0 {
+                    let top = self.stacks.top.pop().unwrap_or_default();
+                    self.program_counter.delta = if top == 0 {
                         Direction::Right
                     } else {
                         Direction::Left
-                    };
+                    }
+                    .into();
                     move_pc!();
                     Ok(())
                 }
                 b'|' => {
-                    let top = self.stack.pop().unwrap_or_default();
-                    self.program_counter.direction = if top == 0 {
+                    let top = self.stacks.top.pop().unwrap_or_default();
+                    self.program_counter.delta = if top == 0 {
                         Direction::Down
                     } else {
                         Direction::Up
-                    };
+                    }
+                    .into();
                     move_pc!();
                     Ok(())
                 }
                 // Self-modification
                 b'g' => {
-                    let y = self.stack.pop().unwrap_or_default();
-                    let x = self.stack.pop().unwrap_or_default();
-                    self.stack.push(
-                        if !(0..GRID_WIDTH as Int).contains(&x)
-                            || !(0..GRID_HEIGHT as Int).contains(&y)
-                        {
-                            0
-                        } else {
-                            // make sure to retain signedness, even though ASCII is not really signed
-                            self.program_grid[y as usize][x as usize] as i8 as Int
-                        },
-                    );
+                    let vector = self.pop_vector();
+                    self.stacks
+                        .top
+                        .push(self.cell_value(vector.wrapping_add(self.storage_offset)));
                     move_pc!();
                     Ok(())
                 }
                 b'p' => {
-                    let y = self.stack.pop().unwrap_or_default();
-                    let x = self.stack.pop().unwrap_or_default();
-                    let value = self.stack.pop().unwrap_or_default();
-                    if (0..GRID_WIDTH as Int).contains(&x) && (0..GRID_HEIGHT as Int).contains(&y) {
-                        self.program_grid[y as usize][x as usize] = value as u8;
-                    }
+                    let vector = self.pop_vector();
+                    let value = self.stacks.top.pop().unwrap_or_default();
+                    self.put(vector.wrapping_add(self.storage_offset), value)?;
                     move_pc!();
                     Ok(())
                 }
                 // Misc
-                b'@' => Err(Error::ProgramEnd),
-                _ => Err(Error::IllegalCommand {
-                    command: current_char,
-                }),
+                b'@' => self.stop_ip(),
+                // Befunge-98 instructions are kept out of the way of the Befunge-93 ones
+                _ => self.run_befunge98_step(current_char),
+            }
+        }
+    }
+
+    /// Move the program counter to the next cell, outside of [`Interpreter::run_step`].
+    fn move_pc(&mut self) {
+        self.program_counter.step();
+        if !self.wrap_bounds.contains(self.program_counter.position) {
+            self.wrap_program_counter();
+        }
+    }
+
+    /// Execute a legal instruction that is new in Befunge-98.
+    #[inline(never)]
+    fn run_befunge98_step(&mut self, current_char: u8) -> Result<(), Error> {
+        match current_char {
+            b'a'..=b'f' => {
+                self.stacks.top.push((current_char - b'a' + 10) as Int);
+                self.move_pc();
+                Ok(())
+            }
+            b'n' => {
+                self.stacks.top.clear();
+                self.move_pc();
+                Ok(())
+            }
+            b'\'' => {
+                self.move_pc();
+                let cell = self.program_grid.cell(self.program_counter.position);
+                self.stacks.top.push(if cell.is_ascii() {
+                    cell as Int
+                } else {
+                    self.current_cell_value()
+                });
+                self.move_pc();
+                Ok(())
+            }
+            b's' => {
+                let value = self.stacks.top.pop().unwrap_or_default();
+                self.move_pc();
+                self.put(self.program_counter.position, value)?;
+                self.move_pc();
+                Ok(())
+            }
+            b';' => {
+                self.skip_comment();
+                self.move_pc();
+                Ok(())
+            }
+            b'[' => {
+                self.program_counter.delta = self.program_counter.delta.turned_left();
+                self.move_pc();
+                Ok(())
+            }
+            b']' => {
+                self.program_counter.delta = self.program_counter.delta.turned_right();
+                self.move_pc();
+                Ok(())
+            }
+            b'r' => {
+                self.program_counter.delta = self.program_counter.delta.reversed();
+                self.move_pc();
+                Ok(())
+            }
+            b'w' => {
+                let b = self.stacks.top.pop().unwrap_or_default();
+                let a = self.stacks.top.pop().unwrap_or_default();
+                let delta = self.program_counter.delta;
+                self.program_counter.delta = match a.cmp(&b) {
+                    std::cmp::Ordering::Less => delta.turned_left(),
+                    std::cmp::Ordering::Equal => delta,
+                    std::cmp::Ordering::Greater => delta.turned_right(),
+                };
+                self.move_pc();
+                Ok(())
+            }
+            b'j' => {
+                let distance = self.stacks.top.pop().unwrap_or_default();
+                self.jump(distance);
+                self.move_pc();
+                Ok(())
+            }
+            b'k' => self.iterate(),
+            b'x' => {
+                self.program_counter.delta = Delta(self.pop_vector());
+                self.move_pc();
+                Ok(())
+            }
+            b'h' | b'l' | b'm' => {
+                let high = match current_char {
+                    b'h' => true,
+                    b'l' => false,
+                    _ => self.stacks.top.pop().unwrap_or_default() != 0,
+                };
+                self.program_counter.delta = if high {
+                    Direction::High
+                } else {
+                    Direction::Low
+                }
+                .into();
+                self.move_pc();
+                Ok(())
+            }
+            b'z' => {
+                self.move_pc();
+                Ok(())
+            }
+            b't' => {
+                self.split_ip();
+                self.move_pc();
+                Ok(())
+            }
+            b'q' => {
+                self.exit_status = self.stacks.top.pop().unwrap_or_default();
+                Err(Error::ProgramEnd)
+            }
+            b'{' | b'}' | b'u' => {
+                let count = self.stacks.top.pop().unwrap_or_default();
+                let next = self.program_counter.position + self.program_counter.delta;
+                let dimensions = self.dimensions();
+                let succeeded = match current_char {
+                    b'{' => {
+                        self.stacks
+                            .begin_block(count, &mut self.storage_offset, next, dimensions)
+                    }
+                    b'}' => self
+                        .stacks
+                        .end_block(count, &mut self.storage_offset, dimensions),
+                    _ => self.stacks.under(count),
+                };
+                // the instructions fail if they need a second stack but there is none, or if they would move more
+                // than MAX_STACK_TRANSFER values, as if the interpreter ran out of memory
+                if !succeeded {
+                    self.program_counter.delta = self.program_counter.delta.reversed();
+                }
+                self.move_pc();
+                Ok(())
+            }
+            b'=' => {
+                self.execute_instruction()?;
+                self.move_pc();
+                Ok(())
+            }
+            b'i' | b'o' => {
+                self.file_instruction(current_char == b'o')?;
+                self.move_pc();
+                Ok(())
+            }
+            b'y' => {
+                let count = self.stacks.top.pop().unwrap_or_default();
+                self.push_system_info(count);
+                self.move_pc();
+                Ok(())
+            }
+            b'(' | b')' => {
+                self.load_fingerprint(current_char == b'(');
+                self.move_pc();
+                Ok(())
+            }
+            b'A'..=b'Z' => {
+                self.run_fingerprint_instruction(current_char)?;
+                self.move_pc();
+                Ok(())
+            }
+            _ => Err(Error::IllegalCommand {
+                command: current_char,
+            }),
+        }
+    }
+}
+
+/// How a time-sliced run ended, see [`Interpreter::run_for_duration`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// The time slice expired while the program was still running.
+    Running,
+    /// The program terminated normally.
+    Finished,
+}
+
+impl<'rw> Interpreter<'rw> {
+    /// Run until the time slice has expired or the program terminates, whichever comes first.
+    /// The clock is only checked every few thousand steps, so the slice may be overrun by a few microseconds.
+    pub fn run_for_duration(
+        &mut self,
+        duration: std::time::Duration,
+    ) -> Result<StepOutcome, Error> {
+        let deadline = std::time::Instant::now() + duration;
+        loop {
+            for _ in 0..CLOCK_CHECK_INTERVAL {
+                let result = self.run_step();
+                if result.as_ref().is_err_and(|e| e == &Error::ProgramEnd) {
+                    return Ok(StepOutcome::Finished);
+                }
+                result?;
+            }
+            if std::time::Instant::now() >= deadline {
+                return Ok(StepOutcome::Running);
             }
         }
     }
@@ -555,88 +3060,53 @@ impl<'rw> Executer for Interpreter<'rw> {
     }
 }
 
-fn compile_embedded_befunge(grid: String, output: PathBuf) -> Result<(), Error> {
-    #[cfg(not(windows))]
-    const EXECUTABLE_NAME: &str = "embedded_befunge";
-    #[cfg(windows)]
-    const EXECUTABLE_NAME: &str = "embedded_befunge.exe";
-
-    let grid = Interpreter::parse_grid(&grid)?;
-    let stringified_grid = format!("{:?}", grid);
-
-    let mut gridfile = tempfile::NamedTempFile::new()?;
-    gridfile.write_all(stringified_grid.as_bytes())?;
-    let target_dir = tempfile::tempdir()?;
-
-    // This is a big fucking hack and only works when you have the repo locally and call the program from the right directory.
-    Command::new("cargo")
-        .args(["build", "-q", "--release", "--bin", "embedded_befunge"])
-        .args([
-            &OsString::try_from(&"--target-dir")
-                .expect("--target-dir is not a valid OS string. Your system sure is fucked."),
-            target_dir.path().as_os_str(),
-        ])
-        .env("BEFUNGE_CODE_SRC", gridfile.path())
-        .status()?;
-
-    let mut executable = target_dir.path().to_owned();
-    executable.push("release");
-    executable.push(EXECUTABLE_NAME);
-    std::fs::rename(executable, &output)?;
-
-    println!(
-        "Written embedded befunge executable to {}",
-        output.to_string_lossy()
-    );
+/// Result of running a program from one entry point, see [`run_entries`].
+#[derive(Debug)]
+pub struct EntryResult {
+    pub start: PC,
+    pub output: Vec<u8>,
+    pub steps: usize,
+    pub result: Result<(), Error>,
+}
 
-    Ok(())
+/// Run the program once from each of the given start PCs, in order.
+/// Every run starts with a fresh stack, reads its input from a freshly opened input and has its output captured.
+/// `configure` sets up each interpreter before it is moved to its start.
+pub fn run_entries(
+    grid: &Grid,
+    entries: &[PC],
+    open_input: impl Fn() -> Result<Box<dyn Read>, Error>,
+    configure: impl Fn(Interpreter<'_>) -> Result<Interpreter<'_>, Error>,
+) -> Result<Vec<EntryResult>, Error> {
+    entries
+        .iter()
+        .map(|&start| {
+            check_start(grid, start)?;
+            let mut output = Vec::new();
+            let mut interpreter = configure(Interpreter::new_with_io_and_grid(
+                grid.clone(),
+                open_input()?,
+                Box::new(&mut output),
+            ))?
+            .with_start(start);
+            let result = interpreter.run_forever();
+            let steps = interpreter.steps();
+            drop(interpreter);
+            Ok(EntryResult {
+                start,
+                output,
+                steps,
+                result,
+            })
+        })
+        .collect()
 }
 
-pub fn run_interpreter(args: Arguments) -> Result<(), Error> {
-    let mut grid: String = String::new();
-    if args.input == Path::new("-") {
-        io::stdin().read_to_string(&mut grid)?;
+/// A start must lie within the 80x25 area of a bounded grid, while an unbounded grid has room everywhere.
+fn check_start(grid: &Grid, start: PC) -> Result<(), Error> {
+    if grid.is_unbounded() || Bounds::GRID.contains(start.position) {
+        Ok(())
     } else {
-        File::open(args.input)?.read_to_string(&mut grid)?;
-    }
-
-    if let Some(output) = args.output {
-        compile_embedded_befunge(grid, output)?;
-        return Ok(());
-    }
-
-    let mut interpreter = Box::new(args.stdin.map_or_else(
-        || Interpreter::new(&grid),
-        |stdin| {
-            Interpreter::new_with_io(&grid, Box::new(File::open(stdin)?), Box::new(io::stdout()))
-        },
-    )?);
-
-    let start = std::time::Instant::now();
-    let result = interpreter.run_forever();
-    let end = std::time::Instant::now();
-
-    match result {
-        Ok(_) => {}
-        Err(ref why) => eprintln!("error at {}: {}", interpreter.position(), why),
-    }
-
-    if args.show_performance {
-        let time = end - start;
-        let time_per_step = time / interpreter.steps() as u32;
-        println!();
-        println!(
-            "execution took {:?}, {} steps, {:?} / step, {:.3} Msteps/s",
-            time,
-            interpreter.steps(),
-            time_per_step,
-            1_000.0 / time_per_step.as_nanos() as f64
-        );
-    }
-
-    if result.is_err() {
-        std::process::exit(1);
+        Err(Error::InvalidStart(start))
     }
-
-    Ok(())
 }
@@ -0,0 +1,246 @@
+//! Run-directory manifests, which describe everything needed to reproduce a run of a multi-file program.
+//!
+//! A run directory contains a `manifest.toml` naming the main program and optionally its input, the language
+//! standard, limits and auxiliary grids to stamp onto the playfield before the program starts:
+//!
+//! ```toml
+//! program = "main.bf"
+//! stdin = "input.txt"
+//! expected = "output.txt"
+//! standard = "93"
+//! max_steps = 1000000
+//! timeout = 10
+//! max_output = 4096
+//! annotations = "notes.txt"
+//!
+//! [[stamp]]
+//! file = "table.bf"
+//! x = 40
+//! y = 0
+//! ```
+//!
+//! Only this subset of TOML is understood: `key = value` pairs with string or integer values, comments and
+//! `[[stamp]]` tables. Paths are relative to the run directory.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use argh::FromArgValue;
+
+use crate::Error;
+use crate::Grid;
+use crate::InputSource;
+use crate::Int;
+use crate::LanguageStandard;
+use crate::Sandbox;
+
+pub const MANIFEST_NAME: &str = "manifest.toml";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub program: PathBuf,
+    pub stdin: Option<InputSource>,
+    /// Output the program must produce, for `--test`.
+    pub expected: Option<PathBuf>,
+    pub standard: Option<LanguageStandard>,
+    pub max_steps: Option<usize>,
+    pub timeout: Option<Duration>,
+    pub max_output: Option<usize>,
+    /// Annotations and named regions, see [`crate::Annotations`].
+    pub annotations: Option<PathBuf>,
+    /// Auxiliary grids, stamped onto the playfield in order.
+    pub stamps: Vec<Stamp>,
+}
+
+/// An auxiliary grid whose top left corner is placed at the given position of the playfield.
+/// Every character of the file is copied, including spaces, but the file is not padded to a rectangle.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stamp {
+    pub file: PathBuf,
+    pub x: Int,
+    pub y: Int,
+}
+
+enum Value {
+    String(String),
+    Integer(i64),
+}
+
+impl Manifest {
+    /// Load the manifest of a run directory, resolving all paths relative to the directory.
+    pub fn load(directory: &Path) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(directory.join(MANIFEST_NAME))?;
+        let mut manifest = Self::parse(&text)?;
+        manifest.program = directory.join(&manifest.program);
+        if let Some(InputSource::File(path)) = &mut manifest.stdin {
+            *path = directory.join(&*path);
+        }
+        if let Some(expected) = &mut manifest.expected {
+            *expected = directory.join(&*expected);
+        }
+        if let Some(annotations) = &mut manifest.annotations {
+            *annotations = directory.join(&*annotations);
+        }
+        for stamp in &mut manifest.stamps {
+            stamp.file = directory.join(&stamp.file);
+        }
+        Ok(manifest)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut manifest = Self::default();
+        let mut has_program = false;
+        for (number, line) in text.lines().enumerate() {
+            let invalid =
+                |why: &str| Error::InvalidManifest(format!("line {}: {}", number + 1, why));
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "[[stamp]]" {
+                manifest.stamps.push(Stamp::default());
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected \"key = value\""))?;
+            let key = key.trim();
+            let value = parse_value(value.trim()).ok_or_else(|| invalid("invalid value"))?;
+            if let Some(stamp) = manifest.stamps.last_mut() {
+                match (key, value) {
+                    ("file", Value::String(file)) => stamp.file = file.into(),
+                    ("x", Value::Integer(x)) => stamp.x = x as Int,
+                    ("y", Value::Integer(y)) => stamp.y = y as Int,
+                    _ => {
+                        return Err(invalid(&format!(
+                            "unknown stamp key or wrong type for \"{}\"",
+                            key
+                        )))
+                    }
+                }
+                continue;
+            }
+            match (key, value) {
+                ("program", Value::String(program)) => {
+                    manifest.program = program.into();
+                    has_program = true;
+                }
+                ("stdin", Value::String(stdin)) => {
+                    manifest.stdin =
+                        Some(InputSource::from_arg_value(&stdin).map_err(|why| invalid(&why))?)
+                }
+                ("expected", Value::String(expected)) => manifest.expected = Some(expected.into()),
+                ("standard", Value::String(standard)) => {
+                    manifest.standard = Some(
+                        LanguageStandard::from_arg_value(&standard).map_err(|why| invalid(&why))?,
+                    )
+                }
+                ("max_steps", Value::Integer(steps)) => {
+                    manifest.max_steps =
+                        Some(usize::try_from(steps).map_err(|_| invalid("negative step limit"))?)
+                }
+                ("timeout", Value::Integer(seconds)) => {
+                    manifest.timeout = Some(Duration::from_secs(
+                        u64::try_from(seconds).map_err(|_| invalid("negative timeout"))?,
+                    ))
+                }
+                ("max_output", Value::Integer(bytes)) => {
+                    manifest.max_output =
+                        Some(usize::try_from(bytes).map_err(|_| invalid("negative output limit"))?)
+                }
+                ("annotations", Value::String(annotations)) => {
+                    manifest.annotations = Some(annotations.into())
+                }
+                _ => {
+                    return Err(invalid(&format!(
+                        "unknown key or wrong type for \"{}\"",
+                        key
+                    )))
+                }
+            }
+        }
+        if !has_program {
+            return Err(Error::InvalidManifest("no program given".to_string()));
+        }
+        if let Some(stamp) = manifest
+            .stamps
+            .iter()
+            .find(|stamp| stamp.file.as_os_str().is_empty())
+        {
+            return Err(Error::InvalidManifest(format!(
+                "stamp at {},{} has no file",
+                stamp.x, stamp.y
+            )));
+        }
+        Ok(manifest)
+    }
+
+    /// Apply the manifest's limits on top of the given sandbox.
+    pub fn limit(&self, sandbox: &mut Sandbox) {
+        if self.max_steps.is_some() {
+            sandbox.max_steps = self.max_steps;
+        }
+        if self.timeout.is_some() {
+            sandbox.timeout = self.timeout;
+        }
+        if self.max_output.is_some() {
+            sandbox.max_output = self.max_output;
+        }
+    }
+
+    /// Stamp all auxiliary grids onto the playfield.
+    pub fn stamp(&self, grid: &mut Grid) -> Result<(), Error> {
+        for stamp in &self.stamps {
+            stamp.apply(grid, &std::fs::read_to_string(&stamp.file)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl Stamp {
+    /// Copy the given auxiliary grid onto the playfield. All of it must fit into the playfield.
+    pub fn apply(&self, grid: &mut Grid, source: &str) -> Result<(), Error> {
+        for (row, line) in source.lines().enumerate() {
+            for (column, character) in line.chars().enumerate() {
+                if !character.is_ascii() {
+                    return Err(Error::NonAsciiSource(character));
+                }
+                let (x, y) = (self.x + column as Int, self.y + row as Int);
+                grid.set(x, y, character as u8).ok_or_else(|| {
+                    Error::InvalidManifest(format!(
+                        "stamp {} does not fit into the playfield at {},{}",
+                        self.file.display(),
+                        x,
+                        y
+                    ))
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_value(value: &str) -> Option<Value> {
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut string = String::new();
+        let mut characters = rest.chars();
+        loop {
+            match characters.next()? {
+                '"' => break,
+                '\\' => string.push(match characters.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    escaped @ ('"' | '\\') => escaped,
+                    _ => return None,
+                }),
+                character => string.push(character),
+            }
+        }
+        let rest = characters.as_str().trim();
+        (rest.is_empty() || rest.starts_with('#')).then_some(Value::String(string))
+    } else {
+        let number = value.split('#').next()?.trim().replace('_', "");
+        number.parse().ok().map(Value::Integer)
+    }
+}
@@ -0,0 +1,175 @@
+//! The conformance matrix, which shows how edge cases that reference interpreters disagree on behave under
+//! each of boxfunge's policy settings.
+
+use crate::run;
+use crate::BridgeEdge;
+use crate::CellValues;
+use crate::ExitReason;
+use crate::LanguageStandard;
+use crate::RunRequest;
+use crate::StringSpaces;
+use crate::WrapMode;
+
+/// Step limit for matrix programs, which loop forever under some settings.
+const MATRIX_STEPS: usize = 10_000;
+
+/// A setting as a command-line option and how to apply it to a run.
+type Setting = (&'static str, fn(&mut RunRequest));
+
+/// A micro-program exercising one edge case.
+#[derive(Clone, Debug)]
+pub struct MatrixCase {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub program: String,
+    pub input: &'static [u8],
+}
+
+/// What a matrix case did under one setting.
+#[derive(Debug)]
+pub struct MatrixEntry {
+    /// The setting as a command-line option, e.g. `--wrap lahey`.
+    pub setting: &'static str,
+    pub output: Vec<u8>,
+    pub exit: ExitReason,
+}
+
+impl MatrixEntry {
+    /// The output and how the program stopped, in one line.
+    pub fn behavior(&self) -> String {
+        let exit = match &self.exit {
+            ExitReason::Error(why) => why.to_string(),
+            exit => exit.name().to_string(),
+        };
+        format!("{:?} ({})", String::from_utf8_lossy(&self.output), exit)
+    }
+}
+
+/// The edge cases of the matrix.
+pub fn matrix_cases() -> Vec<MatrixCase> {
+    vec![
+        MatrixCase {
+            name: "eof-character",
+            description: "~ at the end of the input",
+            program: "~.@".to_string(),
+            input: b"",
+        },
+        MatrixCase {
+            name: "eof-number",
+            description: "& at the end of the input",
+            program: "&.@".to_string(),
+            input: b"",
+        },
+        MatrixCase {
+            name: "division-by-zero",
+            description: "/ and % with a zero divisor",
+            program: "10/.10%.@".to_string(),
+            input: b"",
+        },
+        MatrixCase {
+            name: "bridge-at-edge",
+            description: "# skipping over the right edge of the playfield",
+            // the 1 is in the last column of the 80x25 grid
+            program: format!(" v\n#<@.{:75}1", ""),
+            input: b"",
+        },
+        MatrixCase {
+            name: "negative-get",
+            description: "g of a cell that p set to a negative value or above 127",
+            program: "01-00p00g.\"~\"2+00p00g.@".to_string(),
+            input: b"",
+        },
+        MatrixCase {
+            name: "number-format",
+            description: ". of zero, a negative number and the empty stack",
+            program: "0.01-..@".to_string(),
+            input: b"",
+        },
+        MatrixCase {
+            name: "string-spaces",
+            description: "a run of spaces in string mode",
+            program: "\"b  a\">:#,_@".to_string(),
+            input: b"",
+        },
+        MatrixCase {
+            name: "unknown-instruction",
+            description: "an instruction that is not part of Befunge-93",
+            program: "1X.@".to_string(),
+            input: b"",
+        },
+    ]
+}
+
+/// Run every case of the matrix under every setting of the language standard, wrap mode, `#` edge behavior,
+/// string spaces and cell values. Each setting is applied on top of the base request, whose program and input are ignored.
+pub fn conformance_matrix(base: &RunRequest) -> Vec<(MatrixCase, Vec<MatrixEntry>)> {
+    let settings: [Setting; 14] = [
+        ("-s 93", |request| {
+            request.standard = Some(LanguageStandard::Befunge93)
+        }),
+        ("-s 96", |request| {
+            request.standard = Some(LanguageStandard::Befunge96)
+        }),
+        ("-s 97", |request| {
+            request.standard = Some(LanguageStandard::Befunge97)
+        }),
+        ("-s 98", |request| {
+            request.standard = Some(LanguageStandard::Befunge98)
+        }),
+        ("--wrap torus", |request| {
+            request.wrap_mode = Some(WrapMode::Torus)
+        }),
+        ("--wrap lahey", |request| {
+            request.wrap_mode = Some(WrapMode::Lahey)
+        }),
+        ("--wrap reflect", |request| {
+            request.wrap_mode = Some(WrapMode::Reflect)
+        }),
+        ("--bridge-edge skip", |request| {
+            request.bridge_edge = BridgeEdge::Skip
+        }),
+        ("--bridge-edge land", |request| {
+            request.bridge_edge = BridgeEdge::Land
+        }),
+        ("--string-spaces literal", |request| {
+            request.string_spaces = StringSpaces::Literal
+        }),
+        ("--string-spaces sgml", |request| {
+            request.string_spaces = StringSpaces::Sgml
+        }),
+        ("--cell-values signed", |request| {
+            request.cell_values = Some(CellValues::Signed)
+        }),
+        ("--cell-values unsigned", |request| {
+            request.cell_values = Some(CellValues::Unsigned)
+        }),
+        ("--cell-values full", |request| {
+            request.cell_values = Some(CellValues::Full)
+        }),
+    ];
+
+    matrix_cases()
+        .into_iter()
+        .map(|case| {
+            let entries = settings
+                .iter()
+                .map(|(setting, apply)| {
+                    let mut request = RunRequest {
+                        program: case.program.clone(),
+                        input: case.input.to_vec(),
+                        max_steps: Some(MATRIX_STEPS),
+                        ..base.clone()
+                    };
+                    apply(&mut request);
+                    let report = run(request);
+                    MatrixEntry {
+                        setting,
+                        output: report.output,
+                        exit: report.exit,
+                    }
+                })
+                .collect();
+            (case, entries)
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+//! Optional program metadata headers.
+//!
+//! A program may start with any number of header lines of the form `;; key: value`.
+//! These lines are not part of the grid; they are removed before the program is parsed, so the first
+//! line after the header is row 0. Recognized keys are `title`, `author`, `standard` (93, 96, 97 or 98) and
+//! `extensions` (comma-separated); any other keys are kept verbatim.
+//!
+//! Since `;` is an instruction in Befunge-98, only lines with exactly this shape belong to the header: a key made of
+//! letters, digits, `-` and `_` directly followed by a colon. The header ends at the first line of any other shape,
+//! such as `;;v`, which is a program's first line.
+
+use argh::FromArgValue;
+
+use crate::Bounds;
+use crate::Error;
+use crate::Grid;
+use crate::LanguageStandard;
+use crate::Position;
+
+const HEADER_PREFIX: &str = ";; ";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub standard: Option<LanguageStandard>,
+    pub extensions: Vec<String>,
+    /// Unrecognized header entries, in order of appearance.
+    pub other: Vec<(String, String)>,
+}
+
+impl Metadata {
+    /// Split the metadata header off the program source, returning the metadata and the remaining program.
+    pub fn parse(source: &str) -> Result<(Self, &str), Error> {
+        let mut metadata = Self::default();
+        let mut rest = source;
+        while let Some((key, value, remainder)) = header_line(rest) {
+            rest = remainder;
+            match key {
+                "title" => metadata.title = Some(value.to_string()),
+                "author" => metadata.author = Some(value.to_string()),
+                "standard" => {
+                    metadata.standard = Some(
+                        LanguageStandard::from_arg_value(value)
+                            .map_err(|_| Error::InvalidMetadata(format!("{}: {}", key, value)))?,
+                    )
+                }
+                "extensions" => metadata.extensions.extend(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|extension| !extension.is_empty())
+                        .map(str::to_string),
+                ),
+                _ => metadata.other.push((key.to_string(), value.to_string())),
+            }
+        }
+        Ok((metadata, rest))
+    }
+}
+
+/// Split a `;; key: value` line off the source, returning the trimmed key and value and the rest of the source.
+fn header_line(source: &str) -> Option<(&str, &str, &str)> {
+    let line = source.strip_prefix(HEADER_PREFIX)?;
+    let (line, rest) = line.split_once('\n').unwrap_or((line, ""));
+    let (key, value) = line.split_once(':')?;
+    let is_key = !key.is_empty()
+        && key
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+    is_key.then_some((key, value.trim(), rest))
+}
+
+/// Basic static statistics about a program grid.
+/// These only look at the cells, so instruction characters within string literals are counted as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GridStats {
+    /// Smallest rectangle containing all non-space cells, or `None` if there are none. For unbounded grids, this may
+    /// extend beyond the 80x25 area.
+    pub bounds: Option<Bounds>,
+    pub non_space_cells: usize,
+    pub reads_input: bool,
+    pub modifies_itself: bool,
+    pub uses_randomness: bool,
+}
+
+impl GridStats {
+    pub fn new(grid: &Grid) -> Self {
+        let mut stats = Self::default();
+        for (position, value) in grid.non_space_cells() {
+            stats.bounds = Some(match stats.bounds {
+                Some(bounds) => bounds.including(position),
+                None => Bounds {
+                    min: position,
+                    max: position,
+                },
+            });
+            stats.non_space_cells += 1;
+            match u8::try_from(value) {
+                Ok(b'~' | b'&') => stats.reads_input = true,
+                Ok(b'p') => stats.modifies_itself = true,
+                Ok(b'?') => stats.uses_randomness = true,
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    /// Width, height and depth of [`bounds`](Self::bounds), which are zero for a grid without non-space cells.
+    pub fn size(&self) -> Position {
+        self.bounds.map_or(Position::ZERO, |bounds| bounds.size())
+    }
+}
@@ -0,0 +1,160 @@
+//! Live metrics of a running program, served over HTTP in the Prometheus text format, so that long-running programs
+//! can be monitored like any other service.
+
+use std::fmt::Write as _;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use crate::Executer;
+use crate::Interpreter;
+
+/// Steps between updates of the metrics by [`Metrics::record`], to keep their cost out of the main loop.
+pub const METRICS_INTERVAL: usize = 1024;
+
+/// The latest state of a program, shared between the thread running it and the thread serving the metrics.
+#[derive(Debug)]
+pub struct Metrics {
+    start: Instant,
+    steps: AtomicUsize,
+    instruction_pointers: AtomicUsize,
+    stack_values: AtomicUsize,
+    grid_cells: AtomicU64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            steps: AtomicUsize::new(0),
+            instruction_pointers: AtomicUsize::new(1),
+            stack_values: AtomicUsize::new(0),
+            grid_cells: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take over the interpreter's current state.
+    pub fn record(&self, interpreter: &Interpreter) {
+        let size = interpreter.grid().bounds().size();
+        let stack_values = interpreter.stack().len()
+            + interpreter
+                .stack_stack()
+                .iter()
+                .map(Vec::len)
+                .sum::<usize>();
+        self.steps.store(interpreter.steps(), Ordering::Relaxed);
+        self.instruction_pointers
+            .store(interpreter.ip_count(), Ordering::Relaxed);
+        self.stack_values.store(stack_values, Ordering::Relaxed);
+        self.grid_cells
+            .store((size.x * size.y * size.z) as u64, Ordering::Relaxed);
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let steps = self.steps.load(Ordering::Relaxed);
+        let seconds = self.start.elapsed().as_secs_f64();
+        let steps_per_second = if seconds > 0.0 {
+            steps as f64 / seconds
+        } else {
+            0.0
+        };
+        let mut text = String::new();
+        for (name, kind, help, value) in [
+            (
+                "boxfunge_steps_total",
+                "counter",
+                "Steps executed so far.",
+                steps.to_string(),
+            ),
+            (
+                "boxfunge_steps_per_second",
+                "gauge",
+                "Average steps per second since the program started.",
+                format!("{:.3}", steps_per_second),
+            ),
+            (
+                "boxfunge_uptime_seconds",
+                "gauge",
+                "Seconds since the program started.",
+                format!("{:.3}", seconds),
+            ),
+            (
+                "boxfunge_instruction_pointers",
+                "gauge",
+                "Instruction pointers alive.",
+                self.instruction_pointers
+                    .load(Ordering::Relaxed)
+                    .to_string(),
+            ),
+            (
+                "boxfunge_stack_values",
+                "gauge",
+                "Values on all stacks of the current instruction pointer.",
+                self.stack_values.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "boxfunge_grid_cells",
+                "gauge",
+                "Cells within the bounds of the playfield.",
+                self.grid_cells.load(Ordering::Relaxed).to_string(),
+            ),
+        ] {
+            let _ = write!(
+                text,
+                "# HELP {0} {1}\n# TYPE {0} {2}\n{0} {3}\n",
+                name, help, kind, value
+            );
+        }
+        text
+    }
+
+    /// Serve the metrics at `/metrics` on a background thread until the process exits.
+    pub fn serve(self: Arc<Self>, listener: TcpListener) {
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // a misbehaving client must not take the server down
+                let _ = respond(stream, &self);
+            }
+        });
+    }
+}
+
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // the headers are not needed, but have to be read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let (status, content_type, body) = match request.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", "/metrics", _] => ("200 OK", "text/plain; version=0.0.4", metrics.render()),
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
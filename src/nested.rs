@@ -0,0 +1,135 @@
+//! Running a program with a Befunge interpreter written in Befunge, such as `programs/self_interpreter.bf`.
+//!
+//! The interpreting program reads the interpreted program's source from its input, followed by a `;` and the
+//! interpreted program's input, and its output is the interpreted program's output. Its own limits bound the whole
+//! run, but they are a poor measure of whether the interpreted program still gets anywhere: interpretation costs
+//! a varying number of outer steps per inner step. The interpreted program's progress is therefore judged by its
+//! input and output instead, see [`NestedRequest::max_stall_steps`].
+
+use std::cell::RefCell;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::Error;
+use crate::Executer;
+use crate::ExitReason;
+use crate::Interpreter;
+use crate::LanguageStandard;
+use crate::RunReport;
+use crate::RunRequest;
+
+/// Separates the interpreted program's source from its input.
+pub const NESTED_SEPARATOR: u8 = b';';
+
+/// A program and the program interpreting it, see [`run_nested`].
+#[derive(Clone, Debug, Default)]
+pub struct NestedRequest {
+    /// The interpreting program with the limits of the whole run. Its input is replaced by the interpreted program
+    /// and that program's input.
+    pub outer: RunRequest,
+    /// Source of the interpreted program.
+    pub program: String,
+    /// Input of the interpreted program.
+    pub input: Vec<u8>,
+    /// Stop once the interpreting program has run this many steps since the interpreted program last made progress,
+    /// i.e. since a byte of the source or input was read or a byte was output.
+    pub max_stall_steps: Option<usize>,
+}
+
+/// Results of a nested run, see [`run_nested`].
+#[derive(Debug)]
+pub struct NestedReport {
+    /// The interpreting program's report, which includes the interpreted program's output.
+    pub report: RunReport,
+    /// Whether the interpreting program read the entire source, so that the interpreted program got to run.
+    pub source_read: bool,
+    /// Bytes of the interpreted program's input that were read.
+    pub input_read: usize,
+}
+
+/// Input whose position can be seen from the outside while an interpreter reads it.
+struct SharedInput(Rc<RefCell<io::Cursor<Vec<u8>>>>);
+
+impl Read for SharedInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+/// Output that can be seen from the outside while an interpreter writes it.
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Run a program with an interpreter written in Befunge, stopping it by the outer limits or once the interpreted
+/// program stalls.
+pub fn run_nested(request: NestedRequest) -> NestedReport {
+    let start = Instant::now();
+    let mut data = request.program.into_bytes();
+    let source_length = data.len() as u64;
+    if !request.input.is_empty() {
+        data.push(NESTED_SEPARATOR);
+        data.extend(request.input);
+    }
+    let input = Rc::new(RefCell::new(io::Cursor::new(data)));
+    let output = Rc::new(RefCell::new(Vec::new()));
+
+    let standard = request
+        .outer
+        .standard
+        .unwrap_or(LanguageStandard::Befunge93);
+    let (exit, steps) = match Interpreter::parse_grid_for_standard(&request.outer.program, standard)
+    {
+        Err(why) => (ExitReason::Error(why), 0),
+        Ok(grid) => {
+            let mut interpreter = request.outer.interpreter_with_io(
+                grid,
+                Box::new(SharedInput(input.clone())),
+                Box::new(SharedOutput(output.clone())),
+            );
+            let mut progress = (0, 0);
+            let mut progress_step = 0;
+            let exit = loop {
+                match interpreter.run_step() {
+                    Ok(()) => {}
+                    Err(Error::ProgramEnd) => break interpreter.exit_reason(Ok(())),
+                    Err(why) => break interpreter.exit_reason(Err(why)),
+                }
+                let current = (input.borrow().position(), output.borrow().len());
+                if current != progress {
+                    progress = current;
+                    progress_step = interpreter.steps();
+                } else if request
+                    .max_stall_steps
+                    .is_some_and(|max| interpreter.steps() - progress_step > max)
+                {
+                    break ExitReason::Stalled;
+                }
+            };
+            (exit, interpreter.steps())
+        }
+    };
+
+    let position = input.borrow().position();
+    NestedReport {
+        report: RunReport {
+            output: output.take(),
+            exit,
+            steps,
+            duration: start.elapsed(),
+        },
+        source_read: position >= source_length,
+        input_read: position.saturating_sub(source_length + 1) as usize,
+    }
+}
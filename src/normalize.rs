@@ -0,0 +1,46 @@
+//! Normalization of program output for comparing it with expected output in tests.
+
+/// Differences between outputs to ignore when comparing them. [`Normalize::ALL`] is what most golden tests want.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Normalize {
+    /// Treat `\r\n` line endings like `\n`.
+    pub line_endings: bool,
+    /// Ignore whitespace at the end of every line, such as the space `.` writes after every number.
+    pub trailing_whitespace: bool,
+    /// Ignore empty lines at the end of the output.
+    pub trailing_newlines: bool,
+}
+
+impl Normalize {
+    pub const ALL: Self = Self {
+        line_endings: true,
+        trailing_whitespace: true,
+        trailing_newlines: true,
+    };
+
+    /// Normalize the output. Normalizing a lossily decoded output is fine, since programs only output ASCII.
+    pub fn apply(&self, output: &str) -> String {
+        let mut normalized = if self.line_endings {
+            output.replace("\r\n", "\n")
+        } else {
+            output.to_string()
+        };
+        if self.trailing_whitespace {
+            normalized = normalized
+                .split('\n')
+                .map(|line| line.trim_end_matches([' ', '\t']))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        if self.trailing_newlines {
+            let length = normalized.trim_end_matches('\n').len();
+            normalized.truncate(length);
+        }
+        normalized
+    }
+
+    /// Whether the outputs are equal after normalization.
+    pub fn equal(&self, left: &str, right: &str) -> bool {
+        self.apply(left) == self.apply(right)
+    }
+}
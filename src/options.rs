@@ -0,0 +1,543 @@
+//! The options of the command-line interface.
+//!
+//! argh parses the command line into a flat list of options, which [`Arguments`] groups by what they configure: the
+//! options of the daemon, the debugger, the sandbox and the analyses each have their own struct.
+
+use argh::EarlyExit;
+use argh::FromArgs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::Bounds;
+use crate::Breakpoint;
+use crate::BridgeEdge;
+use crate::CellValues;
+use crate::ExecParadigm;
+use crate::Expression;
+use crate::InputSource;
+use crate::LanguageStandard;
+use crate::Manifest;
+use crate::Newline;
+use crate::OutputEncoding;
+use crate::Position;
+use crate::ProtectPolicy;
+use crate::Sandbox;
+use crate::SandboxProfile;
+use crate::StackFormat;
+use crate::StringSpaces;
+use crate::SystemInfoItem;
+use crate::WrapMode;
+use crate::WriteRate;
+use crate::PC;
+
+// argh parses the command line into this flat struct, whose doc comments make up the help, and `Arguments` groups its
+// options
+#[derive(FromArgs)]
+/// Befunge-93 interpreter.
+struct CommandLine {
+    /// input file to read; "-" or nothing reads the program from stdin
+    #[argh(positional, default = "PathBuf::from(\"-\")")]
+    input: PathBuf,
+    /// collect and show performance metrics
+    #[argh(switch, short = 'p')]
+    show_performance: bool,
+    /// language standard to use: 93, the drafts 96 and 97, 98, which has an unbounded playfield and of which most instructions are supported, or trefunge for three-dimensional Befunge-98. default: 98, or trefunge for .tf and .t98 files
+    #[argh(option, short = 's', default = "LanguageStandard::default()")]
+    language_standard: LanguageStandard,
+    /// file to use as stdin for the program; particularly useful with self-interpreters.
+    /// "-" is boxfunge's own stdin, "null" provides no input at all, "zero" provides endless zero bytes.
+    /// May be given multiple times, together with --stdin-str, to read the sources one after another.
+    #[argh(option, short = 'i')]
+    stdin: Vec<InputSource>,
+    /// text to use as stdin for the program, in the order given among the sources of --stdin; may be given multiple times
+    #[argh(option)]
+    stdin_str: Vec<String>,
+    /// output program name. If this is given, boxfunge produces an executable from the given source file instead of running it.
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+    /// write the stack depth and top-of-stack value after every step to the given CSV file
+    #[argh(option)]
+    stack_log: Option<PathBuf>,
+    /// expression to print to stderr whenever its value changes after a step, with the changed values highlighted, e.g. "stack[0] + stack[1]" or "cell(10, 3)"; may be given multiple times
+    #[argh(option)]
+    watch: Vec<Expression>,
+    /// stop the program when its program counter reaches the cell "x,y" (or "x,y,z" in Trefunge) or a region "@name" of the annotations, only for one instruction pointer with "x,y thread id", or only if a condition holds with "x,y if condition", e.g. "5,5 if stack.len() > 100 && cell(5, 5) == 64"; may be given multiple times
+    #[argh(option)]
+    breakpoint: Vec<Breakpoint>,
+    /// start position and direction of the program counter as "x,y,direction", where direction is one of >, <, ^, v; Befunge-93 programs must start within 80x25. default: 0,0,>
+    /// If given multiple times, the program is run once from every start position and a report of all runs is printed.
+    #[argh(option)]
+    start: Vec<PC>,
+    /// mirror all input consumed by the program to stderr
+    #[argh(switch)]
+    echo_input: bool,
+    /// accept underscores as digit separators in numbers read by &
+    #[argh(switch)]
+    lenient_numbers: bool,
+    /// print the program's metadata header and basic statistics instead of running it
+    #[argh(switch)]
+    info: bool,
+    /// load a Befunge-98 program lazily: cells outside of 80x25 are read from the file when the program reaches them,
+    /// which makes very large programs start quickly. With the mmap feature, the file is mapped into memory
+    #[argh(switch)]
+    lazy: bool,
+    /// what the program counter does at the edge of the playfield: torus (Befunge-93), lahey (Befunge-98) or reflect. default: that of the language standard
+    #[argh(option)]
+    wrap: Option<WrapMode>,
+    /// what # does when the cell to skip lies across the edge of the playfield: skip it or land on it. default: skip
+    #[argh(option, default = "BridgeEdge::default()")]
+    bridge_edge: BridgeEdge,
+    /// enable a debugging instruction that prints the stack to stderr, e.g. "D". It must not be a regular instruction.
+    #[argh(option, from_str_fn(parse_instruction))]
+    stack_dump: Option<u8>,
+    /// limits and permissions for running the program: strict (for untrusted code), default or trusted. default: default
+    #[argh(option, default = "SandboxProfile::default()")]
+    sandbox: SandboxProfile,
+    /// stop the program with an error once it has written more than this many bytes of output; overrides the sandbox's limit
+    #[argh(option)]
+    max_output: Option<usize>,
+    /// write a self-contained HTML report of the run, with an execution heatmap and the program's output, to the given file
+    #[argh(option)]
+    report: Option<PathBuf>,
+    /// print the symbolic stack at every branch point reachable from the start position instead of running the program
+    #[argh(switch)]
+    branches: bool,
+    /// print the string literals the program can push, in the order they are pushed, instead of running it
+    #[argh(switch)]
+    strings: bool,
+    /// print a JSON outline of the program (entry, branch points, loops, string literals and grid writes) instead of running it
+    #[argh(switch)]
+    outline: bool,
+    /// write-protect the region "x,y,w,h" of the playfield against p; may be given multiple times
+    #[argh(option, from_str_fn(parse_region))]
+    protect: Vec<Bounds>,
+    /// what happens when p writes to a protected region: ignore the write or stop with an error. default: error
+    #[argh(option, default = "ProtectPolicy::default()")]
+    protect_policy: ProtectPolicy,
+    /// seed for the random number generator used by ?, making runs reproducible
+    #[argh(option)]
+    seed: Option<u64>,
+    /// record the run as a golden test in the given directory: the program, its input (.in), its output (.expected) and the seed (.seed)
+    #[argh(option)]
+    golden: Option<PathBuf>,
+    /// render the execution as an ANSI animation to the given file, which can be played back with cat. Every instruction pointer has its own color and its stack next to the playfield
+    #[argh(option)]
+    animate_to: Option<PathBuf>,
+    /// number of steps between animation frames. default: 1
+    #[argh(option, default = "1")]
+    animate_interval: usize,
+    /// line ending to write for a newline output by the program: lf, crlf or native. default: lf
+    #[argh(option, default = "Newline::default()")]
+    newline: Newline,
+    /// encoding of the output: ascii (same as utf-8), utf-16le or utf-16be. default: ascii
+    #[argh(option, default = "OutputEncoding::default()")]
+    output_encoding: OutputEncoding,
+    /// print the instructions of the language standard with their stack effects and descriptions and exit
+    #[argh(switch)]
+    ops: bool,
+    /// print how edge cases that reference interpreters disagree on behave under each setting of -s, --wrap, --bridge-edge, --string-spaces and --cell-values, starting from the other given options, and exit
+    #[argh(switch)]
+    matrix: bool,
+    /// check the interpreter's internal invariants before every step; slow, but useful when running untrusted code
+    #[argh(switch)]
+    paranoid: bool,
+    /// run concurrent instruction pointers in strict round-robin order of creation, with a new one taking its first turn at the end of the round it was created in, instead of right before its parent
+    #[argh(switch)]
+    deterministic_scheduling: bool,
+    /// number of stack values to preallocate room for. default: 64
+    #[argh(option)]
+    stack_capacity: Option<usize>,
+    /// track the peak stack depth and print it to stderr after the run; slows down execution
+    #[argh(switch)]
+    stack_stats: bool,
+    /// flag cells written by p more than N times within M steps, given as N/M, e.g. 100/1000, and print them and the other cells written most to stderr after the run; slows down execution
+    #[argh(option)]
+    write_hotspots: Option<WriteRate>,
+    /// how cells store values written by p, and how g reads them back: signed or unsigned bytes, or full values. default: signed for Befunge-93 and -96, full for Befunge-97 and -98
+    #[argh(option)]
+    cell_values: Option<CellValues>,
+    /// how string mode treats runs of spaces: push each one (literal, Befunge-93) or a single one (sgml, Befunge-98). default: literal
+    #[argh(option, default = "StringSpaces::default()")]
+    string_spaces: StringSpaces,
+    /// run the program described by the manifest.toml in the given directory or .bfpkg package, with its input, limits and auxiliary grids
+    #[argh(option)]
+    run_dir: Option<PathBuf>,
+    /// bundle the run directory given with --run-dir into a .bfpkg package with the given name instead of running it
+    #[argh(option)]
+    pack: Option<PathBuf>,
+    /// compare the output with the expected output named by the manifest of --run-dir, ignoring trailing whitespace
+    #[argh(switch)]
+    test: bool,
+    /// file of cell patches to apply to the program when loading it, without changing its source; may be given multiple times
+    #[argh(option)]
+    overlay: Vec<PathBuf>,
+    /// print the cells the overlays change, with their old and new characters, instead of running the program
+    #[argh(switch)]
+    overlay_diff: bool,
+    /// print the overlay that turns the program, including its overlays, into the given edited copy of it and exit
+    #[argh(option)]
+    overlay_from: Option<PathBuf>,
+    /// file of comments on cells and regions of the program, shown in the HTML report, the animation and the post-mortem debugger
+    #[argh(option)]
+    annotations: Option<PathBuf>,
+    /// write the stack to the given file when the program stops, so that a later run can continue with it via --load-stack
+    #[argh(option)]
+    dump_stack_on_exit: Option<PathBuf>,
+    /// start the program with the stack saved in the given file instead of an empty one
+    #[argh(option)]
+    load_stack: Option<PathBuf>,
+    /// write the program's state, i.e. its grid, stacks and instruction pointers, to the given file when it stops, e.g. at a breakpoint, so that a later run can continue from there via --resume
+    #[argh(option)]
+    save_state: Option<PathBuf>,
+    /// continue the program whose state was saved to the given file by --save-state, with new input, instead of loading a program. Options other than the standard have to be given again
+    #[argh(option)]
+    resume: Option<PathBuf>,
+    /// format of the files of --dump-stack-on-exit and --load-stack: text, with one value per line, or binary, with 64-bit little-endian values. Either way, the bottom of the stack comes first. default: text
+    #[argh(option, default = "StackFormat::default()")]
+    stack_format: StackFormat,
+    /// hide information from the y instruction of Befunge-98: time, arguments or environment, which is also hidden by the strict sandbox; may be given multiple times
+    #[argh(option)]
+    hide_sysinfo: Vec<SystemInfoItem>,
+    /// let Befunge-98 programs access files: the i and o instructions load and save parts of the playfield, and the FILE fingerprint opens, reads, writes and deletes files. Without this, both reflect; the strict sandbox refuses them either way
+    #[argh(switch)]
+    file_io: bool,
+    /// enable the OUTS fingerprint of Befunge-98, which lets programs send their output to stderr instead of stdout, e.g. for diagnostics
+    #[argh(switch)]
+    output_streams: bool,
+    /// how the = instruction of Befunge-98 runs commands: "system" like C's system(), the path of a specific shell, which gets the command after -c, or "unavailable" to have = reflect. Only the trusted sandbox lets = run commands at all. default: system
+    #[argh(option, default = "ExecParadigm::default()")]
+    exec: ExecParadigm,
+    /// run the program twice with the same seed and input and report anything that makes it depend on more than those, such as the wall-clock time, the host's cell width or popping the empty stack, instead of running it normally
+    #[argh(switch)]
+    audit_determinism: bool,
+    /// when the program stops with an error, e.g. an illegal instruction, a limit or a breakpoint, read debugger commands from stdin to inspect its state at that moment
+    #[argh(switch)]
+    post_mortem: bool,
+    /// run the post-mortem debugger's commands from the given file before reading more from stdin, e.g. to repeat a session logged with --debug-log; implies --post-mortem
+    #[argh(option)]
+    debug_script: Option<PathBuf>,
+    /// append every command given to the post-mortem debugger to the given file, which --debug-script can run again
+    #[argh(option)]
+    debug_log: Option<PathBuf>,
+    /// instead of running a program, listen on the Unix socket at the given path for programs to run with their input and limits, and answer with their output until killed. The limits of --sandbox and --max-output apply to requests that don't set their own
+    #[argh(option)]
+    daemon: Option<PathBuf>,
+    /// serve live metrics of the running program, such as its steps per second, in the Prometheus text format at http://ADDRESS/metrics, e.g. 127.0.0.1:9100
+    #[argh(option)]
+    metrics: Option<SocketAddr>,
+    /// print why the program stopped (via @, in an input EOF loop, by a limit or with an error), its step count and position to stderr
+    #[argh(switch)]
+    exit_summary: bool,
+    /// argument for the program, which it reads with & or ~ before its input, one per line; may be given multiple times.
+    /// All command-line arguments after "--" are passed to the program as well.
+    #[argh(option, long = "arg")]
+    program_arguments: Vec<String>,
+}
+
+/// The options boxfunge was started with, see `boxfunge --help` for what each of them does.
+#[derive(Default)]
+pub struct Arguments {
+    pub input: PathBuf,
+    pub show_performance: bool,
+    pub language_standard: LanguageStandard,
+    pub stdin: Vec<InputSource>,
+    pub stdin_str: Vec<String>,
+    pub output: Option<PathBuf>,
+    pub stack_log: Option<PathBuf>,
+    pub start: Vec<PC>,
+    pub echo_input: bool,
+    pub lenient_numbers: bool,
+    pub lazy: bool,
+    pub wrap: Option<WrapMode>,
+    pub bridge_edge: BridgeEdge,
+    pub stack_dump: Option<u8>,
+    pub report: Option<PathBuf>,
+    pub protect: Vec<Bounds>,
+    pub protect_policy: ProtectPolicy,
+    pub seed: Option<u64>,
+    pub golden: Option<PathBuf>,
+    pub animate_to: Option<PathBuf>,
+    pub animate_interval: usize,
+    pub newline: Newline,
+    pub output_encoding: OutputEncoding,
+    pub ops: bool,
+    pub matrix: bool,
+    pub paranoid: bool,
+    pub deterministic_scheduling: bool,
+    pub stack_capacity: Option<usize>,
+    pub stack_stats: bool,
+    pub write_hotspots: Option<WriteRate>,
+    pub cell_values: Option<CellValues>,
+    pub string_spaces: StringSpaces,
+    pub run_dir: Option<PathBuf>,
+    pub pack: Option<PathBuf>,
+    pub test: bool,
+    pub overlay: Vec<PathBuf>,
+    pub overlay_diff: bool,
+    pub overlay_from: Option<PathBuf>,
+    pub annotations: Option<PathBuf>,
+    pub dump_stack_on_exit: Option<PathBuf>,
+    pub load_stack: Option<PathBuf>,
+    pub save_state: Option<PathBuf>,
+    pub resume: Option<PathBuf>,
+    pub stack_format: StackFormat,
+    pub output_streams: bool,
+    pub metrics: Option<SocketAddr>,
+    pub exit_summary: bool,
+    pub daemon: DaemonOptions,
+    pub debugger: DebuggerOptions,
+    pub sandbox: SandboxOptions,
+    pub analysis: AnalysisOptions,
+    /// Arguments for the program, from `--arg` and after "--".
+    pub program_arguments: Vec<String>,
+}
+
+/// Options of the daemon, see [`run_daemon`](crate::run_daemon).
+#[derive(Default)]
+pub struct DaemonOptions {
+    /// The Unix socket to serve requests on instead of running a program, `--daemon`.
+    pub socket: Option<PathBuf>,
+}
+
+/// Options for debugging the program while it runs and after it failed.
+#[derive(Default)]
+pub struct DebuggerOptions {
+    pub watch: Vec<Expression>,
+    pub breakpoint: Vec<Breakpoint>,
+    /// Start the post-mortem debugger when the program fails, see [`post_mortem`](crate::post_mortem).
+    pub post_mortem: bool,
+    /// Debugger commands to run before those from stdin, `--debug-script`. Implies `post_mortem`.
+    pub script: Option<PathBuf>,
+    /// File to append the debugger commands to, `--debug-log`.
+    pub log: Option<PathBuf>,
+}
+
+/// Limits and permissions of the program.
+#[derive(Default)]
+pub struct SandboxOptions {
+    pub profile: SandboxProfile,
+    /// Limit of the output, overriding that of the profile, `--max-output`.
+    pub max_output: Option<usize>,
+    pub file_io: bool,
+    pub exec: ExecParadigm,
+    pub hide_sysinfo: Vec<SystemInfoItem>,
+}
+
+impl SandboxOptions {
+    /// The sandbox of the profile, limited further by the manifest and with the output limit of `--max-output`.
+    pub fn sandbox(&self, manifest: Option<&Manifest>) -> Sandbox {
+        let mut sandbox = self.profile.sandbox();
+        if let Some(manifest) = manifest {
+            manifest.limit(&mut sandbox);
+        }
+        if self.max_output.is_some() {
+            sandbox.max_output = self.max_output;
+        }
+        sandbox
+    }
+}
+
+/// Analyses of the program that are printed instead of running it.
+#[derive(Default)]
+pub struct AnalysisOptions {
+    /// Print the metadata header and basic statistics, `--info`.
+    pub info: bool,
+    pub branches: bool,
+    pub strings: bool,
+    pub outline: bool,
+    pub audit_determinism: bool,
+}
+
+impl FromArgs for Arguments {
+    fn from_args(command_name: &[&str], args: &[&str]) -> Result<Self, EarlyExit> {
+        let CommandLine {
+            input,
+            show_performance,
+            language_standard,
+            stdin,
+            stdin_str,
+            output,
+            stack_log,
+            watch,
+            breakpoint,
+            start,
+            echo_input,
+            lenient_numbers,
+            info,
+            lazy,
+            wrap,
+            bridge_edge,
+            stack_dump,
+            sandbox,
+            max_output,
+            report,
+            branches,
+            strings,
+            outline,
+            protect,
+            protect_policy,
+            seed,
+            golden,
+            animate_to,
+            animate_interval,
+            newline,
+            output_encoding,
+            ops,
+            matrix,
+            paranoid,
+            deterministic_scheduling,
+            stack_capacity,
+            stack_stats,
+            write_hotspots,
+            cell_values,
+            string_spaces,
+            run_dir,
+            pack,
+            test,
+            overlay,
+            overlay_diff,
+            overlay_from,
+            annotations,
+            dump_stack_on_exit,
+            load_stack,
+            save_state,
+            resume,
+            stack_format,
+            hide_sysinfo,
+            file_io,
+            output_streams,
+            exec,
+            audit_determinism,
+            post_mortem,
+            debug_script,
+            debug_log,
+            daemon,
+            metrics,
+            exit_summary,
+            program_arguments,
+        } = CommandLine::from_args(command_name, args)?;
+        Ok(Self {
+            input,
+            show_performance,
+            language_standard,
+            stdin,
+            stdin_str,
+            output,
+            stack_log,
+            start,
+            echo_input,
+            lenient_numbers,
+            lazy,
+            wrap,
+            bridge_edge,
+            stack_dump,
+            report,
+            protect,
+            protect_policy,
+            seed,
+            golden,
+            animate_to,
+            animate_interval,
+            newline,
+            output_encoding,
+            ops,
+            matrix,
+            paranoid,
+            deterministic_scheduling,
+            stack_capacity,
+            stack_stats,
+            write_hotspots,
+            cell_values,
+            string_spaces,
+            run_dir,
+            pack,
+            test,
+            overlay,
+            overlay_diff,
+            overlay_from,
+            annotations,
+            dump_stack_on_exit,
+            load_stack,
+            save_state,
+            resume,
+            stack_format,
+            output_streams,
+            metrics,
+            exit_summary,
+            daemon: DaemonOptions { socket: daemon },
+            debugger: DebuggerOptions {
+                watch,
+                breakpoint,
+                post_mortem,
+                script: debug_script,
+                log: debug_log,
+            },
+            sandbox: SandboxOptions {
+                profile: sandbox,
+                max_output,
+                file_io,
+                exec,
+                hide_sysinfo,
+            },
+            analysis: AnalysisOptions {
+                info,
+                branches,
+                strings,
+                outline,
+                audit_determinism,
+            },
+            program_arguments,
+        })
+    }
+
+    fn redact_arg_values(command_name: &[&str], args: &[&str]) -> Result<Vec<String>, EarlyExit> {
+        CommandLine::redact_arg_values(command_name, args)
+    }
+}
+
+impl Arguments {
+    /// Put the texts of --stdin-str among the sources of --stdin in the order they were given on the command line,
+    /// which argh can't tell since it collects each option separately.
+    pub fn order_input_sources(&mut self, options: &[&str]) {
+        let mut sources = std::mem::take(&mut self.stdin).into_iter();
+        let mut texts = std::mem::take(&mut self.stdin_str).into_iter();
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            let source = match *option {
+                "-i" | "--stdin" => sources.next(),
+                "--stdin-str" => texts.next().map(InputSource::Text),
+                _ => continue,
+            };
+            self.stdin.extend(source);
+            // the value can't be an option itself
+            options.next();
+        }
+        self.stdin.extend(sources);
+        self.stdin.extend(texts.map(InputSource::Text));
+    }
+}
+
+fn parse_instruction(value: &str) -> Result<u8, String> {
+    match value.as_bytes() {
+        [instruction] if instruction.is_ascii() => Ok(*instruction),
+        _ => Err(format!("'{}' is not a single ASCII character", value)),
+    }
+}
+
+pub(crate) fn parse_region(value: &str) -> Result<Bounds, String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.trim().parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("invalid region '{}', expected \"x,y,w,h\"", value))?;
+    let [x, y, width, height] = parts[..] else {
+        return Err(format!("invalid region '{}', expected \"x,y,w,h\"", value));
+    };
+    if width < 1 || height < 1 {
+        return Err(format!("region '{}' is empty", value));
+    }
+    Ok(Bounds {
+        min: Position::new(x, y, 0),
+        max: Position::new(x + width - 1, y + height - 1, 0),
+    })
+}
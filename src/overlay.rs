@@ -0,0 +1,117 @@
+//! Overlays, which patch individual cells of a program at load time without modifying its source file.
+//!
+//! An overlay file lists one patch per line as the cell's coordinates and its new character in single quotes;
+//! lines starting with `#` are comments:
+//!
+//! ```text
+//! # print a 4 instead of a 2
+//! 3,0 '4'
+//! 10,1 ' '
+//! ```
+
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::Error;
+use crate::Grid;
+use crate::Int;
+
+/// A set of patches, applied in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Overlay {
+    pub patches: Vec<Patch>,
+}
+
+/// A new value for a single cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Patch {
+    pub x: Int,
+    pub y: Int,
+    pub value: u8,
+}
+
+impl Overlay {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut patches = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let invalid = || {
+                Error::InvalidOverlay(format!(
+                    "line {}: expected \"x,y 'c'\", got \"{}\"",
+                    number + 1,
+                    line
+                ))
+            };
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let (position, value) = line.trim().split_once(' ').ok_or_else(invalid)?;
+            let (x, y) = position.split_once(',').ok_or_else(invalid)?;
+            let value = value
+                .trim()
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+                .ok_or_else(invalid)?;
+            let &[value] = value.as_bytes() else {
+                return Err(invalid());
+            };
+            if !value.is_ascii() {
+                return Err(invalid());
+            }
+            patches.push(Patch {
+                x: x.trim().parse().map_err(|_| invalid())?,
+                y: y.trim().parse().map_err(|_| invalid())?,
+                value,
+            });
+        }
+        Ok(Self { patches })
+    }
+
+    /// The overlay that turns the base grid into the patched one.
+    /// Only the cells within the bounds of either grid are compared.
+    pub fn diff(base: &Grid, patched: &Grid) -> Self {
+        let bounds = base
+            .bounds()
+            .including(patched.bounds().min)
+            .including(patched.bounds().max);
+        let mut patches = Vec::new();
+        for y in bounds.min.y..=bounds.max.y {
+            for x in bounds.min.x..=bounds.max.x {
+                // Int is not an i64 on every platform
+                #[allow(clippy::unnecessary_cast)]
+                let (x, y) = (x as Int, y as Int);
+                let new = patched.get(x, y).unwrap_or(b' ');
+                if base.get(x, y).unwrap_or(b' ') != new {
+                    patches.push(Patch { x, y, value: new });
+                }
+            }
+        }
+        Self { patches }
+    }
+
+    /// Patch the grid. All patches must lie within it.
+    pub fn apply(&self, grid: &mut Grid) -> Result<(), Error> {
+        for patch in &self.patches {
+            grid.set(patch.x, patch.y, patch.value).ok_or_else(|| {
+                Error::InvalidOverlay(format!(
+                    "patch at {},{} lies outside of the playfield",
+                    patch.x, patch.y
+                ))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// The overlay in the format [`Overlay::parse`] reads.
+impl Display for Overlay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for patch in &self.patches {
+            writeln!(f, "{},{} '{}'", patch.x, patch.y, patch.value as char)?;
+        }
+        Ok(())
+    }
+}
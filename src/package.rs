@@ -0,0 +1,224 @@
+//! `.bfpkg` packages, which bundle a run directory into a single file for distribution.
+//!
+//! A package is a ZIP archive of a run directory: its `manifest.toml` (see [`Manifest`](crate::Manifest)), the
+//! program with its metadata header, and the input, expected output and auxiliary grids the manifest names.
+//! Packages are written without compression, and only uncompressed archives can be read, which is what
+//! `zip -0` creates.
+
+use std::path::Path;
+
+use crate::Error;
+
+/// Signatures of the ZIP records boxfunge reads and writes.
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+/// ZIP 2.0, the first version with directories, which is all boxfunge needs.
+const ZIP_VERSION: u16 = 20;
+/// 1980-01-01 00:00, the earliest DOS date; packages don't record modification times so they are reproducible.
+const DOS_DATE: u16 = 0x21;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Package {
+    /// Relative paths with `/` separators and file contents, in archive order.
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+impl Package {
+    /// Collect all files of a run directory, including those in subdirectories, sorted by path.
+    pub fn from_dir(directory: &Path) -> Result<Self, Error> {
+        fn collect(
+            directory: &Path,
+            prefix: &str,
+            files: &mut Vec<(String, Vec<u8>)>,
+        ) -> Result<(), Error> {
+            for entry in std::fs::read_dir(directory)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let name = format!("{}{}", prefix, name);
+                if entry.file_type()?.is_dir() {
+                    collect(&entry.path(), &format!("{}/", name), files)?;
+                } else {
+                    files.push((name, std::fs::read(entry.path())?));
+                }
+            }
+            Ok(())
+        }
+
+        let mut files = Vec::new();
+        collect(directory, "", &mut files)?;
+        files.sort();
+        Ok(Self { files })
+    }
+
+    /// Write all files into a directory, creating subdirectories as needed.
+    pub fn extract(&self, directory: &Path) -> Result<(), Error> {
+        for (name, contents) in &self.files {
+            let path = directory.join(name);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, contents)?;
+        }
+        Ok(())
+    }
+
+    pub fn to_zip(&self) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut central_directory = Vec::new();
+        for (name, contents) in &self.files {
+            let offset = zip.len() as u32;
+            let crc = crc32(contents);
+            put_u32(&mut zip, LOCAL_HEADER);
+            put_common_header(&mut zip, name, contents, crc);
+            zip.extend(name.as_bytes());
+            zip.extend(contents);
+
+            put_u32(&mut central_directory, CENTRAL_HEADER);
+            put_u16(&mut central_directory, ZIP_VERSION);
+            put_common_header(&mut central_directory, name, contents, crc);
+            // comment length, disk number, internal and external attributes
+            put_u16(&mut central_directory, 0);
+            put_u16(&mut central_directory, 0);
+            put_u16(&mut central_directory, 0);
+            put_u32(&mut central_directory, 0);
+            put_u32(&mut central_directory, offset);
+            central_directory.extend(name.as_bytes());
+        }
+        let central_directory_offset = zip.len() as u32;
+        zip.extend(&central_directory);
+        put_u32(&mut zip, END_OF_CENTRAL_DIRECTORY);
+        // this disk and the disk with the central directory
+        put_u16(&mut zip, 0);
+        put_u16(&mut zip, 0);
+        put_u16(&mut zip, self.files.len() as u16);
+        put_u16(&mut zip, self.files.len() as u16);
+        put_u32(&mut zip, central_directory.len() as u32);
+        put_u32(&mut zip, central_directory_offset);
+        // comment length
+        put_u16(&mut zip, 0);
+        zip
+    }
+
+    pub fn from_zip(zip: &[u8]) -> Result<Self, Error> {
+        let invalid = |why: &str| Error::InvalidPackage(why.to_string());
+        // the end of central directory record is at least 22 bytes, followed by a comment of up to 64 KiB
+        let end = (0..zip.len().saturating_sub(21))
+            .rev()
+            .take(22 + u16::MAX as usize)
+            .find(|&offset| get_u32(zip, offset) == Some(END_OF_CENTRAL_DIRECTORY))
+            .ok_or_else(|| invalid("not a ZIP archive"))?;
+        let entries = get_u16(zip, end + 10).ok_or_else(|| invalid("truncated archive"))?;
+        let mut offset =
+            get_u32(zip, end + 16).ok_or_else(|| invalid("truncated archive"))? as usize;
+
+        let mut files = Vec::new();
+        for _ in 0..entries {
+            let field = |relative: usize| get_u32(zip, offset + relative);
+            let field16 = |relative: usize| get_u16(zip, offset + relative);
+            if field(0) != Some(CENTRAL_HEADER) {
+                return Err(invalid("corrupt central directory"));
+            }
+            let (Some(method), Some(crc), Some(size), Some(name_length)) =
+                (field16(10), field(16), field(24), field16(28))
+            else {
+                return Err(invalid("truncated archive"));
+            };
+            let (Some(extra_length), Some(comment_length), Some(local)) =
+                (field16(30), field16(32), field(42))
+            else {
+                return Err(invalid("truncated archive"));
+            };
+            let name = zip
+                .get(offset + 46..offset + 46 + name_length as usize)
+                .ok_or_else(|| invalid("truncated archive"))?;
+            let name = String::from_utf8_lossy(name).into_owned();
+            if method != 0 {
+                return Err(invalid(&format!(
+                    "{} is compressed; only uncompressed packages are supported",
+                    name
+                )));
+            }
+            // a package must not write outside of the directory it is extracted to
+            if name.starts_with('/') || name.split(['/', '\\']).any(|part| part == "..") {
+                return Err(invalid(&format!("{} is not a relative path", name)));
+            }
+
+            let local = local as usize;
+            if get_u32(zip, local) != Some(LOCAL_HEADER) {
+                return Err(invalid("corrupt local header"));
+            }
+            let (Some(local_name_length), Some(local_extra_length)) =
+                (get_u16(zip, local + 26), get_u16(zip, local + 28))
+            else {
+                return Err(invalid("truncated archive"));
+            };
+            let start = local + 30 + local_name_length as usize + local_extra_length as usize;
+            let contents = zip
+                .get(start..start + size as usize)
+                .ok_or_else(|| invalid("truncated archive"))?;
+            if crc32(contents) != crc {
+                return Err(invalid(&format!("{} is corrupt", name)));
+            }
+            if !name.ends_with('/') {
+                files.push((name, contents.to_vec()));
+            }
+            offset += 46 + name_length as usize + extra_length as usize + comment_length as usize;
+        }
+        Ok(Self { files })
+    }
+}
+
+/// The part shared by local and central file headers, from the version needed to extract up to the extra field length.
+fn put_common_header(zip: &mut Vec<u8>, name: &str, contents: &[u8], crc: u32) {
+    put_u16(zip, ZIP_VERSION);
+    // flags: file names are UTF-8
+    put_u16(zip, 1 << 11);
+    // stored without compression
+    put_u16(zip, 0);
+    // modification time and date
+    put_u16(zip, 0);
+    put_u16(zip, DOS_DATE);
+    put_u32(zip, crc);
+    put_u32(zip, contents.len() as u32);
+    put_u32(zip, contents.len() as u32);
+    put_u16(zip, name.len() as u16);
+    // extra field length
+    put_u16(zip, 0);
+}
+
+fn put_u16(zip: &mut Vec<u8>, value: u16) {
+    zip.extend(value.to_le_bytes());
+}
+
+fn put_u32(zip: &mut Vec<u8>, value: u32) {
+    zip.extend(value.to_le_bytes());
+}
+
+fn get_u16(zip: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        zip.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn get_u32(zip: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        zip.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// The CRC-32 checksum ZIP uses, computed bit by bit since packages are small.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
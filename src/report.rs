@@ -0,0 +1,391 @@
+//! Self-contained HTML reports of a program run, for sharing with people who don't have boxfunge installed.
+
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::Annotations;
+use crate::Error;
+use crate::Executer;
+use crate::Grid;
+use crate::GridStats;
+use crate::Int;
+use crate::Interpreter;
+use crate::Metadata;
+use crate::Position;
+use crate::GRID_HEIGHT;
+use crate::GRID_WIDTH;
+
+/// How often each cell of the grid was executed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Heatmap {
+    counts: [[usize; GRID_WIDTH]; GRID_HEIGHT],
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        Self {
+            counts: [[0; GRID_WIDTH]; GRID_HEIGHT],
+        }
+    }
+}
+
+impl Heatmap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one execution of the cell at the position. Positions outside the grid are ignored.
+    pub fn record(&mut self, position: Position) {
+        if let Some(count) = self
+            .counts
+            .get_mut(position.y as usize)
+            .and_then(|line| line.get_mut(position.x as usize))
+        {
+            *count += 1;
+        }
+    }
+
+    pub fn count(&self, x: usize, y: usize) -> usize {
+        self.counts[y][x]
+    }
+
+    /// Highest count of any cell.
+    pub fn max(&self) -> usize {
+        self.counts.iter().flatten().copied().max().unwrap_or(0)
+    }
+}
+
+/// Input or output wrapper that keeps a copy of everything read or written through it.
+pub struct Transcript<'a, T> {
+    inner: T,
+    transcript: &'a RefCell<Vec<u8>>,
+}
+
+impl<'a, T> Transcript<'a, T> {
+    pub fn new(inner: T, transcript: &'a RefCell<Vec<u8>>) -> Self {
+        Self { inner, transcript }
+    }
+}
+
+impl<R: Read> Read for Transcript<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.transcript
+            .borrow_mut()
+            .extend_from_slice(&buf[..count]);
+        Ok(count)
+    }
+}
+
+impl<W: Write> Write for Transcript<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.inner.write(buf)?;
+        self.transcript
+            .borrow_mut()
+            .extend_from_slice(&buf[..count]);
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// ANSI background colors that tell concurrent instruction pointers apart, picked by their IDs.
+const IP_COLORS: [u8; 6] = [43, 46, 45, 42, 44, 41];
+
+/// Number of values from the top of each stack shown next to the playfield.
+const SIDEBAR_STACK_VALUES: usize = 8;
+
+/// The ANSI escape sequence that starts the cell of an instruction pointer. A single instruction pointer is shown in
+/// reverse video; concurrent ones get a color each, and the highlighted one is underlined as well.
+pub fn ip_style(id: Int, highlighted: bool, concurrent: bool) -> String {
+    if !concurrent {
+        return "\x1b[7m".to_string();
+    }
+    let color = IP_COLORS[id.rem_euclid(IP_COLORS.len() as Int) as usize];
+    format!("\x1b[30;{}{}m", color, if highlighted { ";4" } else { "" })
+}
+
+/// Renders the execution of a program as a sequence of ANSI terminal frames, which can be played back
+/// by printing the file to a terminal.
+pub struct Animation<W> {
+    output: W,
+    /// Number of steps between frames.
+    interval: usize,
+    /// Comments shown below each frame for the cell of the program counter.
+    annotations: Annotations,
+}
+
+impl<W: Write> Animation<W> {
+    pub fn new(output: W, interval: usize) -> Self {
+        Self {
+            output,
+            interval: interval.max(1),
+            annotations: Annotations::default(),
+        }
+    }
+
+    pub fn with_annotations(mut self, annotations: Annotations) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
+    /// Render a frame if the step is on the frame interval.
+    pub fn step(&mut self, interpreter: &Interpreter) -> io::Result<()> {
+        if interpreter.steps().is_multiple_of(self.interval) {
+            self.frame(interpreter)?;
+        }
+        Ok(())
+    }
+
+    /// Render a frame showing the playfield with the program counters highlighted, and a sidebar with the top of
+    /// every instruction pointer's stack. Concurrent instruction pointers have a color each in both, and the current
+    /// one is underlined.
+    pub fn frame(&mut self, interpreter: &Interpreter) -> io::Result<()> {
+        // move to the top left and clear the screen
+        write!(self.output, "\x1b[H\x1b[2J")?;
+        let grid = interpreter.grid();
+        let ips = interpreter.ips().collect::<Vec<_>>();
+        let concurrent = ips.len() > 1;
+        let position = interpreter.position();
+        let sidebar = ips
+            .iter()
+            .enumerate()
+            .map(|(index, ip)| {
+                let skipped = ip.stack.len().saturating_sub(SIDEBAR_STACK_VALUES);
+                let values = ip.stack[skipped..]
+                    .iter()
+                    .map(Int::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "{}ip {}\x1b[0m [{}{}]",
+                    ip_style(ip.id, index == 0, concurrent),
+                    ip.id,
+                    if skipped > 0 { "..., " } else { "" },
+                    values
+                )
+            })
+            .collect::<Vec<_>>();
+        let max = grid.bounds().max;
+        for y in 0..=max.y {
+            for x in 0..=max.x {
+                let cell = grid[y as usize][x as usize];
+                let cell = if cell.is_ascii_graphic() || cell == b' ' {
+                    cell as char
+                } else {
+                    '?'
+                };
+                // the current instruction pointer comes first, so it wins over others on the same cell
+                match ips
+                    .iter()
+                    .enumerate()
+                    .find(|(_, ip)| ip.program_counter.position == Position::new(x, y, 0))
+                {
+                    Some((index, ip)) => write!(
+                        self.output,
+                        "{}{}\x1b[0m",
+                        ip_style(ip.id, index == 0, concurrent),
+                        cell
+                    )?,
+                    None => write!(self.output, "{}", cell)?,
+                }
+            }
+            if let Some(line) = sidebar.get(y as usize) {
+                write!(self.output, " {}", line)?;
+            }
+            writeln!(self.output)?;
+        }
+        // instruction pointers that don't fit next to the playfield are listed below it
+        for line in sidebar.iter().skip(max.y as usize + 1) {
+            writeln!(
+                self.output,
+                "{:width$} {}",
+                "",
+                line,
+                width = max.x as usize + 1
+            )?;
+        }
+        let step = interpreter.steps();
+        match self.annotations.describe(position) {
+            Some(annotation) => writeln!(self.output, "step {}: {}", step, annotation),
+            None => writeln!(self.output, "step {}", step),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// Everything shown in an HTML report.
+pub struct Report<'a> {
+    pub metadata: &'a Metadata,
+    pub grid: &'a Grid,
+    pub heatmap: &'a Heatmap,
+    pub output: &'a [u8],
+    pub steps: usize,
+    pub duration: Duration,
+    pub result: &'a Result<(), Error>,
+    pub annotations: &'a Annotations,
+}
+
+impl Report<'_> {
+    /// Render the report as a single HTML document without any external resources.
+    pub fn to_html(&self) -> String {
+        let title = self.metadata.title.as_deref().unwrap_or("Befunge program");
+        let mut html = String::new();
+        // writing to a String cannot fail
+        let _ = writeln!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>",
+            escape(title)
+        );
+        html.push_str(
+            "<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             pre { font-family: monospace; line-height: 1.2; background: #f8f8f8; padding: 1em; }\n\
+             td, th { text-align: left; padding: 0 1em 0 0; }\n\
+             </style>\n</head>\n<body>\n",
+        );
+        let _ = writeln!(html, "<h1>{}</h1>", escape(title));
+        if let Some(author) = &self.metadata.author {
+            let _ = writeln!(html, "<p>by {}</p>", escape(author));
+        }
+
+        let stats = GridStats::new(self.grid);
+        let outcome = match self.result {
+            Ok(()) => "terminated normally".to_string(),
+            Err(why) => why.to_string(),
+        };
+        html.push_str("<h2>Statistics</h2>\n<table>\n");
+        for (name, value) in [
+            ("Result", outcome),
+            ("Steps", self.steps.to_string()),
+            ("Duration", format!("{:?}", self.duration)),
+            ("Size", format!("{} x {}", stats.size().x, stats.size().y)),
+            ("Non-space cells", stats.non_space_cells.to_string()),
+            ("Reads input", yes_no(stats.reads_input)),
+            ("Modifies itself", yes_no(stats.modifies_itself)),
+            ("Uses randomness", yes_no(stats.uses_randomness)),
+        ] {
+            let _ = writeln!(
+                html,
+                "<tr><th>{}</th><td>{}</td></tr>",
+                name,
+                escape(&value)
+            );
+        }
+        html.push_str("</table>\n");
+
+        html.push_str(
+            "<h2>Program</h2>\n<p>Cells are colored by how often they were executed. \
+             Annotated cells are underlined.</p>\n<pre>",
+        );
+        let max = self.heatmap.max();
+        // the heatmap only covers the 80x25 area, so only the part of the program within it is shown
+        let (width, height) = stats.bounds.map_or((0, 0), |bounds| {
+            (
+                (bounds.max.x + 1).clamp(0, GRID_WIDTH as i64) as usize,
+                (bounds.max.y + 1).clamp(0, GRID_HEIGHT as i64) as usize,
+            )
+        });
+        for y in 0..height {
+            for x in 0..width {
+                let cell = self.grid[y][x] as char;
+                let count = self.heatmap.count(x, y);
+                let annotation = self
+                    .annotations
+                    .describe(Position::new(x as i64, y as i64, 0));
+                if count == 0 && annotation.is_none() {
+                    html.push_str(&escape(&cell.to_string()));
+                    continue;
+                }
+                let mut title = format!("({}, {}): {} times", x, y, count);
+                let mut style = String::new();
+                if count > 0 {
+                    // logarithmic scale, since loops easily run millions of times more often than the rest
+                    let heat = ((count as f64).ln_1p() / (max as f64).ln_1p()).max(0.1);
+                    let _ = write!(style, "background: rgba(255, 80, 0, {:.2});", heat);
+                }
+                if let Some(annotation) = annotation {
+                    let _ = write!(title, "\n{}", annotation);
+                    style.push_str(" text-decoration: underline dotted;");
+                }
+                let _ = write!(
+                    html,
+                    "<span title=\"{}\" style=\"{}\">{}</span>",
+                    escape(&title),
+                    style.trim_start(),
+                    escape(&cell.to_string())
+                );
+            }
+            html.push('\n');
+        }
+        html.push_str("</pre>\n");
+        if !self.annotations.annotations.is_empty() {
+            html.push_str(
+                "<h2>Annotations</h2>\n<table>\n\
+                 <tr><th>Region</th><th>Executions</th><th>Comment</th></tr>\n",
+            );
+            for annotation in &self.annotations.annotations {
+                let mut region = if annotation.min == annotation.max {
+                    format!("({}, {})", annotation.min.x, annotation.min.y)
+                } else {
+                    format!(
+                        "({}, {}) to ({}, {})",
+                        annotation.min.x, annotation.min.y, annotation.max.x, annotation.max.y
+                    )
+                };
+                if let Some(name) = &annotation.name {
+                    region = format!("@{} {}", name, region);
+                }
+                // the part of the region outside the grid never runs
+                let min = annotation.min.max(Position::ZERO);
+                let max = annotation
+                    .max
+                    .min(Position::new(width as i64, height as i64, 0) - 1);
+                let executions: usize = (min.y..=max.y)
+                    .flat_map(|y| (min.x..=max.x).map(move |x| (x as usize, y as usize)))
+                    .map(|(x, y)| self.heatmap.count(x, y))
+                    .sum();
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    escape(&region),
+                    executions,
+                    escape(&annotation.text)
+                );
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("<h2>Output</h2>\n<pre>");
+        html.push_str(&escape(&String::from_utf8_lossy(self.output)));
+        html.push_str("</pre>\n</body>\n</html>\n");
+        html
+    }
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_string()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,89 @@
+//! Limits and permissions for running untrusted programs.
+
+use std::time::Duration;
+
+use argh::FromArgValue;
+
+/// Resource limits and permissions applied to a program run.
+/// The permissions govern instructions that reach outside the interpreter; Befunge-93 has none of those.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sandbox {
+    /// Stop the program after this many steps.
+    pub max_steps: Option<usize>,
+    /// Stop the program after it has run for this long.
+    pub timeout: Option<Duration>,
+    /// Stop the program once it has written more than this many bytes of output.
+    pub max_output: Option<usize>,
+    pub allow_filesystem: bool,
+    pub allow_network: bool,
+    pub allow_exec: bool,
+    pub allow_environment: bool,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        SandboxProfile::default().sandbox()
+    }
+}
+
+/// Predefined sandbox configurations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SandboxProfile {
+    /// Strict limits and no access to anything outside the interpreter, for untrusted code.
+    Strict,
+    /// No limits; filesystem and environment access, but no network access or command execution.
+    #[default]
+    Default,
+    /// No limits and all permissions.
+    Trusted,
+}
+
+impl SandboxProfile {
+    pub fn sandbox(self) -> Sandbox {
+        match self {
+            Self::Strict => Sandbox {
+                max_steps: Some(1_000_000_000),
+                timeout: Some(Duration::from_secs(10)),
+                max_output: Some(1 << 20),
+                allow_filesystem: false,
+                allow_network: false,
+                allow_exec: false,
+                allow_environment: false,
+            },
+            Self::Default => Sandbox {
+                max_steps: None,
+                timeout: None,
+                max_output: None,
+                allow_filesystem: true,
+                allow_network: false,
+                allow_exec: false,
+                allow_environment: true,
+            },
+            Self::Trusted => Sandbox {
+                max_steps: None,
+                timeout: None,
+                max_output: None,
+                allow_filesystem: true,
+                allow_network: true,
+                allow_exec: true,
+                allow_environment: true,
+            },
+        }
+    }
+}
+
+impl FromArgValue for SandboxProfile {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "strict" => Self::Strict,
+            "default" => Self::Default,
+            "trusted" => Self::Trusted,
+            _ => {
+                return Err(
+                    "unknown sandbox profile, possible values are [strict, default, trusted]"
+                        .to_string(),
+                )
+            }
+        })
+    }
+}
@@ -0,0 +1,203 @@
+//! The Befunge-98 stack stack.
+
+use std::io::Write;
+
+use argh::FromArgValue;
+
+use crate::Error;
+use crate::Int;
+use crate::Position;
+
+/// Largest number of values the Befunge-98 stack stack instructions move or fill in at once.
+pub const MAX_STACK_TRANSFER: usize = 1 << 12;
+
+/// Top of stack last.
+pub type Stack = Vec<Int>;
+
+/// Pop a vector of the given number of dimensions, whose last coordinate is on top. Missing coordinates are 0.
+pub fn pop_vector(stack: &mut Stack, dimensions: usize) -> Position {
+    let mut coordinates = [0; 3];
+    for coordinate in coordinates[..dimensions].iter_mut().rev() {
+        // Int is not an i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        {
+            *coordinate = stack.pop().unwrap_or_default() as i64;
+        }
+    }
+    Position::from_array(coordinates)
+}
+
+/// Push the first coordinates of a vector for the given number of dimensions, so that [`pop_vector`] pops it back.
+pub fn push_vector(stack: &mut Stack, vector: Position, dimensions: usize) {
+    // Int is not an i64 on every platform
+    #[allow(clippy::unnecessary_cast)]
+    stack.extend(
+        vector.to_array()[..dimensions]
+            .iter()
+            .map(|&coordinate| coordinate as Int),
+    );
+}
+
+/// File format of a stack saved with `--dump-stack-on-exit` and loaded with `--load-stack`.
+/// Either way, the bottom of the stack comes first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StackFormat {
+    /// One decimal value per line.
+    #[default]
+    Text,
+    /// Every value as a little-endian 64-bit integer.
+    Binary,
+}
+
+impl FromArgValue for StackFormat {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "text" => Self::Text,
+            "binary" => Self::Binary,
+            _ => return Err("unknown stack format, possible values are [text, binary]".to_string()),
+        })
+    }
+}
+
+impl StackFormat {
+    pub fn write(self, stack: &[Int], mut output: impl Write) -> Result<(), Error> {
+        for &value in stack {
+            match self {
+                Self::Text => writeln!(output, "{}", value)?,
+                // Int is not an i64 on every platform
+                #[allow(clippy::unnecessary_cast)]
+                Self::Binary => output.write_all(&(value as i64).to_le_bytes())?,
+            }
+        }
+        Ok(output.flush()?)
+    }
+
+    pub fn read(self, data: &[u8]) -> Result<Stack, Error> {
+        match self {
+            Self::Text => String::from_utf8_lossy(data)
+                .split_whitespace()
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| Error::InvalidStack(format!("\"{}\" is not a number", value)))
+                })
+                .collect(),
+            Self::Binary => {
+                let values = data.chunks_exact(8);
+                if !values.remainder().is_empty() {
+                    return Err(Error::InvalidStack(format!(
+                        "length of {} bytes is not a multiple of 8",
+                        data.len()
+                    )));
+                }
+                values
+                    .map(|bytes| {
+                        let value = i64::from_le_bytes(bytes.try_into().unwrap());
+                        Int::try_from(value).map_err(|_| {
+                            Error::InvalidStack(format!(
+                                "{} does not fit into a stack value",
+                                value
+                            ))
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A stack of stacks. Befunge-93 programs only ever use the top stack.
+/// The top stack is stored apart from the ones below it so that instructions can work on it directly.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StackStack {
+    pub top: Stack,
+    /// The stacks below the top one, bottom first.
+    pub below: Vec<Stack>,
+}
+
+impl StackStack {
+    /// Begin a block for `{`: move the top `count` values to a new stack, or push `-count` zeros if it is negative,
+    /// and save the storage offset below them as a vector of the given dimensions before replacing it with the new
+    /// offset. Returns false if the instruction fails.
+    pub fn begin_block(
+        &mut self,
+        count: Int,
+        storage_offset: &mut Position,
+        new_offset: Position,
+        dimensions: usize,
+    ) -> bool {
+        let Some(count) = transfer_count(count) else {
+            return false;
+        };
+        let mut new = Stack::new();
+        if count >= 0 {
+            let count = count as usize;
+            // missing values are zeros
+            new.resize(count.saturating_sub(self.top.len()), 0);
+            new.extend(self.top.drain(self.top.len().saturating_sub(count)..));
+        } else {
+            self.top.resize(self.top.len() + count.unsigned_abs(), 0);
+        }
+        push_vector(&mut self.top, *storage_offset, dimensions);
+        *storage_offset = new_offset;
+        self.below.push(std::mem::replace(&mut self.top, new));
+        true
+    }
+
+    /// End a block for `}`: restore the storage offset saved by [`StackStack::begin_block`] and drop the top stack,
+    /// moving its top `count` values to the stack below, or dropping `-count` values from that if it is negative.
+    /// Returns false if the instruction fails because there is only one stack.
+    pub fn end_block(
+        &mut self,
+        count: Int,
+        storage_offset: &mut Position,
+        dimensions: usize,
+    ) -> bool {
+        let Some(count) = transfer_count(count) else {
+            return false;
+        };
+        let Some(mut second) = self.below.pop() else {
+            return false;
+        };
+        *storage_offset = pop_vector(&mut second, dimensions);
+        if count >= 0 {
+            let count = count as usize;
+            second.resize(second.len() + count.saturating_sub(self.top.len()), 0);
+            second.extend(self.top.drain(self.top.len().saturating_sub(count)..));
+        } else {
+            second.truncate(second.len().saturating_sub(count.unsigned_abs()));
+        }
+        self.top = second;
+        true
+    }
+
+    /// Move `count` values one by one from the second stack to the top one for `u`, or `-count` values back if it
+    /// is negative. Returns false if the instruction fails because there is only one stack.
+    pub fn under(&mut self, count: Int) -> bool {
+        let Some(count) = transfer_count(count) else {
+            return false;
+        };
+        let Some(second) = self.below.last_mut() else {
+            return false;
+        };
+        let (from, to) = if count >= 0 {
+            (second, &mut self.top)
+        } else {
+            (&mut self.top, second)
+        };
+        for _ in 0..count.unsigned_abs() {
+            to.push(from.pop().unwrap_or_default());
+        }
+        true
+    }
+}
+
+/// The count as an `isize`, or `None` if it exceeds [`MAX_STACK_TRANSFER`].
+fn transfer_count(count: Int) -> Option<isize> {
+    // Int is not an i64 on every platform
+    #[allow(clippy::unnecessary_cast)]
+    if count.unsigned_abs() as u64 > MAX_STACK_TRANSFER as u64 {
+        return None;
+    }
+    Some(count as isize)
+}
@@ -0,0 +1,266 @@
+//! State files, which save the state of a stopped program with `--save-state`, so that `--resume` can continue it
+//! later with new input. Programs that initialize themselves expensively can stop at a breakpoint once they are
+//! done, and every run after that starts from there.
+//!
+//! A state file is text with one `key values` line per item. Cells list the non-space cells of the grid with their
+//! values. Every instruction pointer starts with an `ip` line giving its position, delta, string mode, storage
+//! offset and ID, followed by its stack, the stacks below it bottom first, and the fingerprint IDs in effect for
+//! its instructions. The instruction pointer that runs next comes first. Positions have as many coordinates as the
+//! standard has dimensions, so those of Trefunge-98 programs have three:
+//!
+//! ```text
+//! boxfunge state 1
+//! standard 98
+//! bounds 0 0 5 0
+//! cell 0 0 38
+//! cell 5 0 64
+//! ip 1 0 > 0 0 0 0
+//! stack 21 4
+//! below 3
+//! semantics R 1380928833
+//! ```
+
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use argh::FromArgValue;
+
+use crate::Bounds;
+use crate::Coordinates;
+use crate::Delta;
+use crate::Error;
+use crate::Grid;
+use crate::InstructionPointer;
+use crate::Int;
+use crate::LanguageStandard;
+use crate::Position;
+use crate::Semantics;
+use crate::Snapshot;
+use crate::GRID_HEIGHT;
+use crate::GRID_WIDTH;
+use crate::PC;
+
+/// First line of every state file, with the version of the format.
+const HEADER: &str = "boxfunge state 1";
+
+/// A program's state together with the standard it runs under, which decides how the grid and instructions behave.
+#[derive(Clone, Debug)]
+pub struct State {
+    pub standard: LanguageStandard,
+    pub snapshot: Snapshot,
+}
+
+fn numbers<T: FromStr>(values: &[&str]) -> Option<Vec<T>> {
+    values.iter().map(|value| value.parse().ok()).collect()
+}
+
+/// A position from the given number of coordinates, or `None` if there are more than three.
+fn position(coordinates: &[i64]) -> Option<Position> {
+    let mut position = Position::ZERO;
+    for (axis, &coordinate) in coordinates.iter().enumerate() {
+        *position.as_mut().get_mut(axis)? = coordinate;
+    }
+    Some(position)
+}
+
+/// The coordinates of a position for the given number of dimensions, separated by spaces.
+fn coordinates(position: Position, dimensions: usize) -> String {
+    position.to_array()[..dimensions]
+        .iter()
+        .map(i64::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl State {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut lines = text.lines().enumerate();
+        if lines.next().map(|(_, line)| line.trim()) != Some(HEADER) {
+            return Err(Error::InvalidState(format!(
+                "expected \"{}\" on the first line",
+                HEADER
+            )));
+        }
+        let mut standard: Option<LanguageStandard> = None;
+        let mut bounds = None;
+        let mut cells = Vec::new();
+        let mut ips: Vec<InstructionPointer> = Vec::new();
+        for (number, line) in lines {
+            let invalid =
+                || Error::InvalidState(format!("line {}: invalid \"{}\"", number + 1, line));
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let Some((&key, values)) = words.split_first() else {
+                continue;
+            };
+            // the standard comes first, so it is known by the time positions are read
+            let dimensions = standard.map_or(2, LanguageStandard::dimensions);
+            match (key, values) {
+                ("standard", [value]) => {
+                    standard = Some(LanguageStandard::from_arg_value(value).map_err(|_| invalid())?)
+                }
+                ("bounds", _) => match numbers::<i64>(values) {
+                    Some(corners) if corners.len() == 2 * dimensions => {
+                        let (min, max) = corners.split_at(dimensions);
+                        let (Some(min), Some(max)) = (position(min), position(max)) else {
+                            return Err(invalid());
+                        };
+                        if min.cmpgt(max).any() {
+                            return Err(invalid());
+                        }
+                        bounds = Some(Bounds { min, max })
+                    }
+                    _ => return Err(invalid()),
+                },
+                ("cell", _) => match numbers::<i64>(values) {
+                    Some(numbers) if numbers.len() == dimensions + 1 => {
+                        let cell = position(&numbers[..dimensions]).ok_or_else(invalid)?;
+                        let value = Int::try_from(numbers[dimensions]).map_err(|_| invalid())?;
+                        cells.push((cell, value));
+                    }
+                    _ => return Err(invalid()),
+                },
+                ("ip", _) if values.len() == 2 * dimensions + 3 => {
+                    let (at, rest) = values.split_at(dimensions);
+                    let (offset, id) = rest[2..].split_at(dimensions);
+                    let (Some(at), Some(offset), Ok(delta), Ok(id)) = (
+                        numbers(at).as_deref().and_then(position),
+                        numbers(offset).as_deref().and_then(position),
+                        Delta::from_arg_value(rest[0]),
+                        id[0].parse(),
+                    ) else {
+                        return Err(invalid());
+                    };
+                    ips.push(InstructionPointer {
+                        program_counter: PC {
+                            position: at,
+                            delta,
+                        },
+                        string_mode: rest[1] == "1",
+                        stack: Vec::new(),
+                        stack_stack: Vec::new(),
+                        storage_offset: offset,
+                        id,
+                        semantics: Semantics::default(),
+                    });
+                }
+                ("stack" | "below" | "semantics", _) => {
+                    // these belong to the instruction pointer before them
+                    let ip = ips.last_mut().ok_or_else(invalid)?;
+                    match key {
+                        "stack" => ip.stack = numbers(values).ok_or_else(invalid)?,
+                        "below" => ip.stack_stack.push(numbers(values).ok_or_else(invalid)?),
+                        _ => {
+                            let instruction = match values.first().map(|value| value.as_bytes()) {
+                                Some(&[instruction @ b'A'..=b'Z']) => instruction,
+                                _ => return Err(invalid()),
+                            };
+                            ip.semantics[(instruction - b'A') as usize] =
+                                numbers(&values[1..]).ok_or_else(invalid)?;
+                        }
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        let standard =
+            standard.ok_or_else(|| Error::InvalidState("the standard is missing".to_string()))?;
+        let mut grid = Grid::new([[b' '; GRID_WIDTH]; GRID_HEIGHT]);
+        grid.set_unbounded(standard.is_unbounded());
+        for (position, value) in cells {
+            grid.set_value_at(position, value).ok_or_else(|| {
+                Error::InvalidState(format!(
+                    "cell {} lies outside of the grid",
+                    Coordinates(position)
+                ))
+            })?;
+        }
+        if let Some(bounds) = bounds {
+            grid.set_bounds(bounds);
+        }
+        if ips.is_empty() {
+            return Err(Error::InvalidState(
+                "there is no instruction pointer".to_string(),
+            ));
+        }
+        let current = ips.remove(0);
+        Ok(Self {
+            standard,
+            snapshot: Snapshot {
+                grid,
+                stack: current.stack,
+                program_counter: current.program_counter,
+                string_mode: current.string_mode,
+                stack_stack: current.stack_stack,
+                storage_offset: current.storage_offset,
+                ip_id: current.id,
+                semantics: current.semantics,
+                other_ips: ips,
+            },
+        })
+    }
+}
+
+impl Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let snapshot = &self.snapshot;
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "standard {}", self.standard)?;
+        let dimensions = self.standard.dimensions();
+        let bounds = snapshot.grid.bounds();
+        writeln!(
+            f,
+            "bounds {} {}",
+            coordinates(bounds.min, dimensions),
+            coordinates(bounds.max, dimensions)
+        )?;
+        for (position, value) in snapshot.grid.non_space_cells() {
+            writeln!(f, "cell {} {}", coordinates(position, dimensions), value)?;
+        }
+        let current = InstructionPointer {
+            program_counter: snapshot.program_counter,
+            string_mode: snapshot.string_mode,
+            stack: snapshot.stack.clone(),
+            stack_stack: snapshot.stack_stack.clone(),
+            storage_offset: snapshot.storage_offset,
+            id: snapshot.ip_id,
+            semantics: snapshot.semantics.clone(),
+        };
+        let values = |values: &[Int]| {
+            values
+                .iter()
+                .map(|value| format!(" {}", value))
+                .collect::<String>()
+        };
+        for ip in [&current].into_iter().chain(&snapshot.other_ips) {
+            writeln!(
+                f,
+                "ip {} {} {} {} {}",
+                coordinates(ip.program_counter.position, dimensions),
+                ip.program_counter.delta,
+                ip.string_mode as u8,
+                coordinates(ip.storage_offset, dimensions),
+                ip.id
+            )?;
+            writeln!(f, "stack{}", values(&ip.stack))?;
+            for stack in &ip.stack_stack {
+                writeln!(f, "below{}", values(stack))?;
+            }
+            for (instruction, ids) in (b'A'..=b'Z').zip(&ip.semantics) {
+                if !ids.is_empty() {
+                    writeln!(f, "semantics {}{}", instruction as char, values(ids))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,533 @@
+//! Symbolic execution of the straight-line segments between branch points.
+//!
+//! Starting at the entry point, every path through the program is followed once, tracking for every stack
+//! value whether it is a known constant, derived from input, or unknown. Like the control flow analysis,
+//! this doesn't know about changes made by `p`, and every program state is only visited along the first
+//! path that reaches it. The same walk also collects the string literals the program can push.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::Bounds;
+use crate::Direction;
+use crate::Grid;
+use crate::InstructionSet;
+use crate::Int;
+use crate::LanguageStandard;
+use crate::Position;
+use crate::WrapMode;
+use crate::PC;
+
+/// A stack value as far as it is known without running the program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Symbol {
+    Constant(Int),
+    /// Read from input, or computed from values read from input.
+    Input,
+    Unknown,
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Constant(value) => write!(f, "{}", value),
+            Self::Input => write!(f, "input"),
+            Self::Unknown => write!(f, "?"),
+        }
+    }
+}
+
+impl Symbol {
+    fn combine(a: Self, b: Self, operation: impl FnOnce(Int, Int) -> Int) -> Self {
+        match (a, b) {
+            (Self::Constant(a), Self::Constant(b)) => Self::Constant(operation(a, b)),
+            (Self::Input, _) | (_, Self::Input) => Self::Input,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// The symbolic stack at a branching instruction (`_`, `|` or `?`), bottom first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BranchPoint {
+    /// Position of the branching instruction and the direction the program counter arrived in.
+    pub pc: PC,
+    pub instruction: u8,
+    pub stack: Vec<Symbol>,
+}
+
+/// A string literal as the program pushes it, in the order of its characters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StringLiteral {
+    /// Position of the opening quote and the direction the string is read in.
+    pub pc: PC,
+    pub text: String,
+}
+
+/// A `p` instruction and the cell it writes to, if that is known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridWrite {
+    /// Position of the `p` instruction and the direction the program counter arrived in.
+    pub pc: PC,
+    pub target: Option<Position>,
+}
+
+/// Results of following every path through a program.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathAnalysis {
+    pub entry: PC,
+    /// Symbolic stacks at branch points, in the order they were found.
+    pub branches: Vec<BranchPoint>,
+    /// One program state in every loop: the branch points that can reach themselves, and for loops without any
+    /// branch the state at which the loop closes.
+    pub loops: Vec<PC>,
+    /// All string literals that can be executed, in the order they were found.
+    pub strings: Vec<StringLiteral>,
+    pub writes: Vec<GridWrite>,
+}
+
+/// How a straight-line segment of a path ends.
+#[derive(Clone, Copy)]
+enum SegmentEnd {
+    /// At `@`, an illegal instruction, or outside the grid.
+    Halt,
+    Branch(usize),
+    /// In a state already visited by an earlier segment, which continues from there.
+    Merge(usize),
+    /// In a state visited earlier by the same segment.
+    Cycle(PC),
+}
+
+fn pop(stack: &mut Vec<Symbol>) -> Symbol {
+    stack.pop().unwrap_or(Symbol::Constant(0))
+}
+
+/// A loop that the program provably never leaves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EndlessLoop {
+    /// Program state at which the loop was seen to repeat.
+    pub pc: PC,
+    /// Number of steps the program takes for one round through the loop.
+    pub length: usize,
+}
+
+/// Number of steps after which [`find_endless_loop`] gives up.
+const LOOP_SEARCH_STEPS: usize = 10_000;
+/// Number of values at the top of the stack that are remembered for every program state. Loops that reach deeper
+/// into the stack aren't found.
+const LOOP_STACK_WINDOW: usize = 64;
+
+/// Apply an instruction that only works on the stack, or a no-op, to the symbolic stack.
+fn execute(cell: u8, stack: &mut Vec<Symbol>) {
+    match cell {
+        b'0'..=b'9' => stack.push(Symbol::Constant((cell - b'0') as Int)),
+        b':' => {
+            let top = pop(stack);
+            stack.extend([top, top]);
+        }
+        b'\\' => {
+            let top = pop(stack);
+            let second = pop(stack);
+            stack.extend([top, second]);
+        }
+        b'$' | b'.' | b',' => {
+            stack.pop();
+        }
+        b'!' => {
+            let value = pop(stack);
+            stack.push(Symbol::combine(value, value, |a, _| (a == 0) as Int));
+        }
+        b'+' | b'-' | b'*' | b'/' | b'%' | b'`' => {
+            let b = pop(stack);
+            let a = pop(stack);
+            stack.push(Symbol::combine(a, b, |a, b| match cell {
+                b'+' => a.wrapping_add(b),
+                b'-' => a.wrapping_sub(b),
+                b'*' => a.wrapping_mul(b),
+                b'/' if b == 0 => 0,
+                b'/' => a.wrapping_div(b),
+                b'%' if b == 0 => 0,
+                b'%' => a.wrapping_rem(b),
+                b'`' => (a > b) as Int,
+                _ => unreachable!(),
+            }));
+        }
+        b'&' | b'~' => stack.push(Symbol::Input),
+        b'g' => {
+            pop(stack);
+            pop(stack);
+            stack.push(Symbol::Unknown);
+        }
+        _ => {}
+    }
+}
+
+/// Number of values an instruction pops off the stack.
+fn arity(cell: u8) -> usize {
+    match cell {
+        b':' | b'$' | b'.' | b',' | b'!' | b'_' | b'|' => 1,
+        b'\\' | b'+' | b'-' | b'*' | b'/' | b'%' | b'`' | b'g' => 2,
+        b'p' => 3,
+        _ => 0,
+    }
+}
+
+/// Follow every path from the start, collecting branch points, loops, string literals and grid writes.
+pub fn analyze_paths(grid: &Grid, start: PC) -> PathAnalysis {
+    let instructions = InstructionSet::for_standard(LanguageStandard::Befunge93);
+    let mut analysis = PathAnalysis {
+        entry: start,
+        ..Default::default()
+    };
+    let branches = &mut analysis.branches;
+    // segment that first visited each state
+    let mut visited = HashMap::new();
+    // the branch point each segment starts at and how it ends
+    let mut segments: Vec<(Option<usize>, SegmentEnd)> = Vec::new();
+    let mut paths = vec![(start, false, Vec::new(), None)];
+
+    while let Some((mut pc, mut string_mode, mut stack, origin)) = paths.pop() {
+        let segment = segments.len();
+        segments.push((origin, SegmentEnd::Halt));
+        // only strings that are closed on this path are kept
+        let mut literal = None;
+        loop {
+            match visited.entry((pc, string_mode)) {
+                Entry::Occupied(entry) => {
+                    segments[segment].1 = if *entry.get() == segment {
+                        SegmentEnd::Cycle(pc)
+                    } else {
+                        SegmentEnd::Merge(*entry.get())
+                    };
+                    break;
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(segment);
+                }
+            }
+            // Int is not an i64 on every platform
+            #[allow(clippy::unnecessary_cast)]
+            let Some(cell) = grid.get(pc.position.x as Int, pc.position.y as Int) else {
+                break;
+            };
+            if string_mode {
+                if cell == b'"' {
+                    string_mode = false;
+                    analysis.strings.extend(literal.take());
+                } else {
+                    stack.push(Symbol::Constant(cell as Int));
+                    if let Some(StringLiteral { text, .. }) = &mut literal {
+                        text.push(cell as char);
+                    }
+                }
+            } else if !instructions.is_legal(cell) {
+                break;
+            } else {
+                match cell {
+                    b'@' => break,
+                    b'>' => pc.delta = Direction::Right.into(),
+                    b'<' => pc.delta = Direction::Left.into(),
+                    b'^' => pc.delta = Direction::Up.into(),
+                    b'v' => pc.delta = Direction::Down.into(),
+                    b'_' | b'|' | b'?' => {
+                        segments[segment].1 = SegmentEnd::Branch(branches.len());
+                        branches.push(BranchPoint {
+                            pc,
+                            instruction: cell,
+                            stack: stack.clone(),
+                        });
+                        let directions = match cell {
+                            b'_' => [Direction::Right, Direction::Left].as_slice(),
+                            b'|' => &[Direction::Down, Direction::Up],
+                            _ => &[
+                                Direction::Up,
+                                Direction::Down,
+                                Direction::Left,
+                                Direction::Right,
+                            ],
+                        };
+                        if cell != b'?' {
+                            stack.pop();
+                        }
+                        for &direction in directions {
+                            let mut next = PC {
+                                position: pc.position,
+                                delta: direction.into(),
+                            };
+                            next.step();
+                            next.constrain(WrapMode::Torus, &Bounds::GRID);
+                            paths.push((next, false, stack.clone(), Some(branches.len() - 1)));
+                        }
+                        break;
+                    }
+                    b'#' => {
+                        pc.step();
+                        pc.constrain(WrapMode::Torus, &Bounds::GRID);
+                    }
+                    b'"' => {
+                        string_mode = true;
+                        literal = Some(StringLiteral {
+                            pc,
+                            text: String::new(),
+                        });
+                    }
+                    b'p' => {
+                        let y = pop(&mut stack);
+                        let x = pop(&mut stack);
+                        pop(&mut stack);
+                        let target = match (x, y) {
+                            // Int is not an i64 on every platform
+                            #[allow(clippy::unnecessary_cast)]
+                            (Symbol::Constant(x), Symbol::Constant(y)) => {
+                                Some(Position::new(x as i64, y as i64, 0))
+                            }
+                            _ => None,
+                        };
+                        analysis.writes.push(GridWrite { pc, target });
+                    }
+                    _ => execute(cell, &mut stack),
+                }
+            }
+            pc.step();
+            pc.constrain(WrapMode::Torus, &Bounds::GRID);
+        }
+    }
+
+    let resolve = |mut segment: usize| loop {
+        match segments[segment].1 {
+            SegmentEnd::Merge(earlier) => segment = earlier,
+            end => return end,
+        }
+    };
+    let mut successors = vec![Vec::new(); analysis.branches.len()];
+    for (segment, &(origin, end)) in segments.iter().enumerate() {
+        if let SegmentEnd::Cycle(pc) = end {
+            analysis.loops.push(pc);
+        }
+        if let (Some(origin), SegmentEnd::Branch(branch)) = (origin, resolve(segment)) {
+            successors[origin].push(branch);
+        }
+    }
+    for branch in 0..analysis.branches.len() {
+        let mut seen = vec![false; analysis.branches.len()];
+        let mut pending = successors[branch].clone();
+        while let Some(next) = pending.pop() {
+            if next == branch {
+                analysis.loops.push(analysis.branches[branch].pc);
+                break;
+            }
+            if !std::mem::replace(&mut seen[next], true) {
+                pending.extend(&successors[next]);
+            }
+        }
+    }
+    analysis
+}
+
+impl PathAnalysis {
+    /// A structural outline of the program as a JSON object, for editors and other tools.
+    /// Positions are given as `{"x": 0, "y": 0, "direction": ">"}`.
+    pub fn outline_json(&self) -> String {
+        fn pc_json(pc: PC) -> String {
+            format!(
+                r#"{{"x": {}, "y": {}, "direction": "{}"}}"#,
+                pc.position.x, pc.position.y, pc.delta
+            )
+        }
+        fn list(items: impl Iterator<Item = String>) -> String {
+            format!("[{}]", items.collect::<Vec<_>>().join(", "))
+        }
+
+        let branches = list(self.branches.iter().map(|branch| {
+            format!(
+                r#"{{"at": {}, "instruction": {}, "stack": {}}}"#,
+                pc_json(branch.pc),
+                json_string(&(branch.instruction as char).to_string()),
+                list(branch.stack.iter().map(|symbol| match symbol {
+                    Symbol::Constant(value) => value.to_string(),
+                    Symbol::Input => r#""input""#.to_string(),
+                    Symbol::Unknown => "null".to_string(),
+                }))
+            )
+        }));
+        let loops = list(self.loops.iter().map(|&pc| pc_json(pc)));
+        let strings = list(self.strings.iter().map(|string| {
+            format!(
+                r#"{{"at": {}, "text": {}}}"#,
+                pc_json(string.pc),
+                json_string(&string.text)
+            )
+        }));
+        let writes = list(self.writes.iter().map(|write| {
+            format!(
+                r#"{{"at": {}, "target": {}}}"#,
+                pc_json(write.pc),
+                match write.target {
+                    Some(target) => format!(r#"{{"x": {}, "y": {}}}"#, target.x, target.y),
+                    None => "null".to_string(),
+                }
+            )
+        }));
+        format!(
+            r#"{{"entry": {}, "branches": {}, "loops": {}, "strings": {}, "writes": {}}}"#,
+            pc_json(self.entry),
+            branches,
+            loops,
+            strings,
+            writes
+        )
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from('"');
+    for character in text.chars() {
+        match character {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            character if character.is_control() => {
+                json.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => json.push(character),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Formats the string literal as e.g. `"olleh" at 1,0 (>)`.
+impl Display for StringLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Position { x, y, .. } = self.pc.position;
+        write!(f, "{:?} at {},{} ({})", self.text, x, y, self.pc.delta)
+    }
+}
+
+/// Formats the branch point as e.g. `_ at 2,1 (arriving >): [1, input, ?]`.
+impl Display for BranchPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Position { x, y, .. } = self.pc.position;
+        write!(
+            f,
+            "{} at {},{} (arriving {}): [",
+            self.instruction as char, x, y, self.pc.delta
+        )?;
+        for (index, symbol) in self.stack.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", symbol)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// Follow the one path the program takes from the start, for as long as it doesn't depend on input, randomness or
+/// changes made by `p`, and report a loop that the program can never leave.
+///
+/// When the program counter comes back to a state after a round without input or output, and the values the round
+/// used from the stack are the same constants again, the next round must go exactly the same way. This finds rings of
+/// arrows as well as loops whose branches test a constant.
+pub fn find_endless_loop(grid: &Grid, start: PC) -> Option<EndlessLoop> {
+    let instructions = InstructionSet::for_standard(LanguageStandard::Befunge93);
+    // step, number of input and output instructions before it, stack height and top of the stack at the last
+    // visit of each state
+    let mut visits = HashMap::new();
+    // lowest stack height during every step
+    let mut lows = Vec::new();
+    let mut inputs_and_outputs = 0;
+    let (mut pc, mut string_mode, mut stack) = (start, false, Vec::new());
+
+    for step in 0..LOOP_SEARCH_STEPS {
+        let top = stack[stack.len().saturating_sub(LOOP_STACK_WINDOW)..].to_vec();
+        if let Some((visit, visit_inputs_and_outputs, height, visit_top)) = visits.insert(
+            (pc, string_mode),
+            (step, inputs_and_outputs, stack.len(), top),
+        ) {
+            let low = lows[visit..].iter().copied().fold(height, usize::min);
+            let used = height - low;
+            // once the stack ran empty, the round also used the zeros below it
+            let unused_are_zero = low > 0
+                || stack[..stack.len() - used.min(stack.len())]
+                    .iter()
+                    .all(|&value| value == Symbol::Constant(0));
+            if inputs_and_outputs == visit_inputs_and_outputs
+                && used <= visit_top.len()
+                && used <= stack.len()
+                && unused_are_zero
+                && stack[stack.len() - used..]
+                    .iter()
+                    .zip(&visit_top[visit_top.len() - used..])
+                    .all(|(value, visit_value)| {
+                        matches!(value, Symbol::Constant(_)) && value == visit_value
+                    })
+            {
+                return Some(EndlessLoop {
+                    pc,
+                    length: step - visit,
+                });
+            }
+        }
+
+        // Int is not an i64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        let cell = grid.get(pc.position.x as Int, pc.position.y as Int)?;
+        if string_mode {
+            lows.push(stack.len());
+            if cell == b'"' {
+                string_mode = false;
+            } else {
+                stack.push(Symbol::Constant(cell as Int));
+            }
+        } else if !instructions.is_legal(cell) {
+            return None;
+        } else {
+            lows.push(stack.len().saturating_sub(arity(cell)));
+            match cell {
+                b'@' | b'?' | b'p' => return None,
+                b'>' => pc.delta = Direction::Right.into(),
+                b'<' => pc.delta = Direction::Left.into(),
+                b'^' => pc.delta = Direction::Up.into(),
+                b'v' => pc.delta = Direction::Down.into(),
+                b'_' | b'|' => {
+                    let Symbol::Constant(condition) = pop(&mut stack) else {
+                        return None;
+                    };
+                    pc.delta = match (cell, condition == 0) {
+                        (b'_', true) => Direction::Right,
+                        (b'_', false) => Direction::Left,
+                        (_, true) => Direction::Down,
+                        (_, false) => Direction::Up,
+                    }
+                    .into();
+                }
+                b'#' => {
+                    pc.step();
+                    pc.constrain(WrapMode::Torus, &Bounds::GRID);
+                }
+                b'"' => string_mode = true,
+                b'.' | b',' | b'&' | b'~' => {
+                    inputs_and_outputs += 1;
+                    execute(cell, &mut stack);
+                }
+                _ => execute(cell, &mut stack),
+            }
+        }
+        pc.step();
+        pc.constrain(WrapMode::Torus, &Bounds::GRID);
+    }
+    None
+}
+
+/// Formats the loop as e.g. `3,0 (arriving >), repeating every 4 steps`.
+impl Display for EndlessLoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Position { x, y, .. } = self.pc.position;
+        write!(
+            f,
+            "{},{} (arriving {}), repeating every {} steps",
+            x, y, self.pc.delta, self.length
+        )
+    }
+}
@@ -0,0 +1,194 @@
+//! System information reported by the Befunge-98 `y` instruction.
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+use argh::FromArgValue;
+
+use crate::stack::push_vector;
+use crate::Bounds;
+use crate::Int;
+use crate::Position;
+use crate::Sandbox;
+use crate::StackStack;
+
+/// boxfunge's handprint, "BOXF" read as a big-endian number.
+pub const HANDPRINT: Int = 0x424f_5846;
+
+/// Parts of the system information that can be hidden from programs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SystemInfoItem {
+    /// The current date and time, which are reported as 0 when hidden.
+    Time,
+    /// The program's file name and arguments.
+    Arguments,
+    /// The environment variables.
+    Environment,
+}
+
+impl FromArgValue for SystemInfoItem {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        Ok(match value {
+            "time" => Self::Time,
+            "arguments" => Self::Arguments,
+            "environment" => Self::Environment,
+            _ => return Err(
+                "unknown system information, possible values are [time, arguments, environment]"
+                    .to_string(),
+            ),
+        })
+    }
+}
+
+/// What `y` reports about the world outside the interpreter. By default, nothing is exposed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SystemInfo {
+    /// The program's file name followed by its arguments.
+    pub arguments: Vec<String>,
+    /// Environment variables as "NAME=value".
+    pub environment: Vec<String>,
+    /// Report the current date and time instead of 0.
+    pub time: bool,
+    /// Added to the reported date and time, so the determinism audit can tell whether a program depends on them.
+    pub time_shift: Duration,
+}
+
+/// The state of the current instruction pointer and the playfield at the time `y` runs.
+#[derive(Clone, Copy, Debug)]
+pub struct MachineState<'a> {
+    pub ip_id: Int,
+    /// Number of coordinates of the vectors, see [`LanguageStandard::dimensions`](crate::LanguageStandard::dimensions).
+    pub dimensions: usize,
+    pub position: Position,
+    pub delta: Position,
+    pub storage_offset: Position,
+    pub bounds: Bounds,
+    pub stacks: &'a StackStack,
+    /// Whether `i` and `o` may access files.
+    pub files: bool,
+    /// How `=` runs commands, see [`ExecParadigm::paradigm`](crate::ExecParadigm::paradigm).
+    pub exec_paradigm: Int,
+}
+
+impl SystemInfo {
+    /// The system information of this process, without the hidden items.
+    /// The environment is only exposed if the sandbox allows access to it.
+    pub fn collect(arguments: Vec<String>, hidden: &[SystemInfoItem], sandbox: &Sandbox) -> Self {
+        let environment =
+            if sandbox.allow_environment && !hidden.contains(&SystemInfoItem::Environment) {
+                std::env::vars()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+        Self {
+            arguments: if hidden.contains(&SystemInfoItem::Arguments) {
+                Vec::new()
+            } else {
+                arguments
+            },
+            environment,
+            time: !hidden.contains(&SystemInfoItem::Time),
+            time_shift: Duration::ZERO,
+        }
+    }
+
+    /// All values `y` pushes, top of stack last, so that the first value of the Befunge-98 specification is the
+    /// last one here.
+    pub fn cells(&self, machine: &MachineState) -> Vec<Int> {
+        let mut cells = Vec::new();
+        push_strings(&mut cells, &self.environment);
+        push_strings(&mut cells, &self.arguments);
+        // an empty list of arguments still ends in two zeros
+        if self.arguments.is_empty() {
+            cells.push(0);
+        }
+        cells.extend(
+            machine
+                .stacks
+                .below
+                .iter()
+                .chain([&machine.stacks.top])
+                .map(|stack| stack.len() as Int),
+        );
+        cells.push(machine.stacks.below.len() as Int + 1);
+        let (date, time) = if self.time {
+            date_and_time(SystemTime::now() + self.time_shift)
+        } else {
+            (0, 0)
+        };
+        cells.extend([time, date]);
+        for vector in [
+            machine.bounds.max.wrapping_sub(machine.bounds.min),
+            machine.bounds.min,
+            machine.storage_offset,
+            machine.delta,
+            machine.position,
+        ] {
+            push_vector(&mut cells, vector, machine.dimensions);
+        }
+        cells.extend([
+            // team number
+            0,
+            machine.ip_id,
+            machine.dimensions as Int,
+            std::path::MAIN_SEPARATOR as Int,
+            machine.exec_paradigm,
+            version(),
+            HANDPRINT,
+            std::mem::size_of::<Int>() as Int,
+            // flags: t, and i and o if files may be accessed; input and output are buffered
+            if machine.files { 0b111 } else { 0b1 },
+        ]);
+        cells
+    }
+}
+
+/// Push a list of strings as a series of zero-terminated strings, ending with another zero, so that the first
+/// character of the first string is on top.
+fn push_strings(cells: &mut Vec<Int>, strings: &[String]) {
+    cells.push(0);
+    for string in strings.iter().rev() {
+        cells.push(0);
+        cells.extend(string.bytes().rev().map(Int::from));
+    }
+}
+
+/// boxfunge's version as a single number, e.g. 10203 for version 1.2.3.
+fn version() -> Int {
+    env!("CARGO_PKG_VERSION")
+        .split('.')
+        .take(3)
+        .map(|part| part.parse::<Int>().unwrap_or_default())
+        .fold(0, |version, part| version * 100 + part)
+}
+
+/// The UTC date as `(year - 1900) * 256 * 256 + month * 256 + day` and the time as
+/// `hour * 256 * 256 + minute * 256 + second`, like `y` reports them.
+fn date_and_time(now: SystemTime) -> (Int, Int) {
+    let seconds = now
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let (days, second_of_day) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    // civil date from the number of days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let date = (year - 1900) * 256 * 256 + month * 256 + day;
+    let time =
+        second_of_day / 3600 * 256 * 256 + second_of_day % 3600 / 60 * 256 + second_of_day % 60;
+    (date as Int, time as Int)
+}
@@ -2,11 +2,91 @@
 
 use std::path::Path;
 
+use argh::FromArgValue;
+use argh::FromArgs;
+
+use crate::arithmetic::Wide;
+use crate::audit_determinism;
+use crate::conformance_matrix;
+use crate::fingerprint_id;
+use crate::float::format_float;
+use crate::float::pack_f32;
+use crate::float::pack_f64;
+use crate::float::unpack_f32;
+use crate::float::unpack_f64;
+use crate::post_mortem;
+use crate::run_daemon;
 use crate::run_interpreter;
+use crate::run_interpreter_with_output;
+use crate::run_nested;
+use crate::serve_requests;
+use crate::sysinfo::HANDPRINT;
+use crate::Annotations;
 use crate::Arguments;
+use crate::Bounds;
+use crate::Breakpoint;
+use crate::Breakpoints;
+use crate::BridgeEdge;
+use crate::CellChange;
+use crate::CellValues;
+use crate::DebuggerOptions;
+use crate::Delta;
+use crate::Direction;
+use crate::EncodeOutput;
 use crate::Error;
+use crate::ExecParadigm;
 use crate::Executer;
+use crate::ExitReason;
+use crate::Expression;
+use crate::FileIo;
+use crate::Finding;
+use crate::Fingerprint;
+use crate::GameScript;
+use crate::Grid;
+use crate::GridStats;
+use crate::Heatmap;
+use crate::InputEvent;
+use crate::InputSource;
+use crate::InstructionPointer;
+use crate::InstructionSet;
+use crate::Int;
 use crate::Interpreter;
+use crate::LanguageStandard;
+use crate::LazySource;
+use crate::Location;
+use crate::MachineState;
+use crate::Manifest;
+use crate::Metadata;
+use crate::Metrics;
+use crate::NestedRequest;
+use crate::Newline;
+use crate::Normalize;
+use crate::OutputEncoding;
+use crate::OutputEvent;
+use crate::Outs;
+use crate::Overlay;
+use crate::Package;
+use crate::Position;
+use crate::ProtectPolicy;
+use crate::Reachability;
+use crate::Remainder;
+use crate::RunRequest;
+use crate::Sandbox;
+use crate::SandboxProfile;
+use crate::Snapshot;
+use crate::StackFormat;
+use crate::StackStack;
+use crate::State;
+use crate::StepOutcome;
+use crate::StringSpaces;
+use crate::Symbol;
+use crate::SystemInfo;
+use crate::SystemInfoItem;
+use crate::Watches;
+use crate::WrapMode;
+use crate::WriteHotspots;
+use crate::WriteRate;
+use crate::PC;
 
 fn run_file(path: impl AsRef<Path>) -> Result<String, Error> {
     run_file_with_input(path, &[] as &[u8])
@@ -93,23 +173,3553 @@ fn self_interpreter() {
     check_interpreters_equal!("programs/primesieve.bf");
 }
 
+#[test]
+fn nested_interpretation() {
+    let nested = |program: &str, input: &[u8], max_steps, max_output| {
+        run_nested(NestedRequest {
+            outer: RunRequest {
+                program: std::fs::read_to_string("programs/self_interpreter.bf").unwrap(),
+                max_steps: Some(max_steps),
+                max_output,
+                ..Default::default()
+            },
+            program: program.to_string(),
+            input: input.to_vec(),
+            max_stall_steps: Some(100_000),
+        })
+    };
+    for file in ["programs/hello_world.bf", "programs/kquine1.bf"] {
+        let report = nested(
+            &std::fs::read_to_string(file).unwrap(),
+            b"",
+            10_000_000,
+            None,
+        );
+        assert_eq!(report.report.exit, ExitReason::Finished);
+        assert_eq!(
+            String::from_utf8(report.report.output).unwrap(),
+            run_file(file).unwrap()
+        );
+        assert!(report.source_read);
+    }
+    let report = nested("&2*.@", b"21 4", 10_000_000, None);
+    assert_eq!(report.report.exit, ExitReason::Finished);
+    assert_eq!(report.report.output, b"42 ");
+    // & stops after the space following the number
+    assert_eq!(report.input_read, 3);
+
+    // the outer limits hold no matter what the interpreted program does
+    let report = nested("&2*.@", b"21", 100, None);
+    assert_eq!(report.report.exit, ExitReason::StepLimit);
+    assert!(!report.source_read);
+    let report = nested("1.", b"", 10_000_000, Some(10));
+    assert_eq!(report.report.exit, ExitReason::OutputLimit);
+    // a program that runs on without input or output stalls long before the outer step limit
+    let report = nested(">", b"", 10_000_000, None);
+    assert_eq!(report.report.exit, ExitReason::Stalled);
+    assert!(report.source_read);
+    assert!(report.report.steps < 1_000_000);
+}
+
 #[test]
 fn cli() {
-    run_interpreter(Arguments {
-        input: "programs/hello_world.bf".into(),
-        show_performance: false,
-        language_standard: crate::LanguageStandard::Befunge93,
-        stdin: None,
-        output: None,
-    })
+    let mut output = Vec::new();
+    run_interpreter_with_output(
+        Arguments {
+            input: "programs/hello_world.bf".into(),
+            show_performance: false,
+            language_standard: crate::LanguageStandard::Befunge93,
+            ..Default::default()
+        },
+        Box::new(&mut output),
+    )
     .unwrap();
+    assert_eq!(output, b"Hello World!");
 
-    run_interpreter(Arguments {
-        input: "programs/self_interpreter.bf".into(),
-        show_performance: true,
-        language_standard: crate::LanguageStandard::Befunge93,
-        stdin: Some("programs/kquine3.bf".into()),
-        output: None,
-    })
+    let mut output = Vec::new();
+    run_interpreter_with_output(
+        Arguments {
+            input: "programs/self_interpreter.bf".into(),
+            show_performance: true,
+            language_standard: crate::LanguageStandard::Befunge93,
+            stdin: vec![InputSource::File("programs/kquine3.bf".into())],
+            ..Default::default()
+        },
+        Box::new(&mut output),
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap().trim_end(),
+        std::fs::read_to_string("programs/kquine3.bf")
+            .unwrap()
+            .trim_end()
+    );
+}
+
+#[test]
+fn stack_log() {
+    let log = tempfile::NamedTempFile::new().unwrap();
+    run_interpreter_with_output(
+        Arguments {
+            input: "programs/hello_world.bf".into(),
+            language_standard: crate::LanguageStandard::Befunge93,
+            stack_log: Some(log.path().to_owned()),
+            ..Default::default()
+        },
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+
+    let log = std::fs::read_to_string(log.path()).unwrap();
+    let mut lines = log.lines();
+    assert_eq!(lines.next(), Some("step,depth,top"));
+    // The first step is the space at (0, 0), which leaves the stack empty.
+    assert_eq!(lines.next(), Some("1,0,"));
+    // The final `@` leaves behind the zero duplicated by `:`.
+    assert_eq!(lines.last(), Some("110,1,0"));
+}
+
+#[test]
+fn report() {
+    let report = tempfile::NamedTempFile::new().unwrap();
+    run_interpreter_with_output(
+        Arguments {
+            input: "programs/hello_world.bf".into(),
+            language_standard: crate::LanguageStandard::Befunge93,
+            report: Some(report.path().to_owned()),
+            ..Default::default()
+        },
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+
+    let report = std::fs::read_to_string(report.path()).unwrap();
+    assert!(report.starts_with("<!DOCTYPE html>"));
+    assert!(report.contains("<tr><th>Steps</th><td>110</td></tr>"));
+    // the `,` of the printing loop runs once per character
+    assert!(report.contains("<span title=\"(1, 3): 12 times\""));
+    assert!(report.contains("Hello World!"));
+
+    let mut heatmap = Heatmap::new();
+    heatmap.record(Position::new(2, 1, 0));
+    heatmap.record(Position::new(2, 1, 0));
+    heatmap.record(Position::new(-1, 1, 0));
+    assert_eq!(heatmap.count(2, 1), 2);
+    assert_eq!(heatmap.max(), 2);
+}
+
+#[test]
+fn animation() {
+    let animation = tempfile::NamedTempFile::new().unwrap();
+    run_interpreter_with_output(
+        Arguments {
+            input: "programs/hello_world.bf".into(),
+            language_standard: crate::LanguageStandard::Befunge93,
+            animate_to: Some(animation.path().to_owned()),
+            animate_interval: 50,
+            ..Default::default()
+        },
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+
+    let animation = std::fs::read_to_string(animation.path()).unwrap();
+    // frames at steps 0, 50 and 100, and the final frame
+    assert_eq!(animation.matches("\x1b[2J").count(), 4);
+    assert!(animation.starts_with("\x1b[H\x1b[2J\x1b[7m \x1b[0m>"));
+    assert!(animation.ends_with("step 110\n"));
+
+    // concurrent instruction pointers have their own colors and stacks in the sidebar
+    let program = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(program.path(), "t1.2.@.3.4").unwrap();
+    let animation = tempfile::NamedTempFile::new().unwrap();
+    run_interpreter_with_output(
+        Arguments {
+            input: program.path().to_owned(),
+            animate_to: Some(animation.path().to_owned()),
+            ..Default::default()
+        },
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+    let animation = std::fs::read_to_string(animation.path()).unwrap();
+    let frames: Vec<&str> = animation.split("\x1b[H\x1b[2J").skip(1).collect();
+    assert_eq!(
+        frames[0],
+        "\x1b[7mt\x1b[0m1.2.@.3.4 \x1b[7mip 0\x1b[0m []\nstep 0\n"
+    );
+    assert_eq!(
+        frames[3],
+        "t1\x1b[30;43m.\x1b[0m2.@.3\x1b[30;46;4m.\x1b[0m4 \x1b[30;46;4mip 1\x1b[0m [4]\n\
+         \x20          \x1b[30;43mip 0\x1b[0m [1]\nstep 3\n"
+    );
+}
+
+#[test]
+fn annotations() {
+    let annotations = Annotations::parse(
+        "# hello world\n 0,0 start\n\n3,3-1,3  print loop \n-2,-1--1,0 outside\n",
+    )
+    .unwrap();
+    assert_eq!(annotations.annotations.len(), 3);
+    assert_eq!(annotations.annotations[1].min, Position::new(1, 3, 0));
+    assert_eq!(annotations.annotations[1].max, Position::new(3, 3, 0));
+    assert_eq!(
+        annotations.at(Position::new(2, 3, 0)).collect::<Vec<_>>(),
+        ["print loop"]
+    );
+    assert_eq!(
+        annotations.describe(Position::new(-1, 0, 0)).as_deref(),
+        Some("outside")
+    );
+    assert_eq!(annotations.describe(Position::new(4, 3, 0)), None);
+    for invalid in [
+        "0,0",
+        "0 start",
+        "x,0 start",
+        "0,0-1 start",
+        "@ 0,0",
+        "@a-b 0,0",
+        "@name",
+    ] {
+        assert!(matches!(
+            Annotations::parse(invalid),
+            Err(Error::InvalidAnnotations(_))
+        ));
+    }
+
+    // named regions don't need a comment
+    let regions = Annotations::parse("@loop 1,0-5,0\n@outer 0,0-9,9 everything\n").unwrap();
+    assert_eq!(regions.region("loop").unwrap().max, Position::new(5, 0, 0));
+    assert_eq!(regions.region("print"), None);
+    assert_eq!(
+        regions.describe(Position::new(2, 0, 0)).as_deref(),
+        Some("everything")
+    );
+    assert_eq!(regions.locate(Position::new(2, 0, 0)), "[2, 0] in @loop");
+    assert_eq!(regions.locate(Position::new(2, 1, 0)), "[2, 1] in @outer");
+    assert_eq!(regions.locate(Position::new(20, 1, 0)), "[20, 1]");
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), "5,2 never runs\n@print 1,3-4,3 print <loop>\n").unwrap();
+    let report = tempfile::NamedTempFile::new().unwrap();
+    let animation = tempfile::NamedTempFile::new().unwrap();
+    run_interpreter_with_output(
+        Arguments {
+            input: "programs/hello_world.bf".into(),
+            language_standard: crate::LanguageStandard::Befunge93,
+            report: Some(report.path().to_owned()),
+            animate_to: Some(animation.path().to_owned()),
+            animate_interval: 50,
+            annotations: Some(file.path().to_owned()),
+            ..Default::default()
+        },
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+    let report = std::fs::read_to_string(report.path()).unwrap();
+    assert!(report.contains("<span title=\"(1, 3): 12 times\nprint &lt;loop&gt;\""));
+    // annotated cells are marked even if they never ran
+    assert!(report.contains(
+        "<span title=\"(5, 2): 0 times\nnever runs\" style=\"text-decoration: underline dotted;\">"
+    ));
+    assert!(report.contains(
+        "<tr><td>@print (1, 3) to (4, 3)</td><td>38</td><td>print &lt;loop&gt;</td></tr>"
+    ));
+    let animation = std::fs::read_to_string(animation.path()).unwrap();
+    assert!(animation.ends_with("step 110: print <loop>\n"));
+
+    let mut interpreter =
+        Interpreter::new_with_io("123+Z@", Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+            .unwrap();
+    let error = interpreter.run_forever().unwrap_err();
+    let annotations = Annotations::parse("4,0 not an instruction\n0,0-5,0 the program").unwrap();
+    let mut output = Vec::new();
+    post_mortem(
+        &interpreter,
+        &error,
+        &annotations,
+        None,
+        "info cell 4 0\ninfo cell 6 0\n".as_bytes(),
+        &mut output,
+        None,
+    )
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("90 'Z'\n  # not an instruction\n  # the program\n(boxfunge) 32\n"));
+}
+
+#[test]
+fn start_position() {
+    let start = PC::from_arg_value("3, 1, <").unwrap();
+    assert_eq!(
+        start,
+        PC {
+            position: Position::new(3, 1, 0),
+            delta: Direction::Left.into()
+        }
+    );
+    assert!(PC::from_arg_value("x,0,>").is_err());
+    assert!(PC::from_arg_value("0,0").is_err());
+
+    let mut output = Vec::new();
+    let mut interpreter =
+        Interpreter::new_with_io("@\n@.2", Box::new(&[] as &[u8]), Box::new(&mut output))
+            .unwrap()
+            .with_start(start);
+    interpreter.run_forever().unwrap();
+    drop(interpreter);
+    assert_eq!(output, b"2 ");
+
+    // only bounded grids end at 80x25
+    let program = format!("@\n{}@.2", " ".repeat(100));
+    let start = PC::from_arg_value("103, 1, <").unwrap();
+    let standard = crate::LanguageStandard::Befunge98;
+    let grid = Interpreter::parse_grid_for_standard(&program, standard).unwrap();
+    let results = crate::run_entries(
+        &grid,
+        &[start],
+        || Ok(Box::new(std::io::empty())),
+        |interpreter| Ok(interpreter.with_standard(standard)),
+    )
+    .unwrap();
+    assert_eq!(results[0].output, b"2 ");
+    assert_eq!(
+        run_interpreter_with_output(
+            Arguments {
+                input: "programs/hello_world.bf".into(),
+                language_standard: crate::LanguageStandard::Befunge93,
+                start: vec![start],
+                ..Default::default()
+            },
+            Box::new(std::io::sink()),
+        ),
+        Err(Error::InvalidStart(start))
+    );
+    // with an exit code of its own, unlike the usage errors of the command line
+    assert_eq!(Error::InvalidStart(start).exit_code(), 14);
+    let grid = Interpreter::parse_grid("@").unwrap();
+    assert_eq!(
+        crate::run_entries(
+            &grid,
+            &[start],
+            || Ok(Box::new(std::io::empty())),
+            |interpreter| Ok(interpreter)
+        )
+        .map(|_| ()),
+        Err(Error::InvalidStart(start))
+    );
+}
+
+#[test]
+fn entries() {
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut input, b"A").unwrap();
+    let grid = Interpreter::parse_grid("@\n@.2\n@.~").unwrap();
+    let results = crate::run_entries(
+        &grid,
+        &[
+            PC::default(),
+            PC::from_arg_value("3,1,<").unwrap(),
+            PC::from_arg_value("3,2,<").unwrap(),
+        ],
+        || Ok(InputSource::File(input.path().to_owned()).open()?),
+        |interpreter| Ok(interpreter),
+    )
+    .unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|entry| entry.result.is_ok()));
+    assert_eq!(results[0].output, b"");
+    assert_eq!(results[1].output, b"2 ");
+    // every entry receives the whole input
+    assert_eq!(results[2].output, b"65 ");
+    assert_eq!(results[2].steps, 4);
+}
+
+#[test]
+fn input_sources() {
+    fn run_with_source(source: &str, input: &InputSource) -> Result<String, Error> {
+        let mut output = Vec::new();
+        let mut interpreter =
+            Interpreter::new_with_io(source, input.open()?, Box::new(&mut output))?;
+        interpreter.run_forever()?;
+        drop(interpreter);
+        Ok(String::from_utf8_lossy(&output).to_string())
+    }
+
+    assert_eq!(
+        InputSource::from_arg_value("null").unwrap(),
+        InputSource::Null
+    );
+    assert_eq!(
+        run_with_source("~.~.@", &InputSource::Null).unwrap(),
+        "-1 -1 "
+    );
+    assert_eq!(
+        run_with_source("~.~.@", &InputSource::Zero).unwrap(),
+        "0 0 "
+    );
+    // reading a number at EOF must fail instead of waiting for input forever
+    assert!(matches!(
+        run_with_source("&.@", &InputSource::Null),
+        Err(Error::Io(_))
+    ));
+    assert!(matches!(
+        run_with_source("&.@", &InputSource::Zero),
+        Err(Error::InvalidNumber { .. })
+    ));
+    // and endless tokens are an error rather than being split into several numbers
+    let mut interpreter = Interpreter::new_with_io(
+        "&.@",
+        Box::new(std::io::repeat(b'7')),
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+    assert!(matches!(
+        interpreter.run_forever(),
+        Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::InvalidData
+    ));
+
+    // several sources are read one after another, with EOF only after the last
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut file, b"1 ").unwrap();
+    let file = file.path().to_str().unwrap();
+    let options = [
+        "-i",
+        file,
+        "--stdin-str",
+        "2 ",
+        "--stdin",
+        "null",
+        "--stdin-str",
+        "3",
+    ];
+    let mut args = Arguments::from_args(&["boxfunge"], &options).unwrap();
+    args.order_input_sources(&options);
+    assert_eq!(
+        args.stdin,
+        [
+            InputSource::File(file.into()),
+            InputSource::Text("2 ".to_string()),
+            InputSource::Null,
+            InputSource::Text("3".to_string()),
+        ]
+    );
+    let mut output = Vec::new();
+    let mut interpreter = Interpreter::new_with_io(
+        "&.&.&.~.@",
+        InputSource::open_all(&args.stdin).unwrap(),
+        Box::new(&mut output),
+    )
+    .unwrap();
+    interpreter.run_forever().unwrap();
+    drop(interpreter);
+    assert_eq!(output, b"1 2 3 -1 ");
+    assert_eq!(
+        InputSource::from_arg_value("-").unwrap(),
+        InputSource::Stdin
+    );
+}
+
+#[test]
+fn echo_input() {
+    let mut echo = Vec::new();
+    let mut output = Vec::new();
+    let input = Box::new(crate::EchoInput::new(&b" 12 34"[..], &mut echo));
+    let mut interpreter = Interpreter::new_with_io("&.@", input, Box::new(&mut output)).unwrap();
+    interpreter.run_forever().unwrap();
+    drop(interpreter);
+    assert_eq!(output, b"12 ");
+    // only the consumed bytes are echoed, including the whitespace skipped and terminating the number
+    assert_eq!(
+        echo,
+        b"\x1b[7m \x1b[0m\x1b[7m1\x1b[0m\x1b[7m2\x1b[0m\x1b[7m \x1b[0m"
+    );
+}
+
+#[test]
+fn number_input() {
+    fn read_number(input: &[u8], lenient: bool) -> Result<String, Error> {
+        let mut output = Vec::new();
+        let mut interpreter =
+            Interpreter::new_with_io("~$&.@", Box::new(input), Box::new(&mut output))?
+                .with_lenient_numbers(lenient);
+        interpreter.run_forever()?;
+        drop(interpreter);
+        Ok(String::from_utf8_lossy(&output).to_string())
+    }
+
+    assert_eq!(read_number(b"x +12", false).unwrap(), "12 ");
+    assert_eq!(read_number(b"x 1_000", true).unwrap(), "1000 ");
+    assert_eq!(
+        read_number(b"x  1_000", false),
+        Err(Error::InvalidNumber {
+            text: "1_000".to_string(),
+            offset: 3
+        })
+    );
+}
+
+#[test]
+fn grid_observers() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let changes = Rc::new(RefCell::new(Vec::new()));
+    let mut output = Vec::new();
+    let mut interpreter = Interpreter::new_with_io(
+        "\"A\"52p52g,@",
+        Box::new(&[] as &[u8]),
+        Box::new(&mut output),
+    )
+    .unwrap();
+    let observed = changes.clone();
+    interpreter
+        .grid_mut()
+        .subscribe(move |change| observed.borrow_mut().push(*change));
+    interpreter.run_forever().unwrap();
+
+    assert_eq!(interpreter.grid().get(5, 2), Some(b'A'));
+    assert_eq!(interpreter.grid().get(80, 0), None);
+    drop(interpreter);
+    assert_eq!(output, b"A");
+    assert_eq!(
+        *changes.borrow(),
+        [CellChange {
+            position: Position::new(5, 2, 0),
+            old: b' ',
+            new: b'A'
+        }]
+    );
+}
+
+#[test]
+fn metadata() {
+    let source = ";; title: Hello\r\n;; author: Someone\n;; standard: 93\n;; extensions: ROMA, NULL\n;; license: MIT\n>\"!\",@\n";
+    let (metadata, program) = Metadata::parse(source).unwrap();
+    assert_eq!(
+        metadata,
+        Metadata {
+            title: Some("Hello".into()),
+            author: Some("Someone".into()),
+            standard: Some(crate::LanguageStandard::Befunge93),
+            extensions: vec!["ROMA".into(), "NULL".into()],
+            other: vec![("license".into(), "MIT".into())],
+        }
+    );
+    assert_eq!(program, ">\"!\",@\n");
+    assert_eq!(
+        Metadata::parse(";; standard: 99\n@").unwrap_err(),
+        Error::InvalidMetadata("standard: 99".into())
+    );
+    // Befunge-98 programs may start with ; themselves, which must not be mistaken for a header
+    for source in [";;v\n@", ";; nonsense\n@", ";;: v\n@", ";;\n@"] {
+        assert_eq!(
+            Metadata::parse(source).unwrap(),
+            (Metadata::default(), source)
+        );
+    }
+
+    let stats = GridStats::new(&Interpreter::parse_grid(program).unwrap());
+    assert_eq!(
+        (stats.size(), stats.non_space_cells),
+        (Position::new(6, 1, 1), 6)
+    );
+    assert!(!stats.reads_input && !stats.modifies_itself && !stats.uses_randomness);
+    // the statistics cover the whole unbounded grid
+    let far = Interpreter::parse_grid_for_standard(
+        &format!("{}&\n{}p", " ".repeat(100), "\n".repeat(30)),
+        crate::LanguageStandard::Befunge98,
+    )
+    .unwrap();
+    let stats = GridStats::new(&far);
+    assert_eq!(
+        (stats.size(), stats.non_space_cells),
+        (Position::new(101, 32, 1), 2)
+    );
+    assert!(stats.reads_input && stats.modifies_itself);
+
+    // the header's standard applies to the program
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let program = format!("a.@{}", " ".repeat(80));
+    std::io::Write::write_all(
+        &mut file,
+        format!(";; standard: 93\n{}!", program).as_bytes(),
+    )
+    .unwrap();
+    let run = |input: &std::path::Path| {
+        let mut output = Vec::new();
+        let result = run_interpreter_with_output(
+            Arguments {
+                input: input.into(),
+                ..Default::default()
+            },
+            Box::new(&mut output),
+        );
+        result.map(|_| output)
+    };
+    assert_eq!(run(file.path()), Err(Error::InvalidGridSize(84, 1)));
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut file, format!("{}!", program).as_bytes()).unwrap();
+    assert_eq!(run(file.path()), Ok(b"10 ".to_vec()));
+}
+
+#[test]
+fn exit_codes() {
+    let load_error = Interpreter::parse_grid("ä@").unwrap_err();
+    assert_eq!(load_error, Error::NonAsciiSource('ä'));
+    assert_eq!(load_error.exit_code(), 2);
+    assert_eq!(run_file("programs/hello_world.bf").map(|_| 0), Ok(0));
+    assert_eq!(
+        Interpreter::new_with_io("x", Box::new(&[] as &[u8]), Box::new(Vec::new()))
+            .unwrap()
+            .run_forever()
+            .unwrap_err()
+            .exit_code(),
+        3
+    );
+
+    // the command line returns the exit code of a failed or quitting program instead of ending the process
+    let exit_code = |program: &[u8]| {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, program).unwrap();
+        run_interpreter_with_output(
+            Arguments {
+                input: file.path().into(),
+                ..Default::default()
+            },
+            Box::new(std::io::sink()),
+        )
+    };
+    assert_eq!(exit_code(b"@"), Ok(0));
+    assert_eq!(exit_code(b";; standard: 93\nx"), Ok(3));
+    assert_eq!(exit_code(b"4q"), Ok(4));
+}
+
+#[test]
+fn no_panics() {
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    // division and modulo by zero push 0
+    let mut output = Vec::new();
+    let mut interpreter =
+        Interpreter::new_with_io("50/.50%.@", Box::new(&[] as &[u8]), Box::new(&mut output))
+            .unwrap();
+    interpreter.run_forever().unwrap();
+    drop(interpreter);
+    assert_eq!(output, b"0 0 ");
+    // lines longer than the first line are rejected instead of overflowing the grid
+    assert_eq!(
+        Interpreter::parse_grid(&format!("@\n{}", " ".repeat(81))).unwrap_err(),
+        Error::InvalidGridSize(81, 2)
+    );
+
+    // random programs with random input must only ever fail with errors
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0xb0f);
+    // k runs an instruction as often as the top of the stack says within a single step, and the programs must not
+    // write random files into the working directory with o
+    let sandbox = Sandbox {
+        max_steps: Some(100_000),
+        allow_filesystem: false,
+        ..Default::default()
+    };
+    for _ in 0..500 {
+        let source = (0..4)
+            .map(|_| {
+                (0..20)
+                    .map(|_| rng.gen_range(b' '..=b'~') as char)
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let input = (0..8).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+        let standard = if rng.gen() {
+            LanguageStandard::Befunge93
+        } else {
+            LanguageStandard::Befunge98
+        };
+        let mut interpreter =
+            Interpreter::new_with_io(&source, Box::new(&input[..]), Box::new(std::io::sink()))
+                .unwrap()
+                .with_standard(standard)
+                .with_sandbox(sandbox.clone())
+                .with_seed(rng.gen());
+        for _ in 0..1000 {
+            if interpreter.run_step().is_err() {
+                break;
+            }
+        }
+    }
+
+    // so must resuming from random states, including ones only reachable through self-modification
+    let mut interpreter = Interpreter::new_with_io(
+        "",
+        Box::new(std::io::repeat(b'7')),
+        Box::new(std::io::sink()),
+    )
+    .unwrap()
+    .with_standard(LanguageStandard::Befunge98)
+    .with_seed(rng.gen());
+    for _ in 0..500 {
+        // every state gets a step limit of its own
+        let steps = interpreter.steps();
+        interpreter = interpreter.with_sandbox(Sandbox {
+            max_steps: Some(steps + 100_000),
+            ..sandbox.clone()
+        });
+        let mut cells = [[b' '; crate::GRID_WIDTH]; crate::GRID_HEIGHT];
+        for _ in 0..200 {
+            cells[rng.gen_range(0..crate::GRID_HEIGHT)][rng.gen_range(0..crate::GRID_WIDTH)] =
+                rng.gen();
+        }
+        let random_stack = |rng: &mut rand::rngs::SmallRng| {
+            (0..rng.gen_range(0..6))
+                .map(|_| match rng.gen_range(0..4) {
+                    0 => Int::MIN,
+                    1 => Int::MAX,
+                    2 => rng.gen_range(-100..100),
+                    _ => rng.gen(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let stack = random_stack(&mut rng);
+        let stack_stack = (0..rng.gen_range(0..3))
+            .map(|_| random_stack(&mut rng))
+            .collect();
+        interpreter.restore(Snapshot {
+            grid: crate::Grid::new(cells),
+            stack,
+            program_counter: PC {
+                position: Position::new(rng.gen_range(-100..200), rng.gen_range(-100..200), 0),
+                delta: Delta::new(rng.gen_range(-2..=2), rng.gen_range(-2..=2), 0),
+            },
+            string_mode: rng.gen(),
+            stack_stack,
+            storage_offset: Position::new(rng.gen_range(-100..100), rng.gen_range(-100..100), 0),
+            ip_id: 0,
+            semantics: Default::default(),
+            other_ips: (1..rng.gen_range(1..4))
+                .map(|id| InstructionPointer {
+                    program_counter: PC {
+                        position: Position::new(rng.gen_range(0..80), rng.gen_range(0..25), 0),
+                        delta: Delta::new(rng.gen_range(-2..=2), rng.gen_range(-2..=2), 0),
+                    },
+                    string_mode: rng.gen(),
+                    stack: random_stack(&mut rng),
+                    stack_stack: Vec::new(),
+                    storage_offset: Position::ZERO,
+                    id,
+                    semantics: Default::default(),
+                })
+                .collect(),
+        });
+        for _ in 0..1000 {
+            if interpreter.run_step().is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[test]
+fn time_slices() {
+    use std::time::Duration;
+
+    let mut endless =
+        Interpreter::new_with_io(">", Box::new(&[] as &[u8]), Box::new(Vec::new())).unwrap();
+    assert_eq!(
+        endless.run_for_duration(Duration::from_millis(5)),
+        Ok(StepOutcome::Running)
+    );
+    let steps = endless.steps();
+    assert!(steps > 0);
+    assert_eq!(
+        endless.run_for_duration(Duration::ZERO),
+        Ok(StepOutcome::Running)
+    );
+    assert!(endless.steps() > steps);
+
+    let mut output = Vec::new();
+    let mut hello = Interpreter::new_with_io(
+        &std::fs::read_to_string("programs/hello_world.bf").unwrap(),
+        Box::new(&[] as &[u8]),
+        Box::new(&mut output),
+    )
+    .unwrap();
+    assert_eq!(
+        hello.run_for_duration(Duration::from_secs(10)),
+        Ok(StepOutcome::Finished)
+    );
+    drop(hello);
+    assert_eq!(output, b"Hello World!");
+}
+
+#[test]
+fn matrix() {
+    let matrix = conformance_matrix(&RunRequest::default());
+    let behavior = |name: &str, setting: &str| {
+        let (_, entries) = matrix.iter().find(|(case, _)| case.name == name).unwrap();
+        let entry = entries
+            .iter()
+            .find(|entry| entry.setting == setting)
+            .unwrap();
+        entry.behavior()
+    };
+    assert_eq!(behavior("eof-character", "-s 93"), "\"-1 \" (finished)");
+    assert_eq!(behavior("eof-character", "-s 98"), "\"\" (finished)");
+    assert_eq!(behavior("division-by-zero", "-s 93"), "\"0 0 \" (finished)");
+    assert_eq!(
+        behavior("bridge-at-edge", "--bridge-edge skip"),
+        "\"0 \" (finished)"
+    );
+    assert_eq!(
+        behavior("bridge-at-edge", "--bridge-edge land"),
+        "\"1 \" (finished)"
+    );
+    assert_eq!(behavior("negative-get", "-s 93"), "\"-1 -128 \" (finished)");
+    assert_eq!(
+        behavior("string-spaces", "--string-spaces sgml"),
+        "\"a b\" (finished)"
+    );
+    assert!(behavior("unknown-instruction", "-s 93").contains("Illegal command"));
+}
+
+#[test]
+fn headless_run() {
+    let report = crate::run(RunRequest {
+        program: "&.@".into(),
+        input: b"42".to_vec(),
+        ..Default::default()
+    });
+    assert_eq!(report.exit, ExitReason::Finished);
+    assert_eq!(report.output, b"42 ");
+    assert_eq!(report.steps, 3);
+
+    let report = crate::run(RunRequest {
+        program: ">".into(),
+        max_steps: Some(100),
+        ..Default::default()
+    });
+    assert_eq!(report.exit, ExitReason::StepLimit);
+    assert_eq!(report.steps, 100);
+
+    let report = crate::run(RunRequest {
+        program: ">".into(),
+        timeout: Some(std::time::Duration::from_millis(1)),
+        ..Default::default()
+    });
+    assert_eq!(report.exit, ExitReason::Timeout);
+
+    let report = crate::run(RunRequest {
+        program: "x".repeat(81),
+        ..Default::default()
+    });
+    assert_eq!(
+        report.exit,
+        ExitReason::Error(Error::InvalidGridSize(81, 1))
+    );
+
+    let dna = std::fs::read_to_string("programs/dna1.bf").unwrap();
+    let seeded = || {
+        crate::run(RunRequest {
+            program: dna.clone(),
+            seed: Some(1234),
+            ..Default::default()
+        })
+        .output
+    };
+    assert_eq!(seeded(), seeded());
+}
+
+#[test]
+fn write_journal() {
+    let mut interpreter = Interpreter::new_with_io(
+        "\"A\"52p\"B\"52p\"C\"62p@",
+        Box::new(&[] as &[u8]),
+        Box::new(Vec::new()),
+    )
+    .unwrap()
+    .with_write_journal(2);
+    interpreter.run_forever().unwrap();
+
+    let journal = interpreter.write_journal().unwrap();
+    // the first write has been forgotten already
+    assert_eq!(journal.entries().count(), 2);
+    let last = journal.last_write(Position::new(5, 2, 0)).unwrap();
+    assert_eq!(last.change.old, b'A');
+    assert_eq!(last.change.new, b'B');
+    assert_eq!(last.writer, Position::new(11, 0, 0));
+    assert_eq!(last.step, 12);
+    assert!(journal.last_write(Position::new(0, 0, 0)).is_none());
+}
+
+#[test]
+fn instruction_sets() {
+    let befunge93 = InstructionSet::for_standard(crate::LanguageStandard::Befunge93);
+    assert_eq!(befunge93.instructions().count(), 37);
+    assert!(befunge93.is_legal(b'@'));
+    assert!(!befunge93.is_legal(b'x'));
+    assert!(!befunge93.is_legal(0));
+
+    // every legal instruction is documented
+    assert!(befunge93
+        .documentation()
+        .map(|info| info.instruction)
+        .eq(befunge93.instructions()));
+    assert_eq!(
+        befunge93.describe(b'\\').unwrap().stack_effect,
+        "a b -- b a"
+    );
+    assert_eq!(befunge93.describe(b'x'), None);
+
+    let befunge98 = InstructionSet::for_standard(LanguageStandard::Befunge98);
+    assert!(befunge93
+        .instructions()
+        .all(|instruction| befunge98.is_legal(instruction)));
+    assert!(befunge98.is_legal(b'j'));
+    assert!(befunge98.is_legal(b'x'));
+    // h, l and m only exist in Trefunge
+    assert!(!befunge98.is_legal(b'h'));
+    assert!(befunge98
+        .documentation()
+        .map(|info| info.instruction)
+        .eq(befunge98.instructions()));
+
+    // the drafts in between add to Befunge-93 step by step
+    let befunge96 = InstructionSet::for_standard(LanguageStandard::Befunge96);
+    let befunge97 = InstructionSet::for_standard(LanguageStandard::Befunge97);
+    for (smaller, larger) in [
+        (befunge93, befunge96),
+        (befunge96, befunge97),
+        (befunge97, befunge98),
+    ] {
+        assert!(smaller
+            .instructions()
+            .all(|instruction| larger.is_legal(instruction)));
+        assert!(smaller.instructions().count() < larger.instructions().count());
+    }
+    assert!(befunge96.is_legal(b'x') && !befunge96.is_legal(b'{'));
+    assert!(befunge97.is_legal(b'{') && !befunge97.is_legal(b'('));
+}
+
+#[test]
+fn intermediate_standards() {
+    let run = |program: &str, standard| {
+        let mut output = Vec::new();
+        let mut interpreter =
+            Interpreter::new_with_io(program, Box::new(&[] as &[u8]), Box::new(&mut output))
+                .unwrap()
+                .with_standard(standard);
+        let result = interpreter.run_forever();
+        drop(interpreter);
+        (String::from_utf8(output).unwrap(), result.is_ok())
+    };
+    // { is illegal before Befunge-97, and illegal instructions reflect after Befunge-93
+    assert_eq!(
+        run("{1.@", LanguageStandard::Befunge93),
+        (String::new(), false)
+    );
+    assert_eq!(
+        run("{1.@", LanguageStandard::Befunge96),
+        (String::new(), true)
+    );
+    assert_eq!(
+        run("{1.@", LanguageStandard::Befunge97),
+        ("1 ".into(), true)
+    );
+    // Befunge-96 keeps the 80x25 grid, Befunge-97 is unbounded
+    let wide = format!("{}@", " ".repeat(100));
+    assert_eq!(
+        Interpreter::parse_grid_for_standard(&wide, LanguageStandard::Befunge96).unwrap_err(),
+        Error::InvalidGridSize(101, 1)
+    );
+    assert!(Interpreter::parse_grid_for_standard(&wide, LanguageStandard::Befunge97).is_ok());
+    // Befunge-96 cells are bytes, Befunge-97 cells store full values
+    assert_eq!(
+        run("a2*a*00p00g.@", LanguageStandard::Befunge96),
+        ("-56 ".into(), true)
+    );
+    assert_eq!(
+        run("a2*a*00p00g.@", LanguageStandard::Befunge97),
+        ("200 ".into(), true)
+    );
+    assert_eq!(
+        LanguageStandard::from_arg_value("96").map(|standard| standard.to_string()),
+        Ok("96".into())
+    );
+}
+
+#[test]
+fn trefunge() {
+    let run = |program: &str, standard| {
+        let report = crate::run(RunRequest {
+            program: program.into(),
+            standard: Some(standard),
+            ..Default::default()
+        });
+        (String::from_utf8(report.output).unwrap(), report.exit)
+    };
+    let trefunge = |program: &str| run(program, LanguageStandard::Trefunge98);
+    // every form feed starts the next plane, whose lines start at y = 0 again
+    assert_eq!(trefunge("h\x0c>1.@"), ("1 ".into(), ExitReason::Finished));
+    assert_eq!(trefunge("l\n\x0c>2.@"), ("2 ".into(), ExitReason::Finished));
+    // m goes high on non-zero values, and going low from the lowest plane wraps around to the highest
+    assert_eq!(
+        trefunge("1m\x0c >1.@\x0c >2.@"),
+        ("1 ".into(), ExitReason::Finished)
+    );
+    assert_eq!(
+        trefunge("0m\x0c >1.@\x0c >2.@"),
+        ("2 ".into(), ExitReason::Finished)
+    );
+    // vectors have three components, and y reports three dimensions
+    assert_eq!(trefunge("7002p002g.@"), ("7 ".into(), ExitReason::Finished));
+    assert_eq!(trefunge("7y.@"), ("3 ".into(), ExitReason::Finished));
+    assert_eq!(
+        run("7y.@", LanguageStandard::Befunge98),
+        ("2 ".into(), ExitReason::Finished)
+    );
+    // h, l and m only exist in Trefunge, and form feeds are ordinary cells elsewhere
+    assert!(InstructionSet::for_standard(LanguageStandard::Trefunge98).is_legal(b'h'));
+    assert!(!InstructionSet::for_standard(LanguageStandard::Befunge98).is_legal(b'h'));
+    assert_eq!(
+        Interpreter::parse_grid_for_standard("1\x0c2", LanguageStandard::Befunge98)
+            .unwrap()
+            .get(1, 0),
+        Some(0x0c)
+    );
+
+    assert_eq!(
+        LanguageStandard::from_arg_value("trefunge"),
+        Ok(LanguageStandard::Trefunge98)
+    );
+    assert_eq!(
+        LanguageStandard::for_path(Path::new("cube.t98")),
+        Some(LanguageStandard::Trefunge98)
+    );
+    assert_eq!(LanguageStandard::for_path(Path::new("hello.bf")), None);
+    let pc = PC::from_arg_value("1,2,3,h").unwrap();
+    assert_eq!(pc.position, Position::new(1, 2, 3));
+    assert_eq!(pc.delta.direction(), Some(Direction::High));
+    assert_eq!(pc.to_string(), "1,2,3,h");
+    assert_eq!(Delta::from_arg_value("0,0,-1").unwrap().to_string(), "l");
+    assert_eq!(Delta::from_arg_value("1,2,3").unwrap().to_string(), "1,2,3");
+    assert_eq!(Direction::from_arg_value("low"), Ok(Direction::Low));
+
+    // .tf files are Trefunge programs unless another standard is asked for
+    let program = tempfile::Builder::new().suffix(".tf").tempfile().unwrap();
+    std::fs::write(program.path(), "h\x0c>1.@").unwrap();
+    let mut output = Vec::new();
+    run_interpreter_with_output(
+        Arguments {
+            input: program.path().to_owned(),
+            ..Default::default()
+        },
+        Box::new(&mut output),
+    )
     .unwrap();
+    assert_eq!(output, b"1 ");
+}
+
+#[test]
+fn wrap_modes() {
+    fn run_with(source: &str, start: PC, wrap_mode: WrapMode) -> (String, usize) {
+        let report = crate::run(RunRequest {
+            program: source.into(),
+            start,
+            wrap_mode: Some(wrap_mode),
+            max_steps: Some(1000),
+            ..Default::default()
+        });
+        assert_eq!(report.exit, ExitReason::Finished);
+        (String::from_utf8(report.output).unwrap(), report.steps)
+    }
+
+    assert_eq!(run_with("<@", PC::default(), WrapMode::Torus).1, 80);
+    assert_eq!(run_with("<@", PC::default(), WrapMode::Lahey).1, 2);
+    let start = PC::from_arg_value("4,0,>").unwrap();
+    assert_eq!(run_with("@.3<", start, WrapMode::Torus).0, "");
+    assert_eq!(run_with("@.3<", start, WrapMode::Reflect).0, "3 ");
+
+    let mut grid = Interpreter::parse_grid("\n  x").unwrap();
+    assert_eq!(
+        *grid.bounds(),
+        Bounds {
+            min: Position::new(2, 1, 0),
+            max: Position::new(2, 1, 0)
+        }
+    );
+    grid.set(5, 3, b'a');
+    grid.set(0, 0, b' ');
+    assert_eq!(
+        *grid.bounds(),
+        Bounds {
+            min: Position::new(2, 1, 0),
+            max: Position::new(5, 3, 0)
+        }
+    );
+}
+
+#[test]
+fn bridge_at_edge() {
+    fn output(bridge_edge: BridgeEdge) -> String {
+        let report = crate::run(RunRequest {
+            // the cell skipped by the bridge at (0, 0) heading left is the 1 at (79, 0)
+            program: format!("#{}@..21", " ".repeat(74)),
+            start: PC::from_arg_value("0,0,<").unwrap(),
+            bridge_edge,
+            max_steps: Some(1000),
+            ..Default::default()
+        });
+        assert_eq!(report.exit, ExitReason::Finished);
+        String::from_utf8(report.output).unwrap()
+    }
+
+    assert_eq!(output(BridgeEdge::Skip), "2 0 ");
+    assert_eq!(output(BridgeEdge::Land), "2 1 ");
+}
+
+#[test]
+fn stack_dump() {
+    use std::cell::RefCell;
+
+    let dumps = RefCell::new(Vec::new());
+    let mut output = Vec::new();
+    let mut interpreter =
+        Interpreter::new_with_io("12D+D.@", Box::new(&[] as &[u8]), Box::new(&mut output))
+            .unwrap()
+            .with_stack_dump_instruction(b'D');
+    interpreter
+        .on_stack_dump(|position, stack| dumps.borrow_mut().push((position.x, stack.to_vec())));
+    interpreter.run_forever().unwrap();
+    drop(interpreter);
+
+    assert_eq!(output, b"3 ");
+    assert_eq!(*dumps.borrow(), [(2, vec![1, 2]), (4, vec![3])]);
+}
+
+#[test]
+fn sandbox() {
+    let strict = SandboxProfile::Strict.sandbox();
+    assert!(strict.max_steps.is_some() && strict.timeout.is_some());
+    assert!(!strict.allow_filesystem && !strict.allow_exec);
+    assert!(SandboxProfile::Trusted.sandbox().allow_network);
+
+    let mut interpreter =
+        Interpreter::new_with_io(">", Box::new(&[] as &[u8]), Box::new(Vec::new()))
+            .unwrap()
+            .with_sandbox(Sandbox {
+                max_steps: Some(10),
+                ..strict
+            });
+    let error = interpreter.run_forever().unwrap_err();
+    assert_eq!(error, Error::StepLimit(10));
+    assert_eq!(error.exit_code(), 6);
+    assert_eq!(interpreter.steps(), 10);
+}
+
+#[test]
+fn output_limit() {
+    let mut output = Vec::new();
+    let mut interpreter = Interpreter::new_with_io(
+        "\"olleh\",,,,,@",
+        Box::new(&[] as &[u8]),
+        Box::new(&mut output),
+    )
+    .unwrap()
+    .with_sandbox(Sandbox {
+        max_output: Some(3),
+        ..Sandbox::default()
+    });
+    let error = interpreter.run_forever().unwrap_err();
+    assert_eq!(error, Error::OutputLimit(3));
+    assert_eq!(error.exit_code(), 8);
+    drop(interpreter);
+    assert_eq!(output, b"hel");
+
+    // a number is either written entirely or not at all
+    let report = crate::run(RunRequest {
+        program: "9.99*.@".to_string(),
+        max_output: Some(4),
+        ..Default::default()
+    });
+    assert_eq!(report.exit, ExitReason::OutputLimit);
+    assert_eq!(report.output, b"9 ");
+}
+
+#[test]
+fn flow_targets() {
+    let grid = Interpreter::parse_grid("v #@\n>?_ \n  1").unwrap();
+    let pc = |x, y, direction: Direction| PC {
+        position: Position::new(x, y, 0),
+        delta: direction.into(),
+    };
+
+    assert_eq!(grid.flow_targets(0, 0), [pc(0, 1, Direction::Down)]);
+    assert_eq!(grid.flow_targets(3, 0), []);
+    assert_eq!(
+        grid.flow_targets(2, 1),
+        [pc(3, 1, Direction::Right), pc(1, 1, Direction::Left)]
+    );
+    assert_eq!(grid.flow_targets(1, 1).len(), 4);
+    // # skips a cell in whatever direction the program counter arrives from
+    assert!(grid.flow_targets(2, 0).contains(&pc(0, 0, Direction::Left)));
+    assert!(grid.flow_targets(2, 0).contains(&pc(2, 23, Direction::Up)));
+    // wrapping around the edge
+    assert!(grid
+        .flow_targets(0, 1)
+        .contains(&pc(1, 1, Direction::Right)));
+    assert!(grid.flow_targets(2, 2).contains(&pc(2, 3, Direction::Down)));
+    assert!(grid
+        .flow_targets(0, 0)
+        .iter()
+        .all(|target| target.position != Position::new(0, 24, 0)));
+    assert_eq!(grid.flow_targets(80, 0), []);
+
+    let sources = grid.flow_sources(3, 0);
+    assert!(sources.contains(&pc(4, 0, Direction::Left)));
+    assert!(sources.contains(&pc(3, 1, Direction::Up)));
+    assert!(!sources.contains(&pc(2, 0, Direction::Right)));
+    assert!(grid
+        .flow_sources(4, 0)
+        .contains(&pc(2, 0, Direction::Right)));
+    assert!(grid
+        .flow_sources(1, 1)
+        .contains(&pc(0, 1, Direction::Right)));
+    assert_eq!(grid.flow_sources(-1, 0), []);
+}
+
+#[test]
+fn symbolic_branches() {
+    let grid = Interpreter::parse_grid("25*&+:3`v\n   @    _\"a\"0|").unwrap();
+    let branches = crate::analyze_paths(&grid, PC::default()).branches;
+    let rendered: Vec<_> = branches.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        rendered,
+        [
+            "_ at 8,1 (arriving v): [input, input]",
+            "| at 13,1 (arriving >): [input, 97, 0]",
+            // going up or down, the program counter wraps around to the `|` again
+            "| at 13,1 (arriving ^): [input, 97]",
+            "| at 13,1 (arriving v): [input]",
+        ]
+    );
+    assert_eq!(branches[1].stack[1], Symbol::Constant(b'a' as Int));
+}
+
+#[test]
+fn string_literals() {
+    let grid = Interpreter::parse_grid("v @\"unused\"\n>\"olleh\"v\n@\"dlrow\"<").unwrap();
+    let strings = crate::analyze_paths(&grid, PC::default()).strings;
+    let rendered: Vec<_> = strings.iter().map(ToString::to_string).collect();
+    assert_eq!(rendered, [r#""olleh" at 1,1 (>)"#, r#""world" at 7,2 (<)"#]);
+}
+
+#[test]
+fn outline() {
+    let grid = Interpreter::parse_grid("9>1-:v\n ^   _\"a\"55p@").unwrap();
+    let analysis = crate::analyze_paths(&grid, PC::default());
+    let pc = |x, y, direction: Direction| PC {
+        position: Position::new(x, y, 0),
+        delta: direction.into(),
+    };
+    assert_eq!(analysis.loops, [pc(5, 1, Direction::Down)]);
+    // a loop without any branch closes where the program counter first repeats its state
+    let ring = Interpreter::parse_grid(">v\n^<").unwrap();
+    assert_eq!(
+        crate::analyze_paths(&ring, PC::default()).loops,
+        [pc(1, 0, Direction::Right)]
+    );
+    assert_eq!(analysis.writes.len(), 1);
+    assert_eq!(analysis.writes[0].target, Some(Position::new(5, 5, 0)));
+    assert_eq!(
+        analysis.outline_json(),
+        concat!(
+            r#"{"entry": {"x": 0, "y": 0, "direction": ">"}, "#,
+            r#""branches": [{"at": {"x": 5, "y": 1, "direction": "v"}, "instruction": "_", "stack": [8, 8]}], "#,
+            r#""loops": [{"x": 5, "y": 1, "direction": "v"}], "#,
+            r#""strings": [{"at": {"x": 6, "y": 1, "direction": ">"}, "text": "a"}], "#,
+            r#""writes": [{"at": {"x": 11, "y": 1, "direction": ">"}, "target": {"x": 5, "y": 5}}]}"#
+        )
+    );
+}
+
+#[test]
+fn protected_regions() {
+    let region = crate::options::parse_region("0, 0, 2, 1").unwrap();
+    assert_eq!(
+        region,
+        Bounds {
+            min: Position::new(0, 0, 0),
+            max: Position::new(1, 0, 0),
+        }
+    );
+    assert!(crate::options::parse_region("0,0,0,1").is_err());
+    assert!(crate::options::parse_region("0,0,1").is_err());
+
+    let run = |policy| {
+        let mut interpreter =
+            Interpreter::new_with_io("\"X\"010p@", Box::new(&[] as &[u8]), Box::new(Vec::new()))
+                .unwrap()
+                .with_protection(vec![region], policy);
+        let result = interpreter.run_forever();
+        (result, interpreter.grid()[0][1])
+    };
+    let (result, cell) = run(ProtectPolicy::Error);
+    assert_eq!(result, Err(Error::ProtectedWrite(Position::new(1, 0, 0))));
+    assert_eq!(result.unwrap_err().exit_code(), 9);
+    assert_eq!(cell, b'X');
+    let (result, cell) = run(ProtectPolicy::Ignore);
+    assert!(result.is_ok());
+    assert_eq!(cell, b'X');
+}
+
+#[test]
+fn golden_tests() {
+    let golden = tempfile::tempdir().unwrap();
+    run_interpreter_with_output(
+        Arguments {
+            input: "programs/dna1.bf".into(),
+            language_standard: crate::LanguageStandard::Befunge93,
+            golden: Some(golden.path().to_owned()),
+            ..Default::default()
+        },
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+
+    // replaying the golden test must reproduce the random output exactly
+    let read =
+        |extension| std::fs::read(golden.path().join("dna1").with_extension(extension)).unwrap();
+    let report = crate::run(RunRequest {
+        program: String::from_utf8(read("bf")).unwrap(),
+        input: read("in"),
+        seed: Some(
+            String::from_utf8(read("seed"))
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap(),
+        ),
+        ..Default::default()
+    });
+    assert_eq!(report.exit, ExitReason::Finished);
+    assert_eq!(report.output, read("expected"));
+    assert!(!report.output.is_empty());
+}
+
+#[test]
+fn output_encoding() {
+    let encode = |newline, encoding| {
+        let mut output = Vec::new();
+        let mut encoder = EncodeOutput::new(&mut output, newline, encoding);
+        std::io::Write::write_all(&mut encoder, b"a\nb").unwrap();
+        drop(encoder);
+        output
+    };
+    assert_eq!(encode(Newline::Lf, OutputEncoding::Ascii), b"a\nb");
+    assert_eq!(encode(Newline::Crlf, OutputEncoding::Ascii), b"a\r\nb");
+    assert_eq!(
+        encode(Newline::Crlf, OutputEncoding::Utf16Le),
+        b"a\0\r\0\n\0b\0"
+    );
+    assert_eq!(encode(Newline::Lf, OutputEncoding::Utf16Be), b"\0a\0\n\0b");
+    assert_eq!(
+        encode(Newline::Native, OutputEncoding::Ascii),
+        if cfg!(windows) {
+            &b"a\r\nb"[..]
+        } else {
+            b"a\nb"
+        }
+    );
+    assert!(Newline::from_arg_value("cr").is_err());
+}
+
+#[test]
+fn output_normalization() {
+    let output = run_file("programs/hello_world.bf").unwrap() + "\r\n1 2 \r\n\n";
+    assert_eq!(Normalize::ALL.apply(&output), "Hello World!\n1 2");
+    assert!(Normalize::ALL.equal(&output, "Hello World!\n1 2\n"));
+    assert!(!Normalize::default().equal(&output, "Hello World!\n1 2\n"));
+    assert_eq!(
+        Normalize {
+            trailing_whitespace: true,
+            ..Default::default()
+        }
+        .apply("1 \r\n2 "),
+        "1 \r\n2"
+    );
+}
+
+#[test]
+fn snapshots() {
+    let mut output = Vec::new();
+    let source = std::fs::read_to_string("programs/hello_world.bf").unwrap();
+    let mut interpreter =
+        Interpreter::new_with_io(&source, Box::new(&[] as &[u8]), Box::new(&mut output)).unwrap();
+    for _ in 0..40 {
+        interpreter.run_step().unwrap();
+    }
+    let snapshot = interpreter.snapshot();
+    interpreter.run_forever().unwrap();
+    interpreter.restore(snapshot);
+    interpreter.run_forever().unwrap();
+    drop(interpreter);
+    // everything after the snapshot is output twice
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "Hello World!ello World!"
+    );
+}
+
+#[test]
+fn paranoid() {
+    for wrap_mode in [WrapMode::Torus, WrapMode::Lahey, WrapMode::Reflect] {
+        for program in [
+            "programs/hello_world.bf",
+            "programs/kquine1.bf",
+            "programs/dna1.bf",
+        ] {
+            let source = std::fs::read_to_string(program).unwrap();
+            let mut interpreter = Interpreter::new_with_io(
+                &source,
+                Box::new(&[] as &[u8]),
+                Box::new(std::io::sink()),
+            )
+            .unwrap()
+            .with_wrap_mode(wrap_mode)
+            .with_paranoid(true)
+            .with_sandbox(Sandbox {
+                max_steps: Some(100_000),
+                ..Sandbox::default()
+            });
+            match interpreter.run_forever() {
+                Ok(()) | Err(Error::StepLimit(_)) => {}
+                Err(why) => panic!("{} with {:?}: {}", program, wrap_mode, why),
+            }
+        }
+    }
+    assert_eq!(Error::InvariantViolation("test").exit_code(), 10);
+}
+
+#[test]
+fn stack_stats() {
+    let run = |stats| {
+        let mut interpreter = Interpreter::new_with_io(
+            "123$$4:::$@",
+            Box::new(&[] as &[u8]),
+            Box::new(std::io::sink()),
+        )
+        .unwrap()
+        .with_stack_capacity(1000)
+        .with_stack_stats(stats);
+        interpreter.run_forever().unwrap();
+        (interpreter.peak_stack_depth(), interpreter.stack_capacity())
+    };
+    let (peak, capacity) = run(true);
+    assert_eq!(peak, Some(5));
+    assert!(capacity >= 1000);
+    assert_eq!(run(false).0, None);
+}
+
+#[test]
+fn number_output() {
+    let mut buffer = [0; crate::NUMBER_BUFFER_SIZE];
+    for number in [0, 7, -7, 10, 1234567890, Int::MAX, Int::MIN, Int::MIN + 1] {
+        assert_eq!(
+            crate::format_number(number, &mut buffer),
+            format!("{} ", number).as_bytes()
+        );
+    }
+}
+
+#[test]
+fn string_spaces() {
+    fn run_with(
+        source: &str,
+        wrap_mode: WrapMode,
+        string_spaces: StringSpaces,
+    ) -> (Vec<Int>, usize) {
+        let mut interpreter =
+            Interpreter::new_with_io(source, Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+                .unwrap()
+                .with_wrap_mode(wrap_mode)
+                .with_string_spaces(string_spaces);
+        interpreter.run_forever().unwrap();
+        (interpreter.stack().to_vec(), interpreter.steps())
+    }
+    let a = b'a' as Int;
+    let space = b' ' as Int;
+
+    assert_eq!(
+        run_with("\"a   a\"@", WrapMode::Torus, StringSpaces::Literal),
+        (vec![a, space, space, space, a], 8)
+    );
+    assert_eq!(
+        run_with("\"a   a\"@", WrapMode::Torus, StringSpaces::Sgml),
+        (vec![a, space, a], 6)
+    );
+    // strings wrapping around the edge of the playfield
+    let at = b'@' as Int;
+    let (stack, _) = run_with("\"@", WrapMode::Torus, StringSpaces::Literal);
+    assert_eq!(stack.len(), 79);
+    assert_eq!(stack[0], at);
+    assert!(stack[1..].iter().all(|&value| value == space));
+    assert_eq!(
+        run_with("\"@", WrapMode::Torus, StringSpaces::Sgml).0,
+        [at, space]
+    );
+    assert_eq!(
+        run_with("\"@", WrapMode::Lahey, StringSpaces::Literal).0,
+        [at]
+    );
+    // a string mode running along a line of only spaces terminates
+    let mut interpreter =
+        Interpreter::new_with_io("\"", Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+            .unwrap()
+            .with_string_spaces(StringSpaces::Sgml);
+    for _ in 0..10 {
+        interpreter.run_step().unwrap();
+    }
+}
+
+#[test]
+fn manifest() {
+    let directory = tempfile::tempdir().unwrap();
+    let write =
+        |name: &str, contents: &str| std::fs::write(directory.path().join(name), contents).unwrap();
+    write(
+        "manifest.toml",
+        "# a table stamped next to the program\n\
+         program = \"main.bf\"\n\
+         stdin = \"null\"\n\
+         standard = \"93\" # the original\n\
+         max_steps = 1_000\n\
+         annotations = \"notes.txt\"\n\
+         \n\
+         [[stamp]]\n\
+         file = \"table.bf\"\n\
+         x = 6\n\
+         y = 1\n",
+    );
+    write("main.bf", "61g,@");
+    write("table.bf", "A\n");
+
+    let manifest = Manifest::load(directory.path()).unwrap();
+    assert_eq!(manifest.program, directory.path().join("main.bf"));
+    assert_eq!(manifest.stdin, Some(InputSource::Null));
+    assert_eq!(manifest.standard, Some(LanguageStandard::Befunge93));
+    assert_eq!(manifest.max_steps, Some(1000));
+    assert_eq!(manifest.stamps.len(), 1);
+    assert_eq!(
+        manifest.annotations,
+        Some(directory.path().join("notes.txt"))
+    );
+    let mut sandbox = Sandbox::default();
+    manifest.limit(&mut sandbox);
+    assert_eq!(sandbox.max_steps, Some(1000));
+
+    let mut grid = Interpreter::parse_grid("61g,@").unwrap();
+    manifest.stamp(&mut grid).unwrap();
+    let mut output = Vec::new();
+    let mut interpreter =
+        Interpreter::new_with_io_and_grid(grid, Box::new(&[] as &[u8]), Box::new(&mut output));
+    interpreter.run_forever().unwrap();
+    drop(interpreter);
+    assert_eq!(output, b"A");
+
+    for invalid in [
+        "stdin = \"in.txt\"",
+        "program = main.bf",
+        "program = \"main.bf\"\nsteps = 5",
+        "program = \"main.bf\"\nmax_steps = -1",
+        "program = \"main.bf\"\n[[stamp]]\nx = 3",
+    ] {
+        assert_eq!(
+            Manifest::parse(invalid),
+            Err(Error::InvalidManifest(String::new()))
+        );
+    }
+    let mut grid = Interpreter::parse_grid("61g,@").unwrap();
+    manifest.stamps[0]
+        .apply(&mut grid, &"x".repeat(80))
+        .unwrap_err();
+}
+
+#[test]
+fn incremental_reachability() {
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    let grid = Interpreter::parse_grid("v\n>  @\n").unwrap();
+    let mut reachability = Reachability::new(grid, PC::default());
+    assert!(reachability.is_reachable(0, 1));
+    assert!(reachability.is_reachable(3, 1));
+    assert!(!reachability.is_reachable(1, 0));
+    assert_eq!(reachability.reachable_cells(), 5);
+    // edits of dead code and edits that don't change control flow need no recomputation
+    reachability.edit(5, 0, b'v');
+    reachability.edit(1, 1, b'1');
+    assert_eq!(reachability.recomputations(), 0);
+    // neither do edits that only open up new paths
+    reachability.edit(2, 1, b'?');
+    assert!(reachability.is_reachable(2, 0));
+    assert_eq!(reachability.recomputations(), 0);
+    // cutting off a path does
+    reachability.edit(1, 1, b'@');
+    assert!(!reachability.is_reachable(2, 1));
+    assert_eq!(reachability.recomputations(), 1);
+
+    // the incremental analysis must always agree with analyzing the edited grid from scratch
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0xed17);
+    let instructions = b" v<>^_|#?@1";
+    let grid = Interpreter::parse_grid(">v\n^<").unwrap();
+    let mut reachability = Reachability::new(grid, PC::default());
+    for _ in 0..300 {
+        let (x, y) = (rng.gen_range(0..8), rng.gen_range(0..4));
+        let value = instructions[rng.gen_range(0..instructions.len())];
+        reachability.edit(x, y, value);
+        let fresh = Reachability::new(reachability.grid().clone(), PC::default());
+        for y in 0..25 {
+            for x in 0..80 {
+                assert_eq!(reachability.is_reachable(x, y), fresh.is_reachable(x, y));
+            }
+        }
+    }
+}
+
+#[test]
+fn exit_reasons() {
+    let run = |program: &str, input: &[u8]| {
+        crate::run(RunRequest {
+            program: program.to_string(),
+            input: input.to_vec(),
+            max_steps: Some(1_000_000),
+            ..Default::default()
+        })
+        .exit
+    };
+    assert_eq!(run("~~,,@", b"ab"), ExitReason::Finished);
+    // reading past the end of the input once is fine
+    assert_eq!(run("~.@", b""), ExitReason::Finished);
+    // a program skipping its input that doesn't check for EOF never stops
+    assert_eq!(run("~$", b"abc"), ExitReason::InputEofLoop);
+    assert_eq!(run("1", b""), ExitReason::StepLimit);
+    assert_eq!(
+        run("x", b""),
+        ExitReason::Error(Error::IllegalCommand { command: b'x' })
+    );
+    assert_eq!(ExitReason::InputEofLoop.name(), "input-eof-loop");
+    assert_eq!(ExitReason::Finished.to_string(), "terminated via @");
+}
+
+#[test]
+fn program_arguments() {
+    let run = |source: &str, arguments: &[&str], input: &'static [u8]| {
+        let arguments = arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect::<Vec<_>>();
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new_with_io(
+            source,
+            crate::with_arguments(&arguments, Box::new(input)),
+            Box::new(&mut output),
+        )
+        .unwrap();
+        interpreter.run_forever().unwrap();
+        drop(interpreter);
+        output
+    };
+    // arguments come before the input
+    assert_eq!(run("&&&++.@", &["12", "-3"], b"5"), b"14 ");
+    assert_eq!(run("~,~,~,~,@", &["ab"], b"c"), b"ab\nc");
+    assert_eq!(run("~,~,@", &[], b"cd"), b"cd");
+}
+
+#[test]
+fn befunge98() {
+    fn run98(source: &str, input: &'static [u8]) -> (String, Vec<Int>, Int) {
+        let mut output = Vec::new();
+        let mut interpreter =
+            Interpreter::new_with_io(source, Box::new(input), Box::new(&mut output))
+                .unwrap()
+                .with_standard(LanguageStandard::Befunge98)
+                .with_sandbox(Sandbox {
+                    max_steps: Some(100_000),
+                    ..Sandbox::default()
+                });
+        interpreter.run_forever().unwrap();
+        let result = (interpreter.stack().to_vec(), interpreter.exit_status());
+        drop(interpreter);
+        (String::from_utf8(output).unwrap(), result.0, result.1)
+    }
+    let stack = |source| run98(source, b"").1;
+
+    assert_eq!(stack("af@"), [10, 15]);
+    // the program counter wraps around the program's bounds instead of the 80x25 grid
+    assert_eq!(stack("\"@"), [b'@' as Int]);
+    // which grow with cells written by p
+    assert_eq!(stack("\"@\"f0p"), []);
+    assert_eq!(stack("123n4@"), [4]);
+    assert_eq!(stack("'@'a@"), [b'@' as Int, b'a' as Int]);
+    // s stores into the next cell, which is then skipped
+    assert_eq!(stack("'@s 1@"), [1]);
+    assert_eq!(stack("1;2 3;4@"), [1, 4]);
+    assert_eq!(stack("1j23@"), [3]);
+    assert_eq!(stack("v\n>1-j@\n"), []);
+    // unknown instructions and reading at EOF reflect
+    assert_eq!(stack("1h@"), [1, 1]);
+    assert_eq!(run98("~@", b"").1, []);
+    assert_eq!(run98("2&@", b"").1, [2, 2]);
+    // as does input that isn't a number, and , outputs values beyond ASCII as their lowest byte
+    assert_eq!(run98("2&@", b"x").1, [2, 2]);
+    assert_eq!(run98("88*5*1+,@", b"").0, "A");
+    // [ ] w turn the program counter
+    assert_eq!(stack("v2\n>[\n @"), [2]);
+    assert_eq!(stack("v\n>]\n 2\n @"), [2]);
+    assert_eq!(stack("21w3@\n  4\n  @"), [4]);
+    assert_eq!(stack("12w3@\n  4\n  @"), []);
+    assert_eq!(stack("11w3@\n  4\n  @"), [3]);
+    assert_eq!(run98("7q", b"").2, 7);
+    // k runs the next instruction that many times and then skips it
+    assert_eq!(stack("13k:@"), [1, 1, 1, 1]);
+    assert_eq!(stack("10k:2@"), [1, 2]);
+    assert_eq!(stack("12k ;x; :@"), [1, 1, 1]);
+    // instructions that move the program counter move it from the k
+    assert_eq!(stack("1kv3@\n 2\n @"), [2]);
+    // k reflects on another k
+    assert_eq!(stack("1kk2@"), [1]);
+    // and every run counts towards the step limit
+    let mut interpreter = Interpreter::new_with_io(
+        "'~::**k:@",
+        Box::new(&[] as &[u8]),
+        Box::new(std::io::sink()),
+    )
+    .unwrap()
+    .with_standard(LanguageStandard::Befunge98)
+    .with_sandbox(Sandbox {
+        max_steps: Some(1000),
+        ..Sandbox::default()
+    });
+    assert_eq!(interpreter.run_forever(), Err(Error::StepLimit(1000)));
+    // x sets the delta, which may make the program counter fly over cells
+    assert_eq!(stack("v\n>01x3@\n   4\n   @"), [4]);
+    assert_eq!(stack("v\n>11x\n    2\n     @"), [2]);
+    // and it wraps around to where the line along its delta enters the bounds again
+    assert_eq!(stack("2#@0x 1 2 3"), [1, 2, 3, 2]);
+
+    // { and } move values between stacks and set the storage offset for g and p
+    let mut interpreter = Interpreter::new_with_io(
+        "123 2{ 00g @",
+        Box::new(&[] as &[u8]),
+        Box::new(std::io::sink()),
+    )
+    .unwrap()
+    .with_standard(LanguageStandard::Befunge98);
+    interpreter.run_forever().unwrap();
+    assert_eq!(interpreter.stack(), [2, 3, b' ' as Int]);
+    assert_eq!(interpreter.stack_stack(), [vec![1, 0, 0]]);
+    assert_eq!(stack("123 2{ 4 2} @"), [1, 3, 4]);
+    assert_eq!(stack("12 0{ 1u @"), [0]);
+    // } and u without a second stack reflect
+    assert_eq!(stack("1}@"), [1]);
+    assert_eq!(stack("1u@"), [1]);
+
+    // Befunge-98 programs still need to be selected explicitly
+    assert_eq!(
+        Interpreter::new("a@").unwrap().run_forever(),
+        Err(Error::IllegalCommand { command: b'a' })
+    );
+}
+
+#[test]
+fn overlays() {
+    let overlay = Overlay::parse("# print a 4\n1,0 '4'\n\n 5,0 ' '\n0,1 '''\n").unwrap();
+    assert_eq!(overlay.patches.len(), 3);
+    assert_eq!(overlay.to_string(), "1,0 '4'\n5,0 ' '\n0,1 '''\n");
+    assert_eq!(Overlay::parse(&overlay.to_string()).unwrap(), overlay);
+    for invalid in ["3,0 4", "3 '4'", "x,0 '4'", "3,0 '44'", "3,0 'ä'"] {
+        assert!(matches!(
+            Overlay::parse(invalid),
+            Err(Error::InvalidOverlay(_))
+        ));
+    }
+
+    let base = Interpreter::parse_grid("12.@ x").unwrap();
+    let mut grid = base.clone();
+    overlay.apply(&mut grid).unwrap();
+    assert_eq!(&grid[0][..6], b"14.@  ");
+    assert_eq!(grid[1][0], b'\'');
+    // the diff of the patched grid is the overlay again
+    assert_eq!(Overlay::diff(&base, &grid), overlay);
+    assert_eq!(Overlay::diff(&grid, &grid), Overlay::default());
+
+    assert!(matches!(
+        Overlay::parse("80,0 'x'").unwrap().apply(&mut grid),
+        Err(Error::InvalidOverlay(_))
+    ));
+}
+
+#[test]
+fn concurrency() {
+    let run98 = |program: &str| {
+        let report = crate::run(RunRequest {
+            program: program.into(),
+            max_steps: Some(10_000),
+            standard: Some(LanguageStandard::Befunge98),
+            ..Default::default()
+        });
+        (report.exit, String::from_utf8(report.output).unwrap())
+    };
+    // the new instruction pointer heads left and runs first, then they take turns
+    assert_eq!(
+        run98("t1.2.@.3.4"),
+        (ExitReason::Finished, "4 1 3 2 ".into())
+    );
+    // a timeout doesn't change how often they take turns
+    let report = crate::run(RunRequest {
+        program: "t1.2.@.3.4".into(),
+        timeout: Some(std::time::Duration::from_secs(10)),
+        standard: Some(LanguageStandard::Befunge98),
+        ..Default::default()
+    });
+    assert_eq!(report.output, b"4 1 3 2 ");
+    // q ends all instruction pointers
+    assert_eq!(run98("t0q>"), (ExitReason::Finished, "".into()));
+    // @ only ends the current one
+    assert_eq!(run98("t1.@"), (ExitReason::Finished, "1 ".into()));
+
+    let mut interpreter =
+        Interpreter::new_with_io("9t.@", Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+            .unwrap()
+            .with_standard(LanguageStandard::Befunge98);
+    interpreter.run_step().unwrap();
+    interpreter.run_step().unwrap();
+    assert_eq!(interpreter.ip_count(), 2);
+    let snapshot = interpreter.snapshot();
+    assert_eq!(
+        snapshot.other_ips,
+        [InstructionPointer {
+            program_counter: PC {
+                position: Position::new(0, 0, 0),
+                delta: Direction::Left.into()
+            },
+            string_mode: false,
+            stack: vec![9],
+            stack_stack: Vec::new(),
+            storage_offset: Position::ZERO,
+            id: 1,
+            semantics: Default::default(),
+        }]
+    );
+    // the new instruction pointer's 9 and the current one's . run next
+    interpreter.run_step().unwrap();
+    interpreter.run_step().unwrap();
+    assert_eq!(interpreter.stack(), []);
+    interpreter.restore(snapshot);
+    interpreter.run_forever().unwrap();
+    assert_eq!(interpreter.ip_count(), 1);
+
+    // the order in which the instruction pointers take turns, starting with the current one
+    let schedule = |deterministic_scheduling| {
+        let mut interpreter =
+            Interpreter::new_with_io("2kt#@t", Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+                .unwrap()
+                .with_standard(LanguageStandard::Befunge98)
+                .with_deterministic_scheduling(deterministic_scheduling);
+        for _ in 0..7 {
+            interpreter.run_step().unwrap();
+        }
+        let snapshot = interpreter.snapshot();
+        [snapshot.ip_id]
+            .into_iter()
+            .chain(snapshot.other_ips.iter().map(|ip| ip.id))
+            .collect::<Vec<_>>()
+    };
+    // new instruction pointers run right before their parents, or in order of creation
+    assert_eq!(schedule(false), [2, 0, 3, 1, 4]);
+    assert_eq!(schedule(true), [2, 3, 4, 0, 1]);
+}
+
+#[test]
+fn ip_profile() {
+    let profile = |program: &str, enabled| {
+        let mut interpreter =
+            Interpreter::new_with_io(program, Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+                .unwrap()
+                .with_standard(LanguageStandard::Befunge98)
+                .with_ip_profile(enabled);
+        interpreter.run_forever().unwrap();
+        interpreter
+            .ip_profile()
+            .map(|usage| usage.iter().map(|ip| (ip.id, ip.steps)).collect::<Vec<_>>())
+    };
+    assert_eq!(profile("1.@", false), None);
+    assert_eq!(profile("1.@", true), Some(vec![(0, 3)]));
+    // the new instruction pointer stops one step before its parent, and both keep their steps
+    assert_eq!(profile("t1.2.@.3.4", true), Some(vec![(0, 6), (1, 5)]));
+}
+
+#[test]
+fn cell_values() {
+    let run = |program: &str, standard, cell_values| {
+        let report = crate::run(RunRequest {
+            program: program.into(),
+            standard: Some(standard),
+            cell_values,
+            ..Default::default()
+        });
+        assert_eq!(report.exit, ExitReason::Finished);
+        String::from_utf8(report.output).unwrap()
+    };
+    // p and g round trips of -1, 65, 200 and 1000
+    let round_trips = "01-00p00g.\"A\"00p00g.\"d\"2*00p00g.\"d\"55+*00p00g.@";
+    for (cell_values, output) in [
+        (None, "-1 65 -56 -24 "),
+        (Some(CellValues::Signed), "-1 65 -56 -24 "),
+        (Some(CellValues::Unsigned), "255 65 200 232 "),
+        (Some(CellValues::Full), "-1 65 200 1000 "),
+    ] {
+        assert_eq!(
+            run(round_trips, LanguageStandard::Befunge93, cell_values),
+            output
+        );
+    }
+    // Befunge-98 stores full values by default, which string mode and ' read back too
+    assert_eq!(
+        run(round_trips, LanguageStandard::Befunge98, None),
+        "-1 65 200 1000 "
+    );
+    assert_eq!(
+        run("\"d\"a*90p\"X\".@", LanguageStandard::Befunge98, None),
+        "1000 "
+    );
+    assert_eq!(
+        run("\"d\"2*90p'X.@", LanguageStandard::Befunge98, None),
+        "200 "
+    );
+    assert_eq!(
+        run(
+            "\"d\"2*90p'X.@",
+            LanguageStandard::Befunge98,
+            Some(CellValues::Signed)
+        ),
+        "-56 "
+    );
+
+    let mut grid = Interpreter::parse_grid("@").unwrap();
+    assert_eq!(grid.set_value(1, 0, -5), Some(b' '));
+    assert_eq!(grid.get_value(1, 0), Some(-5));
+    assert_eq!(grid.get(1, 0), Some(crate::WIDE_CELL));
+    // a byte replaces the full value
+    grid.set(1, 0, b'x');
+    assert_eq!(grid.get_value(1, 0), Some(b'x' as Int));
+    assert_eq!(grid.get_value(0, 0), Some(b'@' as Int));
+    assert_eq!(grid.get_value(80, 0), None);
+}
+
+#[test]
+fn unbounded_grid() {
+    let run98 = |program: String, wrap_mode| {
+        let report = crate::run(RunRequest {
+            program,
+            max_steps: Some(10_000),
+            standard: Some(LanguageStandard::Befunge98),
+            wrap_mode: Some(wrap_mode),
+            ..Default::default()
+        });
+        (report.exit, String::from_utf8(report.output).unwrap())
+    };
+
+    // p and g address cells far outside of the 80x25 area; unwritten cells are spaces
+    assert_eq!(
+        run98(
+            "\"A\"aa*:*:0\\-paa*:*:0\\-g,aa*:*:g.@".into(),
+            WrapMode::Torus
+        ),
+        (ExitReason::Finished, "A32 ".into())
+    );
+    // programs larger than 80x25 load, and Lahey wrapping reaches beyond the 80x25 area
+    let wide = format!("1.{:97}@", "");
+    assert_eq!(
+        run98(wide.clone(), WrapMode::Lahey),
+        (ExitReason::Finished, "1 ".into())
+    );
+    let tall = format!("v{}\n2\n.\n@", "\n".repeat(30));
+    assert_eq!(
+        run98(tall.clone(), WrapMode::Lahey),
+        (ExitReason::Finished, "2 ".into())
+    );
+    // Befunge-93 still rejects them
+    assert_eq!(
+        crate::run(RunRequest {
+            program: wide,
+            ..Default::default()
+        })
+        .exit,
+        ExitReason::Error(Error::InvalidGridSize(100, 1))
+    );
+
+    let mut grid =
+        Interpreter::parse_grid_for_standard(&tall, LanguageStandard::Befunge98).unwrap();
+    assert!(grid.is_unbounded() && grid.has_far_cells());
+    assert_eq!(grid.get(0, 31), Some(b'2'));
+    assert_eq!(grid.set(-5, 1000, b'x'), Some(b' '));
+    assert_eq!(grid.get(-5, 1000), Some(b'x'));
+    assert_eq!(grid.bounds().min, Position::new(-5, 0, 0));
+    assert_eq!(grid.bounds().max, Position::new(0, 1000, 0));
+    // a snapshot restores the cells outside of the 80x25 area too
+    let snapshot = grid.clone();
+    grid.set(-5, 1000, b'y');
+    grid.replace_cells(&snapshot);
+    assert_eq!(grid.get(-5, 1000), Some(b'x'));
+    grid.set_unbounded(false);
+    assert_eq!(grid.get(-5, 1000), None);
+    assert!(!grid.has_far_cells());
+
+    // cells at the limits of i64 make the bounds wider than i64
+    let report = crate::run(RunRequest {
+        program: "'A&0p'A&0p1j@@".into(),
+        input: b"9223372036854775807 -9223372036854775807".to_vec(),
+        max_steps: Some(10_000),
+        standard: Some(LanguageStandard::Befunge98),
+        ..Default::default()
+    });
+    assert_eq!(report.exit, ExitReason::Finished);
+    let bounds = Bounds {
+        min: Position::new(-5, i64::MIN, 0),
+        max: Position::new(i64::MAX, 5, 0),
+    };
+    assert_eq!(bounds.size(), Position::new(i64::MAX, i64::MAX, 1));
+    // stepping beyond them wraps around to the other side
+    for (position, direction, wrapped) in [
+        (
+            Position::new(i64::MAX, 0, 0),
+            Direction::Right,
+            Position::new(-5, 0, 0),
+        ),
+        (
+            Position::new(0, i64::MIN, 0),
+            Direction::Up,
+            Position::new(0, 5, 0),
+        ),
+    ] {
+        let mut program_counter = PC {
+            position,
+            delta: direction.into(),
+        };
+        program_counter.step();
+        program_counter.constrain(WrapMode::Lahey, &bounds);
+        assert_eq!(program_counter.position, wrapped);
+    }
+}
+
+#[test]
+fn lazy_loading() {
+    // a header, a first line reaching beyond 80 columns, and lines below the 80x25 area
+    let source = format!(
+        ";; title: far\r\n>{}1.v\r\n{}{}x{}y\n",
+        " ".repeat(98),
+        "\n".repeat(30),
+        " ".repeat(3),
+        " ".repeat(200)
+    );
+    let eager = Interpreter::parse_grid_for_standard(
+        Metadata::parse(&source).unwrap().1,
+        LanguageStandard::Befunge98,
+    )
+    .unwrap();
+    let (metadata, lazy) = LazySource::from_bytes(source.clone().into_bytes()).unwrap();
+    assert_eq!(metadata.title.as_deref(), Some("far"));
+    let mut grid = Grid::lazy(std::sync::Arc::new(lazy));
+    assert!(grid.is_unbounded() && grid.has_far_cells());
+    assert_eq!(grid.bounds(), eager.bounds());
+    for (x, y) in [(0, 0), (99, 0), (101, 0), (3, 31), (204, 31), (5, 1000)] {
+        assert_eq!(grid.get(x, y), eager.get(x, y));
+    }
+
+    // writing to a chunk copies the rest of it from the source
+    assert_eq!(grid.set(101, 0, b'@'), Some(b'v'));
+    assert_eq!(grid.get(100, 0), Some(b'.'));
+    assert_eq!(grid.set(204, 31, b'y'), Some(b'y'));
+    assert_eq!(grid.get(3, 31), Some(b'x'));
+
+    // a lazily loaded program runs like any other
+    let (_, lazy) = LazySource::from_bytes(format!(">{}1.@", " ".repeat(98)).into()).unwrap();
+    let mut output = Vec::new();
+    let mut interpreter = Interpreter::new_with_io_and_grid(
+        Grid::lazy(std::sync::Arc::new(lazy)),
+        Box::new(&[] as &[u8]),
+        Box::new(&mut output),
+    )
+    .with_standard(LanguageStandard::Befunge98);
+    interpreter.run_forever().unwrap();
+    drop(interpreter);
+    assert_eq!(output, b"1 ");
+
+    // only Befunge-98 programs load lazily
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut file, format!(">{}1.@", " ".repeat(98)).as_bytes()).unwrap();
+    assert_eq!(
+        run_interpreter(Arguments {
+            input: file.path().into(),
+            lazy: true,
+            language_standard: LanguageStandard::Befunge93,
+            ..Default::default()
+        }),
+        Err(Error::InvalidGridSize(102, 1))
+    );
+
+    assert_eq!(
+        LazySource::from_bytes("@ä".into()).unwrap_err(),
+        Error::NonAsciiSource('ä')
+    );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn mapped_loading() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let source = format!(
+        ";; title: mapped\n>{}1.@\n{}x",
+        " ".repeat(98),
+        "\n".repeat(30)
+    );
+    std::io::Write::write_all(&mut file, source.as_bytes()).unwrap();
+    let (metadata, lazy) = LazySource::open(file.path()).unwrap();
+    assert_eq!(metadata.title.as_deref(), Some("mapped"));
+    assert_eq!(lazy.source(), source.as_bytes());
+    let grid = Grid::lazy(std::sync::Arc::new(lazy));
+    assert_eq!(grid.get(100, 0), Some(b'.'));
+    assert_eq!(grid.get(0, 31), Some(b'x'));
+    assert_eq!(grid.get(1, 31), Some(b' '));
+}
+
+#[test]
+fn packages() {
+    let directory = tempfile::tempdir().unwrap();
+    let write = |name: &str, contents: &str| {
+        let path = directory.path().join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap()
+    };
+    write(
+        "manifest.toml",
+        "program = \"main.bf\"\nstdin = \"data/input.txt\"\nexpected = \"data/expected.txt\"\nstandard = \"93\"\n",
+    );
+    write("main.bf", ";; title: Echo\n~,~,@");
+    write("data/input.txt", "hi");
+    write("data/expected.txt", "hi\n");
+
+    let package = Package::from_dir(directory.path()).unwrap();
+    assert_eq!(
+        package
+            .files
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>(),
+        [
+            "data/expected.txt",
+            "data/input.txt",
+            "main.bf",
+            "manifest.toml"
+        ]
+    );
+    let zip = package.to_zip();
+    assert_eq!(Package::from_zip(&zip).unwrap(), package);
+
+    // running and testing a package
+    let file = directory.path().join("echo.bfpkg");
+    run_interpreter(Arguments {
+        run_dir: Some(directory.path().to_owned()),
+        pack: Some(file.clone()),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(std::fs::read(&file).unwrap(), zip);
+    run_interpreter_with_output(
+        Arguments {
+            run_dir: Some(file),
+            test: true,
+            language_standard: LanguageStandard::Befunge93,
+            ..Default::default()
+        },
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+
+    // corrupt packages and packages escaping their directory are rejected
+    let mut corrupt = zip.clone();
+    corrupt[47] ^= 1;
+    assert_eq!(
+        Package::from_zip(&corrupt),
+        Err(Error::InvalidPackage(String::new()))
+    );
+    assert!(Package::from_zip(b"not a zip").is_err());
+    let escaping = Package {
+        files: vec![("../escape.bf".to_string(), b"@".to_vec())],
+    };
+    assert!(Package::from_zip(&escaping.to_zip()).is_err());
+}
+
+#[test]
+fn stack_files() {
+    let stack = [1, -2, Int::MAX, 0];
+    for format in [StackFormat::Text, StackFormat::Binary] {
+        let mut data = Vec::new();
+        format.write(&stack, &mut data).unwrap();
+        assert_eq!(format.read(&data).unwrap(), stack);
+    }
+    let mut data = Vec::new();
+    StackFormat::Text.write(&stack[..2], &mut data).unwrap();
+    assert_eq!(data, b"1\n-2\n");
+    assert_eq!(StackFormat::Text.read(b" 3\r\n4").unwrap(), [3, 4]);
+    assert!(matches!(
+        StackFormat::Text.read(b"1\nx\n"),
+        Err(Error::InvalidStack(_))
+    ));
+    assert!(matches!(
+        StackFormat::Binary.read(&[0; 9]),
+        Err(Error::InvalidStack(_))
+    ));
+
+    // a loaded stack is where the program left off
+    let grid = Interpreter::parse_grid("+.@").unwrap();
+    let mut output = Vec::new();
+    let mut interpreter =
+        Interpreter::new_with_io_and_grid(grid, Box::new(&[] as &[u8]), Box::new(&mut output))
+            .with_stack(vec![5, 2, 3]);
+    interpreter.run_forever().unwrap();
+    assert_eq!(interpreter.stack(), [5]);
+    drop(interpreter);
+    assert_eq!(output, b"5 ");
+}
+
+#[test]
+fn state_files() {
+    // loads ROMA, writes outside of the 80x25 area and opens a new stack before reading the input
+    let source = "\"AMOR\"4($$701-01-p342{&I++.@";
+    let mut interpreter =
+        Interpreter::new_with_io(source, Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+            .unwrap()
+            .with_standard(LanguageStandard::Befunge98);
+    while interpreter.position() != Position::new(22, 0, 0) {
+        interpreter.run_step().unwrap();
+    }
+    let text = State {
+        standard: LanguageStandard::Befunge98,
+        snapshot: interpreter.snapshot(),
+    }
+    .to_string();
+    for line in [
+        "cell -1 -1 7",
+        "ip 22 0 > 0 22 0 0",
+        "stack 3 4",
+        "below 0 0",
+        "semantics I 1380928833",
+    ] {
+        assert!(text.lines().any(|text| text == line), "{}", line);
+    }
+    let state = State::parse(&text).unwrap();
+    assert_eq!(state.to_string(), text);
+
+    // the resumed program continues with new input
+    let mut output = Vec::new();
+    let mut resumed = Interpreter::new_with_io_and_grid(
+        state.snapshot.grid.clone(),
+        Box::new(b"10\n" as &[u8]),
+        Box::new(&mut output),
+    )
+    .with_standard(state.standard);
+    resumed.restore(state.snapshot);
+    resumed.run_forever().unwrap();
+    drop(resumed);
+    assert_eq!(output, b"15 ");
+
+    // a program counter beyond the 80x25 area of an unbounded grid resumes where it stopped
+    let state = State::parse(
+        "boxfunge state 1\nstandard 98\ncell 12 0 51\ncell 13 0 46\ncell 92 0 50\ncell 93 0 46\n\
+         cell 94 0 64\nip 92 0 > 0 0 0 0\n",
+    )
+    .unwrap();
+    let mut output = Vec::new();
+    let mut resumed = Interpreter::new_with_io_and_grid(
+        state.snapshot.grid.clone(),
+        Box::new(&[] as &[u8]),
+        Box::new(&mut output),
+    )
+    .with_standard(state.standard);
+    resumed.restore(state.snapshot);
+    resumed.run_forever().unwrap();
+    drop(resumed);
+    assert_eq!(output, b"2 ");
+
+    for text in [
+        "",
+        "boxfunge state 1\nstandard 98\n",
+        "boxfunge state 1\nstandard 93\ncell 100 0 1\nip 0 0 > 0 0 0 0",
+        "boxfunge state 1\nstandard 98\nbounds 5 0 1 0\nip 0 0 > 0 0 0 0",
+    ] {
+        assert!(
+            matches!(State::parse(text), Err(Error::InvalidState(_))),
+            "{}",
+            text
+        );
+    }
+
+    // Trefunge positions have three coordinates
+    let text =
+        "boxfunge state 1\nstandard trefunge\nbounds 0 0 0 2 0 1\ncell 0 0 1 49\ncell 1 0 1 46\n\
+                cell 2 0 1 64\nip 0 0 1 > 0 0 0 0 0\nstack\n";
+    let state = State::parse(text).unwrap();
+    assert_eq!(state.to_string(), text);
+    let mut output = Vec::new();
+    let mut resumed = Interpreter::new_with_io_and_grid(
+        state.snapshot.grid.clone(),
+        Box::new(&[] as &[u8]),
+        Box::new(&mut output),
+    )
+    .with_standard(state.standard);
+    resumed.restore(state.snapshot);
+    resumed.run_forever().unwrap();
+    drop(resumed);
+    assert_eq!(output, b"1 ");
+    assert!(matches!(
+        State::parse("boxfunge state 1\nstandard trefunge\nip 0 0 > 0 0 0 0"),
+        Err(Error::InvalidState(_))
+    ));
+}
+
+#[test]
+fn endless_loops() {
+    fn find(source: &str) -> Option<crate::EndlessLoop> {
+        crate::find_endless_loop(&Interpreter::parse_grid(source).unwrap(), PC::default())
+    }
+    let ring = find(">v\n^<").unwrap();
+    assert_eq!(ring.length, 4);
+    assert_eq!(
+        ring.to_string(),
+        "1,0 (arriving >), repeating every 4 steps"
+    );
+    // the stack grows, but the _ only ever tests the 1 pushed right before it
+    assert!(find(">1_@").is_some());
+    assert!(find("\"a\"v\n   >$\"b\"^").is_some());
+
+    // counts down from 5 and terminates
+    assert_eq!(find("5>1-:v\n ^   _@"), None);
+    // never ends, but prints all the while
+    assert_eq!(find(">1.v\n^  <"), None);
+    assert_eq!(find("&>:v\n ^ _@"), None);
+    assert_eq!(find(">?<"), None);
+}
+
+#[test]
+fn input_timeout() {
+    /// Input that never arrives, like a terminal nobody types into.
+    struct Silent;
+    impl std::io::Read for Silent {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            loop {
+                std::thread::park();
+            }
+        }
+    }
+
+    // a program waiting for input stops at the time limit instead of waiting forever
+    let timeout = std::time::Duration::from_millis(50);
+    let input =
+        crate::ThreadedInput::new(Silent).with_deadline(std::time::Instant::now() + timeout);
+    let mut interpreter =
+        Interpreter::new_with_io("~@", Box::new(input), Box::new(std::io::sink()))
+            .unwrap()
+            .with_sandbox(Sandbox {
+                timeout: Some(timeout),
+                ..Sandbox::default()
+            });
+    let error = interpreter.run_forever().unwrap_err();
+    assert_eq!(error, Error::Timeout(timeout));
+    assert_eq!(error.exit_code(), 7);
+
+    // input that is there in time is read as usual
+    let timeout = std::time::Duration::from_secs(10);
+    let mut output = Vec::new();
+    let input = crate::ThreadedInput::new(b"12 3" as &[u8])
+        .with_deadline(std::time::Instant::now() + timeout);
+    let mut interpreter = Interpreter::new_with_io("&.&.@", Box::new(input), Box::new(&mut output))
+        .unwrap()
+        .with_sandbox(Sandbox {
+            timeout: Some(timeout),
+            ..Sandbox::default()
+        });
+    interpreter.run_forever().unwrap();
+    drop(interpreter);
+    assert_eq!(output, b"12 3 ");
+}
+
+#[test]
+fn system_info() {
+    let info = SystemInfo {
+        arguments: vec!["a.bf".into(), "x".into()],
+        environment: vec!["K=v".into()],
+        ..Default::default()
+    };
+    let stacks = StackStack {
+        top: vec![1, 2, 3],
+        below: vec![vec![4]],
+    };
+    let mut cells = info.cells(&MachineState {
+        ip_id: 7,
+        dimensions: 2,
+        position: Position::new(3, 4, 0),
+        delta: Position::new(-1, 0, 0),
+        storage_offset: Position::new(5, 6, 0),
+        bounds: Bounds::GRID,
+        stacks: &stacks,
+        files: true,
+        exec_paradigm: 2,
+    });
+    // read the cells in the order the specification lists them
+    cells.reverse();
+    // t, i and o are supported
+    assert_eq!(
+        cells[..3],
+        [7, std::mem::size_of::<Int>() as Int, HANDPRINT]
+    );
+    // the paradigm comes after the version
+    assert_eq!(cells[4..9], [2, std::path::MAIN_SEPARATOR as Int, 2, 7, 0]);
+    // vectors are pushed x first, so y comes first here
+    assert_eq!(cells[9..19], [4, 3, 0, -1, 6, 5, 0, 0, 24, 79]);
+    // no time, two stacks of which the top one comes first
+    assert_eq!(cells[19..24], [0, 0, 2, 3, 1]);
+    let strings: Vec<u8> = cells[24..].iter().map(|&cell| cell as u8).collect();
+    assert_eq!(strings, b"a.bf\0x\0\0K=v\0\0");
+
+    let hidden = SystemInfo::collect(
+        vec!["a.bf".into()],
+        &[SystemInfoItem::Arguments, SystemInfoItem::Time],
+        &SandboxProfile::Strict.sandbox(),
+    );
+    assert_eq!(hidden, SystemInfo::default());
+
+    let run98 = |program: &str| {
+        let report = crate::run(RunRequest {
+            program: program.into(),
+            standard: Some(LanguageStandard::Befunge98),
+            ..Default::default()
+        });
+        String::from_utf8(report.output).unwrap()
+    };
+    assert_eq!(
+        run98("1y.2y.9y.@"),
+        format!("1 {} 0 ", std::mem::size_of::<Int>())
+    );
+    // a count beyond the 26 values of the system information picks from the stack below it
+    assert_eq!(run98("739*y.@"), "7 ");
+    // exactly the number of values picks the last of them, the end of the empty environment
+    assert_eq!(run98("7d2*y..@"), "0 7 ");
+    assert_eq!(run98("0y.@"), "1 ");
+}
+
+#[test]
+fn determinism_audit() {
+    let audit = |program: &str, time: bool| {
+        let request = RunRequest {
+            standard: Some(LanguageStandard::Befunge98),
+            system_info: SystemInfo {
+                time,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let grid = Interpreter::parse_grid_for_standard(program, LanguageStandard::Befunge98);
+        audit_determinism(&grid.unwrap(), &request)
+    };
+    assert_eq!(audit("?12+.@", true), []);
+    assert_eq!(
+        audit("$1.@", false),
+        [Finding::EmptyStackPop {
+            step: 1,
+            position: Position::new(0, 0, 0),
+            instruction: b'$'
+        }]
+    );
+    // 50625 * 50625 only fits into 64-bit cells
+    assert!(matches!(
+        audit("ff*:*:*.@", false)[..],
+        [Finding::WideValue { step: 7, .. }]
+    ));
+    assert_eq!(
+        audit("0y@", true),
+        [Finding::Divergence {
+            step: 2,
+            position: Position::new(1, 0, 0)
+        }]
+    );
+    // a hidden time is the same in both runs
+    assert_eq!(audit("0y@", false), []);
+}
+
+#[test]
+fn fingerprints() {
+    /// A fingerprint whose `A` pushes a constant.
+    struct Constant(&'static str, Int);
+    impl Fingerprint for Constant {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+        fn instructions(&self) -> &'static [u8] {
+            b"A"
+        }
+        fn execute(&self, _: u8, interpreter: &mut Interpreter) -> Result<(), Error> {
+            interpreter.push(self.1);
+            Ok(())
+        }
+    }
+    static ANSWER: Constant = Constant("ANSR", 42);
+    static TWENTY: Constant = Constant("TWNY", 20);
+
+    let run98 = |program: &str| {
+        let grid = Interpreter::parse_grid_for_standard(program, LanguageStandard::Befunge98);
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new_with_io_and_grid(
+            grid.unwrap(),
+            Box::new(&[] as &[u8]),
+            Box::new(&mut output),
+        )
+        .with_standard(LanguageStandard::Befunge98)
+        .with_fingerprint(&ANSWER)
+        .with_fingerprint(&TWENTY);
+        interpreter.run_forever().unwrap();
+        drop(interpreter);
+        String::from_utf8(output).unwrap()
+    };
+    assert_eq!(run98("\"RSNA\"4(A.@"), "42 ");
+    // ( pushes the ID and 1
+    assert_eq!(run98("\"RSNA\"4(..@"), "1 1095652178 ");
+    // unknown fingerprints and undefined instructions reflect, which wraps around to the @
+    assert_eq!(run98("0(A.@"), "");
+    assert_eq!(run98("A1.@"), "");
+    // the fingerprint loaded last is in effect, and unloading it uncovers the one loaded before it
+    assert_eq!(run98("\"RSNA\"4(\"YNWT\"4(A.@"), "20 ");
+    assert_eq!(run98("\"RSNA\"4(\"YNWT\"4(\"YNWT\"4)A.@"), "42 ");
+    assert_eq!(run98("\"UDOM\"4(07-3M.07-3R.07-3U.703-M.@"), "2 -1 1 -2 ");
+    assert_eq!(run98("\"AMOR\"4(XI+.@"), "11 ");
+    // NULL makes A reflect even over another fingerprint, until it is unloaded
+    assert_eq!(run98("\"RSNA\"4(\"LLUN\"4(#@A.@"), "");
+    assert_eq!(run98("\"RSNA\"4(\"LLUN\"4(\"LLUN\"4)#@A.@"), "42 ");
+}
+
+#[test]
+fn host_functions() {
+    let run = |program: &str| {
+        let grid = Interpreter::parse_grid(program).unwrap();
+        let mut output = Vec::new();
+        let mut calls = 0;
+        let mut interpreter =
+            Interpreter::new_with_io_and_grid(grid, Box::new(&[] as &[u8]), Box::new(&mut output))
+                .with_host_call_instruction(b'H');
+        interpreter.register_host_function(1, |(a, b): (Int, Int)| a - b);
+        interpreter.register_host_function(2, |(text,): (String,)| text.to_uppercase());
+        interpreter.register_host_function(3, |()| {
+            calls += 1;
+        });
+        interpreter.run_forever().unwrap();
+        drop(interpreter);
+        (String::from_utf8(output).unwrap(), calls)
+    };
+    // arguments are pushed in order, like for -
+    assert_eq!(run("731H.@"), ("4 ".to_string(), 0));
+    assert_eq!(run("0\"olleh\"2H>:#,_@"), ("HELLO".to_string(), 0));
+    assert_eq!(run("3H3H@"), ("".to_string(), 2));
+    // an unknown function reflects, which wraps around to the @
+    assert_eq!(run("9H1.@"), ("".to_string(), 0));
+}
+
+#[test]
+fn game_script() {
+    let script = |program: &str| {
+        let grid = Interpreter::parse_grid(program).unwrap();
+        GameScript::new(100, |input, output| {
+            Interpreter::new_with_io_and_grid(grid, input, output)
+        })
+    };
+    let mut echo = script("~:,.");
+    // the script waits for input instead of reading the end of it
+    assert_eq!(echo.tick(&[]), []);
+    assert_eq!(
+        echo.tick(&[InputEvent::Character(b'a')]),
+        [OutputEvent::Character(b'a'), OutputEvent::Number(97)]
+    );
+    assert_eq!(echo.tick(&[]), []);
+    assert!(!echo.is_stopped());
+
+    let mut double = script("&2*.@");
+    assert_eq!(
+        double.tick(&[InputEvent::Number(-21)]),
+        [
+            OutputEvent::Number(-42),
+            OutputEvent::Stopped(ExitReason::Finished)
+        ]
+    );
+    assert!(double.is_stopped());
+    assert_eq!(double.tick(&[]), []);
+
+    // a script that never waits only runs for the steps of one tick
+    let mut endless = script(">");
+    assert_eq!(endless.tick(&[]), []);
+    assert_eq!(endless.interpreter().steps(), 100);
+}
+
+#[test]
+fn remainders() {
+    for (a, b, truncated, floored, unsigned) in [
+        (7, 3, 1, 1, 1),
+        (-7, 3, -1, 2, 1),
+        (7, -3, 1, -2, 1),
+        (-7, -3, -1, -1, 1),
+        (6, -3, 0, 0, 0),
+        (5, 0, 0, 0, 0),
+        (Int::MIN, -1, 0, 0, 0),
+    ] {
+        assert_eq!(Remainder::Truncated.of(a, b), truncated, "{} % {}", a, b);
+        assert_eq!(Remainder::Floored.of(a, b), floored, "{} % {}", a, b);
+        assert_eq!(Remainder::Unsigned.of(a, b), unsigned, "{} % {}", a, b);
+    }
+    let report = crate::run(RunRequest {
+        program: "07-3%.703-%.@".into(),
+        ..Default::default()
+    });
+    assert_eq!(report.output, b"-1 1 ");
+}
+
+#[test]
+fn fingerprint_semantics() {
+    let interpreter_for = |program: &str| {
+        let grid = Interpreter::parse_grid_for_standard(program, LanguageStandard::Befunge98);
+        Interpreter::new_with_io_and_grid(
+            grid.unwrap(),
+            Box::new(&[] as &[u8]),
+            Box::new(std::io::sink()),
+        )
+        .with_standard(LanguageStandard::Befunge98)
+    };
+    let semantics = |snapshot: &Snapshot, instruction: u8| {
+        snapshot.semantics[(instruction - b'A') as usize].clone()
+    };
+    let (roma, null) = (fingerprint_id("ROMA"), fingerprint_id("NULL"));
+    assert_eq!(roma, 0x524f_4d41);
+
+    // ) unloads the instructions of a fingerprint from the top of each of their stacks, even if another fingerprint
+    // loaded them, so unloading ROMA over NULL uncovers ROMA's instructions and leaves NULL's other ones
+    let mut interpreter = interpreter_for("\"AMOR\"4(\"LLUN\"4(\"AMOR\"4)$$$$@");
+    interpreter.run_forever().unwrap();
+    let snapshot = interpreter.snapshot();
+    assert_eq!(semantics(&snapshot, b'X'), [roma]);
+    assert_eq!(semantics(&snapshot, b'A'), [null]);
+    assert!(snapshot.stack.is_empty());
+
+    // loading the same fingerprint twice needs two ) to unload it
+    let mut interpreter = interpreter_for("\"AMOR\"4(\"AMOR\"4(\"AMOR\"4)$$$$@");
+    interpreter.run_forever().unwrap();
+    assert_eq!(semantics(&interpreter.snapshot(), b'I'), [roma]);
+
+    // restoring a snapshot restores the loaded fingerprints, and t copies them to the new instruction pointer
+    let mut interpreter = interpreter_for("\"AMOR\"4($$tz@");
+    let loaded = {
+        let mut loaded = interpreter_for("\"AMOR\"4(@");
+        loaded.run_forever().unwrap();
+        loaded.snapshot()
+    };
+    while interpreter.ip_count() == 1 {
+        interpreter.run_step().unwrap();
+    }
+    let snapshot = interpreter.snapshot();
+    assert_eq!(snapshot.semantics, loaded.semantics);
+    assert_eq!(snapshot.other_ips[0].semantics, loaded.semantics);
+    let mut restored = interpreter_for("@");
+    restored.restore(loaded.clone());
+    assert_eq!(restored.snapshot().semantics, loaded.semantics);
+}
+
+#[test]
+fn watch_expressions() {
+    assert!(Expression::parse("stack[0] + ").is_err());
+    assert!(Expression::parse("cell(1 2)").is_err());
+    assert!(Expression::parse("stack[0]]").is_err());
+    assert!(Expression::parse("heap[0]").is_err());
+    assert_eq!(
+        Expression::parse(" cell(10, 3) ").unwrap().to_string(),
+        "cell(10, 3)"
+    );
+
+    let mut interpreter =
+        Interpreter::new_with_io("12345 @", Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+            .unwrap();
+    for _ in 0..3 {
+        interpreter.run_step().unwrap();
+    }
+    let evaluate = |source: &str| Expression::parse(source).unwrap().evaluate(&interpreter);
+    assert_eq!(evaluate("stack[0] + stack[1]"), 5);
+    assert_eq!(evaluate("stack[2] * 10 + stack[5]"), 10);
+    assert_eq!(evaluate("2 - 3 * -(depth - 1) % 4"), 4);
+    assert_eq!(evaluate("7 / 0 + 7 % 0"), 0);
+    assert_eq!(evaluate("cell(x + 1, y)"), b'5'.into());
+    assert_eq!(evaluate("cell(-1, 0)"), 0);
+    assert_eq!(evaluate("stack.len() == depth && depth == 3"), 1);
+    assert_eq!(evaluate("stack[0] >= 3 || 1 / 0"), 1);
+    assert_eq!(
+        evaluate("!(stack[0] < 3) + (2 <= 1) * 10 + (stack[1] != 2) * 100"),
+        1
+    );
+    assert!(Expression::parse("1 < 2 &").is_err());
+
+    let mut watches = Watches::new(vec![
+        Expression::parse("stack[0]").unwrap(),
+        Expression::parse("cell(6, 0)").unwrap(),
+    ]);
+    assert_eq!(
+        watches.update(&interpreter),
+        [(3, true), (b'@'.into(), true)]
+    );
+    assert_eq!(watches.changes(&interpreter), None);
+    interpreter.run_step().unwrap();
+    assert_eq!(
+        watches.changes(&interpreter).unwrap(),
+        "step 4: stack[0] = \x1b[7m4\x1b[0m, cell(6, 0) = 64"
+    );
+}
+
+#[test]
+fn breakpoints() {
+    let breakpoint = |value: &str| Breakpoint::from_arg_value(value);
+    assert!(breakpoint("1").is_err());
+    assert!(breakpoint("1,2,3,4").is_err());
+    assert_eq!(
+        breakpoint("1,2,3").unwrap().location,
+        Location::Cell(Position::new(1, 2, 3))
+    );
+    assert!(breakpoint("1,2 if").is_err());
+    assert_eq!(
+        breakpoint("3, 0").unwrap(),
+        Breakpoint {
+            location: Location::Cell(Position::new(3, 0, 0)),
+            ip: None,
+            condition: None
+        }
+    );
+
+    // the loop counts down from 5 and only stops once the counter reached 2
+    let breakpoints = Breakpoints::new(
+        vec![
+            breakpoint("1,0 if stack.len() > 0 && stack[0] == 2").unwrap(),
+            breakpoint("9,9").unwrap(),
+        ],
+        &Annotations::default(),
+    )
+    .unwrap();
+    let mut interpreter = Interpreter::new_with_io(
+        "5>1-:#v_@\n ^    <",
+        Box::new(&[] as &[u8]),
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+    let mut hits = Vec::new();
+    while interpreter.run_step().is_ok() {
+        if breakpoints.triggers(&interpreter) {
+            hits.push(interpreter.stack().to_vec());
+        }
+    }
+    assert_eq!(hits, [vec![2]]);
+    // the error that stops the program tells where
+    assert_eq!(
+        Error::Breakpoint(Position::new(1, 0, 0)).to_string(),
+        "Breakpoint reached at [1, 0]"
+    );
+    assert_eq!(Error::Breakpoint(Position::ZERO).exit_code(), 13);
+
+    // a breakpoint on a region triggers on every cell of the region
+    let annotations = Annotations::parse("@back 1,1-6,1 the way back").unwrap();
+    assert_eq!(
+        breakpoint("@back if stack[0] == 3").unwrap().location,
+        Location::Region("back".to_string())
+    );
+    let breakpoints = Breakpoints::new(
+        vec![breakpoint("@back if stack[0] == 3").unwrap()],
+        &annotations,
+    )
+    .unwrap();
+    let mut interpreter = Interpreter::new_with_io(
+        "5>1-:#v_@\n ^    <",
+        Box::new(&[] as &[u8]),
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+    let mut hits = Vec::new();
+    while interpreter.run_step().is_ok() {
+        if breakpoints.triggers(&interpreter) {
+            hits.push(interpreter.position().x);
+        }
+    }
+    assert_eq!(hits, [6, 5, 4, 3, 2, 1]);
+    assert!(matches!(
+        Breakpoints::new(vec![breakpoint("@front").unwrap()], &annotations),
+        Err(Error::InvalidAnnotations(_))
+    ));
+}
+
+#[test]
+fn toys_fingerprint() {
+    let run98 = |program: &str| {
+        let report = crate::run(RunRequest {
+            program: format!("\"SYOT\"4($${}", program),
+            max_steps: Some(10_000),
+            standard: Some(LanguageStandard::Befunge98),
+            ..Default::default()
+        });
+        String::from_utf8(report.output).unwrap()
+    };
+    assert_eq!(run98("53A...@"), "5 5 5 ");
+    assert_eq!(run98("73B..@"), "4 10 ");
+    assert_eq!(run98("5D.5I.5N.@"), "4 6 -5 ");
+    assert_eq!(run98("234E.234P.@"), "9 24 ");
+    assert_eq!(run98("12H.0f1+-02-H.@"), "4 -4 ");
+    assert_eq!(run98("00T1.@"), "1 ");
+    assert_eq!(run98("X.1.@"), "1 ");
+    assert_eq!(run98("'a01W1.@\na"), "1 ");
+
+    // block copies and moves from low or high coordinates differ when the blocks overlap
+    let row = "01g,11g,21g,31g,@\nabc";
+    assert_eq!(run98("01 31 02C 02g,12g,22g,@\nabc"), "abc");
+    assert_eq!(run98(&format!("01 31 11C {}", row)), "aaaa");
+    assert_eq!(run98(&format!("01 31 11K {}", row)), "aabc");
+    assert_eq!(run98(&format!("01 31 11M {}", row)), " abc");
+    assert_eq!(run98(&format!("11 21 01V {}", row)), "bc  ");
+    assert_eq!(run98(&format!("'x 21 01S {}", row)), "xxc ");
+    // G pushes a block so that F pops it back
+    assert_eq!(run98("1301G 1302F 02g,12g,22g,@\nabc"), "abc");
+
+    // the cells to the right of and behind the program counter, and moving the current column down
+    assert_eq!(run98("R,@\n          z"), "z");
+    assert_eq!(run98("'x Qc0g,@"), "x");
+    assert_eq!(run98("1Jb2g,@\n           a"), "a");
+}
+
+#[test]
+fn orth_fingerprint() {
+    let run98 = |program: &str| {
+        let report = crate::run(RunRequest {
+            program: format!("\"HTRO\"4($${}", program),
+            max_steps: Some(10_000),
+            standard: Some(LanguageStandard::Befunge98),
+            ..Default::default()
+        });
+        String::from_utf8(report.output).unwrap()
+    };
+    assert_eq!(run98("35A.35O.35E.@"), "1 7 6 ");
+    // G and P pop x before y
+    assert_eq!(run98("'x12P12G,21G.@"), "x32 ");
+    assert_eq!(run98("0\"olleh\"S@"), "hello");
+    assert_eq!(run98("0Z1.2.@"), "0 2 ");
+    assert_eq!(run98("1Z2.@"), "2 ");
+    // the program counter moves on after X and Y
+    assert_eq!(run98("fX1.@ 2.@"), "2 ");
+    assert_eq!(run98("1Y@\n            3.@"), "3 ");
+    // V and W set a component of the delta
+    assert_eq!(run98("201-#@ #.V"), "2 ");
+    assert_eq!(run98("0#@W2.@"), "2 ");
+    assert_eq!(
+        run98(&format!(
+            "1W\n{}2\n{}.\n{}@",
+            " ".repeat(12),
+            " ".repeat(13),
+            " ".repeat(14)
+        )),
+        "2 "
+    );
+}
+
+#[test]
+fn float_packing() {
+    assert_eq!(pack_f32(1.0), 0x3f80_0000);
+    assert_eq!(pack_f32(-1.0), -0x4080_0000);
+    assert_eq!(unpack_f32(pack_f32(-1.0)), -1.0);
+    // halves stored zero-extended by interpreters with 64-bit cells unpack the same
+    assert_eq!(unpack_f32(0xbf80_0000), -1.0);
+    assert_eq!(pack_f64(1.0), [0x3ff0_0000, 0]);
+    assert_eq!(pack_f64(-2.5), [-0x3ffc_0000, 0]);
+    assert_eq!(unpack_f64([0xc004_0000, 0]), -2.5);
+    for value in [0.1, -0.0, 1e300, f64::MIN_POSITIVE, f64::INFINITY] {
+        assert_eq!(unpack_f64(pack_f64(value)).to_bits(), value.to_bits());
+    }
+    assert_eq!(format_float(1.5), "1.500000");
+    assert_eq!(format_float(-1.0 / 3.0), "-0.333333");
+    assert_eq!(format_float(f64::NAN), "nan");
+    assert_eq!(format_float(f64::NEG_INFINITY), "-inf");
+}
+
+#[test]
+fn float_fingerprints() {
+    let run98 = |fingerprint: &str, program: &str| {
+        let report = crate::run(RunRequest {
+            program: format!(
+                "\"{}\"4($${}",
+                fingerprint.chars().rev().collect::<String>(),
+                program
+            ),
+            max_steps: Some(10_000),
+            standard: Some(LanguageStandard::Befunge98),
+            ..Default::default()
+        });
+        String::from_utf8(report.output).unwrap()
+    };
+    for fingerprint in ["FPSP", "FPDP"] {
+        assert_eq!(run98(fingerprint, "3F2FDP@"), "1.500000 ");
+        assert_eq!(
+            run98(fingerprint, "2FQP1FXP0FCP@"),
+            "1.414214 2.718282 1.000000 "
+        );
+        assert_eq!(run98(fingerprint, "1FN2FYP2FNVP@"), "1.000000 2.000000 ");
+        assert_eq!(run98(fingerprint, "0\"5.2\"R2FMP@"), "5.000000 ");
+        assert_eq!(run98(fingerprint, "0\"9.7-\"RI.@"), "-7 ");
+        assert_eq!(run98(fingerprint, "0F0FDP@"), "nan ");
+        // R reflects if the string is not a number
+        assert_eq!(run98(fingerprint, "0\"x\"#@R1.@"), "");
+    }
+    // single precision floats take one cell, double precision floats two
+    assert_eq!(run98("FPSP", "1F.@"), "1065353216 ");
+    assert_eq!(run98("FPDP", "1F..@"), "0 1072693248 ");
+}
+
+#[test]
+fn wide_integers() {
+    type Double = Wide<2>;
+    let double = |value: Int| Double::from_int(value);
+    let max = double(Int::MAX);
+    // carries cross into the high cell and back
+    assert_eq!(max.wrapping_add(double(1)).to_cells(), [0, Int::MIN]);
+    assert_eq!(max.wrapping_add(double(1)).wrapping_sub(double(1)), max);
+    assert_eq!(double(-1).to_cells(), [-1, -1]);
+    assert_eq!(
+        max.wrapping_mul(max).to_string(),
+        (Int::MAX as i128 * Int::MAX as i128).to_string()
+    );
+    assert_eq!(
+        double(Int::MIN).wrapping_mul(double(3)).to_string(),
+        (Int::MIN as i128 * 3).to_string()
+    );
+    assert_eq!(
+        max.wrapping_mul(max).checked_div_rem(max),
+        Some((max, Double::ZERO))
+    );
+    assert_eq!(
+        double(-7).checked_div_rem(double(2)),
+        Some((double(-3), double(-1)))
+    );
+    assert_eq!(double(7).checked_div_rem(Double::ZERO), None);
+    assert_eq!(double(1).shift_left(Int::BITS).to_cells(), [1, 0]);
+    assert_eq!(
+        double(1).shift_left(Int::BITS).shift_right(Int::BITS),
+        double(1)
+    );
+    assert_eq!(double(-8).shift_right(2), double(-2));
+    assert_eq!(double(-8).shift_right(1000), double(-1));
+    assert_eq!(double(5).shift_left(1000), Double::ZERO);
+    // the minimum value wraps around to itself
+    let min = double(1).shift_left(Double::BITS - 1);
+    assert_eq!(min.wrapping_neg(), min);
+    assert_eq!(min.checked_div_rem(double(-1)), Some((min, Double::ZERO)));
+    assert_eq!(Double::parse(&min.to_string()), Some(min));
+    assert_eq!(
+        Double::parse(&min.wrapping_sub(double(1)).to_string()),
+        Some(min.wrapping_sub(double(1)))
+    );
+    assert_eq!(Double::parse(&format!("{}0", min)), None);
+    assert_eq!(Double::parse(&min.to_string()[1..]), None);
+    assert_eq!(Double::parse("+42"), Some(double(42)));
+    assert_eq!(Double::parse("4x2"), None);
+    assert_eq!(Double::parse("-"), None);
+    assert_eq!(double(0).to_string(), "0");
+}
+
+#[test]
+fn long_fingerprint() {
+    let run98 = |program: &str| {
+        let report = crate::run(RunRequest {
+            program: format!("\"GNOL\"4($${}", program),
+            max_steps: Some(10_000),
+            standard: Some(LanguageStandard::Befunge98),
+            ..Default::default()
+        });
+        String::from_utf8(report.output).unwrap()
+    };
+    assert_eq!(run98("00 02 AP@"), "2 ");
+    assert_eq!(run98("4E9ESP5ENBP@"), "-5 5 ");
+    assert_eq!(run98("1E88*2-LP@"), "4611686018427387904 ");
+    // products that don't fit into a cell
+    assert_eq!(run98("aaa**:*Eaaa**:*EMP@"), "1000000000000 ");
+    assert_eq!(run98("7NE2EDP7NE2EOP7E0EDP@"), "-3 -1 0 ");
+    assert_eq!(run98("8NE2RP@"), "-2 ");
+    // the high cell is below the low cell
+    assert_eq!(run98("5NE..@"), "-5 -1 ");
+    assert_eq!(run98("0\"01-\"ZP@"), "-10 ");
+    // Z reflects if the string is not a number, L and R if the shift is negative
+    assert_eq!(run98("0\"x\"#@Z1.@"), "");
+    assert_eq!(run98("1E01-#@L1.@"), "");
+}
+
+#[test]
+fn write_hotspots() {
+    assert_eq!(
+        WriteRate::from_arg_value("100/1000"),
+        Ok(WriteRate {
+            writes: 100,
+            steps: 1000
+        })
+    );
+    assert!(WriteRate::from_arg_value("100").is_err());
+    assert!(WriteRate::from_arg_value("1/0").is_err());
+
+    // the writes are counted in consecutive windows of steps
+    let mut hotspots = WriteHotspots::new(WriteRate::from_arg_value("2/10").unwrap());
+    for step in [0, 1, 2, 10, 11, 25] {
+        hotspots.record(Position::new(1, 1, 0), step);
+    }
+    hotspots.record(Position::new(0, 0, 0), 3);
+    let cells = hotspots.hotspots();
+    assert_eq!(cells[0].0, Position::new(1, 1, 0));
+    assert_eq!((cells[0].1.total, cells[0].1.peak), (6, 3));
+    assert_eq!(cells[0].1.flagged_at, Some(2));
+    assert_eq!(cells[1].1.flagged_at, None);
+
+    // a loop that keeps rewriting the same cell
+    let run = |rate: &str| {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new_with_io(
+            "55+>:01p1-:v\n   ^       _@",
+            Box::new(&[] as &[u8]),
+            Box::new(&mut output),
+        )
+        .unwrap()
+        .with_write_hotspots(WriteRate::from_arg_value(rate).unwrap());
+        interpreter.run_forever().unwrap();
+        interpreter.write_hotspots().unwrap().to_string()
+    };
+    assert!(run("5/1000").starts_with(
+        "1 cells written, 1 of them more than 5 times within 1000 steps\n  0,1: 10 writes, at most 10 within 1000 steps, flagged at step "
+    ));
+    assert_eq!(
+        run("20/1000"),
+        "1 cells written, 0 of them more than 20 times within 1000 steps\n  0,1: 10 writes, at most 10 within 1000 steps\n"
+    );
+}
+
+#[test]
+fn outs_fingerprint() {
+    let run = |program: &str, enabled: bool| {
+        let grid = Interpreter::parse_grid_for_standard(
+            &format!("\"STUO\"4($${}", program),
+            LanguageStandard::Befunge98,
+        );
+        let mut output = Vec::new();
+        let mut errors = Vec::new();
+        let mut interpreter = Interpreter::new_with_io_and_grid(
+            grid.unwrap(),
+            Box::new(&[] as &[u8]),
+            Box::new(&mut output),
+        )
+        .with_standard(LanguageStandard::Befunge98)
+        .with_error_output(Box::new(&mut errors));
+        if enabled {
+            interpreter = interpreter.with_fingerprint(&Outs);
+        }
+        interpreter.run_forever().unwrap();
+        drop(interpreter);
+        (
+            String::from_utf8(output).unwrap(),
+            String::from_utf8(errors).unwrap(),
+        )
+    };
+    assert_eq!(
+        run("1.E2.S.O3.S.@", true),
+        ("1 3 0 ".to_string(), "2 1 ".to_string())
+    );
+    // the fingerprint is only available when enabled
+    assert_eq!(run("@", false), (String::new(), String::new()));
+}
+
+#[test]
+fn file_fingerprint() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("file.txt");
+    // FILE takes zero-terminated strings with the first character on top
+    let path_string = format!(
+        "0\"{}\"",
+        path.display().to_string().chars().rev().collect::<String>()
+    );
+    let run = |program: &str, sandbox: Sandbox, file_io: bool| {
+        let sandbox = Sandbox {
+            max_steps: Some(10_000),
+            ..sandbox
+        };
+        let grid = Interpreter::parse_grid_for_standard(program, LanguageStandard::Befunge98);
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new_with_io_and_grid(
+            grid.unwrap(),
+            Box::new(&[] as &[u8]),
+            Box::new(&mut output),
+        )
+        .with_standard(LanguageStandard::Befunge98)
+        .with_sandbox(sandbox);
+        if file_io {
+            interpreter = interpreter.with_fingerprint(&FileIo);
+        }
+        let _ = interpreter.run_forever();
+        drop(interpreter);
+        String::from_utf8(output).unwrap()
+    };
+
+    // write a string and the buffer, then read a line, seek back and read into the buffer
+    let write = format!("\"ELIF\"4($$01 1 {}O 0\"olleh\"P 3W C@\nxyz", path_string);
+    run(&write, Sandbox::default(), true);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "helloxyz");
+    std::fs::write(&path, "ab\ncd").unwrap();
+    let read = format!(
+        "\"ELIF\"4($$01 0 {}O G.,,,$ 00S 5R L. C 01g,41g,@",
+        path_string
+    );
+    assert_eq!(run(&read, Sandbox::default(), true), "3 ab\n5 ad");
+
+    // operations on handles that are not open and failing operations reflect
+    assert_eq!(run("\"ELIF\"4($$7C1.@", Sandbox::default(), true), "");
+    let missing = format!(
+        "\"ELIF\"4($$00 0 {}O1.@",
+        path_string
+            .replace("file.txt", "missing.txt")
+            .replace("txt.elif", "txt.gnissim")
+    );
+    assert_eq!(run(&missing, Sandbox::default(), true), "");
+    let delete = format!("\"ELIF\"4($${}D1.@", path_string);
+    assert_eq!(run(&delete, Sandbox::default(), true), "1 ");
+    assert!(!path.exists());
+
+    // the fingerprint is only available when enabled, and the strict sandbox refuses it
+    assert_eq!(run("\"ELIF\"4(1.@", Sandbox::default(), true), "1 ");
+    assert_eq!(run("\"ELIF\"4(1.@", Sandbox::default(), false), "");
+    assert_eq!(
+        run("\"ELIF\"4(1.@", SandboxProfile::Strict.sandbox(), true),
+        ""
+    );
+}
+
+#[test]
+fn file_instructions() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = |name: &str| {
+        let path = directory.path().join(name);
+        let string = format!(
+            "0\"{}\"",
+            path.display().to_string().chars().rev().collect::<String>()
+        );
+        (path, string)
+    };
+    let (input, input_string) = file("in.txt");
+    let (output, output_string) = file("out.txt");
+    let run = |program: &str, sandbox: Sandbox, file_io: bool| {
+        let grid = Interpreter::parse_grid_for_standard(program, LanguageStandard::Befunge98);
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new_with_io_and_grid(
+            grid.unwrap(),
+            Box::new(&[] as &[u8]),
+            Box::new(&mut output),
+        )
+        .with_standard(LanguageStandard::Befunge98)
+        .with_sandbox(Sandbox {
+            max_steps: Some(10_000),
+            ..sandbox
+        })
+        .with_file_io(file_io);
+        let _ = interpreter.run_forever();
+        drop(interpreter);
+        String::from_utf8(output).unwrap()
+    };
+
+    // i pushes the size and position of what it loaded, which o takes to write the same area back
+    std::fs::write(&input, "ab\r\n c\n").unwrap();
+    let load = format!("a5 0 {}i ....@", input_string);
+    assert_eq!(run(&load, Sandbox::default(), true), "5 10 2 2 ");
+    let copy = format!("\"Z\"a6p a5 0 {}i 1 {}o@", input_string, output_string);
+    run(&copy, Sandbox::default(), true);
+    // spaces don't overwrite cells, and linear output drops trailing spaces and empty lines
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "ab\nZc\n");
+    let wide = format!("a5 0 {}i $$$$ 33a5 0 {}o@", input_string, output_string);
+    run(&wide, Sandbox::default(), true);
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "ab \n c \n   \n");
+
+    // a binary file goes into a single row
+    std::fs::write(&input, "x\ny").unwrap();
+    let binary = format!("a5 1 {}i ....a5g,a1+5g.@", input_string);
+    assert_eq!(run(&binary, Sandbox::default(), true), "5 10 1 3 x10 ");
+
+    // failures, the sandbox and not enabling file access make both reflect
+    let missing = format!("a5 0 {}i1.@", file("missing.txt").1);
+    assert_eq!(run(&missing, Sandbox::default(), true), "");
+    let loaded = format!("a5 0 {}i1.@", input_string);
+    assert_eq!(run(&loaded, Sandbox::default(), true), "1 ");
+    assert_eq!(run(&loaded, SandboxProfile::Strict.sandbox(), true), "");
+    assert_eq!(run(&loaded, Sandbox::default(), false), "");
+    let negative = format!("01-1 00 0 {}o1.@", output_string);
+    assert_eq!(run(&negative, Sandbox::default(), true), "");
+}
+
+#[test]
+fn execute_instruction() {
+    let run = |program: &str, sandbox: Sandbox, paradigm: ExecParadigm| {
+        let grid = Interpreter::parse_grid_for_standard(program, LanguageStandard::Befunge98);
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new_with_io_and_grid(
+            grid.unwrap(),
+            Box::new(&[] as &[u8]),
+            Box::new(&mut output),
+        )
+        .with_standard(LanguageStandard::Befunge98)
+        .with_sandbox(sandbox)
+        .with_exec_paradigm(paradigm);
+        interpreter.run_forever().unwrap();
+        drop(interpreter);
+        String::from_utf8(output).unwrap()
+    };
+    let trusted = || SandboxProfile::Trusted.sandbox();
+
+    // y reports the paradigm, which is unavailable unless the sandbox allows running commands
+    assert_eq!(run("5y.@", trusted(), ExecParadigm::System), "1 ");
+    assert_eq!(
+        run(
+            "5y.@",
+            trusted(),
+            ExecParadigm::from_arg_value("/bin/sh").unwrap()
+        ),
+        "2 "
+    );
+    assert_eq!(run("5y.@", trusted(), ExecParadigm::Unavailable), "0 ");
+    assert_eq!(run("5y.@", Sandbox::default(), ExecParadigm::System), "0 ");
+
+    // a reflected = runs back into the @ at the start
+    assert_eq!(
+        run("#@0\"3 tixe\"=.@", Sandbox::default(), ExecParadigm::System),
+        ""
+    );
+    #[cfg(unix)]
+    {
+        assert_eq!(
+            run("#@0\"3 tixe\"=.@", trusted(), ExecParadigm::System),
+            "3 "
+        );
+        // the command's output becomes program output
+        assert_eq!(
+            run(
+                "#@0\"ih ohce\"=.@",
+                trusted(),
+                ExecParadigm::Shell("sh".into())
+            ),
+            "hi\n0 "
+        );
+        assert_eq!(
+            run(
+                "#@0\"3 tixe\"=.@",
+                trusted(),
+                ExecParadigm::Shell("/nonexistent/shell".into())
+            ),
+            ""
+        );
+    }
+}
+
+#[test]
+fn daemon() {
+    let requests = "program 6\n&2*.@\n\ninput 3\n21\n\nrun\n\
+                    program 3\n\"a\"\nmax_steps 10\nrun\n\
+                    program 2\nr@\nstandard 98\nseed 3\nrun\n\
+                    input 1\nxx\nrun\n\
+                    program 3\n.@\n\nrun\n";
+    let defaults = RunRequest {
+        max_steps: Some(1000),
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    serve_requests(requests.as_bytes(), &mut output, &defaults).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let responses: Vec<Vec<&str>> = output
+        .split_inclusive("end\n")
+        .map(|response| {
+            response
+                .lines()
+                .filter(|line| !line.starts_with("duration_us"))
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        responses,
+        [
+            vec![
+                "exit finished",
+                "message terminated via @",
+                "steps 5",
+                "output 3",
+                "42 ",
+                "end"
+            ],
+            // limits of the request replace those of the daemon
+            vec![
+                "exit step-limit",
+                "message stopped by the step limit",
+                "steps 10",
+                "output 0",
+                "",
+                "end"
+            ],
+            vec![
+                "exit finished",
+                "message terminated via @",
+                "steps 2",
+                "output 0",
+                "",
+                "end"
+            ],
+            // the daemon stops reading after an invalid request
+            vec!["error input is longer than 1 bytes", "end"],
+        ]
+    );
+
+    #[cfg(unix)]
+    {
+        use std::io::Read;
+        use std::io::Write;
+
+        let directory = tempfile::tempdir().unwrap();
+        let socket = directory.path().join("boxfunge.sock");
+        // a stale socket is replaced
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        let path = socket.clone();
+        std::thread::spawn(move || run_daemon(&path, &defaults));
+        let mut stream = loop {
+            match std::os::unix::net::UnixStream::connect(&socket) {
+                Ok(stream) => break stream,
+                Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        };
+        stream.write_all(b"program 4\n7.@\n\nrun\n").unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("exit finished\n"));
+        assert!(response.ends_with("output 2\n7 \nend\n"));
+    }
+}
+
+#[test]
+fn metrics() {
+    use std::io::Read;
+    use std::io::Write;
+
+    let mut interpreter =
+        Interpreter::new_with_io("123@", Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+            .unwrap();
+    let _ = interpreter.run_forever();
+    let metrics = std::sync::Arc::new(Metrics::new());
+    metrics.record(&interpreter);
+    let text = metrics.render();
+    assert!(text.contains("# TYPE boxfunge_steps_total counter\nboxfunge_steps_total 4\n"));
+    assert!(text.contains("\nboxfunge_stack_values 3\n"));
+    assert!(text.contains("\nboxfunge_instruction_pointers 1\n"));
+    assert!(text.contains("\nboxfunge_grid_cells 4\n"));
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    metrics.serve(listener);
+    let get = |path: &str| {
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let response = get("/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with(&text[text.find("# HELP boxfunge_instruction_pointers").unwrap()..]));
+    assert!(get("/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+#[test]
+fn post_mortem_debugger() {
+    let mut interpreter = Interpreter::new_with_io(
+        "123+Z@\n v",
+        Box::new(&[] as &[u8]),
+        Box::new(std::io::sink()),
+    )
+    .unwrap();
+    let error = interpreter.run_forever().unwrap_err();
+    let mut output = Vec::new();
+    let commands = "where\ninfo stack\n\nprint stack[0] * 2 + depth\ninfo cell 1 1\ninfo cell 1\nprint (\nlist\nfrobnicate\nquit\ninfo stack\n";
+    post_mortem(
+        &interpreter,
+        &error,
+        &Annotations::default(),
+        None,
+        commands.as_bytes(),
+        &mut output,
+        None,
+    )
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.split("(boxfunge) ").collect();
+    assert_eq!(
+        lines,
+        [
+            "post-mortem: Illegal command 'Z' (5a), type \"help\" for a list of commands\n",
+            "stopped at [4, 0] moving > after 5 steps: Illegal command 'Z' (5a)\n",
+            "stack: [1, 5]\n",
+            "",
+            "12\n",
+            "118 'v'\n",
+            "usage: info cell X Y [Z]\n",
+            "invalid expression: unexpected end of the expression\n",
+            "   0 123+\x1b[7mZ\x1b[0m@\n   1  v    \n",
+            "unknown command \"frobnicate\", type \"help\" for a list of commands\n",
+            "",
+        ]
+    );
+
+    // a script runs first and is echoed, then the input takes over, and the log can be run as a script again
+    let mut output = Vec::new();
+    let mut log = Vec::new();
+    post_mortem(
+        &interpreter,
+        &error,
+        &Annotations::default(),
+        Some(&mut "# the top is 5\nprint stack[0]\n\ninfo stack".as_bytes()),
+        "print depth\n".as_bytes(),
+        &mut output,
+        Some(&mut log),
+    )
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.split("(boxfunge) ").skip(1).collect();
+    assert_eq!(
+        lines,
+        [
+            "# the top is 5\n",
+            "print stack[0]\n5\n",
+            "\n",
+            "info stack\nstack: [1, 5]\n",
+            "2\n",
+            "\n",
+        ]
+    );
+    let log = String::from_utf8(log).unwrap();
+    assert_eq!(
+        log,
+        "# post-mortem: Illegal command 'Z' (5a)\n# the top is 5\nprint stack[0]\ninfo stack\nprint depth\n"
+    );
+
+    // --debug-script implies --post-mortem and doesn't need stdin if it quits
+    let program = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(program.path(), "123+Z@").unwrap();
+    let script = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(script.path(), log + "quit\n").unwrap();
+    let replayed = tempfile::NamedTempFile::new().unwrap();
+    let exit_code = run_interpreter_with_output(
+        Arguments {
+            input: program.path().to_owned(),
+            language_standard: LanguageStandard::Befunge93,
+            debugger: DebuggerOptions {
+                script: Some(script.path().to_owned()),
+                log: Some(replayed.path().to_owned()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Box::new(std::io::sink()),
+    );
+    assert_eq!(
+        exit_code,
+        Ok(Error::IllegalCommand { command: b'Z' }.exit_code())
+    );
+    // the replayed session logs the same commands under a header of its own
+    assert_eq!(
+        std::fs::read_to_string(replayed.path()).unwrap(),
+        format!(
+            "# post-mortem: Illegal command 'Z' (5a)\n{}",
+            std::fs::read_to_string(script.path()).unwrap()
+        )
+    );
+}
+
+#[test]
+fn concurrent_debugging() {
+    // both instruction pointers end on the @ in the middle, the new one first
+    let program = "t1.2.@.3.4";
+    let hits = |breakpoint: &str| {
+        let breakpoints = Breakpoints::new(
+            vec![Breakpoint::from_arg_value(breakpoint).unwrap()],
+            &Annotations::default(),
+        )
+        .unwrap();
+        let mut interpreter =
+            Interpreter::new_with_io(program, Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+                .unwrap()
+                .with_standard(LanguageStandard::Befunge98);
+        let mut hits = Vec::new();
+        loop {
+            interpreter.schedule().unwrap();
+            if breakpoints.triggers(&interpreter) {
+                hits.push(interpreter.ip_id());
+            }
+            if interpreter.run_step().is_err() {
+                return hits;
+            }
+        }
+    };
+    assert_eq!(hits("5,0"), [1, 0]);
+    assert_eq!(hits("5,0 thread 1"), [1]);
+    assert_eq!(hits("5,0 thread 0 if depth == 0"), [0]);
+    assert!(Breakpoint::from_arg_value("5,0 thread one").is_err());
+
+    let mut interpreter =
+        Interpreter::new_with_io(program, Box::new(&[] as &[u8]), Box::new(std::io::sink()))
+            .unwrap()
+            .with_standard(LanguageStandard::Befunge98);
+    for _ in 0..4 {
+        interpreter.run_step().unwrap();
+    }
+    interpreter.schedule().unwrap();
+    let mut output = Vec::new();
+    let commands = "info threads\nthread 1\nwhere\nprint stack.len() + x\nlist\nthread 2\nthread\n";
+    post_mortem(
+        &interpreter,
+        &Error::Breakpoint(interpreter.position()),
+        &Annotations::default(),
+        None,
+        commands.as_bytes(),
+        &mut output,
+        None,
+    )
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.split("(boxfunge) ").skip(1).collect();
+    assert_eq!(
+        lines,
+        [
+            "* thread 0 at [2, 0] moving >, stack depth 1\n  thread 1 at [7, 0] moving <, stack depth 0\n",
+            "thread 1 selected\n",
+            "thread 1 waits at [7, 0] moving <\n",
+            "7\n",
+            // every instruction pointer has its own color, and the selected one is underlined
+            "   0 t1\x1b[30;43m.\x1b[0m2.@.\x1b[30;46;4m3\x1b[0m.4\n",
+            "no thread 2, see \"info threads\"\n",
+            "thread 1 is selected\n",
+            "\n",
+        ]
+    );
 }
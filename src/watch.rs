@@ -0,0 +1,61 @@
+//! Watch expressions, which are printed whenever their values change after a step, e.g. `stack[0] + stack[1]` or
+//! `cell(10, 3)`.
+
+use std::fmt::Write;
+
+use crate::Executer;
+use crate::Expression;
+use crate::Int;
+use crate::Interpreter;
+
+/// A set of watch expressions together with their last values, so that changes can be highlighted.
+#[derive(Clone, Debug, Default)]
+pub struct Watches {
+    watches: Vec<Expression>,
+    values: Vec<Option<Int>>,
+}
+
+impl Watches {
+    pub fn new(watches: Vec<Expression>) -> Self {
+        Self {
+            values: vec![None; watches.len()],
+            watches,
+        }
+    }
+
+    /// Evaluate all expressions and return their values, along with whether each one changed since the last update.
+    /// On the first update, every value counts as changed.
+    pub fn update(&mut self, interpreter: &Interpreter) -> Vec<(Int, bool)> {
+        self.watches
+            .iter()
+            .zip(&mut self.values)
+            .map(|(watch, last)| {
+                let value = watch.evaluate(interpreter);
+                let changed = *last != Some(value);
+                *last = Some(value);
+                (value, changed)
+            })
+            .collect()
+    }
+
+    /// Evaluate all expressions and render them as a line like "step 3: stack[0] = 5, depth = 2" with the changed
+    /// values in inverse video, or return `None` if no value changed.
+    pub fn changes(&mut self, interpreter: &Interpreter) -> Option<String> {
+        let values = self.update(interpreter);
+        if !values.iter().any(|&(_, changed)| changed) {
+            return None;
+        }
+        let mut line = format!("step {}: ", interpreter.steps());
+        for (index, (watch, (value, changed))) in self.watches.iter().zip(values).enumerate() {
+            if index > 0 {
+                line.push_str(", ");
+            }
+            if changed {
+                let _ = write!(line, "{} = \x1b[7m{}\x1b[0m", watch, value);
+            } else {
+                let _ = write!(line, "{} = {}", watch, value);
+            }
+        }
+        Some(line)
+    }
+}